@@ -0,0 +1,21 @@
+use std::env;
+
+use modbus::{
+    client::rtu::{RtuClient, RtuConfig},
+    pdu::request::Request as PduRequest,
+    transport::serial::{SerialConfig, SerialPort},
+};
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| "/dev/ttyUSB0".into());
+    let port = SerialPort::open(&path, SerialConfig::new(19200)).unwrap();
+    let mut client = RtuClient::new(port, RtuConfig::default());
+
+    let mut res_buf = [0_u8; 256];
+    let reply = client.send(1, PduRequest::ReadHoldingRegisters(0, 4), &mut res_buf);
+    println!("{reply:?}");
+
+    // Broadcasts aren't answered, the next request waits for the turnaround delay
+    let reply = client.send(0, PduRequest::WriteSingleRegister(0, 1), &mut res_buf);
+    println!("{reply:?}");
+}
@@ -96,6 +96,18 @@ fn handle_connection(mut stream: TcpStream) {
                     println!("Modbus exception code: {fn_code:?} {exception_code:?}");
                     break;
                 }
+                DecodeError::InvalidCrc { expected, actual } => {
+                    println!("Invalid CRC: {actual:#06x}, expected {expected:#06x}");
+                    break;
+                }
+                DecodeError::InvalidLrc { expected, actual } => {
+                    println!("Invalid LRC: {actual:#04x}, expected {expected:#04x}");
+                    break;
+                }
+                DecodeError::InvalidAsciiFrame => {
+                    println!("Invalid ASCII frame");
+                    break;
+                }
             },
         };
 
@@ -0,0 +1,416 @@
+use std::str::FromStr;
+
+use modbus::{
+    client::{Client, Error, RESPONSE_BUF_SIZE, split},
+    exception_code::ExceptionCode,
+    pdu::{
+        Address, Quantity, RegisterType,
+        request::Request as PduRequest,
+        response::Response as PduResponse,
+        value::{DataType, Value, WordOrder},
+    },
+};
+use serde_json::{Value as Json, json};
+
+use crate::output::{table_name, value_to_json};
+
+pub const READ_COLUMNS: &[&str] = &["unit", "table", "address", "value"];
+pub const SCAN_COLUMNS: &[&str] = &["unit", "table", "address", "quantity", "result"];
+
+/// Ranges read by `scan` on every unit answering, the first one deciding whether a
+/// unit is there
+pub const SCAN_PROBES: &[(RegisterType, Address, Quantity)] = &[
+    (RegisterType::HoldingRegister, 0, 10),
+    (RegisterType::InputRegister, 0, 10),
+    (RegisterType::Coil, 0, 16),
+    (RegisterType::DiscreteInput, 0, 16),
+    (RegisterType::HoldingRegister, 1000, 10),
+    (RegisterType::InputRegister, 1000, 10),
+    (RegisterType::HoldingRegister, 4000, 10),
+    (RegisterType::InputRegister, 3000, 10),
+    (RegisterType::InputRegister, 30000, 10),
+    (RegisterType::HoldingRegister, 40000, 10),
+];
+
+/// Table from its name, also accepting the usual short forms `co`, `di`, `ir` and `hr`
+pub fn parse_table(s: &str) -> Result<RegisterType, String> {
+    match s {
+        "co" | "coils" => Ok(RegisterType::Coil),
+        "di" | "discrete_inputs" => Ok(RegisterType::DiscreteInput),
+        "ir" | "input_registers" => Ok(RegisterType::InputRegister),
+        "hr" | "holding_registers" => Ok(RegisterType::HoldingRegister),
+        s => RegisterType::from_str(s).map_err(|()| format!("unknown table {s:?}")),
+    }
+}
+
+/// Value of the type from its text, bools also being `on` or `off`
+pub fn parse_value(data_type: DataType, s: &str) -> Result<Value, String> {
+    let invalid = || format!("invalid {data_type:?} value {s:?}");
+    Ok(match data_type {
+        DataType::Bool => match s {
+            "on" | "true" | "1" => Value::Bool(true),
+            "off" | "false" | "0" => Value::Bool(false),
+            _ => return Err(invalid()),
+        },
+        DataType::U16 => Value::U16(parse_int(s).map_err(|_| invalid())?),
+        DataType::I16 => Value::I16(parse_int(s).map_err(|_| invalid())?),
+        DataType::U32 => Value::U32(parse_int(s).map_err(|_| invalid())?),
+        DataType::I32 => Value::I32(parse_int(s).map_err(|_| invalid())?),
+        DataType::F32 => Value::F32(s.parse().map_err(|_| invalid())?),
+        DataType::U64 => Value::U64(parse_int(s).map_err(|_| invalid())?),
+        DataType::I64 => Value::I64(parse_int(s).map_err(|_| invalid())?),
+        DataType::F64 => Value::F64(s.parse().map_err(|_| invalid())?),
+    })
+}
+
+/// Integer in decimal or with a `0x` prefix in hex
+fn parse_int<T: TryFrom<i128>>(s: &str) -> Result<T, ()> {
+    let value = match s.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    value
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or(())
+}
+
+fn describe_exception(exception_code: ExceptionCode) -> String {
+    match exception_code {
+        ExceptionCode::Other(code) => format!("exception {code:#04x}"),
+        code => code.to_string(),
+    }
+}
+
+/// Reads `count` values of the type starting at `address`, a row per value
+pub fn read(
+    mut client: &mut dyn Client,
+    unit_id: u8,
+    table: RegisterType,
+    address: Address,
+    count: usize,
+    data_type: DataType,
+    order: WordOrder,
+) -> Result<Vec<Vec<Json>>, String> {
+    if !data_type.fits(table) {
+        return Err(format!(
+            "{data_type:?} values aren't stored in {}s",
+            table_name(table)
+        ));
+    }
+    let quantity = data_type.quantity();
+    let values: Vec<Value> = if table.is_bit() {
+        let mut bits = vec![false; count];
+        split::read_bits(&mut client, unit_id, table, address, &mut bits)
+            .map_err(|err| err.error.to_string())?;
+        bits.into_iter().map(Value::Bool).collect()
+    } else {
+        let mut words = vec![0_u16; count * quantity];
+        split::read_registers(&mut client, unit_id, table, address, &mut words)
+            .map_err(|err| err.error.to_string())?;
+        words
+            .chunks_exact(quantity)
+            .map(|words| Value::decode_words(data_type, order, words).unwrap())
+            .collect()
+    };
+
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            vec![
+                json!(unit_id),
+                json!(table_name(table)),
+                json!(address as usize + i * quantity),
+                value_to_json(value),
+            ]
+        })
+        .collect())
+}
+
+/// Writes the values to coils or holding registers starting at `address`. A single
+/// coil or register is written with the single write function codes.
+pub fn write(
+    mut client: &mut dyn Client,
+    unit_id: u8,
+    table: RegisterType,
+    address: Address,
+    values: &[Value],
+    order: WordOrder,
+) -> Result<(), String> {
+    match table {
+        RegisterType::Coil => {
+            let coils = values
+                .iter()
+                .map(|value| match value {
+                    Value::Bool(coil) => Ok(*coil),
+                    _ => Err("coils take bool values".to_string()),
+                })
+                .collect::<Result<Vec<bool>, String>>()?;
+            if let [coil] = coils[..] {
+                let req = PduRequest::WriteSingleCoil(address, coil);
+                write_single(
+                    client,
+                    unit_id,
+                    req,
+                    PduResponse::WriteSingleCoil(address, coil),
+                )
+            } else {
+                split::write_coils(&mut client, unit_id, address, &coils)
+                    .map_err(|err| err.error.to_string())
+            }
+        }
+        RegisterType::HoldingRegister => {
+            let mut words = vec![];
+            for value in values {
+                if value.data_type() == DataType::Bool {
+                    return Err("holding registers don't take bool values".to_string());
+                }
+                let mut value_words = [0_u16; 4];
+                let len = value.encode_words(order, &mut value_words);
+                words.extend_from_slice(&value_words[..len]);
+            }
+            if let [word] = words[..] {
+                let req = PduRequest::WriteSingleRegister(address, word);
+                let echo = PduResponse::WriteSingleRegister(address, word);
+                write_single(client, unit_id, req, echo)
+            } else {
+                split::write_registers(&mut client, unit_id, address, &words)
+                    .map_err(|err| err.error.to_string())
+            }
+        }
+        table => Err(format!("{}s are read-only", table_name(table))),
+    }
+}
+
+/// Sends a single write, whose response echoes the request
+fn write_single(
+    client: &mut dyn Client,
+    unit_id: u8,
+    req: PduRequest<'_>,
+    echo: PduResponse<'_>,
+) -> Result<(), String> {
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    match client.request(unit_id, req, &mut res_buf) {
+        Ok(res) if res == echo => Ok(()),
+        Ok(_) => Err(Error::UnexpectedResponse.to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Probes the units with [`SCAN_PROBES`], a row per probe answered. Units not answering
+/// the first probe in time are skipped.
+pub fn scan(client: &mut dyn Client, units: impl IntoIterator<Item = u8>) -> Vec<Vec<Json>> {
+    let mut rows = vec![];
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    for unit_id in units {
+        for (i, (table, address, quantity)) in SCAN_PROBES.iter().enumerate() {
+            let req = table.read_request(*address, *quantity);
+            let result = match client.request(unit_id, req, &mut res_buf) {
+                Ok(_) => "ok".to_string(),
+                Err(Error::Exception(_, exception_code)) => describe_exception(exception_code),
+                Err(_) if i == 0 => break,
+                Err(err) => err.to_string(),
+            };
+            rows.push(vec![
+                json!(unit_id),
+                json!(table_name(*table)),
+                json!(address),
+                json!(quantity),
+                json!(result),
+            ]);
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, net::TcpListener, process, thread, time::Duration};
+
+    use modbus::{
+        client::Client,
+        pdu::{
+            RegisterType,
+            value::{DataType, Value, WordOrder},
+        },
+        server::{registers::RegisterMap, tcp::serve_connection},
+        transport::{
+            record::Recording,
+            replay::{Replay, ReplayConfig},
+        },
+    };
+    use serde_json::json;
+
+    use crate::target::{SerialOptions, Target, connect};
+
+    use super::{parse_table, parse_value, read, scan, write};
+
+    /// Serves `map` to the first connection, returning its address
+    fn spawn_server(mut map: RegisterMap) -> Target {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve_connection(stream, &mut map).unwrap();
+        });
+        Target::Tcp(addr.to_string())
+    }
+
+    #[test]
+    fn parses_arguments() {
+        assert_eq!(parse_table("hr"), Ok(RegisterType::HoldingRegister));
+        assert_eq!(
+            parse_table("discrete_input"),
+            Ok(RegisterType::DiscreteInput)
+        );
+        assert!(parse_table("registers").is_err());
+
+        assert_eq!(parse_value(DataType::Bool, "on"), Ok(Value::Bool(true)));
+        assert_eq!(parse_value(DataType::U16, "0xff00"), Ok(Value::U16(0xff00)));
+        assert_eq!(parse_value(DataType::I32, "-5"), Ok(Value::I32(-5)));
+        assert_eq!(parse_value(DataType::F32, "1.5"), Ok(Value::F32(1.5)));
+        assert!(parse_value(DataType::U16, "70000").is_err());
+        assert!(parse_value(DataType::Bool, "yes").is_err());
+    }
+
+    #[test]
+    fn reads_and_writes_over_tcp() {
+        let mut map = RegisterMap::new();
+        map.set_registers(RegisterType::HoldingRegister, 100, &[0; 4]);
+        map.set_registers(RegisterType::Coil, 0, &[0; 8]);
+        let target = spawn_server(map);
+        let mut client = connect(
+            &target,
+            &SerialOptions::default(),
+            Duration::from_secs(1),
+            None,
+        )
+        .unwrap();
+
+        let values = [Value::F32(1.5), Value::F32(-2.0)];
+        write(
+            &mut *client,
+            1,
+            RegisterType::HoldingRegister,
+            100,
+            &values,
+            WordOrder::Cdab,
+        )
+        .unwrap();
+        let rows = read(
+            &mut *client,
+            1,
+            RegisterType::HoldingRegister,
+            100,
+            2,
+            DataType::F32,
+            WordOrder::Cdab,
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![json!(1), json!("holding_register"), json!(100), json!(1.5)],
+                vec![json!(1), json!("holding_register"), json!(102), json!(-2.0)],
+            ]
+        );
+
+        write(
+            &mut *client,
+            1,
+            RegisterType::Coil,
+            5,
+            &[Value::Bool(true)],
+            WordOrder::Abcd,
+        )
+        .unwrap();
+        let rows = read(
+            &mut *client,
+            1,
+            RegisterType::Coil,
+            4,
+            2,
+            DataType::Bool,
+            WordOrder::Abcd,
+        )
+        .unwrap();
+        assert_eq!(rows[0][3], json!(false));
+        assert_eq!(rows[1][3], json!(true));
+
+        let err = read(
+            &mut *client,
+            1,
+            RegisterType::HoldingRegister,
+            200,
+            1,
+            DataType::U16,
+            WordOrder::Abcd,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "FC03 ReadHoldingRegisters: exception IllegalDataAddress"
+        );
+    }
+
+    #[test]
+    fn scans_units() {
+        let mut map = RegisterMap::new();
+        map.set_registers(RegisterType::HoldingRegister, 0, &[0; 10]);
+        let target = spawn_server(map);
+        let mut client = connect(
+            &target,
+            &SerialOptions::default(),
+            Duration::from_secs(1),
+            None,
+        )
+        .unwrap();
+
+        let rows = scan(&mut *client, [1]);
+        assert_eq!(rows.len(), super::SCAN_PROBES.len());
+        assert_eq!(
+            rows[0],
+            vec![
+                json!(1),
+                json!("holding_register"),
+                json!(0),
+                json!(10),
+                json!("ok")
+            ]
+        );
+        assert_eq!(rows[1][4], json!("IllegalDataAddress"));
+    }
+
+    #[test]
+    fn records_and_replays_reads() {
+        let mut map = RegisterMap::new();
+        map.set_registers(RegisterType::HoldingRegister, 0, &[7, 8]);
+        let target = spawn_server(map);
+        let path = env::temp_dir().join(format!("modbus-cli-{}.log", process::id()));
+        let timeout = Duration::from_secs(1);
+        let serial = SerialOptions::default();
+
+        let mut client = connect(&target, &serial, timeout, Some(&path)).unwrap();
+        let read_hr = |client: &mut dyn Client| {
+            let table = RegisterType::HoldingRegister;
+            read(client, 1, table, 0, 2, DataType::U16, WordOrder::Abcd).unwrap()
+        };
+        let rows = read_hr(&mut *client);
+        drop(client);
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recording.exchanges().len(), 1);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut replay = Replay::new(&recording, ReplayConfig::default());
+            let (stream, _) = listener.accept().unwrap();
+            replay.serve(stream).unwrap();
+        });
+        let target = Target::Tcp(addr.to_string());
+        let mut client = connect(&target, &serial, timeout, None).unwrap();
+        assert_eq!(read_hr(&mut *client), rows);
+    }
+}
@@ -0,0 +1,407 @@
+//! Command-line Modbus client:
+//!
+//! ```text
+//! modbus-cli -t 10.0.0.5:502 read hr 1 0 10
+//! modbus-cli -t rtu:///dev/ttyUSB0 --baud 9600 read hr 1 100 2 --type f32 --order cdab
+//! modbus-cli write coil 1 5 on
+//! modbus-cli -f csv watch ir 1 0 4 --interval 500
+//! modbus-cli --timeout 100 scan --units 1-10
+//! modbus-cli -t rtu:///dev/ttyUSB0 --record site.log watch hr 1 0 4
+//! modbus-cli replay site.log
+//! modbus-cli pcap customer.pcapng --port 502 --port 1502
+//! ```
+
+mod command;
+mod output;
+mod replay;
+mod target;
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Write},
+    ops::RangeInclusive,
+    path::PathBuf,
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::{Args, Parser, Subcommand};
+use modbus::{
+    pcap::{self, DissectorConfig},
+    pdu::{
+        Address, RegisterType,
+        value::{DataType, WordOrder},
+    },
+    transport::{
+        record::Recording,
+        serial::{DataBits, Parity, StopBits},
+    },
+};
+
+use crate::{
+    command::{READ_COLUMNS, SCAN_COLUMNS, parse_table, parse_value},
+    output::{Format, render},
+    target::{SerialOptions, Target, connect},
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Reads and writes Modbus devices over TCP, RTU and ASCII"
+)]
+struct Cli {
+    /// `tcp://host:port` or `host:port`, `rtu:///dev/ttyX` or `ascii:///dev/ttyX`
+    #[arg(short, long, global = true, default_value = "127.0.0.1:502")]
+    target: Target,
+    /// Response timeout in milliseconds
+    #[arg(long, global = true, default_value_t = 1000)]
+    timeout: u64,
+    #[arg(short, long, global = true, default_value = "table", value_parser = str::parse::<Format>)]
+    format: Format,
+    /// Records the traffic with the target to this file, to replay it later
+    #[arg(long, global = true)]
+    record: Option<PathBuf>,
+    #[command(flatten)]
+    serial: SerialArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Args)]
+struct SerialArgs {
+    #[arg(
+        long,
+        global = true,
+        default_value_t = 19200,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    baud: u32,
+    /// none, even or odd
+    #[arg(long, global = true, default_value = "even", value_parser = parse_parity)]
+    parity: Parity,
+    /// 7 or 8, defaults to 8 for RTU and 7 for ASCII
+    #[arg(long, global = true, value_parser = parse_data_bits)]
+    data_bits: Option<DataBits>,
+    /// 1 or 2
+    #[arg(long, global = true, default_value = "1", value_parser = parse_stop_bits)]
+    stop_bits: StopBits,
+}
+
+#[derive(Debug, Args)]
+struct TypeArgs {
+    /// bool, u16, i16, u32, i32, f32, u64, i64 or f64. Defaults to bool for coils and
+    /// discrete inputs, u16 for registers.
+    #[arg(long = "type", value_parser = parse_data_type)]
+    data_type: Option<DataType>,
+    /// Order of the bytes of values spanning several registers: abcd, cdab, badc or dcba
+    #[arg(long, default_value = "abcd", value_parser = parse_order)]
+    order: WordOrder,
+}
+
+impl TypeArgs {
+    fn data_type(&self, table: RegisterType) -> DataType {
+        self.data_type.unwrap_or(if table.is_bit() {
+            DataType::Bool
+        } else {
+            DataType::U16
+        })
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Reads values
+    Read {
+        /// coil (co), discrete_input (di), input_register (ir) or holding_register (hr)
+        #[arg(value_parser = parse_table)]
+        table: RegisterType,
+        unit: u8,
+        address: Address,
+        /// Number of values
+        #[arg(default_value_t = 1)]
+        count: usize,
+        #[command(flatten)]
+        typed: TypeArgs,
+    },
+    /// Writes coils or holding registers, one after the other from the address
+    Write {
+        /// coil (co) or holding_register (hr)
+        #[arg(value_parser = parse_table)]
+        table: RegisterType,
+        unit: u8,
+        address: Address,
+        /// Values, `on` or `off` for coils
+        #[arg(required = true, allow_negative_numbers = true)]
+        values: Vec<String>,
+        #[command(flatten)]
+        typed: TypeArgs,
+    },
+    /// Reads values repeatedly
+    Watch {
+        #[arg(value_parser = parse_table)]
+        table: RegisterType,
+        unit: u8,
+        address: Address,
+        #[arg(default_value_t = 1)]
+        count: usize,
+        #[command(flatten)]
+        typed: TypeArgs,
+        /// Milliseconds between reads
+        #[arg(long, default_value_t = 1000)]
+        interval: u64,
+        /// Stops after this many reads instead of running until interrupted
+        #[arg(long)]
+        times: Option<usize>,
+    },
+    /// Looks for units answering and probes common register ranges on them
+    Scan {
+        /// Unit ids to probe, `first-last` or a single one
+        #[arg(long, default_value = "1-247", value_parser = parse_units)]
+        units: RangeInclusive<u8>,
+    },
+    /// Serves a recording made with `--record`, answering the recorded requests with
+    /// their responses and printing the requests that aren't in it
+    Replay {
+        recording: PathBuf,
+        /// Address to serve TCP recordings on
+        #[arg(long, default_value = "127.0.0.1:5020")]
+        listen: String,
+        /// Serial port to serve RTU and ASCII recordings on, a new pty by default
+        #[arg(long)]
+        device: Option<PathBuf>,
+        /// Answers right away instead of as late as the recorded device did
+        #[arg(long)]
+        no_timing: bool,
+    },
+    /// Dissects the Modbus TCP traffic of a pcap or pcapng capture, printing latency
+    /// stats per unit and function and the protocol violations
+    Pcap {
+        capture: PathBuf,
+        /// Server port, 502 by default. Repeat it for several.
+        #[arg(long = "port")]
+        ports: Vec<u16>,
+    },
+}
+
+fn parse_parity(s: &str) -> Result<Parity, String> {
+    match s {
+        "none" => Ok(Parity::None),
+        "even" => Ok(Parity::Even),
+        "odd" => Ok(Parity::Odd),
+        _ => Err("expected none, even or odd".to_string()),
+    }
+}
+
+fn parse_data_bits(s: &str) -> Result<DataBits, String> {
+    match s {
+        "7" => Ok(DataBits::Seven),
+        "8" => Ok(DataBits::Eight),
+        _ => Err("expected 7 or 8".to_string()),
+    }
+}
+
+fn parse_stop_bits(s: &str) -> Result<StopBits, String> {
+    match s {
+        "1" => Ok(StopBits::One),
+        "2" => Ok(StopBits::Two),
+        _ => Err("expected 1 or 2".to_string()),
+    }
+}
+
+fn parse_data_type(s: &str) -> Result<DataType, String> {
+    s.parse().map_err(|()| format!("unknown type {s:?}"))
+}
+
+fn parse_order(s: &str) -> Result<WordOrder, String> {
+    s.parse().map_err(|()| format!("unknown order {s:?}"))
+}
+
+fn parse_units(s: &str) -> Result<RangeInclusive<u8>, String> {
+    let (first, last) = s.split_once('-').unwrap_or((s, s));
+    let parse = |id: &str| id.parse::<u8>().map_err(|err| format!("{id:?}: {err}"));
+    Ok(parse(first)?..=parse(last)?)
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let serial = SerialOptions {
+        baud_rate: cli.serial.baud,
+        parity: cli.serial.parity,
+        data_bits: cli.serial.data_bits,
+        stop_bits: cli.serial.stop_bits,
+    };
+    if let Command::Replay {
+        recording,
+        listen,
+        device,
+        no_timing,
+    } = &cli.command
+    {
+        let recording =
+            Recording::load(recording).map_err(|err| format!("{recording:?}: {err}"))?;
+        return replay::serve(&recording, !no_timing, listen, device.as_deref(), &serial);
+    }
+    if let Command::Pcap { capture, ports } = &cli.command {
+        let mut config = DissectorConfig::default();
+        if !ports.is_empty() {
+            config.ports = ports.clone();
+        }
+        let file = File::open(capture).map_err(|err| format!("{capture:?}: {err}"))?;
+        let report = pcap::dissect(BufReader::new(file), config)
+            .map_err(|err| format!("{capture:?}: {err}"))?;
+        print!("{report}");
+        return Ok(());
+    }
+
+    let timeout = Duration::from_millis(cli.timeout);
+    let mut client = connect(&cli.target, &serial, timeout, cli.record.as_deref())
+        .map_err(|err| format!("{}: {err}", cli.target))?;
+    let client = &mut *client;
+    let mut stdout = io::stdout().lock();
+    let mut print = |text: String| {
+        stdout
+            .write_all(text.as_bytes())
+            .and_then(|()| stdout.flush())
+            .map_err(|err| err.to_string())
+    };
+
+    match cli.command {
+        Command::Read {
+            table,
+            unit,
+            address,
+            count,
+            typed,
+        } => {
+            let rows = command::read(
+                client,
+                unit,
+                table,
+                address,
+                count,
+                typed.data_type(table),
+                typed.order,
+            )?;
+            print(render(cli.format, READ_COLUMNS, &rows))
+        }
+        Command::Write {
+            table,
+            unit,
+            address,
+            values,
+            typed,
+        } => {
+            let data_type = typed.data_type(table);
+            let values = values
+                .iter()
+                .map(|value| parse_value(data_type, value))
+                .collect::<Result<Vec<_>, _>>()?;
+            command::write(client, unit, table, address, &values, typed.order)
+        }
+        Command::Watch {
+            table,
+            unit,
+            address,
+            count,
+            typed,
+            interval,
+            times,
+        } => {
+            let interval = Duration::from_millis(interval);
+            for _ in 0..times.unwrap_or(usize::MAX) {
+                let started = Instant::now();
+                let data_type = typed.data_type(table);
+                match command::read(client, unit, table, address, count, data_type, typed.order) {
+                    Ok(rows) => print(render(cli.format, READ_COLUMNS, &rows))?,
+                    // Keep watching, the device may come back
+                    Err(err) => eprintln!("{err}"),
+                }
+                thread::sleep(interval.saturating_sub(started.elapsed()));
+            }
+            Ok(())
+        }
+        Command::Scan { units } => {
+            let rows = command::scan(client, units);
+            print(render(cli.format, SCAN_COLUMNS, &rows))
+        }
+        Command::Replay { .. } | Command::Pcap { .. } => unreachable!(),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::{CommandFactory, Parser};
+    use modbus::pdu::{
+        RegisterType,
+        value::{DataType, WordOrder},
+    };
+
+    use super::{Cli, Command, parse_units};
+
+    #[test]
+    fn parses_command_lines() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "modbus-cli",
+            "-t",
+            "rtu:///dev/ttyUSB0",
+            "read",
+            "hr",
+            "1",
+            "100",
+            "2",
+            "--type",
+            "f32",
+            "--order",
+            "cdab",
+        ])
+        .unwrap();
+        let Command::Read {
+            table,
+            unit,
+            address,
+            count,
+            typed,
+        } = cli.command
+        else {
+            panic!("{:?}", cli.command);
+        };
+        assert_eq!(
+            (table, unit, address, count),
+            (RegisterType::HoldingRegister, 1, 100, 2)
+        );
+        assert_eq!(typed.data_type(table), DataType::F32);
+        assert_eq!(typed.order, WordOrder::Cdab);
+
+        let cli = Cli::try_parse_from(["modbus-cli", "write", "coil", "1", "5", "on"]).unwrap();
+        assert!(matches!(cli.command, Command::Write { ref values, .. } if values == &["on"]));
+        assert!(Cli::try_parse_from(["modbus-cli", "write", "hr", "1", "5"]).is_err());
+
+        let cli = Cli::try_parse_from([
+            "modbus-cli",
+            "pcap",
+            "a.pcap",
+            "--port",
+            "502",
+            "--port",
+            "1502",
+        ])
+        .unwrap();
+        assert!(matches!(cli.command, Command::Pcap { ref ports, .. } if ports == &[502, 1502]));
+
+        assert_eq!(parse_units("1-10"), Ok(1..=10));
+        assert_eq!(parse_units("7"), Ok(7..=7));
+        assert!(parse_units("1-300").is_err());
+    }
+}
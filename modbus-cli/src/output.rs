@@ -0,0 +1,178 @@
+use std::{fmt::Write, str::FromStr};
+
+use modbus::pdu::{RegisterType, value::Value};
+use serde_json::{Map, Value as Json};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Table,
+    /// One JSON array of objects per output
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {s:?}, expected table, json or csv")),
+        }
+    }
+}
+
+/// Name of the table, as accepted by `RegisterType::from_str`
+pub fn table_name(register_type: RegisterType) -> &'static str {
+    match register_type {
+        RegisterType::Coil => "coil",
+        RegisterType::DiscreteInput => "discrete_input",
+        RegisterType::InputRegister => "input_register",
+        RegisterType::HoldingRegister => "holding_register",
+    }
+}
+
+/// JSON number or bool of the value. NaN and infinite floats have no JSON form and
+/// become `null`.
+pub fn value_to_json(value: Value) -> Json {
+    match value {
+        Value::Bool(value) => Json::Bool(value),
+        Value::U16(value) => value.into(),
+        Value::I16(value) => value.into(),
+        Value::U32(value) => value.into(),
+        Value::I32(value) => value.into(),
+        Value::F32(value) => {
+            serde_json::Number::from_f64(value as f64).map_or(Json::Null, Json::Number)
+        }
+        Value::U64(value) => value.into(),
+        Value::I64(value) => value.into(),
+        Value::F64(value) => serde_json::Number::from_f64(value).map_or(Json::Null, Json::Number),
+    }
+}
+
+/// Text of a cell in tables and CSV: strings without quotes, `null` as nothing
+fn cell_text(cell: &Json) -> String {
+    match cell {
+        Json::Null => String::new(),
+        Json::String(s) => s.clone(),
+        cell => cell.to_string(),
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Renders the rows, each having a cell per column, ending with a newline
+pub fn render(format: Format, columns: &[&str], rows: &[Vec<Json>]) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Table => {
+            let texts: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| row.iter().map(cell_text).collect())
+                .collect();
+            let widths: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    texts
+                        .iter()
+                        .map(|row| row[i].len())
+                        .fold(column.len(), usize::max)
+                })
+                .collect();
+
+            let mut write_line = |cells: &mut dyn Iterator<Item = &str>| {
+                let line = cells
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{cell:<width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                writeln!(out, "{}", line.trim_end()).unwrap();
+            };
+            write_line(&mut columns.iter().copied());
+            for row in &texts {
+                write_line(&mut row.iter().map(String::as_str));
+            }
+        }
+        Format::Json => {
+            let objects: Vec<Json> = rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Json> = columns
+                        .iter()
+                        .map(|column| column.to_string())
+                        .zip(row.iter().cloned())
+                        .collect();
+                    Json::Object(object)
+                })
+                .collect();
+            writeln!(out, "{}", Json::Array(objects)).unwrap();
+        }
+        Format::Csv => {
+            writeln!(out, "{}", columns.join(",")).unwrap();
+            for row in rows {
+                let fields: Vec<String> =
+                    row.iter().map(|cell| csv_field(&cell_text(cell))).collect();
+                writeln!(out, "{}", fields.join(",")).unwrap();
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use modbus::pdu::value::Value;
+    use serde_json::json;
+
+    use super::{Format, render, value_to_json};
+
+    #[test]
+    fn renders_formats() {
+        let columns = ["unit", "table", "address", "value"];
+        let rows = vec![
+            vec![
+                json!(1),
+                json!("holding_register"),
+                json!(0),
+                value_to_json(Value::F32(1.5)),
+            ],
+            vec![
+                json!(1),
+                json!("holding_register"),
+                json!(2),
+                value_to_json(Value::F32(f32::NAN)),
+            ],
+            vec![json!(12), json!("coil"), json!(100), json!("a, \"b\"")],
+        ];
+
+        assert_eq!(
+            render(Format::Table, &columns, &rows),
+            "unit  table             address  value\n\
+             1     holding_register  0        1.5\n\
+             1     holding_register  2\n\
+             12    coil              100      a, \"b\"\n"
+        );
+        assert_eq!(
+            render(Format::Csv, &columns, &rows),
+            "unit,table,address,value\n\
+             1,holding_register,0,1.5\n\
+             1,holding_register,2,\n\
+             12,coil,100,\"a, \"\"b\"\"\"\n"
+        );
+        assert_eq!(
+            render(Format::Json, &columns, &rows[..2]),
+            "[{\"address\":0,\"table\":\"holding_register\",\"unit\":1,\"value\":1.5},\
+             {\"address\":2,\"table\":\"holding_register\",\"unit\":1,\"value\":null}]\n"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::{net::TcpListener, path::Path};
+
+use modbus::{
+    adu::Framing,
+    transport::{
+        record::Recording,
+        replay::{Mismatch, Replay, ReplayConfig},
+        serial::{DataBits, SerialPort},
+    },
+};
+
+use crate::target::SerialOptions;
+
+/// Serves a recording until interrupted, printing the requests that aren't in it.
+///
+/// TCP recordings are served on `listen`, one connection at a time. Serial ones are
+/// served on `device`, or a new pty whose path is printed.
+pub fn serve(
+    recording: &Recording,
+    timing: bool,
+    listen: &str,
+    device: Option<&Path>,
+    serial: &SerialOptions,
+) -> Result<(), String> {
+    let data_bits = match recording.framing {
+        Framing::Ascii => DataBits::Seven,
+        Framing::Tcp | Framing::Rtu => DataBits::Eight,
+    };
+    let serial_config = serial.config(data_bits);
+    let config = ReplayConfig {
+        timing,
+        frame_gap: serial_config.frame_gap(),
+    };
+    let mut replay = Replay::new(recording, config);
+
+    if recording.framing == Framing::Tcp {
+        let listener = TcpListener::bind(listen).map_err(|err| format!("{listen}: {err}"))?;
+        println!("replaying on {listen}");
+        for stream in listener.incoming() {
+            let stream = stream.map_err(|err| err.to_string())?;
+            let peer = stream.peer_addr().ok();
+            if let Err(err) = replay.serve_with(stream, report) {
+                eprintln!("connection {peer:?}: {err}");
+            }
+        }
+        return Ok(());
+    }
+
+    let (port, _slave) = match device {
+        Some(path) => {
+            let port =
+                SerialPort::open(path, serial_config).map_err(|err| format!("{path:?}: {err}"))?;
+            (port, None)
+        }
+        None => {
+            let (master, slave, path) =
+                SerialPort::open_pty_pair(serial_config).map_err(|err| err.to_string())?;
+            println!("replaying on {}", path.display());
+            (master, Some(slave))
+        }
+    };
+    replay
+        .serve_with(port, report)
+        .map_err(|err| err.to_string())
+}
+
+fn report(mismatch: &Mismatch) {
+    println!("{mismatch}");
+}
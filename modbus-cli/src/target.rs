@@ -0,0 +1,206 @@
+use std::{
+    fmt,
+    fs::File,
+    io,
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+
+use modbus::{
+    adu::Framing,
+    client::{
+        Client,
+        ascii::AsciiClient,
+        rtu::{RtuClient, RtuConfig},
+        tcp::{TcpClient, TcpConfig},
+    },
+    transport::{
+        Transport,
+        record::{Recorder, Side},
+        serial::{DataBits, Parity, SerialConfig, SerialPort, StopBits},
+    },
+};
+
+const DEFAULT_TCP_PORT: u16 = 502;
+
+/// Device to talk to: `tcp://host:port` (or `host:port`, the port defaulting to 502),
+/// `rtu:///dev/ttyUSB0` or `ascii:///dev/ttyUSB0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Tcp(String),
+    Rtu(String),
+    Ascii(String),
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = s.split_once("://").unwrap_or(("tcp", s));
+        if rest.is_empty() {
+            return Err(format!("missing address in target {s:?}"));
+        }
+        match scheme {
+            "tcp"
+                if rest
+                    .rsplit_once(':')
+                    .is_some_and(|(_, port)| !port.contains(']')) =>
+            {
+                Ok(Target::Tcp(rest.to_string()))
+            }
+            "tcp" => Ok(Target::Tcp(format!("{rest}:{DEFAULT_TCP_PORT}"))),
+            "rtu" => Ok(Target::Rtu(rest.to_string())),
+            "ascii" => Ok(Target::Ascii(rest.to_string())),
+            _ => Err(format!(
+                "unknown scheme {scheme:?}, expected tcp, rtu or ascii"
+            )),
+        }
+    }
+}
+
+impl Target {
+    pub fn framing(&self) -> Framing {
+        match self {
+            Target::Tcp(_) => Framing::Tcp,
+            Target::Rtu(_) => Framing::Rtu,
+            Target::Ascii(_) => Framing::Ascii,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Tcp(addr) => write!(f, "tcp://{addr}"),
+            Target::Rtu(path) => write!(f, "rtu://{path}"),
+            Target::Ascii(path) => write!(f, "ascii://{path}"),
+        }
+    }
+}
+
+/// Line settings of serial targets. `data_bits` defaults to 8 for RTU and 7 for ASCII.
+#[derive(Debug, Clone, Copy)]
+pub struct SerialOptions {
+    pub baud_rate: u32,
+    pub parity: Parity,
+    pub data_bits: Option<DataBits>,
+    pub stop_bits: StopBits,
+}
+
+impl Default for SerialOptions {
+    fn default() -> Self {
+        Self {
+            baud_rate: 19200,
+            parity: Parity::Even,
+            data_bits: None,
+            stop_bits: StopBits::One,
+        }
+    }
+}
+
+impl SerialOptions {
+    pub fn config(&self, default_data_bits: DataBits) -> SerialConfig {
+        SerialConfig {
+            parity: self.parity,
+            data_bits: self.data_bits.unwrap_or(default_data_bits),
+            stop_bits: self.stop_bits,
+            ..SerialConfig::new(self.baud_rate)
+        }
+    }
+}
+
+/// Opens the target, waiting at most `timeout` for each response. With `record`, the
+/// traffic is recorded to that file.
+pub fn connect(
+    target: &Target,
+    serial: &SerialOptions,
+    timeout: Duration,
+    record: Option<&Path>,
+) -> io::Result<Box<dyn Client>> {
+    match target {
+        Target::Tcp(addr) => {
+            let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("no address for {addr}"))
+            })?;
+            let stream = TcpStream::connect_timeout(&addr, timeout)?;
+            stream.set_nodelay(true)?;
+            client(target, stream, timeout, record)
+        }
+        Target::Rtu(path) => {
+            let port = SerialPort::open(path, serial.config(DataBits::Eight))?;
+            client(target, port, timeout, record)
+        }
+        Target::Ascii(path) => {
+            let port = SerialPort::open(path, serial.config(DataBits::Seven))?;
+            client(target, port, timeout, record)
+        }
+    }
+}
+
+fn client<T: Transport + 'static>(
+    target: &Target,
+    transport: T,
+    timeout: Duration,
+    record: Option<&Path>,
+) -> io::Result<Box<dyn Client>> {
+    let Some(record) = record else {
+        return Ok(client_on(target, transport, timeout));
+    };
+    let log = File::create(record)?;
+    let recorder = Recorder::new(transport, log, target.framing(), Side::Client)?;
+    Ok(client_on(target, recorder, timeout))
+}
+
+fn client_on<T: Transport + 'static>(
+    target: &Target,
+    transport: T,
+    timeout: Duration,
+) -> Box<dyn Client> {
+    let config = RtuConfig {
+        response_timeout: timeout,
+        ..Default::default()
+    };
+    match target {
+        Target::Tcp(_) => {
+            let config = TcpConfig {
+                response_timeout: timeout,
+            };
+            Box::new(TcpClient::new(transport, config))
+        }
+        Target::Rtu(_) => Box::new(RtuClient::new(transport, config)),
+        Target::Ascii(_) => Box::new(AsciiClient::new(transport, config)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Target;
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!(
+            "tcp://10.0.0.1:5020".parse(),
+            Ok(Target::Tcp("10.0.0.1:5020".to_string()))
+        );
+        assert_eq!(
+            "10.0.0.1".parse(),
+            Ok(Target::Tcp("10.0.0.1:502".to_string()))
+        );
+        assert_eq!(
+            "localhost:1502".parse(),
+            Ok(Target::Tcp("localhost:1502".to_string()))
+        );
+        assert_eq!(
+            "rtu:///dev/ttyUSB0".parse(),
+            Ok(Target::Rtu("/dev/ttyUSB0".to_string()))
+        );
+        assert_eq!(
+            "ascii:///dev/ttyS1".parse(),
+            Ok(Target::Ascii("/dev/ttyS1".to_string()))
+        );
+        assert!("udp://10.0.0.1".parse::<Target>().is_err());
+        assert!("rtu://".parse::<Target>().is_err());
+    }
+}
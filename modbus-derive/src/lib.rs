@@ -0,0 +1,248 @@
+//! `#[derive(ModbusRegisters)]` for structs stored in a block of registers
+//!
+//! ```ignore
+//! #[derive(ModbusRegisters)]
+//! struct Meter {
+//!     #[modbus(addr = 0, ty = "f32", order = "cdab")]
+//!     voltage: f32,
+//!     #[modbus(addr = 2)]
+//!     status: u16,
+//! }
+//! ```
+//!
+//! `addr` is the offset of the field from the start of the block. `ty` defaults to the
+//! type of the field and `order` to `abcd`. Blocks can span up to the 125 registers of
+//! one read, but `write_request` fails above the 123 of one write.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Error, Field, Fields, Ident, LitInt, LitStr, Type, parse_macro_input,
+};
+
+/// Max quantity of one read request
+const MAX_SPAN: usize = 0x7d;
+
+#[proc_macro_derive(ModbusRegisters, attributes(modbus))]
+pub fn derive_modbus_registers(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct RegisterField {
+    ident: Ident,
+    addr: usize,
+    ty: &'static str,
+    order: &'static str,
+    /// Whether the Rust type differs from `ty`
+    cast: bool,
+}
+
+const TYPES: [(&str, &str, usize); 9] = [
+    ("bool", "Bool", 1),
+    ("u16", "U16", 1),
+    ("i16", "I16", 1),
+    ("u32", "U32", 2),
+    ("i32", "I32", 2),
+    ("f32", "F32", 2),
+    ("u64", "U64", 4),
+    ("i64", "I64", 4),
+    ("f64", "F64", 4),
+];
+
+const ORDERS: [(&str, &str); 4] = [
+    ("abcd", "Abcd"),
+    ("cdab", "Cdab"),
+    ("badc", "Badc"),
+    ("dcba", "Dcba"),
+];
+
+impl RegisterField {
+    fn quantity(&self) -> usize {
+        TYPES.iter().find(|(ty, _, _)| *ty == self.ty).unwrap().2
+    }
+
+    fn variant(&self) -> Ident {
+        let variant = TYPES.iter().find(|(ty, _, _)| *ty == self.ty).unwrap().1;
+        Ident::new(variant, Span::call_site())
+    }
+
+    fn order(&self) -> Ident {
+        let order = ORDERS
+            .iter()
+            .find(|(order, _)| *order == self.order)
+            .unwrap()
+            .1;
+        Ident::new(order, Span::call_site())
+    }
+}
+
+fn primitive(ty: &Type) -> Option<&'static str> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let ident = path.path.get_ident()?.to_string();
+    TYPES.iter().map(|(ty, _, _)| *ty).find(|ty| *ty == ident)
+}
+
+fn parse_field(field: &Field) -> Result<RegisterField, Error> {
+    let ident = field.ident.clone().unwrap();
+    let mut addr = None;
+    let mut ty = None;
+    let mut order = "abcd";
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("modbus"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("addr") {
+                addr = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u16>()? as usize);
+            } else if meta.path.is_ident("ty") {
+                let lit = meta.value()?.parse::<LitStr>()?;
+                ty = Some(
+                    TYPES
+                        .iter()
+                        .map(|(ty, _, _)| *ty)
+                        .find(|ty| *ty == lit.value())
+                        .ok_or_else(|| Error::new_spanned(&lit, "unknown data type"))?,
+                );
+            } else if meta.path.is_ident("order") {
+                let lit = meta.value()?.parse::<LitStr>()?;
+                order = ORDERS
+                    .iter()
+                    .map(|(order, _)| *order)
+                    .find(|order| *order == lit.value())
+                    .ok_or_else(|| Error::new_spanned(&lit, "unknown word order"))?;
+            } else {
+                return Err(meta.error("expected `addr`, `ty` or `order`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let addr = addr.ok_or_else(|| Error::new_spanned(field, "missing `#[modbus(addr = ..)]`"))?;
+    let field_ty = primitive(&field.ty);
+    let ty = ty.or(field_ty).ok_or_else(|| {
+        Error::new_spanned(
+            &field.ty,
+            "missing `ty` for a field of a non primitive type",
+        )
+    })?;
+
+    Ok(RegisterField {
+        ident,
+        addr,
+        ty,
+        order,
+        cast: field_ty != Some(ty),
+    })
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(&input, "expected a struct"));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(Error::new_spanned(
+            &input,
+            "expected a struct with named fields",
+        ));
+    };
+
+    let mut fields: Vec<RegisterField> = vec![];
+    for field in &named.named {
+        let register_field = parse_field(field)?;
+        let (start, end) = (
+            register_field.addr,
+            register_field.addr + register_field.quantity(),
+        );
+        if let Some(other) = fields
+            .iter()
+            .find(|other| other.addr < end && start < other.addr + other.quantity())
+        {
+            return Err(Error::new_spanned(
+                field,
+                format!("`{}` overlaps `{}`", register_field.ident, other.ident),
+            ));
+        }
+        fields.push(register_field);
+    }
+
+    let span = fields
+        .iter()
+        .map(|field| field.addr + field.quantity())
+        .max()
+        .unwrap_or(0);
+    if span > MAX_SPAN {
+        return Err(Error::new_spanned(
+            &input.ident,
+            format!("the fields span {span} registers, more than one read of {MAX_SPAN}"),
+        ));
+    }
+
+    let value = quote!(::modbus::pdu::value::Value);
+    let decode = fields.iter().map(|field| {
+        let RegisterField { ident, addr, .. } = field;
+        let variant = field.variant();
+        let order = field.order();
+        let converted = if field.cast {
+            quote!(value as _)
+        } else {
+            quote!(value)
+        };
+        quote! {
+            #ident: match words.value(
+                #addr,
+                ::modbus::pdu::value::DataType::#variant,
+                ::modbus::pdu::value::WordOrder::#order,
+            )? {
+                #value::#variant(value) => #converted,
+                _ => return ::core::option::Option::None,
+            }
+        }
+    });
+    let encode = fields.iter().map(|field| {
+        let RegisterField {
+            ident, addr, ty, ..
+        } = field;
+        let variant = field.variant();
+        let order = field.order();
+        let ty = Ident::new(ty, Span::call_site());
+        let converted = if field.cast {
+            quote!(self.#ident as #ty)
+        } else {
+            quote!(self.#ident)
+        };
+        quote! {
+            #value::#variant(#converted)
+                .encode_words(::modbus::pdu::value::WordOrder::#order, &mut words[#addr..]);
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[allow(clippy::unnecessary_cast)]
+        impl #impl_generics ::modbus::pdu::value::ModbusRegisters for #name #ty_generics #where_clause {
+            const SPAN: usize = #span;
+
+            fn decode(words: &::modbus::pdu::DataWords<'_>) -> ::core::option::Option<Self> {
+                if words.quantity() < #span {
+                    return ::core::option::Option::None;
+                }
+                ::core::option::Option::Some(Self {
+                    #(#decode,)*
+                })
+            }
+
+            fn encode(&self, words: &mut [u16]) {
+                #(#encode)*
+            }
+        }
+    })
+}
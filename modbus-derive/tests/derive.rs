@@ -0,0 +1,94 @@
+use modbus::{
+    error::EncodeError,
+    pdu::{DataWords, request::Request, value::ModbusRegisters},
+};
+use modbus_derive::ModbusRegisters;
+
+#[derive(Debug, PartialEq, ModbusRegisters)]
+struct Meter {
+    #[modbus(addr = 0, ty = "f32", order = "cdab")]
+    voltage: f32,
+    #[modbus(addr = 2)]
+    status: u16,
+    #[modbus(addr = 4, ty = "i32")]
+    energy: i64,
+    #[modbus(addr = 6, ty = "bool")]
+    alarm: bool,
+}
+
+#[test]
+fn decodes_and_encodes() {
+    assert_eq!(Meter::SPAN, 7);
+
+    let meter = Meter {
+        voltage: 230.5,
+        status: 3,
+        energy: -12,
+        alarm: true,
+    };
+    let mut words = [0xffff_u16; 7];
+    meter.encode(&mut words);
+    assert_eq!(words, [0x8000, 0x4366, 3, 0xffff, 0xffff, 0xfff4, 1]);
+
+    let mut buf = [0_u8; 14];
+    let data_words = DataWords::from_words(&words, &mut buf);
+    assert_eq!(Meter::decode(&data_words), Some(meter));
+    assert_eq!(Meter::decode(&DataWords::new(&buf[..12], 6)), None);
+}
+
+#[test]
+fn write_request() {
+    let meter = Meter {
+        voltage: 0.0,
+        status: 1,
+        energy: 2,
+        alarm: false,
+    };
+    let mut buf = [0_u8; 14];
+    let req = meter.write_request(100, &mut buf).unwrap();
+    let Request::WriteMultipleRegisters(address, words) = req else {
+        panic!("unexpected request {req:?}");
+    };
+    assert_eq!(address, 100);
+    assert_eq!(Vec::from(words), [0, 0, 1, 0, 0, 2, 0]);
+}
+
+#[derive(Debug, PartialEq, ModbusRegisters)]
+struct Large {
+    #[modbus(addr = 0)]
+    first: u16,
+    #[modbus(addr = 122, ty = "u32")]
+    last: u32,
+}
+
+#[test]
+fn block_larger_than_one_write() {
+    assert_eq!(Large::SPAN, 124);
+
+    let words: Vec<u16> = (0..124).collect();
+    let mut buf = [0_u8; 248];
+    let data_words = DataWords::from_words(&words, &mut buf);
+    let large = Large::decode(&data_words).unwrap();
+    assert_eq!(
+        large,
+        Large {
+            first: 0,
+            last: 0x007a_007b
+        }
+    );
+
+    let mut buf = [0_u8; 256];
+    assert_eq!(
+        large.write_request(0, &mut buf),
+        Err(EncodeError::InvalidQuantity {
+            quantity: 124,
+            max: 123
+        })
+    );
+}
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,20 @@
+use modbus_derive::ModbusRegisters;
+
+#[derive(ModbusRegisters)]
+struct Meter {
+    #[modbus(addr = 0, ty = "f16")]
+    voltage: f32,
+}
+
+#[derive(ModbusRegisters)]
+struct Name {
+    #[modbus(addr = 0)]
+    name: [u8; 4],
+}
+
+#[derive(ModbusRegisters)]
+struct Missing {
+    status: u16,
+}
+
+fn main() {}
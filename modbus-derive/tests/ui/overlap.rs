@@ -0,0 +1,11 @@
+use modbus_derive::ModbusRegisters;
+
+#[derive(ModbusRegisters)]
+struct Meter {
+    #[modbus(addr = 0, ty = "u32")]
+    energy: u32,
+    #[modbus(addr = 1)]
+    status: u16,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use modbus_derive::ModbusRegisters;
+
+#[derive(ModbusRegisters)]
+struct Block {
+    #[modbus(addr = 0)]
+    first: u16,
+    #[modbus(addr = 124, ty = "u32")]
+    last: u32,
+}
+
+fn main() {}
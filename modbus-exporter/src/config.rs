@@ -0,0 +1,189 @@
+//! Exporter config, read from TOML:
+//!
+//! ```toml
+//! listen = "127.0.0.1:9502"
+//!
+//! [[devices]]
+//! name = "meter-1"
+//! target = "10.0.0.5:502"
+//! unit = 1
+//! profile = "meter.toml"
+//! interval_ms = 5000
+//! timeout_ms = 1000
+//! ```
+//!
+//! Profile paths are relative to the config file.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Address of the HTTP endpoint serving `/metrics`
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    #[serde(default)]
+    pub devices: Vec<DeviceConfig>,
+}
+
+/// Device polled over Modbus TCP
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    /// Value of the `device` label of its metrics
+    pub name: String,
+    /// `host:port`
+    pub target: String,
+    #[serde(default = "default_unit")]
+    pub unit: u8,
+    /// TOML or JSON profile of the points
+    pub profile: PathBuf,
+    /// Milliseconds between the start of two polls
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// Response timeout in milliseconds, also used to connect
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:9502".to_string()
+}
+fn default_unit() -> u8 {
+    1
+}
+fn default_interval_ms() -> u64 {
+    10_000
+}
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    DuplicateDevice(String),
+    /// The interval or the timeout of the device is 0
+    InvalidTiming(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{err}"),
+            ConfigError::Toml(err) => write!(f, "{err}"),
+            ConfigError::DuplicateDevice(name) => write!(f, "duplicate device `{name}`"),
+            ConfigError::InvalidTiming(name) => {
+                write!(f, "device `{name}`: interval and timeout must be positive")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(s).map_err(ConfigError::Toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads the config, making its profile paths relative to its directory
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let mut config = Self::from_toml(&s)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for device in &mut config.devices {
+            device.profile = dir.join(&device.profile);
+        }
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        for (i, device) in self.devices.iter().enumerate() {
+            if self.devices[..i]
+                .iter()
+                .any(|other| other.name == device.name)
+            {
+                return Err(ConfigError::DuplicateDevice(device.name.clone()));
+            }
+            if device.interval_ms == 0 || device.timeout_ms == 0 {
+                return Err(ConfigError::InvalidTiming(device.name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Config, ConfigError, DeviceConfig};
+
+    #[test]
+    fn parses_config() {
+        let config = Config::from_toml(
+            r#"
+            [[devices]]
+            name = "meter-1"
+            target = "10.0.0.5:502"
+            profile = "meter.toml"
+
+            [[devices]]
+            name = "meter-2"
+            target = "10.0.0.6:502"
+            unit = 3
+            profile = "/etc/modbus/meter.json"
+            interval_ms = 500
+            timeout_ms = 200
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.listen, "127.0.0.1:9502");
+        assert_eq!(
+            config.devices,
+            vec![
+                DeviceConfig {
+                    name: "meter-1".to_string(),
+                    target: "10.0.0.5:502".to_string(),
+                    unit: 1,
+                    profile: PathBuf::from("meter.toml"),
+                    interval_ms: 10_000,
+                    timeout_ms: 1000,
+                },
+                DeviceConfig {
+                    name: "meter-2".to_string(),
+                    target: "10.0.0.6:502".to_string(),
+                    unit: 3,
+                    profile: PathBuf::from("/etc/modbus/meter.json"),
+                    interval_ms: 500,
+                    timeout_ms: 200,
+                },
+            ]
+        );
+
+        let device = r#"
+            [[devices]]
+            name = "meter"
+            target = "10.0.0.5:502"
+            profile = "meter.toml"
+        "#;
+        assert!(matches!(
+            Config::from_toml(&device.repeat(2)),
+            Err(ConfigError::DuplicateDevice(name)) if name == "meter"
+        ));
+        assert!(matches!(
+            Config::from_toml(&format!("{device}interval_ms = 0")),
+            Err(ConfigError::InvalidTiming(_))
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+//! Polling of a device, publishing its points and health as gauges:
+//!
+//! - `modbus_point{device, unit, point}`, engineering value of each readable point
+//!   of the profile, NaN when its last read failed
+//! - `modbus_device_up{device, unit}`, 1 if the last poll got a response to every
+//!   request, exception responses included
+//! - `modbus_device_last_error_code{device, unit}`, exception code of the first
+//!   exception response of the last poll, 0 if there was none and -1 for any other
+//!   error
+//! - `modbus_device_scrape_duration_seconds{device, unit}`, duration of the last poll
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use modbus::{
+    client::{
+        Client, Error,
+        coalesce::PlanError,
+        tcp::{TcpClient, TcpConfig},
+    },
+    profile::{Profile, ProfileReader},
+};
+
+use crate::config::DeviceConfig;
+
+#[derive(Debug)]
+pub struct Device {
+    name: String,
+    unit: u8,
+    reader: ProfileReader,
+}
+
+impl Device {
+    /// Plans the reads of the readable points of the profile
+    pub fn new(name: String, unit: u8, profile: Profile) -> Result<Self, PlanError> {
+        Ok(Self {
+            name,
+            unit,
+            reader: ProfileReader::new(profile)?,
+        })
+    }
+
+    /// Reads every point and updates the gauges. Returns false on an I/O error, after
+    /// which the connection should be reopened.
+    pub fn poll<C: Client>(&self, client: &mut C) -> bool {
+        let start = Instant::now();
+        let readings = self.reader.read(client, self.unit);
+
+        for (point, raw) in &readings.values {
+            let value = match raw {
+                Some(raw) => point.to_engineering(*raw),
+                None => f64::NAN,
+            };
+            metrics::gauge!(
+                "modbus_point",
+                "device" => self.name.clone(),
+                "unit" => self.unit.to_string(),
+                "point" => point.name.clone()
+            )
+            .set(value);
+        }
+
+        let mut up = true;
+        let mut error_code = None;
+        for err in readings.errors() {
+            match err {
+                Error::Exception(_, exception_code) => {
+                    error_code.get_or_insert(u8::from(*exception_code) as f64);
+                }
+                _ => {
+                    up = false;
+                    error_code.get_or_insert(-1.0);
+                }
+            }
+        }
+        self.report(up, error_code.unwrap_or(0.0), start.elapsed());
+        !readings.is_disconnected()
+    }
+
+    /// Reports the device as down, when it can't be connected to
+    pub fn report_down(&self) {
+        self.report(false, -1.0, Duration::ZERO);
+    }
+
+    fn report(&self, up: bool, error_code: f64, duration: Duration) {
+        let labels = [
+            ("device", self.name.clone()),
+            ("unit", self.unit.to_string()),
+        ];
+        metrics::gauge!("modbus_device_up", &labels).set(if up { 1.0 } else { 0.0 });
+        metrics::gauge!("modbus_device_last_error_code", &labels).set(error_code);
+        metrics::gauge!("modbus_device_scrape_duration_seconds", &labels)
+            .set(duration.as_secs_f64());
+    }
+
+    /// Polls the device every interval of the config, reconnecting as needed
+    pub fn run(&self, config: &DeviceConfig) -> ! {
+        let interval = Duration::from_millis(config.interval_ms);
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let mut client = None;
+
+        loop {
+            let start = Instant::now();
+            if client.is_none() {
+                let tcp_config = TcpConfig {
+                    response_timeout: timeout,
+                };
+                match TcpClient::connect(&config.target, timeout, tcp_config) {
+                    Ok(connected) => client = Some(connected),
+                    Err(err) => {
+                        eprintln!("{}: {}: {err}", self.name, config.target);
+                        self.report_down();
+                    }
+                }
+            }
+            if let Some(connected) = &mut client
+                && !self.poll(connected)
+            {
+                client = None;
+            }
+            thread::sleep(interval.saturating_sub(start.elapsed()));
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        net::{SocketAddr, TcpListener, TcpStream},
+        thread,
+    };
+
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use modbus::{
+        client::tcp::{TcpClient, TcpConfig},
+        pdu::RegisterType,
+        profile::Profile,
+        server::{registers::RegisterMap, tcp::serve_connection},
+    };
+
+    use super::Device;
+
+    /// Serves one connection to the registers from another thread
+    pub(crate) fn serve(mut registers: RegisterMap) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve_connection(stream, &mut registers).unwrap();
+        });
+        addr
+    }
+
+    pub(crate) fn meter() -> Device {
+        let profile = Profile::from_toml(
+            r#"
+            name = "Meter"
+
+            [[points]]
+            name = "voltage"
+            register_type = "input_register"
+            address = 0
+            data_type = "u16"
+            scale = 0.1
+
+            [[points]]
+            name = "relay"
+            register_type = "coil"
+            address = 0
+            data_type = "bool"
+
+            [[points]]
+            name = "energy"
+            register_type = "holding_register"
+            address = 100
+            data_type = "u32"
+
+            [[points]]
+            name = "setpoint"
+            register_type = "holding_register"
+            address = 10
+            data_type = "u16"
+            access = "write"
+            "#,
+        )
+        .unwrap();
+        Device::new("meter".to_string(), 1, profile).unwrap()
+    }
+
+    #[test]
+    fn polls_points() {
+        let mut registers = RegisterMap::new();
+        registers.set(RegisterType::InputRegister, 0, 2305);
+        registers.set(RegisterType::Coil, 0, 1);
+        // The energy is missing, so reading it answers IllegalDataAddress
+        let addr = serve(registers);
+
+        let device = meter();
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let mut client = TcpClient::new(TcpStream::connect(addr).unwrap(), TcpConfig::default());
+        metrics::with_local_recorder(&recorder, || assert!(device.poll(&mut client)));
+
+        let rendered = recorder.handle().render();
+        for line in [
+            r#"modbus_point{device="meter",unit="1",point="voltage"} 230.5"#,
+            r#"modbus_point{device="meter",unit="1",point="relay"} 1"#,
+            r#"modbus_point{device="meter",unit="1",point="energy"} NaN"#,
+            r#"modbus_device_up{device="meter",unit="1"} 1"#,
+            r#"modbus_device_last_error_code{device="meter",unit="1"} 2"#,
+        ] {
+            assert!(rendered.contains(line), "{line} not in {rendered}");
+        }
+        assert!(!rendered.contains("setpoint"));
+    }
+
+    #[test]
+    fn closed_connection_is_down() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || drop(listener.accept().unwrap()));
+
+        let device = meter();
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let mut client = TcpClient::new(TcpStream::connect(addr).unwrap(), TcpConfig::default());
+        metrics::with_local_recorder(&recorder, || assert!(!device.poll(&mut client)));
+
+        let rendered = recorder.handle().render();
+        for line in [
+            r#"modbus_point{device="meter",unit="1",point="voltage"} NaN"#,
+            r#"modbus_device_up{device="meter",unit="1"} 0"#,
+            r#"modbus_device_last_error_code{device="meter",unit="1"} -1"#,
+        ] {
+            assert!(rendered.contains(line), "{line} not in {rendered}");
+        }
+    }
+}
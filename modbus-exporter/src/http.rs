@@ -0,0 +1,75 @@
+use metrics_exporter_prometheus::PrometheusHandle;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Answers `GET /metrics` with the metrics in the Prometheus text format, and
+/// anything else with 404
+pub fn serve(server: &Server, handle: &PrometheusHandle) {
+    for request in server.incoming_requests() {
+        let path = request.url().split('?').next().unwrap_or_default();
+        let res = if *request.method() == Method::Get && path == "/metrics" {
+            let content_type =
+                Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+            request.respond(Response::from_string(handle.render()).with_header(content_type))
+        } else {
+            request.respond(Response::empty(404))
+        };
+        if let Err(err) = res {
+            eprintln!("HTTP response: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+        thread,
+    };
+
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use modbus::{
+        client::tcp::{TcpClient, TcpConfig},
+        pdu::RegisterType,
+        server::registers::RegisterMap,
+    };
+    use tiny_http::Server;
+
+    use crate::device::test::{meter, serve};
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.0\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        res
+    }
+
+    #[test]
+    fn scrapes_metrics() {
+        let mut registers = RegisterMap::new();
+        registers.set(RegisterType::InputRegister, 0, 2305);
+        let modbus_addr = serve(registers);
+
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let device = meter();
+        let stream = TcpStream::connect(modbus_addr).unwrap();
+        let mut client = TcpClient::new(stream, TcpConfig::default());
+        metrics::with_local_recorder(&recorder, || device.poll(&mut client));
+
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        thread::spawn(move || super::serve(&server, &handle));
+
+        let res = get(addr, "/metrics");
+        assert!(res.starts_with("HTTP/1.0 200"), "{res}");
+        assert!(res.contains("text/plain; version=0.0.4"), "{res}");
+        assert!(res.contains(r#"modbus_point{device="meter",unit="1",point="voltage"} 230.5"#));
+        assert!(res.contains(r#"modbus_device_up{device="meter",unit="1"} 1"#));
+        // Requests counted by the client
+        assert!(res.contains("modbus_requests_total{"));
+
+        assert!(get(addr, "/").starts_with("HTTP/1.0 404"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Prometheus exporter polling the points of Modbus TCP devices, see [`config`] for
+//! its format and [`device`] for the metrics:
+//!
+//! ```text
+//! modbus-exporter exporter.toml
+//! modbus-exporter exporter.toml --listen 0.0.0.0:9502
+//! curl http://127.0.0.1:9502/metrics
+//! ```
+
+mod config;
+mod device;
+mod http;
+
+use std::{path::PathBuf, process::ExitCode, thread};
+
+use clap::Parser;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use modbus::profile::Profile;
+use tiny_http::Server;
+
+use crate::{config::Config, device::Device};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Exports the points of Modbus devices to Prometheus")]
+struct Cli {
+    /// TOML config of the devices
+    config: PathBuf,
+    /// Address to serve the metrics on, instead of the one of the config
+    #[arg(long)]
+    listen: Option<String>,
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let mut config = Config::load(&cli.config).map_err(|err| format!("{:?}: {err}", cli.config))?;
+    if let Some(listen) = cli.listen {
+        config.listen = listen;
+    }
+    if config.devices.is_empty() {
+        return Err("no devices to poll".to_string());
+    }
+
+    let mut devices = vec![];
+    for device_config in config.devices {
+        let profile = Profile::load(&device_config.profile)
+            .map_err(|err| format!("{:?}: {err}", device_config.profile))?;
+        let device = Device::new(device_config.name.clone(), device_config.unit, profile)
+            .map_err(|err| format!("{}: {err}", device_config.name))?;
+        devices.push((device, device_config));
+    }
+
+    let handle = PrometheusBuilder::new()
+        .install_recorder()
+        .map_err(|err| err.to_string())?;
+    let server = Server::http(&config.listen).map_err(|err| format!("{}: {err}", config.listen))?;
+    println!("serving metrics on http://{}/metrics", config.listen);
+
+    for (device, device_config) in devices {
+        thread::spawn(move || device.run(&device_config));
+    }
+    http::serve(&server, &handle);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,448 @@
+//! Polling of the devices, publishing their values, and execution of the write
+//! commands received for them, see [`crate::payload`] for the payloads.
+
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use modbus::{
+    client::{
+        Client, Error, RESPONSE_BUF_SIZE,
+        coalesce::PlanError,
+        tcp::{TcpClient, TcpConfig},
+    },
+    profile::{Profile, ProfileReader, Readings},
+};
+use rumqttc::{Event, MqttOptions, Packet, QoS, SubscribeFilter};
+
+use crate::{
+    config::{Config, DeviceConfig, Format},
+    payload::{self, Command, InvalidCommand, Write},
+};
+
+#[derive(Debug)]
+pub struct Device {
+    name: String,
+    unit: u8,
+    reader: ProfileReader,
+}
+
+impl Device {
+    /// Plans the reads of the readable points of the profile
+    pub fn new(name: String, unit: u8, profile: Profile) -> Result<Self, PlanError> {
+        Ok(Self {
+            name,
+            unit,
+            reader: ProfileReader::new(profile)?,
+        })
+    }
+
+    /// Reads every readable point
+    pub fn poll<C: Client>(&self, client: &mut C) -> Readings<'_> {
+        self.reader.read(client, self.unit)
+    }
+
+    /// Executes the write, checking that the response echoes the request
+    pub fn write<C: Client>(&self, client: &mut C, write: &Write) -> Result<(), Error> {
+        let mut words_buf = [0_u8; RESPONSE_BUF_SIZE];
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+        match client.request(self.unit, write.request(&mut words_buf), &mut buf)? {
+            res if res == write.echo() => Ok(()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+}
+
+/// Connects to the MQTT broker and runs the devices, returning only if the connection
+/// to the broker can't be used anymore
+pub fn run(config: &Config, devices: Vec<(Device, DeviceConfig)>) {
+    let mut options = MqttOptions::new(&config.mqtt.client_id, &config.mqtt.host, config.mqtt.port);
+    options.set_keep_alive(Duration::from_secs(config.mqtt.keep_alive_s));
+    let qos = match config.mqtt.qos {
+        0 => QoS::AtMostOnce,
+        _ => QoS::AtLeastOnce,
+    };
+    let (mqtt, mut connection) = rumqttc::Client::new(options, 64);
+
+    let mut routes = HashMap::new();
+    for (device, device_config) in devices {
+        let (commands, received) = mpsc::channel();
+        routes.insert(config.topics.command(&device.name), commands);
+        let runner = Runner {
+            data_topic: config.topics.data(&device.name),
+            ack_topic: config.topics.ack(&device.name),
+            format: config.topics.format,
+            qos,
+            mqtt: mqtt.clone(),
+            seq: 0,
+        };
+        thread::spawn(move || runner.run(&device, &device_config, received));
+    }
+
+    for event in connection.iter() {
+        match event {
+            // Subscriptions don't outlive the session, so they're renewed on every connection
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                let filters = routes
+                    .keys()
+                    .map(|topic| SubscribeFilter::new(topic.clone(), qos));
+                if let Err(err) = mqtt.try_subscribe_many(filters) {
+                    eprintln!("MQTT: {err}");
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                if let Some(commands) = routes.get(&publish.topic) {
+                    let _ = commands.send(publish.payload.to_vec());
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("MQTT: {err}");
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+    }
+}
+
+/// Thread polling a device and executing its commands
+struct Runner {
+    data_topic: String,
+    ack_topic: String,
+    format: Format,
+    qos: QoS,
+    mqtt: rumqttc::Client,
+    /// Sequence number of the Sparkplug payloads
+    seq: u8,
+}
+
+impl Runner {
+    /// Polls the device every interval of the config, reconnecting as needed, and
+    /// executes the commands received in between
+    fn run(mut self, device: &Device, config: &DeviceConfig, commands: Receiver<Vec<u8>>) {
+        let interval = Duration::from_millis(config.interval_ms);
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let mut client = None;
+        let mut next_poll = Instant::now();
+
+        loop {
+            let now = Instant::now();
+            if now >= next_poll {
+                next_poll = now + interval;
+                if client.is_none() {
+                    let tcp_config = TcpConfig {
+                        response_timeout: timeout,
+                    };
+                    match TcpClient::connect(&config.target, timeout, tcp_config) {
+                        Ok(connected) => client = Some(connected),
+                        Err(err) => eprintln!("{}: {}: {err}", config.name, config.target),
+                    }
+                }
+                if let Some(connected) = &mut client {
+                    let readings = device.poll(connected);
+                    let payload =
+                        payload::data(self.format, &readings.values, timestamp_ms(), self.seq);
+                    self.seq = self.seq.wrapping_add(1);
+                    self.publish(&self.data_topic, payload);
+                    if readings.is_disconnected() {
+                        client = None;
+                    }
+                }
+                continue;
+            }
+
+            let command = match commands.recv_timeout(next_poll - now) {
+                Ok(command) => command,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            let ack = match (Command::parse(&command), &mut client) {
+                (Ok(command), Some(connected)) => {
+                    let res = device.write(connected, &command.write);
+                    if let Err(Error::Io(_)) = res {
+                        client = None;
+                    }
+                    payload::ack(&command.id, res.as_ref().copied())
+                }
+                (Ok(command), None) => payload::nack(&InvalidCommand {
+                    id: command.id,
+                    error: format!("not connected to {}", config.target),
+                }),
+                (Err(invalid), _) => payload::nack(&invalid),
+            };
+            self.publish(&self.ack_topic, ack);
+        }
+    }
+
+    fn publish(&self, topic: &str, payload: Vec<u8>) {
+        if let Err(err) = self.mqtt.publish(topic, self.qos, false, payload) {
+            eprintln!("MQTT: {err}");
+        }
+    }
+}
+
+fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{self, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use bytes::BytesMut;
+    use modbus::{
+        client::{Error, loopback::Loopback},
+        exception_code::ExceptionCode,
+        pdu::{RegisterType, request::Request as PduRequest, response::Response as PduResponse},
+        profile::Profile,
+        server::{Handler, registers::RegisterMap, tcp::serve_connection},
+    };
+    use rumqttc::{
+        ConnAck, ConnectReturnCode, Connection, Event, MqttOptions, Packet, PubAck, Publish, QoS,
+        SubAck, SubscribeReasonCode, matches, mqttbytes,
+    };
+    use serde_json::{Value as JsonValue, json};
+
+    use super::{Device, run};
+    use crate::{
+        config::{Config, DeviceConfig, MqttConfig},
+        payload::Write as WriteCommand,
+    };
+
+    type Subscriptions = Arc<Mutex<Vec<(String, Arc<Mutex<TcpStream>>)>>>;
+
+    /// MQTT broker forwarding the publishes to the subscribed clients at QoS 0, without
+    /// retained messages or sessions
+    fn broker() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let subscriptions = Subscriptions::default();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let subscriptions = subscriptions.clone();
+                thread::spawn(move || broker_connection(stream?, subscriptions));
+            }
+            io::Result::Ok(())
+        });
+        port
+    }
+
+    fn broker_connection(mut stream: TcpStream, subscriptions: Subscriptions) -> io::Result<()> {
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+        let mut buf = BytesMut::new();
+        loop {
+            let packet = match Packet::read(&mut buf, 1 << 16) {
+                Ok(packet) => packet,
+                Err(mqttbytes::Error::InsufficientBytes(_)) => {
+                    let mut read_buf = [0; 1024];
+                    let bytes_read = stream.read(&mut read_buf)?;
+                    if bytes_read == 0 {
+                        return Ok(());
+                    }
+                    buf.extend_from_slice(&read_buf[..bytes_read]);
+                    continue;
+                }
+                Err(err) => return Err(io::Error::other(err.to_string())),
+            };
+
+            let reply = match packet {
+                Packet::Connect(_) => {
+                    Packet::ConnAck(ConnAck::new(ConnectReturnCode::Success, false))
+                }
+                Packet::Subscribe(subscribe) => {
+                    let mut subscriptions = subscriptions.lock().unwrap();
+                    for filter in &subscribe.filters {
+                        subscriptions.push((filter.path.clone(), writer.clone()));
+                    }
+                    let codes = vec![
+                        SubscribeReasonCode::Success(QoS::AtMostOnce);
+                        subscribe.filters.len()
+                    ];
+                    Packet::SubAck(SubAck::new(subscribe.pkid, codes))
+                }
+                Packet::Publish(publish) => {
+                    for (filter, subscriber) in subscriptions.lock().unwrap().iter() {
+                        if matches(&publish.topic, filter) {
+                            let forwarded = Publish::new(
+                                &publish.topic,
+                                QoS::AtMostOnce,
+                                publish.payload.to_vec(),
+                            );
+                            write_packet(&subscriber.lock().unwrap(), &Packet::Publish(forwarded))?;
+                        }
+                    }
+                    if publish.qos == QoS::AtMostOnce {
+                        continue;
+                    }
+                    Packet::PubAck(PubAck::new(publish.pkid))
+                }
+                Packet::PingReq => Packet::PingResp,
+                Packet::Disconnect => return Ok(()),
+                _ => continue,
+            };
+            write_packet(&writer.lock().unwrap(), &reply)?;
+        }
+    }
+
+    fn write_packet(mut stream: &TcpStream, packet: &Packet) -> io::Result<()> {
+        let mut buf = BytesMut::new();
+        packet
+            .write(&mut buf, 1 << 16)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        stream.write_all(&buf)
+    }
+
+    /// Serves one connection to the registers from another thread
+    fn serve(mut registers: RegisterMap) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve_connection(stream, &mut registers).unwrap();
+        });
+        addr
+    }
+
+    /// Next payload published on the topic within a second
+    fn next_payload(connection: &mut Connection, topic: &str) -> Option<JsonValue> {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if let Ok(Ok(Event::Incoming(Packet::Publish(publish)))) =
+                connection.recv_timeout(remaining)
+                && publish.topic == topic
+            {
+                return Some(serde_json::from_slice(&publish.payload).unwrap());
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn bridges_device() {
+        let port = broker();
+        let mut registers = RegisterMap::new();
+        registers.set(RegisterType::InputRegister, 0, 2305);
+        registers.set(RegisterType::HoldingRegister, 10, 5);
+        let modbus_addr = serve(registers);
+
+        let profile = Profile::from_toml(
+            r#"
+            name = "Meter"
+
+            [[points]]
+            name = "voltage"
+            register_type = "input_register"
+            address = 0
+            data_type = "u16"
+            scale = 0.1
+
+            [[points]]
+            name = "limit"
+            register_type = "holding_register"
+            address = 10
+            data_type = "u16"
+            access = "read_write"
+            "#,
+        )
+        .unwrap();
+        let device_config = DeviceConfig {
+            name: "meter".to_string(),
+            target: modbus_addr.to_string(),
+            unit: 1,
+            profile: "meter.toml".into(),
+            interval_ms: 50,
+            timeout_ms: 1000,
+        };
+        let config = Config {
+            mqtt: MqttConfig {
+                host: "127.0.0.1".to_string(),
+                port,
+                ..Default::default()
+            },
+            topics: Default::default(),
+            devices: vec![device_config.clone()],
+        };
+        let device = Device::new("meter".to_string(), 1, profile).unwrap();
+        thread::spawn(move || run(&config, vec![(device, device_config)]));
+
+        let (client, mut connection) =
+            rumqttc::Client::new(MqttOptions::new("test", "127.0.0.1", port), 16);
+        client.subscribe("modbus/meter/+", QoS::AtMostOnce).unwrap();
+        let data = next_payload(&mut connection, "modbus/meter/data").unwrap();
+        assert_eq!(data["values"], json!({"voltage": 230.5, "limit": 5.0}));
+
+        // The bridge may not have subscribed yet, so the command is sent until acknowledged
+        let mut command = |command: JsonValue| {
+            for _ in 0..10 {
+                let payload = command.to_string();
+                client
+                    .publish("modbus/meter/command", QoS::AtMostOnce, false, payload)
+                    .unwrap();
+                if let Some(ack) = next_payload(&mut connection, "modbus/meter/ack") {
+                    return ack;
+                }
+            }
+            panic!("{command} wasn't acknowledged");
+        };
+        let ack = command(
+            json!({"id": 1, "function": "write_single_register", "address": 10, "value": 7}),
+        );
+        assert_eq!(ack, json!({"id": 1, "ok": true}));
+        let ack = command(
+            json!({"id": 2, "function": "write_single_register", "address": 11, "value": 7}),
+        );
+        assert_eq!(ack["exception_code"], json!(2));
+        let ack = command(json!({"id": 3, "function": "read_coils", "address": 0}));
+        assert_eq!(ack["ok"], json!(false));
+
+        let data = next_payload(&mut connection, "modbus/meter/data").unwrap();
+        assert_eq!(data["values"], json!({"voltage": 230.5, "limit": 7.0}));
+    }
+
+    /// Device answering the writes of a register with another value
+    struct WrongEcho;
+
+    impl Handler for WrongEcho {
+        fn handle<'b>(
+            &mut self,
+            _unit_id: u8,
+            req: &PduRequest<'_>,
+            _buf: &'b mut [u8],
+        ) -> Result<PduResponse<'b>, ExceptionCode> {
+            match req {
+                PduRequest::WriteSingleRegister(address, value) => {
+                    Ok(PduResponse::WriteSingleRegister(*address, value + 1))
+                }
+                _ => Err(ExceptionCode::IllegalFunction),
+            }
+        }
+    }
+
+    #[test]
+    fn checks_write_echo() {
+        let profile = Profile::new("Meter".to_string(), vec![]).unwrap();
+        let device = Device::new("meter".to_string(), 1, profile).unwrap();
+        let write = WriteCommand::SingleRegister {
+            address: 10,
+            value: 7,
+        };
+
+        let mut registers = RegisterMap::new();
+        registers.set(RegisterType::HoldingRegister, 10, 0);
+        assert!(device.write(&mut Loopback::new(registers), &write).is_ok());
+        assert!(matches!(
+            device.write(&mut Loopback::new(WrongEcho), &write),
+            Err(Error::UnexpectedResponse)
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+//! Bridge config, read from TOML:
+//!
+//! ```toml
+//! [mqtt]
+//! host = "localhost"
+//! port = 1883
+//! client_id = "modbus-bridge"
+//!
+//! [topics]
+//! data = "modbus/{device}/data"
+//! command = "modbus/{device}/command"
+//! ack = "modbus/{device}/ack"
+//! format = "sparkplug"
+//!
+//! [[devices]]
+//! name = "meter-1"
+//! target = "10.0.0.5:502"
+//! unit = 1
+//! profile = "meter.toml"
+//! interval_ms = 5000
+//! ```
+//!
+//! `{device}` in a topic is replaced by the device name. Profile paths are relative to
+//! the config file.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub topics: TopicsConfig,
+    #[serde(default)]
+    pub devices: Vec<DeviceConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// 0 or 1, QoS 2 isn't supported
+    pub qos: u8,
+    pub keep_alive_s: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "modbus-mqtt".to_string(),
+            qos: 1,
+            keep_alive_s: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TopicsConfig {
+    /// Topic the polled values are published on
+    pub data: String,
+    /// Topic the write commands are received on
+    pub command: String,
+    /// Topic the result of each command is published on
+    pub ack: String,
+    pub format: Format,
+}
+
+impl Default for TopicsConfig {
+    fn default() -> Self {
+        Self {
+            data: "modbus/{device}/data".to_string(),
+            command: "modbus/{device}/command".to_string(),
+            ack: "modbus/{device}/ack".to_string(),
+            format: Format::default(),
+        }
+    }
+}
+
+impl TopicsConfig {
+    pub fn data(&self, device: &str) -> String {
+        self.data.replace("{device}", device)
+    }
+    pub fn command(&self, device: &str) -> String {
+        self.command.replace("{device}", device)
+    }
+    pub fn ack(&self, device: &str) -> String {
+        self.ack.replace("{device}", device)
+    }
+}
+
+/// Payload of the polled values
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// Object of the values by point name
+    #[default]
+    Json,
+    /// JSON with the layout of a Sparkplug B payload
+    Sparkplug,
+}
+
+/// Device polled over Modbus TCP
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    pub name: String,
+    /// `host:port`
+    pub target: String,
+    #[serde(default = "default_unit")]
+    pub unit: u8,
+    /// TOML or JSON profile of the points
+    pub profile: PathBuf,
+    /// Milliseconds between the start of two polls
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// Response timeout in milliseconds, also used to connect
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_unit() -> u8 {
+    1
+}
+fn default_interval_ms() -> u64 {
+    10_000
+}
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    InvalidQos(u8),
+    DuplicateDevice(String),
+    /// The interval or the timeout of the device is 0
+    InvalidTiming(String),
+    /// Several devices have the same command topic
+    DuplicateCommandTopic(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{err}"),
+            ConfigError::Toml(err) => write!(f, "{err}"),
+            ConfigError::InvalidQos(qos) => write!(f, "invalid QoS {qos}, expected 0 or 1"),
+            ConfigError::DuplicateDevice(name) => write!(f, "duplicate device `{name}`"),
+            ConfigError::InvalidTiming(name) => {
+                write!(f, "device `{name}`: interval and timeout must be positive")
+            }
+            ConfigError::DuplicateCommandTopic(topic) => {
+                write!(f, "several devices have the command topic `{topic}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(s).map_err(ConfigError::Toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads the config, making its profile paths relative to its directory
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let mut config = Self::from_toml(&s)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for device in &mut config.devices {
+            device.profile = dir.join(&device.profile);
+        }
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.mqtt.qos > 1 {
+            return Err(ConfigError::InvalidQos(self.mqtt.qos));
+        }
+        for (i, device) in self.devices.iter().enumerate() {
+            let others = &self.devices[..i];
+            if others.iter().any(|other| other.name == device.name) {
+                return Err(ConfigError::DuplicateDevice(device.name.clone()));
+            }
+            let command = self.topics.command(&device.name);
+            if others
+                .iter()
+                .any(|other| self.topics.command(&other.name) == command)
+            {
+                return Err(ConfigError::DuplicateCommandTopic(command));
+            }
+            if device.interval_ms == 0 || device.timeout_ms == 0 {
+                return Err(ConfigError::InvalidTiming(device.name.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Config, ConfigError, DeviceConfig, Format, MqttConfig};
+
+    #[test]
+    fn parses_config() {
+        let config = Config::from_toml(
+            r#"
+            [mqtt]
+            host = "broker"
+            qos = 0
+
+            [topics]
+            data = "site/{device}"
+            format = "sparkplug"
+
+            [[devices]]
+            name = "meter-1"
+            target = "10.0.0.5:502"
+            profile = "meter.toml"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.mqtt,
+            MqttConfig {
+                host: "broker".to_string(),
+                qos: 0,
+                ..Default::default()
+            }
+        );
+        assert_eq!(config.topics.format, Format::Sparkplug);
+        assert_eq!(config.topics.data("meter-1"), "site/meter-1");
+        assert_eq!(config.topics.command("meter-1"), "modbus/meter-1/command");
+        assert_eq!(
+            config.devices,
+            vec![DeviceConfig {
+                name: "meter-1".to_string(),
+                target: "10.0.0.5:502".to_string(),
+                unit: 1,
+                profile: PathBuf::from("meter.toml"),
+                interval_ms: 10_000,
+                timeout_ms: 1000,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        let devices = r#"
+            [[devices]]
+            name = "meter-1"
+            target = "10.0.0.5:502"
+            profile = "meter.toml"
+
+            [[devices]]
+            name = "meter-2"
+            target = "10.0.0.6:502"
+            profile = "meter.toml"
+        "#;
+        assert!(Config::from_toml(devices).is_ok());
+        assert!(matches!(
+            Config::from_toml(&format!("[topics]\ncommand = \"modbus/command\"\n{devices}")),
+            Err(ConfigError::DuplicateCommandTopic(topic)) if topic == "modbus/command"
+        ));
+        assert!(matches!(
+            Config::from_toml(&format!("[mqtt]\nqos = 2\n{devices}")),
+            Err(ConfigError::InvalidQos(2))
+        ));
+        assert!(matches!(
+            Config::from_toml(&devices.replace("meter-2", "meter-1")),
+            Err(ConfigError::DuplicateDevice(name)) if name == "meter-1"
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+//! MQTT bridge publishing the points of Modbus TCP devices and writing them on
+//! command, see [`config`] for its format and [`payload`] for the payloads:
+//!
+//! ```text
+//! modbus-mqtt bridge.toml
+//! modbus-mqtt bridge.toml --host broker.local --port 1883
+//! ```
+
+mod bridge;
+mod config;
+mod payload;
+
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use modbus::profile::Profile;
+
+use crate::{bridge::Device, config::Config};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Bridges Modbus devices to an MQTT broker")]
+struct Cli {
+    /// TOML config of the broker and the devices
+    config: PathBuf,
+    /// Broker host, instead of the one of the config
+    #[arg(long)]
+    host: Option<String>,
+    /// Broker port, instead of the one of the config
+    #[arg(long)]
+    port: Option<u16>,
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let mut config = Config::load(&cli.config).map_err(|err| format!("{:?}: {err}", cli.config))?;
+    if let Some(host) = cli.host {
+        config.mqtt.host = host;
+    }
+    if let Some(port) = cli.port {
+        config.mqtt.port = port;
+    }
+    if config.devices.is_empty() {
+        return Err("no devices to bridge".to_string());
+    }
+
+    let mut devices = vec![];
+    for device_config in &config.devices {
+        let profile = Profile::load(&device_config.profile)
+            .map_err(|err| format!("{:?}: {err}", device_config.profile))?;
+        let device = Device::new(device_config.name.clone(), device_config.unit, profile)
+            .map_err(|err| format!("{}: {err}", device_config.name))?;
+        devices.push((device, device_config.clone()));
+    }
+
+    println!(
+        "bridging {} devices to {}:{}",
+        devices.len(),
+        config.mqtt.host,
+        config.mqtt.port
+    );
+    bridge::run(&config, devices);
+    Err("MQTT connection closed".to_string())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
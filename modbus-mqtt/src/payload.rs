@@ -0,0 +1,316 @@
+//! JSON payloads of the bridge.
+//!
+//! Polled values are published as an object of the engineering values by point name,
+//! `null` when the read failed:
+//!
+//! ```json
+//! {"timestamp": 1700000000000, "values": {"voltage": 230.5, "relay": true}}
+//! ```
+//!
+//! or with the layout of a Sparkplug B payload:
+//!
+//! ```json
+//! {"timestamp": 1700000000000, "seq": 0, "metrics": [
+//!     {"name": "voltage", "timestamp": 1700000000000, "dataType": "Double", "value": 230.5}
+//! ]}
+//! ```
+//!
+//! Commands write registers or coils:
+//!
+//! ```json
+//! {"id": 1, "function": "write_single_register", "address": 10, "value": 3}
+//! {"id": 2, "function": "write_multiple_registers", "address": 10, "values": [1, 2]}
+//! {"id": 3, "function": "write_single_coil", "address": 0, "value": true}
+//! ```
+//!
+//! and are acknowledged with their id, which may be any JSON value:
+//!
+//! ```json
+//! {"id": 1, "ok": true}
+//! {"id": 2, "ok": false, "error": "FC10 WriteMultipleRegisters: exception IllegalDataAddress",
+//!  "exception_code": 2}
+//! ```
+
+use modbus::{
+    client::Error,
+    pdu::{
+        Address, DataWords, MAX_WRITE_REGISTERS, Quantity,
+        request::Request as PduRequest,
+        response::Response as PduResponse,
+        value::{DataType, Value},
+    },
+    profile::Point,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue, json};
+
+use crate::config::Format;
+
+/// Payload of the values read from the points, `None` for the failed reads
+pub fn data(
+    format: Format,
+    readings: &[(&Point, Option<Value>)],
+    timestamp_ms: u64,
+    seq: u8,
+) -> Vec<u8> {
+    let payload = match format {
+        Format::Json => {
+            let values: Map<String, JsonValue> = readings
+                .iter()
+                .map(|(point, raw)| (point.name.clone(), engineering(point, *raw)))
+                .collect();
+            json!({"timestamp": timestamp_ms, "values": values})
+        }
+        Format::Sparkplug => {
+            let metrics: Vec<JsonValue> = readings
+                .iter()
+                .map(|(point, raw)| {
+                    let data_type = match point.data_type {
+                        DataType::Bool => "Boolean",
+                        _ => "Double",
+                    };
+                    let mut metric = json!({
+                        "name": point.name,
+                        "timestamp": timestamp_ms,
+                        "dataType": data_type,
+                    });
+                    match raw {
+                        Some(_) => metric["value"] = engineering(point, *raw),
+                        None => metric["isNull"] = true.into(),
+                    }
+                    metric
+                })
+                .collect();
+            json!({"timestamp": timestamp_ms, "seq": seq, "metrics": metrics})
+        }
+    };
+    payload.to_string().into_bytes()
+}
+
+/// Engineering value, bools staying bools
+fn engineering(point: &Point, raw: Option<Value>) -> JsonValue {
+    match raw {
+        Some(Value::Bool(value)) => value.into(),
+        Some(raw) => point.to_engineering(raw).into(),
+        None => JsonValue::Null,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "function", deny_unknown_fields)]
+pub enum Write {
+    #[serde(rename = "write_single_register")]
+    SingleRegister { address: Address, value: u16 },
+    #[serde(rename = "write_multiple_registers")]
+    MultipleRegisters { address: Address, values: Vec<u16> },
+    #[serde(rename = "write_single_coil")]
+    SingleCoil { address: Address, value: bool },
+}
+
+impl Write {
+    /// Request of the write, `buf` holding the registers of [`Write::MultipleRegisters`]
+    pub fn request<'a>(&self, buf: &'a mut [u8]) -> PduRequest<'a> {
+        match self {
+            Write::SingleRegister { address, value } => {
+                PduRequest::WriteSingleRegister(*address, *value)
+            }
+            Write::MultipleRegisters { address, values } => {
+                PduRequest::WriteMultipleRegisters(*address, DataWords::from_words(values, buf))
+            }
+            Write::SingleCoil { address, value } => PduRequest::WriteSingleCoil(*address, *value),
+        }
+    }
+
+    /// Response of a server executing the write
+    pub fn echo(&self) -> PduResponse<'static> {
+        match self {
+            Write::SingleRegister { address, value } => {
+                PduResponse::WriteSingleRegister(*address, *value)
+            }
+            Write::MultipleRegisters { address, values } => {
+                PduResponse::WriteMultipleRegisters(*address, values.len() as Quantity)
+            }
+            Write::SingleCoil { address, value } => PduResponse::WriteSingleCoil(*address, *value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub id: JsonValue,
+    pub write: Write,
+}
+
+/// Command that can't be executed, still acknowledged with its id if it has one
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidCommand {
+    pub id: JsonValue,
+    pub error: String,
+}
+
+impl Command {
+    pub fn parse(payload: &[u8]) -> Result<Self, InvalidCommand> {
+        let mut command: JsonValue =
+            serde_json::from_slice(payload).map_err(|err| InvalidCommand {
+                id: JsonValue::Null,
+                error: format!("invalid command: {err}"),
+            })?;
+        let id = command
+            .as_object_mut()
+            .and_then(|command| command.remove("id"))
+            .unwrap_or_default();
+        let invalid = |error: String| InvalidCommand {
+            id: id.clone(),
+            error,
+        };
+        let write = Write::deserialize(command)
+            .map_err(|err| invalid(format!("invalid command: {err}")))?;
+        if let Write::MultipleRegisters { values, .. } = &write
+            && (values.is_empty() || values.len() > MAX_WRITE_REGISTERS as usize)
+        {
+            return Err(invalid(format!(
+                "invalid command: 1 to {MAX_WRITE_REGISTERS} values can be written"
+            )));
+        }
+        Ok(Self { id, write })
+    }
+}
+
+/// Acknowledgement of a command with the result of its write
+pub fn ack(id: &JsonValue, res: Result<(), &Error>) -> Vec<u8> {
+    let payload = match res {
+        Ok(()) => json!({"id": id, "ok": true}),
+        Err(err) => {
+            let mut payload = json!({"id": id, "ok": false, "error": err.to_string()});
+            if let Error::Exception(_, exception_code) = err {
+                payload["exception_code"] = u8::from(*exception_code).into();
+            }
+            payload
+        }
+    };
+    payload.to_string().into_bytes()
+}
+
+/// Acknowledgement of a command that couldn't be executed
+pub fn nack(invalid: &InvalidCommand) -> Vec<u8> {
+    json!({"id": invalid.id, "ok": false, "error": invalid.error})
+        .to_string()
+        .into_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use modbus::{
+        client::Error,
+        exception_code::ExceptionCode,
+        pdu::{function_code::FunctionCode, value::Value},
+        profile::Profile,
+    };
+    use serde_json::{Value as JsonValue, json};
+
+    use super::{Command, InvalidCommand, Write, ack, data, nack};
+    use crate::config::Format;
+
+    fn parse(payload: &[u8]) -> JsonValue {
+        serde_json::from_slice(payload).unwrap()
+    }
+
+    #[test]
+    fn encodes_data() {
+        let profile = Profile::from_toml(
+            r#"
+            name = "Meter"
+
+            [[points]]
+            name = "voltage"
+            register_type = "input_register"
+            address = 0
+            data_type = "u16"
+            scale = 0.5
+
+            [[points]]
+            name = "relay"
+            register_type = "coil"
+            address = 0
+            data_type = "bool"
+            "#,
+        )
+        .unwrap();
+        let readings = [
+            (&profile.points[0], Some(Value::U16(461))),
+            (&profile.points[1], None),
+        ];
+
+        assert_eq!(
+            parse(&data(Format::Json, &readings, 1000, 0)),
+            json!({"timestamp": 1000, "values": {"voltage": 230.5, "relay": null}})
+        );
+        assert_eq!(
+            parse(&data(Format::Sparkplug, &readings, 1000, 7)),
+            json!({"timestamp": 1000, "seq": 7, "metrics": [
+                {"name": "voltage", "timestamp": 1000, "dataType": "Double", "value": 230.5},
+                {"name": "relay", "timestamp": 1000, "dataType": "Boolean", "isNull": true},
+            ]})
+        );
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            Command::parse(
+                br#"{"id": "a", "function": "write_single_coil", "address": 3, "value": true}"#
+            ),
+            Ok(Command {
+                id: json!("a"),
+                write: Write::SingleCoil {
+                    address: 3,
+                    value: true
+                },
+            })
+        );
+        assert_eq!(
+            Command::parse(
+                br#"{"function": "write_multiple_registers", "address": 3, "values": [1, 2]}"#
+            ),
+            Ok(Command {
+                id: JsonValue::Null,
+                write: Write::MultipleRegisters {
+                    address: 3,
+                    values: vec![1, 2]
+                },
+            })
+        );
+        let empty =
+            br#"{"id": 4, "function": "write_multiple_registers", "address": 3, "values": []}"#;
+        assert!(matches!(
+            Command::parse(empty),
+            Err(InvalidCommand { id, .. }) if id == json!(4)
+        ));
+        assert!(matches!(
+            Command::parse(br#"{"id": 5, "function": "read_coils", "address": 3}"#),
+            Err(InvalidCommand { id, .. }) if id == json!(5)
+        ));
+        assert!(Command::parse(b"write").is_err());
+    }
+
+    #[test]
+    fn encodes_acks() {
+        assert_eq!(parse(&ack(&json!(1), Ok(()))), json!({"id": 1, "ok": true}));
+        let err = Error::Exception(
+            FunctionCode::WriteSingleRegister,
+            ExceptionCode::IllegalDataAddress,
+        );
+        assert_eq!(
+            parse(&ack(&json!(2), Err(&err))),
+            json!({"id": 2, "ok": false, "error": err.to_string(), "exception_code": 2})
+        );
+        let invalid = InvalidCommand {
+            id: JsonValue::Null,
+            error: "invalid command".to_string(),
+        };
+        assert_eq!(
+            parse(&nack(&invalid)),
+            json!({"id": null, "ok": false, "error": "invalid command"})
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Simulator config, read from TOML:
+//!
+//! ```toml
+//! seed = 42
+//!
+//! [tcp]
+//! listen = "127.0.0.1:5020"
+//!
+//! [rtu]
+//! link = "/tmp/modbus-sim"
+//! baud = 19200
+//!
+//! [[units]]
+//! id = 1
+//!
+//! [[units.blocks]]
+//! table = "holding_register"
+//! address = 0
+//! values = [1, 2, 3]
+//! count = 10
+//!
+//! [[units.behaviors]]
+//! table = "input_register"
+//! address = 0
+//! data_type = "f32"
+//! word_order = "cdab"
+//! kind = "sine"
+//! amplitude = 10.0
+//! offset = 230.0
+//! period_s = 60.0
+//!
+//! [[units.faults]]
+//! kind = "exception"
+//! code = 6
+//! function_code = 16
+//! probability = 0.1
+//! ```
+
+use std::{fmt, fs, io, path::PathBuf, str::FromStr};
+
+use modbus::{
+    exception_code::ExceptionCode,
+    pdu::{
+        Address, RegisterType,
+        value::{DataType, WordOrder},
+    },
+};
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub tcp: Option<TcpConfig>,
+    pub rtu: Option<RtuConfig>,
+    /// Milliseconds between updates of the behaviors
+    #[serde(default = "default_tick_ms")]
+    pub tick_ms: u64,
+    /// Seed of the noise and of the fault probabilities, random when missing
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub units: Vec<UnitConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TcpConfig {
+    pub listen: String,
+}
+
+/// RTU served on a new pty, whose slave path is printed and linked from `link`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RtuConfig {
+    pub link: Option<PathBuf>,
+    #[serde(default = "default_baud")]
+    pub baud: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnitConfig {
+    pub id: u8,
+    #[serde(default)]
+    pub blocks: Vec<Block>,
+    #[serde(default)]
+    pub behaviors: Vec<BehaviorConfig>,
+    #[serde(default)]
+    pub faults: Vec<FaultConfig>,
+}
+
+/// Addresses of a table that exist, starting with `values` and padded with zeros up to
+/// `count`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Block {
+    #[serde(deserialize_with = "from_str")]
+    pub table: RegisterType,
+    pub address: Address,
+    #[serde(default)]
+    pub values: Vec<u16>,
+    pub count: Option<usize>,
+}
+
+impl Block {
+    pub fn words(&self) -> Vec<u16> {
+        let mut words = self.values.clone();
+        words.resize(self.count.unwrap_or(0).max(words.len()), 0);
+        words
+    }
+}
+
+/// Value changing over time, written to its registers on every tick. Bits take the
+/// parity of the rounded value, so counters toggle them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BehaviorConfig {
+    #[serde(deserialize_with = "from_str")]
+    pub table: RegisterType,
+    pub address: Address,
+    #[serde(default = "default_data_type", deserialize_with = "from_str")]
+    pub data_type: DataType,
+    #[serde(default, deserialize_with = "from_str")]
+    pub word_order: WordOrder,
+    #[serde(flatten)]
+    pub behavior: Behavior,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Behavior {
+    /// Goes from `from` to `to` over `period_s`, then starts over
+    Ramp { from: f64, to: f64, period_s: f64 },
+    Sine {
+        #[serde(default)]
+        offset: f64,
+        amplitude: f64,
+        period_s: f64,
+    },
+    /// Uniformly distributed between `min` and `max`
+    Noise { min: f64, max: f64 },
+    /// Adds `step` every `every_s`, going back to `start` when reaching `wrap`
+    Counter {
+        #[serde(default)]
+        start: f64,
+        #[serde(default = "default_step")]
+        step: f64,
+        #[serde(default = "default_every_s")]
+        every_s: f64,
+        wrap: Option<f64>,
+    },
+}
+
+impl Behavior {
+    /// Whether the period is strictly positive, which NaN isn't
+    fn has_valid_period(&self) -> bool {
+        match *self {
+            Behavior::Ramp { period_s, .. } | Behavior::Sine { period_s, .. } => period_s > 0.0,
+            Behavior::Counter { every_s, .. } => every_s > 0.0,
+            Behavior::Noise { .. } => true,
+        }
+    }
+}
+
+/// Fault injected into the requests with the function code, or all of them, with the
+/// probability
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct FaultConfig {
+    pub function_code: Option<u8>,
+    #[serde(default = "default_probability")]
+    pub probability: f64,
+    #[serde(flatten)]
+    pub fault: Fault,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Fault {
+    /// Waits before replying
+    Delay { ms: u64 },
+    /// Answers with the exception code instead of handling the request
+    Exception {
+        #[serde(deserialize_with = "exception_code")]
+        code: ExceptionCode,
+    },
+    /// Handles the request without replying
+    Drop,
+    /// Writes the reply `chunk` bytes at a time, `interval_ms` apart, cut after
+    /// `truncate` bytes
+    Partial {
+        #[serde(default = "default_chunk")]
+        chunk: usize,
+        #[serde(default = "default_interval_ms")]
+        interval_ms: u64,
+        truncate: Option<usize>,
+    },
+}
+
+fn default_tick_ms() -> u64 {
+    100
+}
+fn default_baud() -> u32 {
+    19200
+}
+fn default_data_type() -> DataType {
+    DataType::U16
+}
+fn default_step() -> f64 {
+    1.0
+}
+fn default_every_s() -> f64 {
+    1.0
+}
+fn default_probability() -> f64 {
+    1.0
+}
+fn default_chunk() -> usize {
+    1
+}
+fn default_interval_ms() -> u64 {
+    100
+}
+
+fn from_str<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<T, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse()
+        .map_err(|_| D::Error::custom(format!("invalid value `{s}`")))
+}
+
+fn exception_code<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ExceptionCode, D::Error> {
+    match u8::deserialize(deserializer)? {
+        0 => Err(D::Error::custom("invalid exception code 0")),
+        code => Ok(ExceptionCode::from(code)),
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    DuplicateUnit(u8),
+    /// Unit ids go from 1 to 247
+    InvalidUnit(u8),
+    /// The data type of a behavior doesn't fit its table, its registers go beyond the
+    /// address space or its period isn't positive
+    InvalidBehavior(u8, Address),
+    InvalidProbability(u8),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{err}"),
+            ConfigError::Toml(err) => write!(f, "{err}"),
+            ConfigError::DuplicateUnit(id) => write!(f, "duplicate unit {id}"),
+            ConfigError::InvalidUnit(id) => write!(f, "invalid unit id {id}"),
+            ConfigError::InvalidBehavior(id, address) => {
+                write!(f, "unit {id}: invalid behavior at address {address}")
+            }
+            ConfigError::InvalidProbability(id) => {
+                write!(f, "unit {id}: fault probability not between 0 and 1")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(s).map_err(ConfigError::Toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ConfigError> {
+        let s = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml(&s)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut ids = vec![];
+        for unit in &self.units {
+            if !(1..=247).contains(&unit.id) {
+                return Err(ConfigError::InvalidUnit(unit.id));
+            }
+            if ids.contains(&unit.id) {
+                return Err(ConfigError::DuplicateUnit(unit.id));
+            }
+            ids.push(unit.id);
+
+            for behavior in &unit.behaviors {
+                let end = behavior.address as usize + behavior.data_type.quantity();
+                if !behavior.data_type.fits(behavior.table)
+                    || end > 0x10000
+                    || !behavior.behavior.has_valid_period()
+                {
+                    return Err(ConfigError::InvalidBehavior(unit.id, behavior.address));
+                }
+            }
+            if unit
+                .faults
+                .iter()
+                .any(|fault| !(0.0..=1.0).contains(&fault.probability))
+            {
+                return Err(ConfigError::InvalidProbability(unit.id));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use modbus::{
+        exception_code::ExceptionCode,
+        pdu::{RegisterType, value::DataType},
+    };
+
+    use super::{Behavior, Config, ConfigError, Fault};
+
+    #[test]
+    fn parses_config() {
+        let config = Config::from_toml(
+            r#"
+            [tcp]
+            listen = "127.0.0.1:5020"
+
+            [[units]]
+            id = 3
+
+            [[units.blocks]]
+            table = "holding_register"
+            address = 10
+            values = [1, 2]
+            count = 4
+
+            [[units.behaviors]]
+            table = "input_register"
+            address = 0
+            data_type = "f32"
+            kind = "ramp"
+            from = 0.0
+            to = 100.0
+            period_s = 10.0
+
+            [[units.behaviors]]
+            table = "coil"
+            address = 0
+            data_type = "bool"
+            kind = "counter"
+
+            [[units.faults]]
+            kind = "exception"
+            code = 6
+            function_code = 16
+
+            [[units.faults]]
+            kind = "partial"
+            truncate = 5
+            probability = 0.5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.tick_ms, 100);
+        let unit = &config.units[0];
+        assert_eq!(unit.blocks[0].words(), [1, 2, 0, 0]);
+        assert_eq!(unit.behaviors[0].table, RegisterType::InputRegister);
+        assert_eq!(unit.behaviors[0].data_type, DataType::F32);
+        assert_eq!(
+            unit.behaviors[1].behavior,
+            Behavior::Counter {
+                start: 0.0,
+                step: 1.0,
+                every_s: 1.0,
+                wrap: None
+            }
+        );
+        assert_eq!(
+            unit.faults[0].fault,
+            Fault::Exception {
+                code: ExceptionCode::ServerDeviceBusy
+            }
+        );
+        assert_eq!(unit.faults[0].function_code, Some(16));
+        assert_eq!(
+            unit.faults[1].fault,
+            Fault::Partial {
+                chunk: 1,
+                interval_ms: 100,
+                truncate: Some(5)
+            }
+        );
+
+        let err = Config::from_toml(
+            r#"
+            [[units]]
+            id = 1
+            [[units.behaviors]]
+            table = "coil"
+            address = 0
+            kind = "noise"
+            min = 0.0
+            max = 1.0
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidBehavior(1, 0)));
+        for behavior in [
+            r#"kind = "ramp"
+            from = 0.0
+            to = 1.0
+            period_s = 0.0"#,
+            r#"kind = "sine"
+            amplitude = 1.0
+            period_s = -1.0"#,
+            r#"kind = "counter"
+            every_s = 0.0"#,
+        ] {
+            let config = format!(
+                r#"
+                [[units]]
+                id = 1
+                [[units.behaviors]]
+                table = "holding_register"
+                address = 2
+                {behavior}
+                "#
+            );
+            let err = Config::from_toml(&config).unwrap_err();
+            assert!(matches!(err, ConfigError::InvalidBehavior(1, 2)));
+        }
+        assert!(matches!(
+            Config::from_toml("[[units]]\nid = 0"),
+            Err(ConfigError::InvalidUnit(0))
+        ));
+    }
+}
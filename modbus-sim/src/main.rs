@@ -0,0 +1,129 @@
+//! Modbus device simulator serving the units of a config file, see [`config`] for its
+//! format:
+//!
+//! ```text
+//! modbus-sim sim.toml
+//! modbus-sim sim.toml --tcp 0.0.0.0:502 --pty /tmp/ttySIM
+//! ```
+
+mod config;
+mod serve;
+mod sim;
+
+use std::{
+    fs, io,
+    net::TcpListener,
+    os::unix::fs::symlink,
+    path::PathBuf,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use modbus::transport::serial::{SerialConfig, SerialPort};
+
+use crate::{
+    config::{Config, RtuConfig, TcpConfig},
+    sim::{SimHandle, Simulator},
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Simulates Modbus devices over TCP and RTU on a pty")]
+struct Cli {
+    /// TOML config of the units
+    config: PathBuf,
+    /// Address to listen on, instead of the one of the config
+    #[arg(long)]
+    tcp: Option<String>,
+    /// Serves RTU on a pty linked from this path, instead of the one of the config
+    #[arg(long)]
+    pty: Option<PathBuf>,
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let mut config = Config::load(&cli.config).map_err(|err| format!("{:?}: {err}", cli.config))?;
+    if let Some(listen) = cli.tcp {
+        config.tcp = Some(TcpConfig { listen });
+    }
+    if let Some(link) = cli.pty {
+        let baud = config.rtu.as_ref().map_or(19200, |rtu| rtu.baud);
+        config.rtu = Some(RtuConfig {
+            link: Some(link),
+            baud,
+        });
+    }
+    if config.tcp.is_none() && config.rtu.is_none() {
+        return Err("nothing to serve, configure tcp or rtu".to_string());
+    }
+
+    let sim = Arc::new(Mutex::new(Simulator::new(&config)));
+    let handle = SimHandle::new(sim.clone());
+    let unit_ids: Vec<u8> = sim.lock().unwrap().unit_ids().collect();
+    let mut servers = vec![];
+
+    if let Some(tcp) = &config.tcp {
+        let listener =
+            TcpListener::bind(&tcp.listen).map_err(|err| format!("{}: {err}", tcp.listen))?;
+        println!(
+            "serving TCP on {}",
+            listener.local_addr().map_err(|err| err.to_string())?
+        );
+        let handle = handle.clone();
+        servers.push(thread::spawn(move || serve::serve_tcp(listener, handle)));
+    }
+
+    if let Some(rtu) = &config.rtu {
+        let serial_config = SerialConfig::new(rtu.baud);
+        let (master, slave, path) =
+            SerialPort::open_pty_pair(serial_config).map_err(|err| err.to_string())?;
+        if let Some(link) = &rtu.link {
+            match fs::remove_file(link) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(format!("{link:?}: {err}"));
+                }
+                _ => {}
+            }
+            symlink(&path, link).map_err(|err| format!("{link:?}: {err}"))?;
+            println!("serving RTU on {} ({})", link.display(), path.display());
+        } else {
+            println!("serving RTU on {}", path.display());
+        }
+
+        let frame_gap = serial_config.frame_gap();
+        let handle = handle.clone();
+        let unit_ids = unit_ids.clone();
+        servers.push(thread::spawn(move || {
+            let _slave = slave;
+            serve::serve_rtu(master, handle, unit_ids, frame_gap)
+        }));
+    }
+
+    let tick = Duration::from_millis(config.tick_ms.max(1));
+    let started = Instant::now();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(tick);
+            sim.lock().unwrap().update(started.elapsed());
+        }
+    });
+
+    for server in servers {
+        match server.join() {
+            Ok(res) => res.map_err(|err| err.to_string())?,
+            Err(_) => return Err("server thread panicked".to_string()),
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
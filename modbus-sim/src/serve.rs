@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind, Read, Write},
+    net::TcpListener,
+    thread,
+    time::Duration,
+};
+
+use modbus::{
+    adu::{
+        rtu::{BROADCAST_UNIT_ID, MAX_ADU_SIZE as RTU_MAX_ADU_SIZE},
+        tcp::{MAX_ADU_SIZE, header::Header},
+    },
+    server::{rtu::RtuServer, tcp::process_frame},
+    transport::Transport,
+};
+
+use crate::sim::{Plan, SimHandle};
+
+/// Writes the reply as the faults of the plan say
+fn reply<W: Write>(stream: &mut W, res: &[u8], plan: &Plan) -> io::Result<()> {
+    thread::sleep(plan.delay);
+    if plan.drop {
+        return Ok(());
+    }
+    let Some(partial) = plan.partial else {
+        stream.write_all(res)?;
+        return stream.flush();
+    };
+
+    let res = &res[..partial.truncate.unwrap_or(res.len()).min(res.len())];
+    for (i, chunk) in res.chunks(partial.chunk).enumerate() {
+        if i > 0 {
+            thread::sleep(partial.interval);
+        }
+        stream.write_all(chunk)?;
+        stream.flush()?;
+    }
+    Ok(())
+}
+
+/// Serves the requests of one TCP connection until the peer closes it
+pub fn serve_tcp_connection<S: Read + Write>(
+    mut stream: S,
+    mut handle: SimHandle,
+) -> io::Result<()> {
+    let mut req_buf = [0_u8; MAX_ADU_SIZE];
+    let mut res_buf = [0_u8; MAX_ADU_SIZE];
+    let mut buf_len = 0;
+
+    loop {
+        let bytes_read = match stream.read(&mut req_buf[buf_len..]) {
+            Ok(0) => return Ok(()),
+            Ok(bytes_read) => bytes_read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        buf_len += bytes_read;
+
+        while buf_len >= Header::size() {
+            let header = Header::decode(&req_buf[..buf_len]).unwrap();
+            let adu_len = *header.length() as usize + Header::size() - 1;
+            if *header.protocol_id() != 0 || *header.length() < 2 || adu_len > MAX_ADU_SIZE {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "invalid MBAP header",
+                ));
+            }
+            if adu_len > buf_len {
+                break;
+            }
+
+            let frame = &req_buf[..adu_len];
+            let plan = handle.plan(*header.unit_id(), frame[Header::size()]);
+            handle.exception = plan.exception;
+            if let Some(res_len) = process_frame(frame, &mut res_buf, &mut handle) {
+                reply(&mut stream, &res_buf[..res_len], &plan)?;
+            }
+
+            req_buf.copy_within(adu_len..buf_len, 0);
+            buf_len -= adu_len;
+        }
+    }
+}
+
+/// Accepts TCP connections, serving each on its own thread
+pub fn serve_tcp(listener: TcpListener, handle: SimHandle) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let handle = handle.clone();
+        thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            if let Err(err) = serve_tcp_connection(stream, handle) {
+                eprintln!("connection {peer:?}: {err}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Serves the units on a serial line until the transport fails. A frame ends when
+/// nothing was received for `frame_gap`.
+pub fn serve_rtu<T: Transport>(
+    mut transport: T,
+    handle: SimHandle,
+    unit_ids: impl IntoIterator<Item = u8>,
+    frame_gap: Duration,
+) -> io::Result<()> {
+    // A server per unit, each with its own diagnostics counters
+    let mut servers: BTreeMap<u8, RtuServer<SimHandle>> = unit_ids
+        .into_iter()
+        .map(|unit_id| (unit_id, RtuServer::new(unit_id, handle.clone())))
+        .collect();
+    // The simulator applies broadcasts to every unit, so they are handled once
+    let mut broadcast_server = RtuServer::new(BROADCAST_UNIT_ID, handle.clone());
+    let mut frame_buf = [0_u8; RTU_MAX_ADU_SIZE];
+    let mut res_buf = [0_u8; RTU_MAX_ADU_SIZE];
+
+    loop {
+        let mut frame_len = 0;
+        transport.set_read_timeout(None)?;
+        while frame_len < frame_buf.len() {
+            match transport.read(&mut frame_buf[frame_len..]) {
+                Ok(0) => return Ok(()),
+                Ok(bytes_read) => {
+                    frame_len += bytes_read;
+                    transport.set_read_timeout(Some(frame_gap))?;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) if matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                    break;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if frame_len < 2 {
+            continue;
+        }
+        let frame = &frame_buf[..frame_len];
+
+        let unit_id = frame[0];
+        if unit_id == BROADCAST_UNIT_ID {
+            broadcast_server.process_frame(frame, &mut res_buf);
+            continue;
+        }
+        let Some(server) = servers.get_mut(&unit_id) else {
+            continue;
+        };
+        let plan = handle.plan(unit_id, frame[1]);
+        server.handler_mut().exception = plan.exception;
+        if let Some(res_len) = server.process_frame(frame, &mut res_buf) {
+            reply(&mut transport, &res_buf[..res_len], &plan)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use modbus::{
+        client::{
+            Client, Error, RESPONSE_BUF_SIZE,
+            rtu::{Reply, RtuClient, RtuConfig},
+            tcp::{TcpClient, TcpConfig},
+        },
+        exception_code::ExceptionCode,
+        pdu::{
+            DataWords, function_code::FunctionCode, request::Request as PduRequest,
+            response::Response as PduResponse,
+        },
+        transport::serial::{SerialConfig, SerialPort},
+    };
+
+    use crate::{
+        config::Config,
+        sim::{SimHandle, Simulator},
+    };
+
+    use super::{serve_rtu, serve_tcp};
+
+    const CONFIG: &str = r#"
+        seed = 1
+        [[units]]
+        id = 1
+        [[units.blocks]]
+        table = "holding_register"
+        address = 0
+        values = [10, 20, 30]
+        [[units.blocks]]
+        table = "holding_register"
+        address = 10
+        values = [0]
+        [[units.faults]]
+        kind = "exception"
+        code = 6
+        function_code = 6
+        [[units.faults]]
+        kind = "drop"
+        function_code = 4
+        [[units.faults]]
+        kind = "partial"
+        chunk = 2
+        interval_ms = 10
+        function_code = 1
+        [[units.faults]]
+        kind = "delay"
+        ms = 100
+        function_code = 16
+
+        [[units]]
+        id = 2
+        [[units.blocks]]
+        table = "holding_register"
+        address = 10
+        values = [0]
+    "#;
+
+    fn handle() -> SimHandle {
+        let config = Config::from_toml(CONFIG).unwrap();
+        SimHandle::new(Arc::new(Mutex::new(Simulator::new(&config))))
+    }
+
+    fn tcp_client(timeout: Duration) -> TcpClient<TcpStream> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = handle();
+        thread::spawn(move || serve_tcp(listener, handle));
+        let stream = TcpStream::connect(addr).unwrap();
+        TcpClient::new(
+            stream,
+            TcpConfig {
+                response_timeout: timeout,
+            },
+        )
+    }
+
+    #[test]
+    fn serves_tcp_with_faults() {
+        let mut client = tcp_client(Duration::from_millis(500));
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+
+        let res = client.request(1, PduRequest::ReadHoldingRegisters(1, 2), &mut buf);
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadHoldingRegisters(DataWords::new(&[0, 20, 0, 30], 2))
+        );
+
+        let res = client.request(1, PduRequest::WriteSingleRegister(0, 1), &mut buf);
+        assert!(matches!(
+            res,
+            Err(Error::Exception(
+                FunctionCode::WriteSingleRegister,
+                ExceptionCode::ServerDeviceBusy
+            ))
+        ));
+
+        let started = Instant::now();
+        let mut data_buf = [0_u8; 4];
+        let words = DataWords::from_words(&[1, 2], &mut data_buf);
+        let res = client.request(1, PduRequest::WriteMultipleRegisters(0, words), &mut buf);
+        assert_eq!(res.unwrap(), PduResponse::WriteMultipleRegisters(0, 2));
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        let res = client.request(2, PduRequest::ReadHoldingRegisters(0, 1), &mut buf);
+        assert!(matches!(
+            res,
+            Err(Error::Exception(_, ExceptionCode::IllegalDataAddress))
+        ));
+        let res = client.request(3, PduRequest::ReadHoldingRegisters(0, 1), &mut buf);
+        assert!(matches!(
+            res,
+            Err(Error::Exception(
+                _,
+                ExceptionCode::GatewayTargetDeviceFailedToRespond
+            ))
+        ));
+    }
+
+    #[test]
+    fn drops_and_splits_tcp_replies() {
+        let mut client = tcp_client(Duration::from_millis(200));
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+
+        let res = client.request(1, PduRequest::ReadInputRegisters(0, 1), &mut buf);
+        assert!(matches!(res, Err(Error::Timeout)));
+
+        // Arrives in 2 byte chunks, so it takes 4 intervals
+        let res = client.request(1, PduRequest::ReadCoils(0, 1), &mut buf);
+        assert!(matches!(
+            res,
+            Err(Error::Exception(_, ExceptionCode::IllegalDataAddress))
+        ));
+    }
+
+    #[test]
+    fn serves_rtu_on_pty() {
+        let (master, path) = SerialPort::open_pty(SerialConfig::new(19200)).unwrap();
+        let handle = handle();
+        thread::spawn(move || serve_rtu(master, handle, [1, 2], Duration::from_millis(5)));
+
+        let port = SerialPort::open(&path, SerialConfig::new(19200)).unwrap();
+        let mut client = RtuClient::new(port, RtuConfig::default());
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+        let res = client.request(1, PduRequest::ReadHoldingRegisters(0, 3), &mut buf);
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadHoldingRegisters(DataWords::new(&[0, 10, 0, 20, 0, 30], 3))
+        );
+
+        let res = client.request(3, PduRequest::ReadHoldingRegisters(0, 1), &mut buf);
+        assert!(matches!(res, Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn applies_rtu_broadcasts_to_every_unit() {
+        let (master, path) = SerialPort::open_pty(SerialConfig::new(19200)).unwrap();
+        let handle = handle();
+        thread::spawn(move || serve_rtu(master, handle, [1, 2], Duration::from_millis(5)));
+
+        let port = SerialPort::open(&path, SerialConfig::new(19200)).unwrap();
+        let mut client = RtuClient::new(port, RtuConfig::default());
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+        let res = client.send(0, PduRequest::WriteSingleRegister(10, 7), &mut buf);
+        assert!(matches!(res, Ok(Reply::Broadcast)));
+
+        for unit_id in [1, 2] {
+            let res = client.request(unit_id, PduRequest::ReadHoldingRegisters(10, 1), &mut buf);
+            assert_eq!(
+                res.unwrap(),
+                PduResponse::ReadHoldingRegisters(DataWords::new(&[0, 7], 1))
+            );
+        }
+    }
+}
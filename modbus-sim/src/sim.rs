@@ -0,0 +1,358 @@
+use std::{
+    collections::BTreeMap,
+    f64::consts::TAU,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use modbus::{
+    adu::rtu::BROADCAST_UNIT_ID,
+    exception_code::ExceptionCode,
+    pdu::{request::Request as PduRequest, response::Response as PduResponse, value::Value},
+    server::{Handler, registers::RegisterMap},
+};
+
+use crate::config::{Behavior, BehaviorConfig, Config, Fault, FaultConfig};
+
+/// xorshift64* generator, so runs with the same seed inject the same faults
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state must not be 0
+        Self(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Self::new(nanos as u64)
+    }
+
+    /// Uniformly distributed in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let value = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+impl Behavior {
+    /// Value `elapsed` after the start of the simulation
+    pub fn value(&self, elapsed: Duration, rng: &mut Rng) -> f64 {
+        let t = elapsed.as_secs_f64();
+        match *self {
+            Behavior::Ramp { from, to, period_s } => from + (to - from) * (t / period_s).fract(),
+            Behavior::Sine {
+                offset,
+                amplitude,
+                period_s,
+            } => offset + amplitude * (TAU * t / period_s).sin(),
+            Behavior::Noise { min, max } => min + (max - min) * rng.next_f64(),
+            Behavior::Counter {
+                start,
+                step,
+                every_s,
+                wrap,
+            } => {
+                let value = start + step * (t / every_s).floor();
+                match wrap {
+                    Some(wrap) if wrap != start => start + (value - start).rem_euclid(wrap - start),
+                    _ => value,
+                }
+            }
+        }
+    }
+}
+
+/// What to do with the reply to a request, from the faults of its unit
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Plan {
+    pub delay: Duration,
+    pub exception: Option<ExceptionCode>,
+    pub drop: bool,
+    pub partial: Option<Partial>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partial {
+    pub chunk: usize,
+    pub interval: Duration,
+    pub truncate: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Unit {
+    map: RegisterMap,
+    behaviors: Vec<BehaviorConfig>,
+    faults: Vec<FaultConfig>,
+}
+
+/// Register maps of the units, updated by their behaviors
+#[derive(Debug)]
+pub struct Simulator {
+    units: BTreeMap<u8, Unit>,
+    rng: Rng,
+}
+
+impl Simulator {
+    pub fn new(config: &Config) -> Self {
+        let units = config
+            .units
+            .iter()
+            .map(|unit| {
+                let mut map = RegisterMap::new();
+                for block in &unit.blocks {
+                    map.set_registers(block.table, block.address, &block.words());
+                }
+                let unit_state = Unit {
+                    map,
+                    behaviors: unit.behaviors.clone(),
+                    faults: unit.faults.clone(),
+                };
+                (unit.id, unit_state)
+            })
+            .collect();
+        let rng = config.seed.map_or_else(Rng::from_time, Rng::new);
+
+        let mut sim = Self { units, rng };
+        sim.update(Duration::ZERO);
+        sim
+    }
+
+    pub fn unit_ids(&self) -> impl Iterator<Item = u8> + '_ {
+        self.units.keys().copied()
+    }
+
+    #[cfg(test)]
+    pub fn map(&self, unit_id: u8) -> Option<&RegisterMap> {
+        self.units.get(&unit_id).map(|unit| &unit.map)
+    }
+
+    /// Writes the values of the behaviors `elapsed` after the start of the simulation
+    pub fn update(&mut self, elapsed: Duration) {
+        for unit in self.units.values_mut() {
+            for behavior in &unit.behaviors {
+                let value = behavior.behavior.value(elapsed, &mut self.rng);
+                if behavior.table.is_bit() {
+                    let bit = value.round().rem_euclid(2.0) != 0.0;
+                    unit.map.set(behavior.table, behavior.address, bit as u16);
+                    continue;
+                }
+                // Values out of the range of the type leave the registers as they are
+                let Some(value) = Value::from_f64(behavior.data_type, value) else {
+                    continue;
+                };
+                let mut words = [0_u16; 4];
+                let len = value.encode_words(behavior.word_order, &mut words);
+                unit.map
+                    .set_registers(behavior.table, behavior.address, &words[..len]);
+            }
+        }
+    }
+
+    /// Draws the faults of the unit applying to a request with the function code
+    pub fn plan(&mut self, unit_id: u8, fn_code: u8) -> Plan {
+        let mut plan = Plan::default();
+        let Some(unit) = self.units.get(&unit_id) else {
+            return plan;
+        };
+        for fault in &unit.faults {
+            if fault.function_code.is_some_and(|code| code != fn_code)
+                || self.rng.next_f64() >= fault.probability
+            {
+                continue;
+            }
+            match fault.fault {
+                Fault::Delay { ms } => plan.delay += Duration::from_millis(ms),
+                Fault::Exception { code } => plan.exception = Some(code),
+                Fault::Drop => plan.drop = true,
+                Fault::Partial {
+                    chunk,
+                    interval_ms,
+                    truncate,
+                } => {
+                    plan.partial = Some(Partial {
+                        chunk: chunk.max(1),
+                        interval: Duration::from_millis(interval_ms),
+                        truncate,
+                    })
+                }
+            }
+        }
+        plan
+    }
+}
+
+impl Handler for Simulator {
+    /// Requests to units that aren't simulated are answered like a gateway would.
+    /// Broadcasts are applied to every unit, returning the response of the last one.
+    fn handle<'b>(
+        &mut self,
+        unit_id: u8,
+        req: &PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, ExceptionCode> {
+        if unit_id == BROADCAST_UNIT_ID {
+            let mut units = self.units.values_mut();
+            let last = units
+                .next_back()
+                .ok_or(ExceptionCode::GatewayTargetDeviceFailedToRespond)?;
+            for unit in units {
+                let _ = unit.map.handle(unit_id, req, buf);
+            }
+            return last.map.handle(unit_id, req, buf);
+        }
+        match self.units.get_mut(&unit_id) {
+            Some(unit) => unit.map.handle(unit_id, req, buf),
+            None => Err(ExceptionCode::GatewayTargetDeviceFailedToRespond),
+        }
+    }
+}
+
+/// Handler of one connection or serial line, sharing the simulator with the others
+#[derive(Debug, Clone)]
+pub struct SimHandle {
+    sim: Arc<Mutex<Simulator>>,
+    /// Answered to the next request instead of handling it
+    pub exception: Option<ExceptionCode>,
+}
+
+impl SimHandle {
+    pub fn new(sim: Arc<Mutex<Simulator>>) -> Self {
+        Self {
+            sim,
+            exception: None,
+        }
+    }
+
+    pub fn plan(&self, unit_id: u8, fn_code: u8) -> Plan {
+        self.sim.lock().unwrap().plan(unit_id, fn_code)
+    }
+}
+
+impl Handler for SimHandle {
+    fn handle<'b>(
+        &mut self,
+        unit_id: u8,
+        req: &PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, ExceptionCode> {
+        if let Some(code) = self.exception.take() {
+            return Err(code);
+        }
+        self.sim.lock().unwrap().handle(unit_id, req, buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use modbus::{exception_code::ExceptionCode, pdu::RegisterType};
+
+    use crate::config::{Behavior, Config};
+
+    use super::{Rng, Simulator};
+
+    #[test]
+    fn behaviors() {
+        let mut rng = Rng::new(1);
+        let secs = Duration::from_secs_f64;
+
+        let ramp = Behavior::Ramp {
+            from: 10.0,
+            to: 20.0,
+            period_s: 4.0,
+        };
+        assert_eq!(ramp.value(secs(1.0), &mut rng), 12.5);
+        assert_eq!(ramp.value(secs(5.0), &mut rng), 12.5);
+
+        let sine = Behavior::Sine {
+            offset: 5.0,
+            amplitude: 2.0,
+            period_s: 4.0,
+        };
+        assert!((sine.value(secs(1.0), &mut rng) - 7.0).abs() < 1e-9);
+        assert!((sine.value(secs(3.0), &mut rng) - 3.0).abs() < 1e-9);
+
+        let counter = Behavior::Counter {
+            start: 1.0,
+            step: 2.0,
+            every_s: 0.5,
+            wrap: Some(7.0),
+        };
+        let values: Vec<f64> = (0..5)
+            .map(|i| counter.value(secs(i as f64 * 0.5), &mut rng))
+            .collect();
+        assert_eq!(values, [1.0, 3.0, 5.0, 1.0, 3.0]);
+
+        let noise = Behavior::Noise {
+            min: -1.0,
+            max: 1.0,
+        };
+        for _ in 0..100 {
+            let value = noise.value(Duration::ZERO, &mut rng);
+            assert!((-1.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn updates_registers_and_plans_faults() {
+        let config = Config::from_toml(
+            r#"
+            seed = 7
+            [[units]]
+            id = 1
+            [[units.blocks]]
+            table = "holding_register"
+            address = 0
+            count = 2
+            [[units.behaviors]]
+            table = "input_register"
+            address = 0
+            data_type = "u32"
+            word_order = "cdab"
+            kind = "counter"
+            step = 100000.0
+            [[units.behaviors]]
+            table = "coil"
+            address = 3
+            data_type = "bool"
+            kind = "counter"
+            [[units.faults]]
+            kind = "delay"
+            ms = 50
+            function_code = 3
+            [[units.faults]]
+            kind = "exception"
+            code = 4
+            probability = 0.0
+            "#,
+        )
+        .unwrap();
+        let mut sim = Simulator::new(&config);
+
+        let map = sim.map(1).unwrap();
+        assert_eq!(map.get(RegisterType::HoldingRegister, 1), Some(0));
+        assert_eq!(map.get(RegisterType::Coil, 3), Some(0));
+
+        sim.update(Duration::from_secs(3));
+        let map = sim.map(1).unwrap();
+        // 300000 = 0x0004_93e0, low word first
+        assert_eq!(map.get(RegisterType::InputRegister, 0), Some(0x93e0));
+        assert_eq!(map.get(RegisterType::InputRegister, 1), Some(0x0004));
+        assert_eq!(map.get(RegisterType::Coil, 3), Some(1));
+
+        let plan = sim.plan(1, 3);
+        assert_eq!(plan.delay, Duration::from_millis(50));
+        assert_eq!(plan.exception, None::<ExceptionCode>);
+        assert_eq!(sim.plan(1, 4).delay, Duration::ZERO);
+        assert_eq!(sim.plan(2, 3).delay, Duration::ZERO);
+    }
+}
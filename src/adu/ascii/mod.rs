@@ -0,0 +1,146 @@
+//! Modbus ASCII framing: `:`, the unit id, PDU and LRC as uppercase hex digits, then
+//! CR LF
+
+pub mod request;
+pub mod response;
+
+use crate::error::{DecodeError, EncodeError};
+
+/// Max size of an ASCII frame: `:` (1) + hex of unit id, PDU and LRC (2 * 255) + CR LF (2)
+pub const MAX_ADU_SIZE: usize = 513;
+
+/// Max size of the binary unit id, PDU and LRC of a frame
+const MAX_BINARY_SIZE: usize = 255;
+
+/// Longitudinal redundancy check: the two's complement of the sum of the bytes
+pub fn lrc(data: &[u8]) -> u8 {
+    data.iter()
+        .fold(0_u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg()
+}
+
+/// Length of the first frame of `buf` up to its LF, `None` if it isn't complete yet
+pub fn frame_len(buf: &[u8]) -> Option<usize> {
+    buf.iter()
+        .position(|byte| *byte == b'\n')
+        .map(|pos| pos + 1)
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+fn hex_value(digit: u8) -> Result<u8, DecodeError> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        _ => Err(DecodeError::InvalidAsciiFrame),
+    }
+}
+
+/// Writes the frame of the binary unit id and PDU `data` to `buf`
+fn encode_frame(data: &[u8], buf: &mut [u8]) -> Result<usize, EncodeError> {
+    let len = 1 + (data.len() + 1) * 2 + 2;
+    if len > buf.len() {
+        return Err(EncodeError::InvalidBufferSize {
+            needed: len,
+            available: buf.len(),
+        });
+    }
+
+    buf[0] = b':';
+    for (i, byte) in data.iter().chain([lrc(data)].iter()).enumerate() {
+        buf[1 + i * 2] = HEX_DIGITS[(byte >> 4) as usize];
+        buf[2 + i * 2] = HEX_DIGITS[(byte & 0xf) as usize];
+    }
+    buf[len - 2..len].copy_from_slice(b"\r\n");
+
+    Ok(len)
+}
+
+/// Decodes the hex digits of the frame to `buf` and checks the LRC. Returns the length
+/// of the unit id and PDU, without the LRC.
+fn decode_frame(frame: &[u8], buf: &mut [u8]) -> Result<usize, DecodeError> {
+    let Some(len) = frame_len(frame) else {
+        return Err(DecodeError::IncompleteBuffer {
+            current_size: frame.len(),
+            min_needed_size: frame.len() + 1,
+        });
+    };
+    let frame = &frame[..len];
+    if frame[0] != b':' || !frame.ends_with(b"\r\n") {
+        return Err(DecodeError::InvalidAsciiFrame);
+    }
+
+    let digits = &frame[1..len - 2];
+    let binary_len = digits.len() / 2;
+    // Unit id, function code and LRC at least
+    if !digits.len().is_multiple_of(2)
+        || binary_len < 3
+        || binary_len > MAX_BINARY_SIZE.min(buf.len())
+    {
+        return Err(DecodeError::InvalidAsciiFrame);
+    }
+    for (byte, pair) in buf.iter_mut().zip(digits.chunks_exact(2)) {
+        *byte = hex_value(pair[0])? << 4 | hex_value(pair[1])?;
+    }
+
+    let (data, actual) = (&buf[..binary_len - 1], buf[binary_len - 1]);
+    let expected = lrc(data);
+    if expected != actual {
+        return Err(DecodeError::InvalidLrc { expected, actual });
+    }
+
+    Ok(binary_len - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::DecodeError;
+
+    use super::{decode_frame, encode_frame, lrc};
+
+    #[test]
+    fn lrc_of_data() {
+        assert_eq!(lrc(&[0x11, 0x03, 0x00, 0x6b, 0x00, 0x03]), 0x7e);
+        assert_eq!(lrc(&[0xff, 0x01]), 0x00);
+        assert_eq!(lrc(&[]), 0x00);
+    }
+
+    #[test]
+    fn encodes_and_decodes_frames() {
+        let mut buf = [0_u8; 32];
+        let len = encode_frame(&[0x11, 0x03, 0x00, 0x6b, 0x00, 0x03], &mut buf).unwrap();
+        assert_eq!(&buf[..len], b":1103006B00037E\r\n");
+
+        let mut data = [0_u8; 8];
+        assert_eq!(decode_frame(b":1103006b00037E\r\n", &mut data), Ok(6));
+        assert_eq!(data[..6], [0x11, 0x03, 0x00, 0x6b, 0x00, 0x03]);
+
+        assert_eq!(
+            decode_frame(b":1103006B00037F\r\n", &mut data),
+            Err(DecodeError::InvalidLrc {
+                expected: 0x7e,
+                actual: 0x7f
+            })
+        );
+        assert_eq!(
+            decode_frame(b":1103006B00037", &mut data),
+            Err(DecodeError::IncompleteBuffer {
+                current_size: 14,
+                min_needed_size: 15
+            })
+        );
+        for frame in [
+            &b"1103006B00037E\r\n"[..],
+            b":1103006B00037\r\n",
+            b":11G3006B00037E\r\n",
+            b":11037E\n",
+            b":117E\r\n",
+        ] {
+            assert_eq!(
+                decode_frame(frame, &mut data),
+                Err(DecodeError::InvalidAsciiFrame)
+            );
+        }
+    }
+}
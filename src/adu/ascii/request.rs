@@ -0,0 +1,108 @@
+use core::fmt;
+
+use crate::{
+    adu::rtu::BROADCAST_UNIT_ID,
+    error::{DecodeError, EncodeError},
+    pdu::request::Request as PduRequest,
+};
+
+use super::{MAX_BINARY_SIZE, decode_frame, encode_frame};
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Request<'a> {
+    unit_id: u8,
+    pdu: PduRequest<'a>,
+}
+
+impl<'a> Request<'a> {
+    pub fn new(unit_id: u8, pdu_req: PduRequest<'a>) -> Self {
+        Self {
+            unit_id,
+            pdu: pdu_req,
+        }
+    }
+
+    pub fn unit_id(&self) -> &u8 {
+        &self.unit_id
+    }
+    pub fn pdu(&self) -> &PduRequest<'a> {
+        &self.pdu
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        self.unit_id == BROADCAST_UNIT_ID
+    }
+
+    pub fn pdu_len(&self) -> usize {
+        self.pdu.pdu_len()
+    }
+
+    pub fn adu_len(&self) -> usize {
+        1 + (1 + self.pdu_len() + 1) * 2 + 2
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut data = [0_u8; MAX_BINARY_SIZE];
+        data[0] = self.unit_id;
+        let pdu_size = self.pdu.encode(&mut data[1..])?;
+        encode_frame(&data[..1 + pdu_size], buf)
+    }
+
+    /// Decodes the ASCII frame at the start of `frame`, up to its LF. The binary unit id
+    /// and PDU are written to `buf`, which the request borrows.
+    pub fn decode(frame: &[u8], buf: &'a mut [u8]) -> Result<Self, DecodeError> {
+        let len = decode_frame(frame, buf)?;
+        let buf = &buf[..len];
+        Ok(Self {
+            unit_id: buf[0],
+            pdu: PduRequest::try_from(&buf[1..])?,
+        })
+    }
+}
+
+/// `unit=1 FC03 ReadHoldingRegisters addr=100 qty=10`
+impl fmt::Display for Request<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unit={} {}", self.unit_id, self.pdu)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::DecodeError,
+        pdu::{DataWords, request::Request as PduRequest},
+    };
+
+    use super::Request;
+
+    #[test]
+    fn encodes_and_decodes_requests() {
+        let req = Request::new(0x11, PduRequest::ReadHoldingRegisters(0x6b, 3));
+        let mut buf = [0_u8; 32];
+        assert_eq!(req.encode(&mut buf), Ok(17));
+        assert_eq!(req.adu_len(), 17);
+        assert_eq!(&buf[..17], b":1103006B00037E\r\n");
+
+        let mut data = [0_u8; 8];
+        assert_eq!(Request::decode(&buf[..17], &mut data), Ok(req));
+
+        let mut words_buf = [0_u8; 4];
+        let req = Request::new(
+            1,
+            PduRequest::WriteMultipleRegisters(
+                1,
+                DataWords::from_words(&[10, 258], &mut words_buf),
+            ),
+        );
+        let len = req.encode(&mut buf).unwrap();
+        let mut data = [0_u8; 16];
+        assert_eq!(Request::decode(&buf[..len], &mut data), Ok(req));
+        assert!(matches!(
+            Request::decode(b":0103000000\r\n", &mut data),
+            Err(DecodeError::InvalidLrc { .. })
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use core::fmt;
+
+use crate::{
+    adu::write_response_pdu,
+    error::{DecodeError, EncodeError},
+    pdu::{exception_response::ExceptionResponse, response::Response as PduResponse},
+};
+
+use super::{MAX_BINARY_SIZE, decode_frame, encode_frame};
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Response<'a> {
+    unit_id: u8,
+    pdu: Result<PduResponse<'a>, ExceptionResponse>,
+}
+
+impl<'a> Response<'a> {
+    pub fn new(unit_id: u8, pdu_res: Result<PduResponse<'a>, ExceptionResponse>) -> Self {
+        Self {
+            unit_id,
+            pdu: pdu_res,
+        }
+    }
+
+    pub fn unit_id(&self) -> &u8 {
+        &self.unit_id
+    }
+    pub fn pdu(&self) -> &Result<PduResponse<'a>, ExceptionResponse> {
+        &self.pdu
+    }
+    pub fn into_pdu(self) -> Result<PduResponse<'a>, ExceptionResponse> {
+        self.pdu
+    }
+
+    pub fn pdu_len(&self) -> usize {
+        match &self.pdu {
+            Ok(pdu) => pdu.pdu_len(),
+            Err(pdu) => pdu.pdu_len(),
+        }
+    }
+
+    pub fn adu_len(&self) -> usize {
+        1 + (1 + self.pdu_len() + 1) * 2 + 2
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut data = [0_u8; MAX_BINARY_SIZE];
+        data[0] = self.unit_id;
+        let pdu_size = match &self.pdu {
+            Ok(pdu) => pdu.encode(&mut data[1..])?,
+            Err(pdu) => pdu.encode(&mut data[1..])?,
+        };
+        encode_frame(&data[..1 + pdu_size], buf)
+    }
+
+    /// Decodes the ASCII frame at the start of `frame`, up to its LF. The binary unit id
+    /// and PDU are written to `buf`, which the response borrows.
+    pub fn decode(frame: &[u8], buf: &'a mut [u8]) -> Result<Self, DecodeError> {
+        let len = decode_frame(frame, buf)?;
+        let buf = &buf[..len];
+        Ok(Self {
+            unit_id: buf[0],
+            pdu: Ok(PduResponse::try_from(&buf[1..])?),
+        })
+    }
+}
+
+/// `unit=1 FC03 ReadHoldingRegisters qty=2 values=[1, 2]`
+impl fmt::Display for Response<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unit={} ", self.unit_id)?;
+        write_response_pdu(f, &self.pdu)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::DecodeError,
+        exception_code::ExceptionCode,
+        pdu::{
+            DataWords, exception_response::ExceptionResponse, function_code::FunctionCode,
+            response::Response as PduResponse,
+        },
+    };
+
+    use super::Response;
+
+    #[test]
+    fn encodes_and_decodes_responses() {
+        let res = Response::new(
+            1,
+            Ok(PduResponse::ReadHoldingRegisters(DataWords::new(
+                &[0x00, 0x06, 0x00, 0x05],
+                2,
+            ))),
+        );
+        let mut buf = [0_u8; 32];
+        assert_eq!(res.encode(&mut buf), Ok(19));
+        assert_eq!(&buf[..19], b":01030400060005ED\r\n");
+
+        let mut data = [0_u8; 16];
+        assert_eq!(Response::decode(&buf[..19], &mut data), Ok(res));
+
+        let res = Response::new(
+            10,
+            Err(ExceptionResponse::new(
+                FunctionCode::ReadCoils,
+                ExceptionCode::IllegalDataAddress,
+            )),
+        );
+        let len = res.encode(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b":0A810273\r\n");
+        assert_eq!(
+            Response::decode(&buf[..len], &mut data),
+            Err(DecodeError::ModbusExceptionCode(
+                FunctionCode::ReadCoils,
+                ExceptionCode::IllegalDataAddress
+            ))
+        );
+    }
+}
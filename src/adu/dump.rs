@@ -0,0 +1,279 @@
+//! Annotated hex dumps of frames
+
+use core::{fmt, ops::Range, str};
+
+use super::Framing;
+
+/// Bytes per line of a dump
+const LINE_BYTES: usize = 16;
+
+const TCP_HEADER: &[(&str, usize)] = &[
+    ("transaction id", 2),
+    ("protocol id", 2),
+    ("length", 2),
+    ("unit id", 1),
+];
+const SERIAL_HEADER: &[(&str, usize)] = &[("unit id", 1)];
+
+/// A frame written one field per line, with its offset and bytes, splitting the fields
+/// longer than 16 bytes over several lines:
+///
+/// ```text
+/// 0000  00 01                                            transaction id
+/// 0002  00 00                                            protocol id
+/// 0004  00 06                                            length
+/// 0006  01                                               unit id
+/// 0007  03                                               function code
+/// 0008  00 64                                            address
+/// 000a  00 0a                                            quantity
+/// ```
+///
+/// ASCII frames are written as text, each field of the binary frame being 2 hex
+/// digits. Frames don't have to be valid, the bytes beyond the fields their function
+/// code has are labeled as data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HexDump<'a> {
+    framing: Framing,
+    is_request: bool,
+    frame: &'a [u8],
+}
+
+impl<'a> HexDump<'a> {
+    pub fn request(framing: Framing, frame: &'a [u8]) -> Self {
+        Self {
+            framing,
+            is_request: true,
+            frame,
+        }
+    }
+
+    pub fn response(framing: Framing, frame: &'a [u8]) -> Self {
+        Self {
+            framing,
+            is_request: false,
+            frame,
+        }
+    }
+
+    pub fn framing(&self) -> &Framing {
+        &self.framing
+    }
+    pub fn is_request(&self) -> bool {
+        self.is_request
+    }
+    pub fn frame(&self) -> &'a [u8] {
+        self.frame
+    }
+
+    fn write_field(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        range: Range<usize>,
+        label: &str,
+    ) -> fmt::Result {
+        let ascii = self.framing == Framing::Ascii;
+        // Each binary byte takes 2 characters in ASCII
+        let (line_len, width) = match ascii {
+            true => (LINE_BYTES * 2, LINE_BYTES * 2),
+            false => (LINE_BYTES, LINE_BYTES * 3 - 1),
+        };
+        for (i, line) in self.frame[range.clone()].chunks(line_len).enumerate() {
+            write!(f, "{:04x}  ", range.start + i * line_len)?;
+            let len = match ascii {
+                true => {
+                    let mut len = 0;
+                    for byte in line {
+                        let escaped = byte.escape_ascii();
+                        len += escaped.len();
+                        write!(f, "{escaped}")?;
+                    }
+                    len
+                }
+                false => {
+                    for (j, byte) in line.iter().enumerate() {
+                        let separator = if j > 0 { " " } else { "" };
+                        write!(f, "{separator}{byte:02x}")?;
+                    }
+                    line.len() * 3 - 1
+                }
+            };
+            match i {
+                0 => writeln!(f, "{:1$}  {label}", "", width.saturating_sub(len))?,
+                _ => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frame = self.frame;
+        // The characters before and after the binary frame, and how many characters
+        // each of its bytes takes
+        let (start, end, width) = match self.framing {
+            Framing::Ascii => {
+                let start = usize::from(frame.first() == Some(&b':'));
+                let end = match frame.ends_with(b"\r\n") {
+                    true => frame.len().saturating_sub(2).max(start),
+                    false => frame.len(),
+                };
+                // An odd digit left goes with the end
+                (start, end - (end - start) % 2, 2)
+            }
+            Framing::Tcp | Framing::Rtu => (0, frame.len(), 1),
+        };
+        let byte = |index: usize| {
+            let chars = frame.get(start + index * width..start + (index + 1) * width)?;
+            match self.framing {
+                Framing::Ascii => u8::from_str_radix(str::from_utf8(chars).ok()?, 16).ok(),
+                Framing::Tcp | Framing::Rtu => Some(chars[0]),
+            }
+        };
+        let (header, trailer) = match self.framing {
+            Framing::Tcp => (TCP_HEADER, ("", 0)),
+            Framing::Rtu => (SERIAL_HEADER, ("crc", 2)),
+            Framing::Ascii => (SERIAL_HEADER, ("lrc", 1)),
+        };
+        let header_len: usize = header.iter().map(|(_, len)| len).sum();
+        let binary_len = (end - start) / width;
+        // Without the trailer, unless the frame is too short to have one
+        let pdu_end = match binary_len >= header_len + 1 + trailer.1 {
+            true => binary_len - trailer.1,
+            false => binary_len,
+        };
+        let pdu = match byte(header_len) {
+            Some(function_code) => pdu_fields(self.is_request, function_code),
+            None => &[],
+        };
+
+        if start > 0 {
+            self.write_field(f, 0..start, "start")?;
+        }
+        let mut offset = 0_usize;
+        let fields = header
+            .iter()
+            .chain(&[("function code", 1)])
+            .chain(pdu)
+            .chain(&[("data", usize::MAX)]);
+        for &(label, len) in fields {
+            let field_end = offset.saturating_add(len).min(pdu_end);
+            if field_end > offset {
+                self.write_field(f, start + offset * width..start + field_end * width, label)?;
+                offset = field_end;
+            }
+        }
+        if binary_len > offset {
+            self.write_field(
+                f,
+                start + offset * width..start + binary_len * width,
+                trailer.0,
+            )?;
+        }
+        if frame.len() > end {
+            self.write_field(f, end..frame.len(), "end")?;
+        }
+        Ok(())
+    }
+}
+
+/// Fields of a PDU after its function code, the bytes after them being data
+fn pdu_fields(is_request: bool, function_code: u8) -> &'static [(&'static str, usize)] {
+    match (is_request, function_code) {
+        (_, 0x80..) => &[("exception code", 1)],
+        (true, 0x01..=0x04) => &[("address", 2), ("quantity", 2)],
+        (false, 0x01..=0x04 | 0x17) => &[("byte count", 1)],
+        (_, 0x05 | 0x06) => &[("address", 2), ("value", 2)],
+        (true, 0x0f | 0x10) => &[("address", 2), ("quantity", 2), ("byte count", 1)],
+        (false, 0x0f | 0x10) => &[("address", 2), ("quantity", 2)],
+        (_, 0x16) => &[("address", 2), ("and mask", 2), ("or mask", 2)],
+        (true, 0x17) => &[
+            ("read address", 2),
+            ("read quantity", 2),
+            ("write address", 2),
+            ("write quantity", 2),
+            ("byte count", 1),
+        ],
+        _ => &[],
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dumps_tcp_request() {
+        extern crate alloc;
+        use alloc::format;
+
+        let frame = [0, 1, 0, 0, 0, 6, 1, 3, 0, 0x64, 0, 0x0a];
+        assert_eq!(
+            format!("{}", HexDump::request(Framing::Tcp, &frame)),
+            "\
+0000  00 01                                            transaction id
+0002  00 00                                            protocol id
+0004  00 06                                            length
+0006  01                                               unit id
+0007  03                                               function code
+0008  00 64                                            address
+000a  00 0a                                            quantity
+"
+        );
+    }
+
+    #[test]
+    fn dumps_rtu_response() {
+        extern crate alloc;
+        use alloc::{format, vec, vec::Vec};
+
+        let mut frame = vec![1, 3, 18];
+        frame.extend(1..=18);
+        frame.extend([0xab, 0xcd]);
+        assert_eq!(
+            format!("{}", HexDump::response(Framing::Rtu, &frame)),
+            "\
+0000  01                                               unit id
+0001  03                                               function code
+0002  12                                               byte count
+0003  01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f 10  data
+0013  11 12
+0015  ab cd                                            crc
+"
+        );
+
+        // Exception, and a frame cut before its CRC
+        let frame: Vec<u8> = vec![1, 0x83, 2];
+        assert_eq!(
+            format!("{}", HexDump::response(Framing::Rtu, &frame)),
+            "\
+0000  01                                               unit id
+0001  83                                               function code
+0002  02                                               exception code
+"
+        );
+    }
+
+    #[test]
+    fn dumps_ascii_request() {
+        extern crate alloc;
+        use alloc::format;
+
+        assert_eq!(
+            format!(
+                "{}",
+                HexDump::request(Framing::Ascii, b":010600010005F3\r\n")
+            ),
+            "\
+0000  :                                 start
+0001  01                                unit id
+0003  06                                function code
+0005  0001                              address
+0009  0005                              value
+000d  F3                                lrc
+000f  \\r\\n                              end
+"
+        );
+    }
+}
@@ -1 +1,80 @@
+pub mod ascii;
+pub mod dump;
+pub mod rtu;
 pub mod tcp;
+
+use core::{fmt, str::FromStr};
+
+use tcp::header::Header;
+
+use crate::pdu::{exception_response::ExceptionResponse, response::Response as PduResponse};
+
+/// How ADUs are delimited on the wire
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Framing {
+    Tcp,
+    Rtu,
+    Ascii,
+}
+
+impl Framing {
+    /// Length of the first frame of `buf`, `None` if it isn't complete yet or can't be
+    /// told from its content, which is always the case for RTU as its frames end with
+    /// a silent interval
+    pub fn frame_len(&self, buf: &[u8]) -> Option<usize> {
+        match self {
+            Framing::Tcp => {
+                let header = Header::decode(buf).ok()?;
+                // unit_id is included in the header.length and header.size
+                let adu_len = (*header.length() as usize + Header::size() - 1).max(Header::size());
+                (adu_len <= buf.len()).then_some(adu_len)
+            }
+            Framing::Rtu => None,
+            Framing::Ascii => ascii::frame_len(buf),
+        }
+    }
+
+    /// Max size of one of its ADUs
+    pub fn max_adu_size(&self) -> usize {
+        match self {
+            Framing::Tcp => tcp::MAX_ADU_SIZE,
+            Framing::Rtu => rtu::MAX_ADU_SIZE,
+            Framing::Ascii => ascii::MAX_ADU_SIZE,
+        }
+    }
+}
+
+/// Writes the PDU of a response, or its exception
+fn write_response_pdu(
+    f: &mut fmt::Formatter<'_>,
+    pdu: &Result<PduResponse<'_>, ExceptionResponse>,
+) -> fmt::Result {
+    match pdu {
+        Ok(pdu) => write!(f, "{pdu}"),
+        Err(exception) => write!(f, "{exception}"),
+    }
+}
+
+impl fmt::Display for Framing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Framing::Tcp => "tcp",
+            Framing::Rtu => "rtu",
+            Framing::Ascii => "ascii",
+        })
+    }
+}
+
+impl FromStr for Framing {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(Framing::Tcp),
+            "rtu" => Ok(Framing::Rtu),
+            "ascii" => Ok(Framing::Ascii),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error::DecodeError;
+
+const CRC_TABLE: [u16; 256] = crc_table();
+
+const fn crc_table() -> [u16; 256] {
+    let mut table = [0_u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-16/MODBUS. It is sent low byte first, so use `to_le_bytes` when encoding it.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |crc, &byte| {
+        (crc >> 8) ^ CRC_TABLE[((crc ^ byte as u16) & 0xff) as usize]
+    })
+}
+
+/// Checks the CRC in the last 2 bytes of `frame`
+pub(crate) fn check_crc(frame: &[u8]) -> Result<(), DecodeError> {
+    let (data, crc_buf) = frame.split_at(frame.len() - 2);
+    let expected = crc16(data);
+    let actual = u16::from_le_bytes([crc_buf[0], crc_buf[1]]);
+    if expected != actual {
+        return Err(DecodeError::InvalidCrc { expected, actual });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_crc, crc16};
+    use crate::error::DecodeError;
+
+    #[test]
+    fn crc_of_frame() {
+        assert_eq!(crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0a]), 0xcdc5);
+        assert_eq!(crc16(&[0x11, 0x03, 0x00, 0x6b, 0x00, 0x03]), 0x8776);
+        assert_eq!(crc16(&[]), 0xffff);
+    }
+
+    #[test]
+    fn check_crc_of_frame() {
+        assert_eq!(
+            check_crc(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xc5, 0xcd]),
+            Ok(())
+        );
+        assert_eq!(
+            check_crc(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xcd, 0xc5]),
+            Err(DecodeError::InvalidCrc {
+                expected: 0xcdc5,
+                actual: 0xc5cd
+            })
+        );
+    }
+}
@@ -0,0 +1,9 @@
+pub mod crc;
+pub mod request;
+pub mod response;
+
+/// Max size of a RTU frame: unit id (1) + PDU (253) + CRC (2)
+pub const MAX_ADU_SIZE: usize = 256;
+
+/// Unit id of a broadcast request, which is never answered
+pub const BROADCAST_UNIT_ID: u8 = 0;
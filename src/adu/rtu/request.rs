@@ -0,0 +1,197 @@
+use core::fmt;
+
+use crate::{
+    error::{DecodeError, EncodeError},
+    pdu::{function_code::FunctionCode, request::Request as PduRequest},
+};
+
+use super::{
+    BROADCAST_UNIT_ID,
+    crc::{check_crc, crc16},
+};
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Request<'a> {
+    unit_id: u8,
+    pdu: PduRequest<'a>,
+}
+
+impl<'a> Request<'a> {
+    pub fn new(unit_id: u8, pdu_req: PduRequest<'a>) -> Self {
+        Self {
+            unit_id,
+            pdu: pdu_req,
+        }
+    }
+
+    pub fn unit_id(&self) -> &u8 {
+        &self.unit_id
+    }
+    pub fn pdu(&self) -> &PduRequest<'a> {
+        &self.pdu
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        self.unit_id == BROADCAST_UNIT_ID
+    }
+
+    pub fn pdu_len(&self) -> usize {
+        self.pdu.pdu_len()
+    }
+
+    pub fn adu_len(&self) -> usize {
+        1 + self.pdu_len() + 2
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        if self.adu_len() > buf.len() {
+            return Err(EncodeError::InvalidBufferSize {
+                needed: self.adu_len(),
+                available: buf.len(),
+            });
+        }
+
+        buf[0] = self.unit_id;
+        let pdu_size = self.pdu.encode(&mut buf[1..])?;
+        let crc = crc16(&buf[..1 + pdu_size]);
+        buf[1 + pdu_size..3 + pdu_size].copy_from_slice(&crc.to_le_bytes());
+
+        Ok(self.adu_len())
+    }
+
+    /// Decodes a RTU frame. `buf` may be an incomplete frame, but shouldn't contain
+    /// anything after it. Frames of custom function codes have no known length,
+    /// so the whole `buf` is taken as the frame.
+    pub fn decode(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        let adu_len = 1 + request_pdu_len(buf)? + 2;
+        if adu_len > buf.len() {
+            return Err(DecodeError::IncompleteBuffer {
+                current_size: buf.len(),
+                min_needed_size: adu_len,
+            });
+        }
+
+        let frame = &buf[..adu_len];
+        check_crc(frame)?;
+
+        let pdu = PduRequest::try_from(&frame[1..adu_len - 2])?;
+
+        Ok(Self {
+            unit_id: frame[0],
+            pdu,
+        })
+    }
+}
+
+/// Length of the request frame at the start of `buf`, `None` if it isn't complete yet.
+/// Frames of custom function codes take the whole `buf`.
+#[cfg(feature = "embedded-io")]
+pub(crate) fn frame_len(buf: &[u8]) -> Option<usize> {
+    let adu_len = 1 + request_pdu_len(buf).ok()? + 2;
+    (adu_len <= buf.len()).then_some(adu_len)
+}
+
+/// Length of the request PDU in the frame, as far as it can be known from the bytes received
+fn request_pdu_len(buf: &[u8]) -> Result<usize, DecodeError> {
+    if buf.len() < 2 {
+        return Err(DecodeError::IncompleteBuffer {
+            current_size: buf.len(),
+            min_needed_size: 2,
+        });
+    }
+
+    let Ok(fn_code) = FunctionCode::try_from(buf[1]) else {
+        return Ok(2);
+    };
+
+    let byte_count = |pos: usize| match buf.get(pos) {
+        Some(&byte_count) => Ok(byte_count as usize),
+        None => Err(DecodeError::IncompleteBuffer {
+            current_size: buf.len(),
+            min_needed_size: pos + 1,
+        }),
+    };
+
+    let pdu_len = match fn_code {
+        FunctionCode::ReadCoils
+        | FunctionCode::ReadDiscreteInput
+        | FunctionCode::ReadHoldingRegisters
+        | FunctionCode::ReadInputRegisters
+        | FunctionCode::WriteSingleCoil
+        | FunctionCode::WriteSingleRegister => 5,
+        FunctionCode::WriteMultipleCoils | FunctionCode::WriteMultipleRegisters => {
+            6 + byte_count(6)?
+        }
+        FunctionCode::MaskWriteRegister => 7,
+        FunctionCode::ReadWriteMultipleRegisters => 10 + byte_count(10)?,
+        FunctionCode::Custom(_) => buf.len().max(4) - 3,
+    };
+
+    Ok(pdu_len)
+}
+
+/// `unit=1 FC03 ReadHoldingRegisters addr=100 qty=10`
+impl fmt::Display for Request<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unit={} {}", self.unit_id, self.pdu)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Request<'a> {
+    type Error = DecodeError;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::decode(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::DecodeError,
+        pdu::{DataWords, request::Request as PduRequest},
+    };
+
+    use super::Request;
+
+    #[test]
+    fn buffer_from_request() {
+        let req = Request::new(1, PduRequest::ReadHoldingRegisters(0, 10));
+        let buf = &mut [0_u8; 8];
+        assert_eq!(req.encode(buf), Ok(8));
+        assert_eq!(buf, &[0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xc5, 0xcd]);
+        assert!(!req.is_broadcast());
+        assert!(Request::new(0, PduRequest::WriteSingleRegister(1, 3)).is_broadcast());
+    }
+
+    #[test]
+    fn request_from_buffer() {
+        let buf: &[u8] = &[0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xc5, 0xcd];
+        assert_eq!(
+            Request::try_from(buf),
+            Ok(Request::new(1, PduRequest::ReadHoldingRegisters(0, 10)))
+        );
+
+        let buf: &[u8] = &[0x11, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0a];
+        assert_eq!(
+            Request::try_from(buf),
+            Err(DecodeError::IncompleteBuffer {
+                current_size: 9,
+                min_needed_size: 13
+            })
+        );
+
+        let buf: &[u8] = &[
+            0x11, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0a, 0x01, 0x02, 0xc6, 0xf0,
+        ];
+        assert_eq!(
+            Request::try_from(buf),
+            Ok(Request::new(
+                0x11,
+                PduRequest::WriteMultipleRegisters(1, DataWords::new(&[0x00, 0x0a, 0x01, 0x02], 2))
+            ))
+        );
+    }
+}
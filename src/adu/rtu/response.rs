@@ -0,0 +1,236 @@
+use core::fmt;
+
+use crate::{
+    adu::write_response_pdu,
+    error::{DecodeError, EncodeError},
+    pdu::{
+        exception_response::ExceptionResponse, function_code::FunctionCode,
+        response::Response as PduResponse,
+    },
+};
+
+use super::crc::{check_crc, crc16};
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Response<'a> {
+    unit_id: u8,
+    pdu: Result<PduResponse<'a>, ExceptionResponse>,
+}
+
+impl<'a> Response<'a> {
+    pub fn new(unit_id: u8, pdu_res: Result<PduResponse<'a>, ExceptionResponse>) -> Self {
+        Self {
+            unit_id,
+            pdu: pdu_res,
+        }
+    }
+
+    pub fn unit_id(&self) -> &u8 {
+        &self.unit_id
+    }
+    pub fn pdu(&self) -> &Result<PduResponse<'a>, ExceptionResponse> {
+        &self.pdu
+    }
+    pub fn into_pdu(self) -> Result<PduResponse<'a>, ExceptionResponse> {
+        self.pdu
+    }
+
+    pub fn pdu_len(&self) -> usize {
+        match &self.pdu {
+            Ok(pdu) => pdu.pdu_len(),
+            Err(pdu) => pdu.pdu_len(),
+        }
+    }
+
+    pub fn adu_len(&self) -> usize {
+        1 + self.pdu_len() + 2
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        if self.adu_len() > buf.len() {
+            return Err(EncodeError::InvalidBufferSize {
+                needed: self.adu_len(),
+                available: buf.len(),
+            });
+        }
+
+        buf[0] = self.unit_id;
+        let pdu_size = match &self.pdu {
+            Ok(pdu) => pdu.encode(&mut buf[1..])?,
+            Err(pdu) => pdu.encode(&mut buf[1..])?,
+        };
+        let crc = crc16(&buf[..1 + pdu_size]);
+        buf[1 + pdu_size..3 + pdu_size].copy_from_slice(&crc.to_le_bytes());
+
+        Ok(self.adu_len())
+    }
+
+    /// Decodes a RTU frame. `buf` may be an incomplete frame, but shouldn't contain
+    /// anything after it. Frames of custom function codes have no known length,
+    /// so the whole `buf` is taken as the frame.
+    pub fn decode(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        let adu_len = 1 + response_pdu_len(buf)? + 2;
+        if adu_len > buf.len() {
+            return Err(DecodeError::IncompleteBuffer {
+                current_size: buf.len(),
+                min_needed_size: adu_len,
+            });
+        }
+
+        let frame = &buf[..adu_len];
+        check_crc(frame)?;
+
+        let pdu = PduResponse::try_from(&frame[1..adu_len - 2])?;
+
+        Ok(Self {
+            unit_id: frame[0],
+            pdu: Ok(pdu),
+        })
+    }
+}
+
+/// Length of the response PDU in the frame, as far as it can be known from the bytes received
+fn response_pdu_len(buf: &[u8]) -> Result<usize, DecodeError> {
+    if buf.len() < 2 {
+        return Err(DecodeError::IncompleteBuffer {
+            current_size: buf.len(),
+            min_needed_size: 2,
+        });
+    }
+
+    let Ok(fn_code) = FunctionCode::try_from(buf[1]) else {
+        // Exception response: function code + exception code
+        return Ok(2);
+    };
+
+    let pdu_len = match fn_code {
+        FunctionCode::ReadCoils
+        | FunctionCode::ReadDiscreteInput
+        | FunctionCode::ReadHoldingRegisters
+        | FunctionCode::ReadInputRegisters
+        | FunctionCode::ReadWriteMultipleRegisters => {
+            let Some(&byte_count) = buf.get(2) else {
+                return Err(DecodeError::IncompleteBuffer {
+                    current_size: buf.len(),
+                    min_needed_size: 3,
+                });
+            };
+            2 + byte_count as usize
+        }
+        FunctionCode::WriteSingleCoil
+        | FunctionCode::WriteSingleRegister
+        | FunctionCode::WriteMultipleCoils
+        | FunctionCode::WriteMultipleRegisters => 5,
+        FunctionCode::MaskWriteRegister => 7,
+        FunctionCode::Custom(_) => buf.len().max(4) - 3,
+    };
+
+    Ok(pdu_len)
+}
+
+/// `unit=1 FC03 ReadHoldingRegisters qty=2 values=[1, 2]`
+impl fmt::Display for Response<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unit={} ", self.unit_id)?;
+        write_response_pdu(f, &self.pdu)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Response<'a> {
+    type Error = DecodeError;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::decode(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::DecodeError,
+        exception_code::ExceptionCode,
+        pdu::{DataWords, function_code::FunctionCode},
+    };
+
+    use super::{PduResponse, Response};
+
+    #[test]
+    fn response_from_buffer() {
+        let buf: &[u8] = &[0x01, 0x03, 0x04, 0x00, 0x06, 0x00, 0x05, 0xda, 0x31];
+        assert_eq!(
+            Response::try_from(buf),
+            Ok(Response {
+                unit_id: 1,
+                pdu: Ok(PduResponse::ReadHoldingRegisters(DataWords::new(
+                    &[0x00, 0x06, 0x00, 0x05],
+                    2
+                )))
+            })
+        );
+
+        let buf: &[u8] = &[0x0a, 0x81, 0x02, 0xb0, 0x53];
+        assert_eq!(
+            Response::try_from(buf),
+            Err(DecodeError::ModbusExceptionCode(
+                FunctionCode::ReadCoils,
+                ExceptionCode::IllegalDataAddress
+            ))
+        );
+    }
+
+    #[test]
+    fn response_from_incomplete_buffer() {
+        let buf: &[u8] = &[0x01];
+        assert_eq!(
+            Response::try_from(buf),
+            Err(DecodeError::IncompleteBuffer {
+                current_size: 1,
+                min_needed_size: 2,
+            })
+        );
+        let buf: &[u8] = &[0x01, 0x03];
+        assert_eq!(
+            Response::try_from(buf),
+            Err(DecodeError::IncompleteBuffer {
+                current_size: 2,
+                min_needed_size: 3,
+            })
+        );
+        let buf: &[u8] = &[0x01, 0x03, 0x04, 0x00, 0x06, 0x00, 0x05, 0xda];
+        assert_eq!(
+            Response::try_from(buf),
+            Err(DecodeError::IncompleteBuffer {
+                current_size: 8,
+                min_needed_size: 9,
+            })
+        );
+    }
+
+    #[test]
+    fn response_with_invalid_crc() {
+        let buf: &[u8] = &[0x01, 0x06, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00];
+        assert_eq!(
+            Response::try_from(buf),
+            Err(DecodeError::InvalidCrc {
+                expected: 0x0b98,
+                actual: 0x0000
+            })
+        );
+    }
+
+    #[test]
+    fn buffer_from_response() {
+        let res = Response::new(
+            1,
+            Ok(PduResponse::ReadHoldingRegisters(DataWords::new(
+                &[0x00, 0x06, 0x00, 0x05],
+                2,
+            ))),
+        );
+        let buf = &mut [0_u8; 9];
+        assert_eq!(res.encode(buf), Ok(9));
+        assert_eq!(buf, &[0x01, 0x03, 0x04, 0x00, 0x06, 0x00, 0x05, 0xda, 0x31]);
+    }
+}
@@ -1,6 +1,10 @@
+use core::fmt;
+
 use crate::error::{DecodeError, EncodeError};
 
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     transaction_id: u16,
     protocol_id: u16,
@@ -37,7 +41,10 @@ impl Header {
 
     pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
         if Self::size() > buf.len() {
-            return Err(EncodeError::InvalidBufferSize);
+            return Err(EncodeError::InvalidBufferSize {
+                needed: Self::size(),
+                available: buf.len(),
+            });
         }
 
         buf[0..2].copy_from_slice(&self.transaction_id.to_be_bytes());
@@ -70,6 +77,13 @@ impl Header {
     }
 }
 
+/// `tx=1 unit=1`
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tx={} unit={}", self.transaction_id, self.unit_id)
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for Header {
     type Error = DecodeError;
 
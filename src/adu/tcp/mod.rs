@@ -1,3 +1,6 @@
 pub mod header;
 pub mod request;
 pub mod response;
+
+/// Max size of a TCP ADU: MBAP header (7) + PDU (253)
+pub const MAX_ADU_SIZE: usize = 260;
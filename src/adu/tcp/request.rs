@@ -1,8 +1,15 @@
-use crate::{error::EncodeError, pdu::request::Request as PduRequest};
+use core::fmt;
+
+use crate::{
+    error::{DecodeError, EncodeError},
+    pdu::request::Request as PduRequest,
+};
 
 use super::header::Header;
 
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Request<'a> {
     header: Header,
     pdu: PduRequest<'a>,
@@ -33,7 +40,10 @@ impl<'a> Request<'a> {
 
     pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
         if self.adu_len() > buf.len() {
-            return Err(EncodeError::InvalidBufferSize);
+            return Err(EncodeError::InvalidBufferSize {
+                needed: self.adu_len(),
+                available: buf.len(),
+            });
         }
 
         let (header_buf, pdu_buf) = buf.split_at_mut(Header::size());
@@ -43,4 +53,84 @@ impl<'a> Request<'a> {
 
         Ok(header_size + pdu_size)
     }
+
+    pub fn decode(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        if buf.len() < Header::size() {
+            return Err(DecodeError::IncompleteBuffer {
+                current_size: buf.len(),
+                min_needed_size: Header::size(),
+            });
+        };
+
+        let (header_buf, pdu_buf) = buf.split_at(Header::size());
+
+        let header = Header::try_from(header_buf)?;
+        // unit_id is included in the header.length and header.size
+        let adu_len = *header.length() as usize + Header::size() - 1;
+        if adu_len > buf.len() {
+            return Err(DecodeError::IncompleteBuffer {
+                current_size: buf.len(),
+                min_needed_size: adu_len,
+            });
+        };
+
+        let pdu =
+            PduRequest::try_from(&pdu_buf[..adu_len - Header::size()]).map_err(
+                |err| match err {
+                    DecodeError::IncompleteBuffer {
+                        current_size,
+                        min_needed_size,
+                    } => DecodeError::IncompleteBuffer {
+                        current_size: current_size + Header::size(),
+                        min_needed_size: min_needed_size + Header::size(),
+                    },
+                    err => err,
+                },
+            )?;
+
+        Ok(Self { header, pdu })
+    }
+}
+
+/// `tx=1 unit=1 FC03 ReadHoldingRegisters addr=100 qty=10`
+impl fmt::Display for Request<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.header, self.pdu)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Request<'a> {
+    type Error = DecodeError;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::decode(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{error::DecodeError, pdu::request::Request as PduRequest};
+
+    use super::{Header, Request};
+
+    #[test]
+    fn request_from_buffer() {
+        let buf: &[u8] = &[0, 1, 0, 0, 0, 6, 1, 4, 0, 2, 0, 5];
+        assert_eq!(
+            Request::try_from(buf),
+            Ok(Request {
+                header: Header::new(1, 6, 1),
+                pdu: PduRequest::ReadInputRegisters(2, 5)
+            })
+        );
+
+        let buf: &[u8] = &[0, 1, 0, 0, 0, 6, 1, 4, 0, 2];
+        assert_eq!(
+            Request::try_from(buf),
+            Err(DecodeError::IncompleteBuffer {
+                current_size: 10,
+                min_needed_size: 12
+            })
+        );
+    }
 }
@@ -1,4 +1,7 @@
+use core::fmt;
+
 use crate::{
+    adu::write_response_pdu,
     error::{DecodeError, EncodeError},
     pdu::{exception_response::ExceptionResponse, response::Response as PduResponse},
 };
@@ -6,6 +9,8 @@ use crate::{
 use super::header::Header;
 
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Response<'a> {
     header: Header,
     pdu: Result<PduResponse<'a>, ExceptionResponse>,
@@ -34,6 +39,9 @@ impl<'a> Response<'a> {
     pub fn pdu(&self) -> &Result<PduResponse<'a>, ExceptionResponse> {
         &self.pdu
     }
+    pub fn into_pdu(self) -> Result<PduResponse<'a>, ExceptionResponse> {
+        self.pdu
+    }
 
     pub fn pdu_len(&self) -> usize {
         match &self.pdu {
@@ -48,7 +56,10 @@ impl<'a> Response<'a> {
 
     pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
         if self.adu_len() > buf.len() {
-            return Err(EncodeError::InvalidBufferSize);
+            return Err(EncodeError::InvalidBufferSize {
+                needed: self.adu_len(),
+                available: buf.len(),
+            });
         }
 
         let (header_buf, pdu_buf) = buf.split_at_mut(Header::size());
@@ -100,6 +111,14 @@ impl<'a> Response<'a> {
     }
 }
 
+/// `tx=1 unit=1 FC03 ReadHoldingRegisters qty=2 values=[1, 2]`
+impl fmt::Display for Response<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.header)?;
+        write_response_pdu(f, &self.pdu)
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for Response<'a> {
     type Error = DecodeError;
 
@@ -155,4 +174,66 @@ mod test {
             &[0, 1, 0, 0, 0, 13, 1, 4, 10, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5]
         );
     }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn display_response() {
+        extern crate alloc;
+        use alloc::format;
+
+        use crate::{exception_code::ExceptionCode, pdu::function_code::FunctionCode};
+
+        use super::ExceptionResponse;
+
+        let buf: &[u8] = &[0, 1, 0, 0, 0, 7, 1, 3, 4, 0, 1, 0, 2];
+        assert_eq!(
+            format!("{}", Response::try_from(buf).unwrap()),
+            "tx=1 unit=1 FC03 ReadHoldingRegisters qty=2 values=[1, 2]"
+        );
+
+        let res = Response {
+            header: Header::new(9, 3, 2),
+            pdu: Err(ExceptionResponse::new(
+                FunctionCode::WriteSingleRegister,
+                ExceptionCode::IllegalDataAddress,
+            )),
+        };
+        assert_eq!(
+            format!("{res}"),
+            "tx=9 unit=2 FC06 WriteSingleRegister exception=IllegalDataAddress"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_response() {
+        use crate::{exception_code::ExceptionCode, pdu::function_code::FunctionCode};
+
+        use super::ExceptionResponse;
+
+        let buf: &[u8] = &[0, 1, 0, 0, 0, 7, 1, 3, 4, 0, 1, 0, 2];
+        assert_eq!(
+            serde_json::to_string(&Response::try_from(buf).unwrap()).unwrap(),
+            concat!(
+                r#"{"header":{"transaction_id":1,"protocol_id":0,"length":7,"unit_id":1},"#,
+                r#""pdu":{"Ok":{"ReadHoldingRegisters":[1,2]}}}"#
+            )
+        );
+
+        let res = ExceptionResponse::new(
+            FunctionCode::WriteSingleRegister,
+            ExceptionCode::IllegalDataAddress,
+        );
+        let json =
+            r#"{"function_code":"WriteSingleRegister","exception_code":"IllegalDataAddress"}"#;
+        assert_eq!(serde_json::to_string(&res).unwrap(), json);
+        assert_eq!(
+            serde_json::from_str::<ExceptionResponse>(json).unwrap(),
+            res
+        );
+        let header: Header =
+            serde_json::from_str(r#"{"transaction_id":1,"protocol_id":0,"length":7,"unit_id":1}"#)
+                .unwrap();
+        assert_eq!(header, Header::new(1, 7, 1));
+    }
 }
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use crate::{
+    adu::ascii::{frame_len, request::Request as AduRequest, response::Response as AduResponse},
+    error::{DecodeError, EncodeError},
+    pdu::{
+        exception_response::ExceptionResponse, request::Request as PduRequest,
+        response::Response as PduResponse,
+    },
+};
+
+use super::{
+    rtu::RtuConfig,
+    serial::{Framing, SerialClient},
+};
+
+/// ASCII master on a serial line. It has the timing of a RTU master, so it takes a
+/// [`RtuConfig`].
+pub type AsciiClient<T> = SerialClient<T, Ascii>;
+
+/// ASCII framing of a [`SerialClient`]
+#[derive(Debug)]
+pub struct Ascii;
+
+impl Framing for Ascii {
+    fn encode_request(
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        AduRequest::new(unit_id, req).encode(buf)
+    }
+
+    /// Skips anything before the `:`
+    fn frame_start(buf: &[u8]) -> usize {
+        buf.iter()
+            .position(|byte| *byte == b':')
+            .unwrap_or(buf.len())
+    }
+
+    fn frame_len(buf: &[u8]) -> Option<usize> {
+        frame_len(buf)
+    }
+
+    fn unit_id(frame: &[u8]) -> Option<u8> {
+        let digits = core::str::from_utf8(frame.get(1..3)?).ok()?;
+        u8::from_str_radix(digits, 16).ok()
+    }
+
+    fn decode_response<'b>(
+        frame: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<(u8, Result<PduResponse<'b>, ExceptionResponse>), DecodeError> {
+        let res = AduResponse::decode(frame, buf)?;
+        Ok((*res.unit_id(), res.into_pdu()))
+    }
+}
+
+/// Default response timeout of ASCII masters, longer than the RTU one as characters
+/// may be a second apart
+pub fn default_config() -> RtuConfig {
+    RtuConfig {
+        response_timeout: Duration::from_secs(2),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        client::{Client, Error, test::MockTransport},
+        exception_code::ExceptionCode,
+        pdu::{
+            DataWords, function_code::FunctionCode, request::Request as PduRequest,
+            response::Response as PduResponse,
+        },
+    };
+
+    use super::{AsciiClient, default_config};
+
+    #[test]
+    fn read_holding_registers() {
+        let transport = MockTransport::new(&[b"\0:01030400", b"060005", b"ED\r\n"]);
+        let mut client = AsciiClient::new(transport, default_config());
+
+        let mut buf = [0_u8; 256];
+        let res = client.request(1, PduRequest::ReadHoldingRegisters(0, 2), &mut buf);
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadHoldingRegisters(DataWords::new(&[0x00, 0x06, 0x00, 0x05], 2))
+        );
+        assert_eq!(client.transport().written, b":010300000002FA\r\n");
+    }
+
+    #[test]
+    fn exception_response() {
+        let transport = MockTransport::new(&[b":0A810273\r\n"]);
+        let mut client = AsciiClient::new(transport, default_config());
+
+        let mut buf = [0_u8; 256];
+        let res = client.request(10, PduRequest::ReadCoils(0, 1), &mut buf);
+        assert!(matches!(
+            res,
+            Err(Error::Exception(
+                FunctionCode::ReadCoils,
+                ExceptionCode::IllegalDataAddress
+            ))
+        ));
+    }
+
+    #[test]
+    fn exception_from_other_unit() {
+        let transport = MockTransport::new(&[b":0A810273\r\n", b":0a810273\r\n"]);
+        let mut client = AsciiClient::new(transport, default_config());
+
+        let mut buf = [0_u8; 256];
+        let res = client.request(11, PduRequest::ReadCoils(0, 1), &mut buf);
+        assert!(matches!(res, Err(Error::UnexpectedResponse)));
+        let res = client.request(10, PduRequest::ReadDiscreteInput(0, 1), &mut buf);
+        assert!(matches!(res, Err(Error::UnexpectedResponse)));
+    }
+
+    #[test]
+    fn late_response_is_discarded() {
+        let mut transport = MockTransport::new(&[b":0A810273\r\n"]);
+        // Answer to a request that timed out
+        transport.stale = b":0A010101F3\r\n".to_vec();
+        let mut client = AsciiClient::new(transport, default_config());
+
+        let mut buf = [0_u8; 256];
+        let res = client.request(10, PduRequest::ReadCoils(0, 1), &mut buf);
+        assert!(matches!(
+            res,
+            Err(Error::Exception(
+                FunctionCode::ReadCoils,
+                ExceptionCode::IllegalDataAddress
+            ))
+        ));
+    }
+}
@@ -0,0 +1,475 @@
+use std::{fmt, vec, vec::Vec};
+
+use crate::pdu::{
+    Address, MAX_READ_COILS, MAX_READ_REGISTERS, Quantity, RegisterType,
+    request::Request as PduRequest,
+};
+
+use super::{Client, Error, split};
+
+/// Items to read, like one value of a device
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Tag {
+    pub register_type: RegisterType,
+    pub address: Address,
+    pub quantity: Quantity,
+}
+
+impl Tag {
+    pub fn new(register_type: RegisterType, address: Address, quantity: Quantity) -> Self {
+        Self {
+            register_type,
+            address,
+            quantity,
+        }
+    }
+
+    fn end(&self) -> u32 {
+        self.address as u32 + self.quantity as u32
+    }
+}
+
+/// Address range that must never be read, as the device answers `IllegalDataAddress`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Hole {
+    pub register_type: RegisterType,
+    pub address: Address,
+    pub quantity: Quantity,
+}
+
+impl Hole {
+    pub fn new(register_type: RegisterType, address: Address, quantity: Quantity) -> Self {
+        Self {
+            register_type,
+            address,
+            quantity,
+        }
+    }
+
+    fn overlaps(&self, register_type: RegisterType, start: u32, end: u32) -> bool {
+        self.register_type == register_type
+            && (self.address as u32) < end
+            && start < self.address as u32 + self.quantity as u32
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CoalesceConfig {
+    /// Max number of unused items read between two tags to merge them into one request
+    pub max_gap: Quantity,
+    /// Max quantity of a register read, for devices with a lower limit than the protocol
+    pub max_registers: Quantity,
+    /// Max quantity of a coil or discrete input read
+    pub max_bits: Quantity,
+    pub holes: Vec<Hole>,
+}
+
+impl Default for CoalesceConfig {
+    fn default() -> Self {
+        Self {
+            max_gap: 0,
+            max_registers: MAX_READ_REGISTERS,
+            max_bits: MAX_READ_COILS,
+            holes: vec![],
+        }
+    }
+}
+
+impl CoalesceConfig {
+    fn max_quantity(&self, register_type: RegisterType) -> Quantity {
+        if register_type.is_bit() {
+            self.max_bits.min(MAX_READ_COILS)
+        } else {
+            self.max_registers.min(MAX_READ_REGISTERS)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PlanError {
+    /// The tag with this index reads a forbidden hole
+    TagInHole(usize),
+    /// The tag with this index can't be read with one request, or is empty
+    InvalidTagQuantity(usize),
+    /// The tag with this index goes beyond the 16 bit address space
+    TagOutOfRange(usize),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::TagInHole(tag) => write!(f, "tag {tag} reads a hole"),
+            PlanError::InvalidTagQuantity(tag) => {
+                write!(f, "tag {tag} can't be read with one request")
+            }
+            PlanError::TagOutOfRange(tag) => write!(f, "tag {tag} is out of the address space"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// One read request of a plan
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ReadBlock {
+    pub register_type: RegisterType,
+    pub address: Address,
+    pub quantity: Quantity,
+}
+
+impl ReadBlock {
+    pub fn request<'a>(&self) -> PduRequest<'a> {
+        self.register_type.read_request(self.address, self.quantity)
+    }
+}
+
+/// Where the items of a tag are found in the responses of a plan
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Placement {
+    /// Index of the block in [`ReadPlan::blocks`]
+    pub block: usize,
+    /// Offset of the tag's first item in the block
+    pub offset: usize,
+    pub quantity: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ReadPlan {
+    blocks: Vec<ReadBlock>,
+    placements: Vec<Placement>,
+}
+
+/// Merges the tags into as few read requests as the config allows.
+///
+/// Blocks are ordered by register type and address. Tags may overlap each other, they
+/// then share the items read.
+pub fn plan(tags: &[Tag], config: &CoalesceConfig) -> Result<ReadPlan, PlanError> {
+    for (i, tag) in tags.iter().enumerate() {
+        if tag.quantity == 0 || tag.quantity > config.max_quantity(tag.register_type) {
+            return Err(PlanError::InvalidTagQuantity(i));
+        }
+        if tag.end() > 0x10000 {
+            return Err(PlanError::TagOutOfRange(i));
+        }
+        if config
+            .holes
+            .iter()
+            .any(|hole| hole.overlaps(tag.register_type, tag.address as u32, tag.end()))
+        {
+            return Err(PlanError::TagInHole(i));
+        }
+    }
+
+    let mut order: Vec<usize> = (0..tags.len()).collect();
+    order.sort_by_key(|&i| (tags[i].register_type, tags[i].address, tags[i].quantity));
+
+    let mut blocks: Vec<ReadBlock> = vec![];
+    let mut placements = vec![
+        Placement {
+            block: 0,
+            offset: 0,
+            quantity: 0,
+        };
+        tags.len()
+    ];
+
+    for i in order {
+        let tag = &tags[i];
+        let merged = blocks.last_mut().filter(|block| {
+            let start = block.address as u32;
+            let end = start + block.quantity as u32;
+            let merged_end = end.max(tag.end());
+            block.register_type == tag.register_type
+                && tag.address as u32 <= end + config.max_gap as u32
+                && merged_end - start <= config.max_quantity(tag.register_type) as u32
+                && !config
+                    .holes
+                    .iter()
+                    .any(|hole| hole.overlaps(tag.register_type, start, merged_end))
+        });
+
+        match merged {
+            Some(block) => {
+                let merged_end = (block.address as u32 + block.quantity as u32).max(tag.end());
+                block.quantity = (merged_end - block.address as u32) as Quantity;
+            }
+            None => blocks.push(ReadBlock {
+                register_type: tag.register_type,
+                address: tag.address,
+                quantity: tag.quantity,
+            }),
+        }
+
+        let block_index = blocks.len() - 1;
+        placements[i] = Placement {
+            block: block_index,
+            offset: (tag.address - blocks[block_index].address) as usize,
+            quantity: tag.quantity as usize,
+        };
+    }
+
+    Ok(ReadPlan { blocks, placements })
+}
+
+impl ReadPlan {
+    pub fn blocks(&self) -> &[ReadBlock] {
+        &self.blocks
+    }
+
+    /// Placement of the tag with the index it had in the planned tags
+    pub fn placement(&self, tag: usize) -> &Placement {
+        &self.placements[tag]
+    }
+
+    /// Reads every block in order. A failing block doesn't stop the others, only the
+    /// tags in it get its error.
+    pub fn read<C: Client>(&self, client: &mut C, unit_id: u8) -> ReadValues<'_> {
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let result = if block.register_type.is_bit() {
+                    let mut bits = vec![false; block.quantity as usize];
+                    split::read_bits(
+                        client,
+                        unit_id,
+                        block.register_type,
+                        block.address,
+                        &mut bits,
+                    )
+                    .map(|_| BlockData::Bits(bits))
+                } else {
+                    let mut words = vec![0; block.quantity as usize];
+                    split::read_registers(
+                        client,
+                        unit_id,
+                        block.register_type,
+                        block.address,
+                        &mut words,
+                    )
+                    .map(|_| BlockData::Words(words))
+                };
+                result.map_err(|err| err.error)
+            })
+            .collect();
+
+        ReadValues { plan: self, blocks }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BlockData {
+    Words(Vec<u16>),
+    Bits(Vec<bool>),
+}
+
+/// Results of reading a plan, looked up per tag
+#[derive(Debug)]
+pub struct ReadValues<'p> {
+    plan: &'p ReadPlan,
+    blocks: Vec<Result<BlockData, Error>>,
+}
+
+impl ReadValues<'_> {
+    pub fn blocks(&self) -> &[Result<BlockData, Error>] {
+        &self.blocks
+    }
+
+    /// Words of a register tag, `Ok(None)` if it's a bit tag
+    pub fn words(&self, tag: usize) -> Result<Option<&[u16]>, &Error> {
+        let placement = self.plan.placement(tag);
+        match &self.blocks[placement.block] {
+            Ok(BlockData::Words(words)) => Ok(Some(
+                &words[placement.offset..placement.offset + placement.quantity],
+            )),
+            Ok(BlockData::Bits(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Bits of a coil or discrete input tag, `Ok(None)` if it's a register tag
+    pub fn bits(&self, tag: usize) -> Result<Option<&[bool]>, &Error> {
+        let placement = self.plan.placement(tag);
+        match &self.blocks[placement.block] {
+            Ok(BlockData::Bits(bits)) => Ok(Some(
+                &bits[placement.offset..placement.offset + placement.quantity],
+            )),
+            Ok(BlockData::Words(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{vec, vec::Vec};
+
+    use crate::{
+        client::{Error, split::test::MockDevice},
+        pdu::{Address, Quantity, RegisterType::*, function_code::FunctionCode},
+    };
+
+    use super::{CoalesceConfig, Hole, Placement, PlanError, ReadBlock, Tag, plan};
+
+    fn blocks(tags: &[Tag], config: &CoalesceConfig) -> Vec<(Address, Quantity)> {
+        plan(tags, config)
+            .unwrap()
+            .blocks()
+            .iter()
+            .map(|block| (block.address, block.quantity))
+            .collect()
+    }
+
+    #[test]
+    fn merges_adjacent_and_close_tags() {
+        let tags = [
+            Tag::new(HoldingRegister, 10, 2),
+            Tag::new(HoldingRegister, 0, 2),
+            Tag::new(HoldingRegister, 2, 4),
+            Tag::new(HoldingRegister, 30, 1),
+        ];
+        assert_eq!(
+            blocks(&tags, &CoalesceConfig::default()),
+            &[(0, 6), (10, 2), (30, 1)]
+        );
+
+        let config = CoalesceConfig {
+            max_gap: 4,
+            ..Default::default()
+        };
+        assert_eq!(blocks(&tags, &config), &[(0, 12), (30, 1)]);
+
+        let config = CoalesceConfig {
+            max_gap: 18,
+            ..Default::default()
+        };
+        assert_eq!(blocks(&tags, &config), &[(0, 31)]);
+    }
+
+    #[test]
+    fn respects_request_limits() {
+        let tags: Vec<Tag> = (0..100)
+            .map(|i| Tag::new(InputRegister, i * 2, 2))
+            .chain((0..3000).map(|i| Tag::new(Coil, i, 1)))
+            .collect();
+        assert_eq!(
+            blocks(&tags, &CoalesceConfig::default()),
+            &[(0, 2000), (2000, 1000), (0, 124), (124, 76)]
+        );
+
+        let config = CoalesceConfig {
+            max_registers: 50,
+            max_bits: 5000,
+            ..Default::default()
+        };
+        assert_eq!(
+            blocks(&tags, &config),
+            &[
+                (0, 2000),
+                (2000, 1000),
+                (0, 50),
+                (50, 50),
+                (100, 50),
+                (150, 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn never_merges_over_holes_or_register_types() {
+        let tags = [
+            Tag::new(HoldingRegister, 0, 2),
+            Tag::new(HoldingRegister, 10, 2),
+            Tag::new(InputRegister, 4, 2),
+        ];
+        let config = CoalesceConfig {
+            max_gap: 20,
+            holes: vec![Hole::new(HoldingRegister, 5, 1)],
+            ..Default::default()
+        };
+        assert_eq!(blocks(&tags, &config), &[(4, 2), (0, 2), (10, 2)]);
+
+        let config = CoalesceConfig {
+            max_gap: 20,
+            holes: vec![Hole::new(Coil, 5, 1)],
+            ..Default::default()
+        };
+        assert_eq!(blocks(&tags, &config), &[(4, 2), (0, 12)]);
+    }
+
+    #[test]
+    fn places_overlapping_tags() {
+        let tags = [
+            Tag::new(HoldingRegister, 100, 4),
+            Tag::new(HoldingRegister, 102, 2),
+            Tag::new(HoldingRegister, 100, 4),
+        ];
+        let read_plan = plan(&tags, &CoalesceConfig::default()).unwrap();
+        assert_eq!(
+            read_plan.blocks(),
+            &[ReadBlock {
+                register_type: HoldingRegister,
+                address: 100,
+                quantity: 4
+            }]
+        );
+        assert_eq!(
+            read_plan.placement(1),
+            &Placement {
+                block: 0,
+                offset: 2,
+                quantity: 2
+            }
+        );
+        assert_eq!(read_plan.placement(2).offset, 0);
+    }
+
+    #[test]
+    fn rejects_invalid_tags() {
+        let config = CoalesceConfig {
+            holes: vec![Hole::new(HoldingRegister, 5, 10)],
+            ..Default::default()
+        };
+        let tags = [
+            Tag::new(HoldingRegister, 0, 2),
+            Tag::new(HoldingRegister, 14, 2),
+        ];
+        assert_eq!(plan(&tags, &config), Err(PlanError::TagInHole(1)));
+        let tags = [Tag::new(InputRegister, 0, 126)];
+        assert_eq!(plan(&tags, &config), Err(PlanError::InvalidTagQuantity(0)));
+        let tags = [Tag::new(InputRegister, 0xffff, 2)];
+        assert_eq!(plan(&tags, &config), Err(PlanError::TagOutOfRange(0)));
+    }
+
+    #[test]
+    fn reads_plan_and_maps_tags() {
+        let tags = [
+            Tag::new(HoldingRegister, 3, 2),
+            Tag::new(Coil, 4, 3),
+            Tag::new(HoldingRegister, 0, 1),
+            Tag::new(InputRegister, 500, 1),
+        ];
+        let config = CoalesceConfig {
+            max_gap: 2,
+            ..Default::default()
+        };
+        let read_plan = plan(&tags, &config).unwrap();
+        let mut device = MockDevice::new();
+        device.fail_address = Some(500);
+
+        let values = read_plan.read(&mut device, 1);
+        assert_eq!(values.words(0).unwrap(), Some(&[3, 4][..]));
+        assert_eq!(values.bits(1).unwrap(), Some(&[false, false, true][..]));
+        assert_eq!(values.words(2).unwrap(), Some(&[0][..]));
+        assert_eq!(values.bits(2).unwrap(), None);
+        assert!(matches!(values.words(3), Err(Error::Exception(_, _))));
+        assert_eq!(
+            device.requests,
+            vec![
+                (FunctionCode::ReadCoils, 4, 3),
+                (FunctionCode::ReadInputRegisters, 500, 1),
+                (FunctionCode::ReadHoldingRegisters, 0, 5)
+            ]
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    pdu::{
+        function_code::FunctionCode, request::Request as PduRequest,
+        response::Response as PduResponse,
+    },
+    server::Handler,
+};
+
+use super::{Client, Error};
+
+/// Client passing requests straight to a server handler, to run client code against a
+/// simulated device without any transport
+#[derive(Debug, Default)]
+pub struct Loopback<H> {
+    handler: H,
+}
+
+impl<H: Handler> Loopback<H> {
+    pub fn new(handler: H) -> Self {
+        Self { handler }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+    pub fn into_inner(self) -> H {
+        self.handler
+    }
+}
+
+impl<H: Handler> Client for Loopback<H> {
+    fn request<'b>(
+        &mut self,
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, Error> {
+        self.handler
+            .handle(unit_id, &req, buf)
+            .map_err(|code| Error::Exception(FunctionCode::from(&req), code))
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{
+    exception_code::ExceptionCode,
+    pdu::{
+        Address, DataWords, function_code::FunctionCode, mask::BitMask,
+        request::Request as PduRequest, response::Response as PduResponse,
+    },
+};
+
+use super::{Client, Error, RESPONSE_BUF_SIZE};
+
+/// Changes bits of the holding register at `address` with a MaskWriteRegister request
+pub fn mask_write_register<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    address: Address,
+    mask: BitMask,
+) -> Result<(), Error> {
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    let req = mask.request(address);
+    match client.request(unit_id, req, &mut res_buf)? {
+        PduResponse::MaskWriteRegister(res_address, and_mask, or_mask)
+            if res_address == address
+                && and_mask == mask.and_mask()
+                && or_mask == mask.or_mask() =>
+        {
+            Ok(())
+        }
+        _ => Err(Error::UnexpectedResponse),
+    }
+}
+
+/// Changes bits of the holding register at `address` by reading it, then writing the
+/// new value with a ReadWriteMultipleRegisters request reading it back. Returns the
+/// value written.
+///
+/// Unlike MaskWriteRegister this isn't atomic: a write of the other bits between both
+/// requests is lost. Fails with [`Error::ConcurrentWrite`] when the value read back
+/// isn't the one written.
+pub fn read_modify_write<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    address: Address,
+    mask: BitMask,
+) -> Result<u16, Error> {
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    let current = match client.request(
+        unit_id,
+        PduRequest::ReadHoldingRegisters(address, 1),
+        &mut res_buf,
+    )? {
+        PduResponse::ReadHoldingRegisters(words) if words.quantity() == 1 => {
+            words.get(0).ok_or(Error::UnexpectedResponse)?
+        }
+        _ => return Err(Error::UnexpectedResponse),
+    };
+
+    let value = mask.apply(current);
+    let mut data_buf = [0_u8; 2];
+    let words = DataWords::from_words(&[value], &mut data_buf);
+    let req = PduRequest::ReadWriteMultipleRegisters(address, 1, address, words);
+    match client.request(unit_id, req, &mut res_buf)? {
+        PduResponse::ReadWriteMultipleRegisters(words) if words.quantity() == 1 => {
+            match words.get(0) {
+                Some(read) if read == value => Ok(value),
+                Some(_) => Err(Error::ConcurrentWrite(address)),
+                None => Err(Error::UnexpectedResponse),
+            }
+        }
+        _ => Err(Error::UnexpectedResponse),
+    }
+}
+
+/// Changes bits of the holding register at `address` with a MaskWriteRegister request,
+/// or with [`read_modify_write`] if the device answers it with `IllegalFunction`
+pub fn write_bits<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    address: Address,
+    mask: BitMask,
+) -> Result<(), Error> {
+    match mask_write_register(client, unit_id, address, mask) {
+        Err(Error::Exception(FunctionCode::MaskWriteRegister, ExceptionCode::IllegalFunction)) => {
+            read_modify_write(client, unit_id, address, mask).map(|_| ())
+        }
+        res => res,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{vec, vec::Vec};
+
+    use crate::{
+        client::{
+            Error,
+            loopback::Loopback,
+            rtu::{RtuClient, RtuConfig},
+            test::MockTransport,
+        },
+        exception_code::ExceptionCode,
+        pdu::{
+            RegisterType, function_code::FunctionCode, mask::BitMask,
+            request::Request as PduRequest, response::Response as PduResponse,
+        },
+        server::{Handler, registers::RegisterMap},
+    };
+
+    use super::{read_modify_write, write_bits};
+
+    /// Device without MaskWriteRegister, recording the function codes of the requests
+    #[derive(Debug, Default)]
+    struct NoMaskWrite {
+        map: RegisterMap,
+        fn_codes: Vec<FunctionCode>,
+    }
+
+    impl Handler for NoMaskWrite {
+        fn handle<'b>(
+            &mut self,
+            unit_id: u8,
+            req: &PduRequest<'_>,
+            buf: &'b mut [u8],
+        ) -> Result<PduResponse<'b>, ExceptionCode> {
+            self.fn_codes.push(FunctionCode::from(req));
+            if let PduRequest::MaskWriteRegister(_, _, _) = req {
+                return Err(ExceptionCode::IllegalFunction);
+            }
+            self.map.handle(unit_id, req, buf)
+        }
+    }
+
+    #[test]
+    fn writes_bits_with_mask_write() {
+        let mut map = RegisterMap::new();
+        map.set(RegisterType::HoldingRegister, 5, 0x00f0);
+        let mut client = Loopback::new(map);
+
+        write_bits(&mut client, 1, 5, BitMask::new().set(0).clear(4)).unwrap();
+        assert_eq!(
+            client.handler().get(RegisterType::HoldingRegister, 5),
+            Some(0x00e1)
+        );
+        let err = write_bits(&mut client, 1, 6, BitMask::new().set(0)).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Exception(
+                FunctionCode::MaskWriteRegister,
+                ExceptionCode::IllegalDataAddress
+            )
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_read_modify_write() {
+        let mut device = NoMaskWrite::default();
+        device.map.set(RegisterType::HoldingRegister, 5, 0x8001);
+        let mut client = Loopback::new(device);
+
+        write_bits(&mut client, 1, 5, BitMask::new().set(1).clear(15)).unwrap();
+        let device = client.handler();
+        assert_eq!(
+            device.map.get(RegisterType::HoldingRegister, 5),
+            Some(0x0003)
+        );
+        assert_eq!(
+            device.fn_codes,
+            vec![
+                FunctionCode::MaskWriteRegister,
+                FunctionCode::ReadHoldingRegisters,
+                FunctionCode::ReadWriteMultipleRegisters
+            ]
+        );
+
+        assert_eq!(
+            read_modify_write(&mut client, 1, 5, BitMask::new().set(8)).unwrap(),
+            0x0103
+        );
+    }
+
+    #[test]
+    fn falls_back_to_read_modify_write_over_rtu() {
+        let transport = MockTransport::new(&[
+            &[0x01, 0x96, 0x01, 0x8e, 0x60],
+            &[0x01, 0x03, 0x02, 0x80, 0x01, 0x18, 0x44],
+            &[0x01, 0x17, 0x02, 0x00, 0x03, 0xfd, 0xb5],
+        ]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        write_bits(&mut client, 1, 5, BitMask::new().set(1).clear(15)).unwrap();
+        let written: &[&[u8]] = &[
+            &[0x01, 0x16, 0x00, 0x05, 0x7f, 0xfd, 0x00, 0x02, 0x33, 0xe3],
+            &[0x01, 0x03, 0x00, 0x05, 0x00, 0x01, 0x94, 0x0b],
+            &[
+                0x01, 0x17, 0x00, 0x05, 0x00, 0x01, 0x00, 0x05, 0x00, 0x01, 0x02, 0x00, 0x03, 0x04,
+                0xea,
+            ],
+        ];
+        assert_eq!(client.transport().written, written.concat());
+    }
+
+    #[test]
+    fn detects_concurrent_writes() {
+        // Bit 15 is set again by another client
+        let transport = MockTransport::new(&[
+            &[0x01, 0x96, 0x01, 0x8e, 0x60],
+            &[0x01, 0x03, 0x02, 0x80, 0x01, 0x18, 0x44],
+            &[0x01, 0x17, 0x02, 0x80, 0x03, 0x9c, 0x75],
+        ]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let err = write_bits(&mut client, 1, 5, BitMask::new().set(1).clear(15)).unwrap_err();
+        assert!(matches!(err, Error::ConcurrentWrite(5)));
+    }
+}
@@ -0,0 +1,128 @@
+pub mod ascii;
+pub mod coalesce;
+pub mod loopback;
+pub mod mask;
+pub mod poll;
+pub mod rtu;
+pub mod serial;
+pub mod split;
+pub mod tcp;
+
+use crate::pdu::{
+    function_code::FunctionCode, request::Request as PduRequest, response::Response as PduResponse,
+};
+
+pub use crate::error::Error;
+
+/// Size of a response buffer fitting the frames of every transport
+pub const RESPONSE_BUF_SIZE: usize = crate::adu::tcp::MAX_ADU_SIZE;
+
+/// Request/response exchange with a server, implemented by the TCP, RTU and ASCII clients
+pub trait Client {
+    /// Sends the request to `unit_id` and waits for its response, which is decoded
+    /// from `buf`. Exception responses are returned as [`Error::Exception`].
+    ///
+    /// `buf` holds the whole response frame, [`RESPONSE_BUF_SIZE`] bytes always fit.
+    fn request<'b>(
+        &mut self,
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, Error>;
+}
+
+impl<C: Client + ?Sized> Client for &mut C {
+    fn request<'b>(
+        &mut self,
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, Error> {
+        (**self).request(unit_id, req, buf)
+    }
+}
+
+/// Whether the function code reads data from the server
+fn is_read_function(fn_code: FunctionCode) -> bool {
+    matches!(
+        fn_code,
+        FunctionCode::ReadCoils
+            | FunctionCode::ReadDiscreteInput
+            | FunctionCode::ReadHoldingRegisters
+            | FunctionCode::ReadInputRegisters
+            | FunctionCode::ReadWriteMultipleRegisters
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        collections::VecDeque,
+        io::{self, Read, Write},
+        time::Duration,
+        vec,
+        vec::Vec,
+    };
+
+    use crate::transport::Transport;
+
+    /// Transport answering reads with the queued chunks and timing out when there are none
+    #[derive(Debug, Default)]
+    pub(crate) struct MockTransport {
+        pub(crate) written: Vec<u8>,
+        pub(crate) to_read: VecDeque<Vec<u8>>,
+        /// Received before anything was written, read first unless cleared
+        pub(crate) stale: Vec<u8>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn new(chunks: &[&[u8]]) -> Self {
+            Self {
+                written: vec![],
+                to_read: chunks.iter().map(|chunk| chunk.to_vec()).collect(),
+                stale: vec![],
+            }
+        }
+    }
+
+    impl Read for MockTransport {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.stale.is_empty() {
+                let len = self.stale.len().min(buf.len());
+                buf[..len].copy_from_slice(&self.stale[..len]);
+                self.stale.drain(..len);
+                return Ok(len);
+            }
+            let Some(mut chunk) = self.to_read.pop_front() else {
+                return Err(io::ErrorKind::TimedOut.into());
+            };
+            let len = chunk.len().min(buf.len());
+            buf[..len].copy_from_slice(&chunk[..len]);
+            if len < chunk.len() {
+                self.to_read.push_front(chunk.split_off(len));
+            }
+            Ok(len)
+        }
+    }
+
+    impl Write for MockTransport {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn set_read_timeout(&mut self, _timeout: Option<Duration>) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn clear_input(&mut self) -> io::Result<()> {
+            self.stale.clear();
+            Ok(())
+        }
+    }
+}
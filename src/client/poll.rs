@@ -0,0 +1,625 @@
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fmt, thread,
+    time::{Duration, Instant},
+    vec,
+    vec::Vec,
+};
+
+use super::{
+    Client, Error,
+    coalesce::{self, CoalesceConfig, PlanError, ReadPlan, Tag},
+};
+
+/// Source of time for the [`Poller`], so schedules can be tested without waiting
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep_until(&self, deadline: Instant);
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+    fn sleep_until(&self, deadline: Instant) {
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// Clock only moving when advanced or slept on
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Cell<Instant>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(Instant::now()),
+        }
+    }
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+    fn sleep_until(&self, deadline: Instant) {
+        if deadline > self.now.get() {
+            self.now.set(deadline);
+        }
+    }
+}
+
+impl<K: Clock> Clock for &K {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+    fn sleep_until(&self, deadline: Instant) {
+        (**self).sleep_until(deadline)
+    }
+}
+
+/// What to do with the polls missed when a poll ends after the next one was due
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Overrun {
+    /// Poll again right away until the schedule is caught up
+    CatchUp,
+    /// Drop the missed polls and continue with the next one due in the future
+    #[default]
+    Skip,
+}
+
+/// When a tag value is notified
+#[derive(Debug, Default, Copy, Clone)]
+pub enum Notify {
+    /// On every poll
+    Always,
+    /// When it differs from the last notified value
+    #[default]
+    OnChange,
+    /// When the decoded value moved more than the deadband from the last notified value.
+    /// Bit tags are notified on change.
+    Deadband(f64, fn(&[u16]) -> f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct PollTag {
+    pub tag: Tag,
+    pub notify: Notify,
+}
+
+impl PollTag {
+    pub fn new(tag: Tag, notify: Notify) -> Self {
+        Self { tag, notify }
+    }
+}
+
+/// Tags of one device polled together with the same interval
+#[derive(Debug, Clone)]
+pub struct PollGroup {
+    pub unit_id: u8,
+    pub interval: Duration,
+    /// Delay of the first poll, to spread groups with the same interval over time.
+    /// Later polls are scheduled from it and not from when the previous poll ended,
+    /// so slow responses don't shift the schedule.
+    pub phase: Duration,
+    pub overrun: Overrun,
+    pub coalesce: CoalesceConfig,
+    pub tags: Vec<PollTag>,
+}
+
+impl PollGroup {
+    pub fn new(unit_id: u8, interval: Duration, tags: Vec<PollTag>) -> Self {
+        Self {
+            unit_id,
+            interval,
+            phase: Duration::ZERO,
+            overrun: Overrun::default(),
+            coalesce: CoalesceConfig::default(),
+            tags,
+        }
+    }
+}
+
+/// Error of [`Poller::add_group`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GroupError {
+    /// The interval of the group is zero
+    ZeroInterval,
+    Plan(PlanError),
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupError::ZeroInterval => write!(f, "the poll interval is zero"),
+            GroupError::Plan(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for GroupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GroupError::Plan(err) => Some(err),
+            GroupError::ZeroInterval => None,
+        }
+    }
+}
+
+impl From<PlanError> for GroupError {
+    fn from(err: PlanError) -> Self {
+        GroupError::Plan(err)
+    }
+}
+
+/// Delay before polling a device again after it stopped answering, doubled on every
+/// failed retry
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    Words(Vec<u16>),
+    Bits(Vec<bool>),
+}
+
+#[derive(Debug)]
+pub enum Event<'a> {
+    Value {
+        group: usize,
+        tag: usize,
+        value: &'a Value,
+    },
+    /// The device answered the read of the tag with an error, like an exception
+    Error {
+        group: usize,
+        tag: usize,
+        error: &'a Error,
+    },
+    /// Polls dropped with [`Overrun::Skip`]
+    Overrun {
+        group: usize,
+        missed: u32,
+    },
+    /// The device timed out or the connection failed, its groups are polled again
+    /// after the back-off
+    Offline {
+        unit_id: u8,
+    },
+    Online {
+        unit_id: u8,
+    },
+}
+
+#[derive(Debug)]
+struct GroupState {
+    group: PollGroup,
+    plan: ReadPlan,
+    next_due: Instant,
+    notified: Vec<Option<Value>>,
+}
+
+#[derive(Debug, Default)]
+struct DeviceState {
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+/// Polls groups of tags on their schedules and notifies changed values
+#[derive(Debug)]
+pub struct Poller<C, K> {
+    client: C,
+    clock: K,
+    backoff: Backoff,
+    groups: Vec<GroupState>,
+    devices: BTreeMap<u8, DeviceState>,
+}
+
+impl<C: Client, K: Clock> Poller<C, K> {
+    pub fn new(client: C, clock: K, backoff: Backoff) -> Self {
+        Self {
+            client,
+            clock,
+            backoff,
+            groups: vec![],
+            devices: BTreeMap::new(),
+        }
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+    pub fn client_mut(&mut self) -> &mut C {
+        &mut self.client
+    }
+    pub fn clock(&self) -> &K {
+        &self.clock
+    }
+
+    /// Adds the group and returns its index used in events. The first poll is due
+    /// after the group's phase.
+    pub fn add_group(&mut self, group: PollGroup) -> Result<usize, GroupError> {
+        if group.interval.is_zero() {
+            return Err(GroupError::ZeroInterval);
+        }
+        let tags: Vec<Tag> = group.tags.iter().map(|tag| tag.tag).collect();
+        let plan = coalesce::plan(&tags, &group.coalesce)?;
+        self.devices.entry(group.unit_id).or_default();
+        self.groups.push(GroupState {
+            next_due: self.clock.now() + group.phase,
+            notified: vec![None; tags.len()],
+            group,
+            plan,
+        });
+        Ok(self.groups.len() - 1)
+    }
+
+    /// Time of the next poll, `None` without groups
+    pub fn next_due(&self) -> Option<Instant> {
+        self.groups.iter().map(|state| self.due(state)).min()
+    }
+
+    fn due(&self, state: &GroupState) -> Instant {
+        match self.devices[&state.group.unit_id].retry_at {
+            Some(retry_at) => state.next_due.max(retry_at),
+            None => state.next_due,
+        }
+    }
+
+    /// Polls every group due now, in the order they were added, and returns the time
+    /// of the next poll
+    pub fn poll(&mut self, mut on_event: impl FnMut(Event<'_>)) -> Option<Instant> {
+        for index in 0..self.groups.len() {
+            if self.due(&self.groups[index]) <= self.clock.now() {
+                self.poll_group(index, &mut on_event);
+            }
+        }
+        self.next_due()
+    }
+
+    /// Polls forever, sleeping on the clock between polls. Returns only without groups.
+    pub fn run(&mut self, mut on_event: impl FnMut(Event<'_>)) {
+        while let Some(next_due) = self.poll(&mut on_event) {
+            self.clock.sleep_until(next_due);
+        }
+    }
+
+    fn poll_group(&mut self, index: usize, on_event: &mut impl FnMut(Event<'_>)) {
+        let state = &mut self.groups[index];
+        let unit_id = state.group.unit_id;
+        let device = self.devices.get_mut(&unit_id).unwrap();
+        let was_offline = device.retry_at.is_some();
+
+        let values = state.plan.read(&mut self.client, unit_id);
+        let now = self.clock.now();
+
+        let offline = values
+            .blocks()
+            .iter()
+            .any(|block| matches!(block, Err(Error::Timeout | Error::Io(_))));
+        if offline {
+            device.backoff = if was_offline {
+                (device.backoff * 2).min(self.backoff.max)
+            } else {
+                self.backoff.initial
+            };
+            device.retry_at = Some(now + device.backoff);
+            if !was_offline {
+                on_event(Event::Offline { unit_id });
+            }
+        } else {
+            if was_offline {
+                *device = DeviceState::default();
+                on_event(Event::Online { unit_id });
+            }
+
+            for (tag, poll_tag) in state.group.tags.iter().enumerate() {
+                let value = if poll_tag.tag.register_type.is_bit() {
+                    values
+                        .bits(tag)
+                        .map(|bits| Value::Bits(bits.unwrap_or_default().to_vec()))
+                } else {
+                    values
+                        .words(tag)
+                        .map(|words| Value::Words(words.unwrap_or_default().to_vec()))
+                };
+                match value {
+                    Ok(value) => {
+                        let notified = &mut state.notified[tag];
+                        if should_notify(poll_tag.notify, notified.as_ref(), &value) {
+                            on_event(Event::Value {
+                                group: index,
+                                tag,
+                                value: &value,
+                            });
+                            *notified = Some(value);
+                        }
+                    }
+                    Err(error) => {
+                        state.notified[tag] = None;
+                        on_event(Event::Error {
+                            group: index,
+                            tag,
+                            error,
+                        });
+                    }
+                }
+            }
+        }
+
+        state.next_due += state.group.interval;
+        if state.next_due <= now && (state.group.overrun == Overrun::Skip || was_offline) {
+            let behind = (now - state.next_due).as_nanos() / state.group.interval.as_nanos();
+            let missed = behind as u32 + 1;
+            state.next_due += state.group.interval * missed;
+            if !was_offline {
+                on_event(Event::Overrun {
+                    group: index,
+                    missed,
+                });
+            }
+        }
+    }
+}
+
+fn should_notify(notify: Notify, notified: Option<&Value>, value: &Value) -> bool {
+    let Some(notified) = notified else {
+        return true;
+    };
+    match (notify, notified, value) {
+        (Notify::Always, _, _) => true,
+        (Notify::Deadband(deadband, decode), Value::Words(old), Value::Words(new)) => {
+            (decode(new) - decode(old)).abs() > deadband
+        }
+        _ => notified != value,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{time::Duration, vec, vec::Vec};
+
+    use crate::{
+        client::{
+            Client, Error,
+            coalesce::{PlanError, Tag},
+            split::test::MockDevice,
+        },
+        pdu::{RegisterType::*, request::Request as PduRequest, response::Response as PduResponse},
+    };
+
+    use super::{
+        Backoff, Clock, Event, GroupError, ManualClock, Notify, Overrun, PollGroup, PollTag,
+        Poller, Value,
+    };
+
+    /// [`MockDevice`] that can be unplugged
+    struct Device {
+        inner: MockDevice,
+        offline: bool,
+    }
+
+    impl Client for Device {
+        fn request<'b>(
+            &mut self,
+            unit_id: u8,
+            req: PduRequest<'_>,
+            buf: &'b mut [u8],
+        ) -> Result<PduResponse<'b>, Error> {
+            if self.offline {
+                return Err(Error::Timeout);
+            }
+            self.inner.request(unit_id, req, buf)
+        }
+    }
+
+    fn poller(clock: &ManualClock) -> Poller<Device, &ManualClock> {
+        let device = Device {
+            inner: MockDevice::new(),
+            offline: false,
+        };
+        Poller::new(device, clock, Backoff::default())
+    }
+
+    fn values(poller: &mut Poller<Device, &ManualClock>) -> Vec<(usize, usize, Value)> {
+        let mut values = vec![];
+        poller.poll(|event| {
+            if let Event::Value { group, tag, value } = event {
+                values.push((group, tag, value.clone()));
+            }
+        });
+        values
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn rejects_invalid_groups() {
+        let clock = ManualClock::new();
+        let mut poller = poller(&clock);
+        let tag = PollTag::new(Tag::new(HoldingRegister, 0, 1), Notify::Always);
+        assert_eq!(
+            poller.add_group(PollGroup::new(1, Duration::ZERO, vec![tag])),
+            Err(GroupError::ZeroInterval)
+        );
+        let tag = PollTag::new(Tag::new(HoldingRegister, 0, 0), Notify::Always);
+        assert_eq!(
+            poller.add_group(PollGroup::new(1, secs(1), vec![tag])),
+            Err(GroupError::Plan(PlanError::InvalidTagQuantity(0)))
+        );
+        assert_eq!(poller.next_due(), None);
+    }
+
+    #[test]
+    fn polls_groups_on_their_schedule() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let mut poller = poller(&clock);
+        let tag = PollTag::new(Tag::new(HoldingRegister, 0, 1), Notify::Always);
+        poller
+            .add_group(PollGroup::new(1, secs(1), vec![tag.clone()]))
+            .unwrap();
+        let mut slow = PollGroup::new(1, secs(5), vec![tag]);
+        slow.phase = Duration::from_millis(500);
+        poller.add_group(slow).unwrap();
+
+        let mut polls = vec![];
+        for _ in 0..8 {
+            let groups: Vec<usize> = values(&mut poller).iter().map(|value| value.0).collect();
+            polls.push((clock.now() - start, groups));
+            clock.sleep_until(poller.next_due().unwrap());
+        }
+        assert_eq!(
+            polls,
+            vec![
+                (secs(0), vec![0]),
+                (Duration::from_millis(500), vec![1]),
+                (secs(1), vec![0]),
+                (secs(2), vec![0]),
+                (secs(3), vec![0]),
+                (secs(4), vec![0]),
+                (secs(5), vec![0]),
+                (Duration::from_millis(5500), vec![1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn overrun_policies() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let mut poller = poller(&clock);
+        let tag = PollTag::new(Tag::new(HoldingRegister, 0, 1), Notify::Always);
+        poller
+            .add_group(PollGroup::new(1, secs(1), vec![tag.clone()]))
+            .unwrap();
+        let mut catch_up = PollGroup::new(1, secs(1), vec![tag]);
+        catch_up.overrun = Overrun::CatchUp;
+        poller.add_group(catch_up).unwrap();
+
+        poller.poll(|_| {});
+        clock.advance(Duration::from_millis(3500));
+        let mut events = vec![];
+        poller.poll(|event| {
+            if let Event::Overrun { group, missed } = event {
+                events.push((group, missed));
+            }
+        });
+        assert_eq!(events, vec![(0, 2)]);
+        assert_eq!(poller.groups[0].next_due - start, secs(4));
+        assert_eq!(poller.groups[1].next_due - start, secs(2));
+        assert_eq!(poller.next_due(), Some(start + secs(2)));
+
+        assert_eq!(values(&mut poller), vec![(1, 0, Value::Words(vec![0]))]);
+        assert_eq!(values(&mut poller), vec![(1, 0, Value::Words(vec![0]))]);
+        assert_eq!(values(&mut poller), vec![]);
+        assert_eq!(poller.next_due(), Some(start + secs(4)));
+    }
+
+    #[test]
+    fn notifies_changes_and_deadband() {
+        let clock = ManualClock::new();
+        let mut poller = poller(&clock);
+        let tags = vec![
+            PollTag::new(Tag::new(HoldingRegister, 0, 1), Notify::OnChange),
+            PollTag::new(
+                Tag::new(HoldingRegister, 1, 1),
+                Notify::Deadband(10.0, |words| words[0] as f64),
+            ),
+            PollTag::new(Tag::new(Coil, 3, 2), Notify::OnChange),
+        ];
+        poller.add_group(PollGroup::new(1, secs(1), tags)).unwrap();
+
+        assert_eq!(
+            values(&mut poller),
+            vec![
+                (0, 0, Value::Words(vec![0])),
+                (0, 1, Value::Words(vec![1])),
+                (0, 2, Value::Bits(vec![true, false])),
+            ]
+        );
+
+        clock.advance(secs(1));
+        poller.client_mut().inner.registers[1] = 11;
+        assert_eq!(values(&mut poller), vec![]);
+
+        clock.advance(secs(1));
+        poller.client_mut().inner.registers[0] = 7;
+        poller.client_mut().inner.registers[1] = 12;
+        poller.client_mut().inner.coils[4] = true;
+        assert_eq!(
+            values(&mut poller),
+            vec![
+                (0, 0, Value::Words(vec![7])),
+                (0, 1, Value::Words(vec![12])),
+                (0, 2, Value::Bits(vec![true, true])),
+            ]
+        );
+    }
+
+    #[test]
+    fn backs_off_offline_devices() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let mut poller = poller(&clock);
+        let tag = PollTag::new(Tag::new(InputRegister, 9, 1), Notify::OnChange);
+        poller
+            .add_group(PollGroup::new(1, secs(1), vec![tag]))
+            .unwrap();
+        poller.client_mut().offline = true;
+
+        let mut events = vec![];
+        for i in 0..5 {
+            if i == 4 {
+                poller.client_mut().offline = false;
+            }
+            let now = clock.now() - start;
+            poller.poll(|event| match event {
+                Event::Offline { unit_id: 1 } => events.push((now, "offline")),
+                Event::Online { unit_id: 1 } => events.push((now, "online")),
+                Event::Value { .. } => events.push((now, "value")),
+                _ => {}
+            });
+            clock.sleep_until(poller.next_due().unwrap());
+        }
+        assert_eq!(
+            events,
+            vec![
+                (secs(0), "offline"),
+                (secs(1 + 2 + 4 + 8), "online"),
+                (secs(1 + 2 + 4 + 8), "value")
+            ]
+        );
+        assert_eq!(poller.client().inner.requests.len(), 1);
+        assert_eq!(clock.now() - start, secs(16));
+    }
+}
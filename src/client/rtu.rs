@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use crate::{
+    adu::rtu::{request::Request as AduRequest, response::Response as AduResponse},
+    error::{DecodeError, EncodeError},
+    pdu::{
+        exception_response::ExceptionResponse, request::Request as PduRequest,
+        response::Response as PduResponse,
+    },
+};
+
+use super::serial::{Framing, SerialClient};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RtuConfig {
+    /// Max time between sending a request and receiving the complete response
+    pub response_timeout: Duration,
+    /// Time to wait after a broadcast before the next request, so the servers
+    /// have time to process it
+    pub turnaround_delay: Duration,
+}
+
+impl Default for RtuConfig {
+    fn default() -> Self {
+        Self {
+            response_timeout: Duration::from_secs(1),
+            turnaround_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Reply<'a> {
+    Response(PduResponse<'a>),
+    /// The request was broadcast (unit id 0), so there is no response
+    Broadcast,
+}
+
+/// RTU master on a serial line
+pub type RtuClient<T> = SerialClient<T, Rtu>;
+
+/// RTU framing of a [`SerialClient`]
+#[derive(Debug)]
+pub struct Rtu;
+
+impl Framing for Rtu {
+    fn encode_request(
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        AduRequest::new(unit_id, req).encode(buf)
+    }
+
+    fn frame_len(buf: &[u8]) -> Option<usize> {
+        match AduResponse::decode(buf) {
+            Ok(res) => Some(res.adu_len()),
+            Err(DecodeError::IncompleteBuffer { .. }) => None,
+            // Decoded again to return the error
+            Err(_) => Some(buf.len()),
+        }
+    }
+
+    fn unit_id(frame: &[u8]) -> Option<u8> {
+        frame.first().copied()
+    }
+
+    fn decode_response<'b>(
+        frame: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<(u8, Result<PduResponse<'b>, ExceptionResponse>), DecodeError> {
+        let available = buf.len();
+        let buf = buf
+            .get_mut(..frame.len())
+            .ok_or(DecodeError::IncompleteBuffer {
+                current_size: available,
+                min_needed_size: frame.len(),
+            })?;
+        buf.copy_from_slice(frame);
+        let res = AduResponse::decode(buf)?;
+        Ok((*res.unit_id(), res.into_pdu()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        time::{Duration, Instant},
+        vec,
+    };
+
+    use crate::{
+        client::{Error, test::MockTransport},
+        exception_code::ExceptionCode,
+        pdu::{
+            DataWords, function_code::FunctionCode, request::Request as PduRequest,
+            response::Response as PduResponse,
+        },
+    };
+
+    use super::{Reply, RtuClient, RtuConfig};
+
+    #[test]
+    fn read_holding_registers() {
+        let transport =
+            MockTransport::new(&[&[0x01, 0x03, 0x04, 0x00], &[0x06, 0x00, 0x05, 0xda, 0x31]]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(1, PduRequest::ReadHoldingRegisters(0, 2), &mut buf);
+        assert_eq!(
+            reply.unwrap(),
+            Reply::Response(PduResponse::ReadHoldingRegisters(DataWords::new(
+                &[0x00, 0x06, 0x00, 0x05],
+                2
+            )))
+        );
+        assert_eq!(
+            client.transport().written,
+            &[0x01, 0x03, 0x00, 0x00, 0x00, 0x02, 0xc4, 0x0b]
+        );
+    }
+
+    #[test]
+    fn broadcast_isnt_answered() {
+        let transport = MockTransport::new(&[&[0x00, 0x06, 0x00, 0x01, 0x00, 0x03]]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(0, PduRequest::WriteSingleRegister(1, 3), &mut buf);
+        assert_eq!(reply.unwrap(), Reply::Broadcast);
+        // Nothing was read
+        assert_eq!(client.transport().to_read.len(), 1);
+    }
+
+    #[test]
+    fn broadcast_read_is_rejected() {
+        let mut client = RtuClient::new(MockTransport::default(), RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(0, PduRequest::ReadInputRegisters(0, 1), &mut buf);
+        assert!(matches!(
+            reply,
+            Err(Error::BroadcastNotAllowed(FunctionCode::ReadInputRegisters))
+        ));
+        assert!(client.transport().written.is_empty());
+    }
+
+    #[test]
+    fn turnaround_delay_after_broadcast() {
+        let config = RtuConfig {
+            turnaround_delay: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let transport = MockTransport::new(&[&[0x01, 0x06, 0x00, 0x01, 0x00, 0x03, 0x98, 0x0b]]);
+        let mut client = RtuClient::new(transport, config);
+
+        let mut buf = [0_u8; 256];
+        let start = Instant::now();
+        client
+            .send(0, PduRequest::WriteSingleRegister(1, 3), &mut buf)
+            .unwrap();
+        let reply = client.send(1, PduRequest::WriteSingleRegister(1, 3), &mut buf);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(
+            reply.unwrap(),
+            Reply::Response(PduResponse::WriteSingleRegister(1, 3))
+        );
+    }
+
+    #[test]
+    fn response_timeout() {
+        let transport = MockTransport::new(&[&[0x01, 0x03, 0x04, 0x00]]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(1, PduRequest::ReadHoldingRegisters(0, 2), &mut buf);
+        assert!(matches!(reply, Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn exception_response() {
+        let transport = MockTransport::new(&[&[0x0a, 0x81, 0x02, 0xb0, 0x53]]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(10, PduRequest::ReadCoils(0x7d0, 1), &mut buf);
+        assert!(matches!(
+            reply,
+            Err(Error::Exception(
+                FunctionCode::ReadCoils,
+                ExceptionCode::IllegalDataAddress
+            ))
+        ));
+    }
+
+    #[test]
+    fn exception_from_other_unit() {
+        let exception: &[u8] = &[0x0a, 0x81, 0x02, 0xb0, 0x53];
+        let transport = MockTransport::new(&[exception, exception]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(11, PduRequest::ReadCoils(0x7d0, 1), &mut buf);
+        assert!(matches!(reply, Err(Error::UnexpectedResponse)));
+        let reply = client.send(10, PduRequest::ReadDiscreteInput(0x7d0, 1), &mut buf);
+        assert!(matches!(reply, Err(Error::UnexpectedResponse)));
+    }
+
+    #[test]
+    fn late_response_is_discarded() {
+        let mut transport =
+            MockTransport::new(&[&[0x01, 0x06, 0x00, 0x01, 0x00, 0x03, 0x98, 0x0b]]);
+        // Answer to a request that timed out
+        transport.stale = vec![0x01, 0x06, 0x00, 0x01, 0x00, 0x02, 0x59, 0xcb];
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(1, PduRequest::WriteSingleRegister(1, 3), &mut buf);
+        assert_eq!(
+            reply.unwrap(),
+            Reply::Response(PduResponse::WriteSingleRegister(1, 3))
+        );
+    }
+
+    #[test]
+    fn response_from_other_unit() {
+        let transport = MockTransport::new(&[&[0x01, 0x06, 0x00, 0x01, 0x00, 0x03, 0x98, 0x0b]]);
+        let mut client = RtuClient::new(transport, RtuConfig::default());
+
+        let mut buf = [0_u8; 256];
+        let reply = client.send(2, PduRequest::WriteSingleRegister(1, 3), &mut buf);
+        assert!(matches!(reply, Err(Error::UnexpectedResponse)));
+        let reply = client.send(248, PduRequest::WriteSingleRegister(1, 3), &mut buf);
+        assert!(matches!(reply, Err(Error::InvalidUnitId(248))));
+    }
+}
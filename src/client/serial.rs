@@ -0,0 +1,214 @@
+use std::{io, marker::PhantomData, thread, time::Instant};
+
+use crate::{
+    adu::{ascii, rtu::BROADCAST_UNIT_ID},
+    error::{DecodeError, EncodeError},
+    instrument::{Outcome, Role, Transaction},
+    pdu::{
+        exception_response::ExceptionResponse, function_code::FunctionCode,
+        request::Request as PduRequest, response::Response as PduResponse,
+    },
+    transport::Transport,
+};
+
+use super::{
+    Client, Error, is_read_function,
+    rtu::{Reply, RtuConfig},
+};
+
+/// Size of the frame buffers, fitting both RTU and ASCII frames
+const MAX_FRAME_SIZE: usize = ascii::MAX_ADU_SIZE;
+
+/// Framing of the ADUs of a [`SerialClient`], implemented by [`Rtu`](super::rtu::Rtu)
+/// and [`Ascii`](super::ascii::Ascii)
+pub trait Framing {
+    /// Encodes the request to `unit_id` to `buf`, returning its length
+    fn encode_request(
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &mut [u8],
+    ) -> Result<usize, EncodeError>;
+
+    /// Position of the start of the frame in what was received, anything before it is
+    /// dropped
+    fn frame_start(_buf: &[u8]) -> usize {
+        0
+    }
+
+    /// Length of the frame starting `buf`, `None` if it isn't complete yet
+    fn frame_len(buf: &[u8]) -> Option<usize>;
+
+    /// Unit id of a complete frame, without decoding the rest of it
+    fn unit_id(frame: &[u8]) -> Option<u8>;
+
+    /// Decodes a complete frame, to `buf` which the response borrows. Returns its unit id
+    /// and PDU.
+    fn decode_response<'b>(
+        frame: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<(u8, Result<PduResponse<'b>, ExceptionResponse>), DecodeError>;
+}
+
+/// Master on a serial line, with the framing `F`
+#[derive(Debug)]
+pub struct SerialClient<T, F> {
+    transport: T,
+    config: RtuConfig,
+    turnaround_until: Option<Instant>,
+    framing: PhantomData<F>,
+}
+
+impl<T: Transport, F: Framing> SerialClient<T, F> {
+    pub fn new(transport: T, config: RtuConfig) -> Self {
+        Self {
+            transport,
+            config,
+            turnaround_until: None,
+            framing: PhantomData,
+        }
+    }
+
+    pub fn config(&self) -> &RtuConfig {
+        &self.config
+    }
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Sends the request and waits for its response, which is decoded to `buf`.
+    ///
+    /// Requests to unit id 0 are broadcast and return [`Reply::Broadcast`] right
+    /// after sending. RTU frames of custom function codes have no known length, so
+    /// their response is whatever was received by the first read.
+    pub fn send<'b>(
+        &mut self,
+        unit_id: u8,
+        pdu_req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<Reply<'b>, Error> {
+        if unit_id > 247 {
+            return Err(Error::InvalidUnitId(unit_id));
+        }
+        let fn_code = FunctionCode::from(&pdu_req);
+        if unit_id == BROADCAST_UNIT_ID && is_read_function(fn_code) {
+            return Err(Error::BroadcastNotAllowed(fn_code));
+        }
+
+        let transaction = Transaction::start(Role::Client, unit_id, fn_code.into(), None);
+        let res = self.transact(unit_id, pdu_req, buf);
+        let outcome = match &res {
+            Ok(Reply::Broadcast) => Outcome::NoResponse,
+            res => Outcome::of(res),
+        };
+        transaction.finish(outcome);
+        res
+    }
+
+    fn transact<'b>(
+        &mut self,
+        unit_id: u8,
+        pdu_req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<Reply<'b>, Error> {
+        let fn_code = FunctionCode::from(&pdu_req);
+        let mut req_buf = [0_u8; MAX_FRAME_SIZE];
+        let req_len = F::encode_request(unit_id, pdu_req, &mut req_buf)?;
+
+        if let Some(turnaround_until) = self.turnaround_until.take() {
+            let now = Instant::now();
+            if turnaround_until > now {
+                thread::sleep(turnaround_until - now);
+            }
+        }
+
+        // A late response to a previous request would be taken for the answer to this one
+        self.transport.clear_input()?;
+        self.transport.write_all(&req_buf[..req_len])?;
+        self.transport.flush()?;
+
+        if unit_id == BROADCAST_UNIT_ID {
+            self.turnaround_until = Some(Instant::now() + self.config.turnaround_delay);
+            return Ok(Reply::Broadcast);
+        }
+
+        let mut frame_buf = [0_u8; MAX_FRAME_SIZE];
+        let frame = self.read_frame(&mut frame_buf)?;
+        let (res_unit_id, pdu) = match F::decode_response(frame, buf) {
+            // Exceptions are errors of the decoding, so their unit id is checked here
+            Err(DecodeError::ModbusExceptionCode(res_fn_code, _))
+                if F::unit_id(frame) != Some(unit_id) || res_fn_code != fn_code =>
+            {
+                return Err(Error::UnexpectedResponse);
+            }
+            res => res?,
+        };
+        if res_unit_id != unit_id {
+            return Err(Error::UnexpectedResponse);
+        }
+        match pdu {
+            Ok(pdu) if FunctionCode::from(&pdu) == fn_code => Ok(Reply::Response(pdu)),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Reads until a frame is complete, within the response timeout
+    fn read_frame<'f>(&mut self, buf: &'f mut [u8]) -> Result<&'f [u8], Error> {
+        let deadline = Instant::now() + self.config.response_timeout;
+        let mut pos = 0;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Timeout);
+            }
+            if pos == buf.len() {
+                return Err(Error::Decode(DecodeError::IncompleteBuffer {
+                    current_size: pos,
+                    min_needed_size: pos + 1,
+                }));
+            }
+
+            self.transport.set_read_timeout(Some(remaining))?;
+            let bytes_read = match self.transport.read(&mut buf[pos..]) {
+                Ok(0) => return Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+                Ok(bytes_read) => bytes_read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            pos += bytes_read;
+
+            let start = F::frame_start(&buf[..pos]);
+            buf.copy_within(start..pos, 0);
+            pos -= start;
+
+            if let Some(len) = F::frame_len(&buf[..pos]) {
+                return Ok(&buf[..len]);
+            }
+        }
+    }
+}
+
+impl<T: Transport, F: Framing> Client for SerialClient<T, F> {
+    /// Broadcasts have no response, so they can only be sent with
+    /// [`SerialClient::send`]
+    fn request<'b>(
+        &mut self,
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, Error> {
+        if unit_id == BROADCAST_UNIT_ID {
+            return Err(Error::BroadcastNotAllowed(FunctionCode::from(&req)));
+        }
+        match self.send(unit_id, req, buf)? {
+            Reply::Response(res) => Ok(res),
+            Reply::Broadcast => Err(Error::UnexpectedResponse),
+        }
+    }
+}
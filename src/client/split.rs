@@ -0,0 +1,439 @@
+use std::fmt;
+
+use crate::pdu::{
+    Address, DataCoils, DataWords, MAX_PDU_SIZE, MAX_READ_COILS, MAX_WRITE_COILS,
+    MAX_WRITE_REGISTERS, Quantity, RegisterType, request::Request as PduRequest,
+    response::Response as PduResponse,
+};
+
+use super::{Client, Error, RESPONSE_BUF_SIZE};
+
+/// Error of one of the requests of a split read or write, with the address range it covered
+#[derive(Debug)]
+pub struct ChunkError {
+    pub address: Address,
+    pub quantity: usize,
+    pub error: Error,
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} items at {}: {}",
+            self.quantity, self.address, self.error
+        )
+    }
+}
+
+impl std::error::Error for ChunkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Iterator over the `(address, quantity)` of the requests needed for a range of addresses
+#[derive(Debug, Clone)]
+pub struct Chunks {
+    address: u32,
+    end: u32,
+    max_quantity: Quantity,
+}
+
+/// Splits `quantity` items starting at `address` into chunks of at most `max_quantity`
+pub fn chunks(address: Address, quantity: usize, max_quantity: Quantity) -> Chunks {
+    Chunks {
+        address: address as u32,
+        end: (address as u32 + quantity as u32).min(0x10000),
+        max_quantity,
+    }
+}
+
+impl Iterator for Chunks {
+    type Item = (Address, Quantity);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.address >= self.end {
+            return None;
+        }
+        let quantity = (self.end - self.address).min(self.max_quantity as u32);
+        let chunk = (self.address as Address, quantity as Quantity);
+        self.address += quantity;
+        Some(chunk)
+    }
+}
+
+fn check_range(address: Address, quantity: usize) -> Result<(), ChunkError> {
+    if address as usize + quantity > 0x10000 {
+        return Err(ChunkError {
+            address,
+            quantity,
+            error: Error::AddressOutOfRange,
+        });
+    }
+    Ok(())
+}
+
+/// Reads `words.len()` input or holding registers starting at `address`, using as many
+/// requests as the protocol limits need.
+///
+/// # Panics
+///
+/// Panics if `register_type` isn't a register type.
+pub fn read_registers<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    register_type: RegisterType,
+    address: Address,
+    words: &mut [u16],
+) -> Result<(), ChunkError> {
+    assert!(
+        !register_type.is_bit(),
+        "{register_type:?} isn't a register"
+    );
+    check_range(address, words.len())?;
+
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    let mut offset = 0;
+    for (chunk_address, quantity) in chunks(address, words.len(), register_type.max_read_quantity())
+    {
+        let chunk_error = |error| ChunkError {
+            address: chunk_address,
+            quantity: quantity as usize,
+            error,
+        };
+
+        let req = register_type.read_request(chunk_address, quantity);
+        let data_words = match client.request(unit_id, req, &mut res_buf) {
+            Ok(PduResponse::ReadInputRegisters(data_words))
+            | Ok(PduResponse::ReadHoldingRegisters(data_words)) => data_words,
+            Ok(_) => return Err(chunk_error(Error::UnexpectedResponse)),
+            Err(err) => return Err(chunk_error(err)),
+        };
+        if data_words.quantity() != quantity as usize {
+            return Err(chunk_error(Error::UnexpectedResponse));
+        }
+
+        let end = offset + quantity as usize;
+        data_words.copy_words_to(&mut words[offset..end]);
+        offset = end;
+    }
+
+    Ok(())
+}
+
+/// Reads `bits.len()` coils or discrete inputs starting at `address`, using as many
+/// requests as the protocol limits need.
+///
+/// # Panics
+///
+/// Panics if `register_type` isn't a bit type.
+pub fn read_bits<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    register_type: RegisterType,
+    address: Address,
+    bits: &mut [bool],
+) -> Result<(), ChunkError> {
+    assert!(register_type.is_bit(), "{register_type:?} isn't a bit type");
+    check_range(address, bits.len())?;
+
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    // Responses are padded up to a whole byte
+    let mut chunk_bits = [false; MAX_READ_COILS as usize];
+    let mut offset = 0;
+    for (chunk_address, quantity) in chunks(address, bits.len(), register_type.max_read_quantity())
+    {
+        let chunk_error = |error| ChunkError {
+            address: chunk_address,
+            quantity: quantity as usize,
+            error,
+        };
+
+        let req = register_type.read_request(chunk_address, quantity);
+        let data_coils = match client.request(unit_id, req, &mut res_buf) {
+            Ok(PduResponse::ReadCoils(data_coils))
+            | Ok(PduResponse::ReadDiscreteInput(data_coils)) => data_coils,
+            Ok(_) => return Err(chunk_error(Error::UnexpectedResponse)),
+            Err(err) => return Err(chunk_error(err)),
+        };
+        if data_coils.data_len() != (quantity as usize).div_ceil(8) {
+            return Err(chunk_error(Error::UnexpectedResponse));
+        }
+
+        let end = offset + quantity as usize;
+        let received = data_coils.copy_coils_to(&mut chunk_bits);
+        bits[offset..end].copy_from_slice(&received[..quantity as usize]);
+        offset = end;
+    }
+
+    Ok(())
+}
+
+/// Writes `words` to the holding registers starting at `address`, using as many
+/// requests as the protocol limits need. The requests are sent in address order, so
+/// when one fails the registers before it have been written.
+pub fn write_registers<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    address: Address,
+    words: &[u16],
+) -> Result<(), ChunkError> {
+    check_range(address, words.len())?;
+
+    let mut data_buf = [0_u8; MAX_PDU_SIZE];
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    let mut offset = 0;
+    for (chunk_address, quantity) in chunks(address, words.len(), MAX_WRITE_REGISTERS) {
+        let chunk_error = |error| ChunkError {
+            address: chunk_address,
+            quantity: quantity as usize,
+            error,
+        };
+
+        let end = offset + quantity as usize;
+        let data_words = DataWords::from_words(&words[offset..end], &mut data_buf);
+        let req = PduRequest::WriteMultipleRegisters(chunk_address, data_words);
+        match client.request(unit_id, req, &mut res_buf) {
+            Ok(PduResponse::WriteMultipleRegisters(res_address, res_quantity))
+                if res_address == chunk_address && res_quantity == quantity => {}
+            Ok(_) => return Err(chunk_error(Error::UnexpectedResponse)),
+            Err(err) => return Err(chunk_error(err)),
+        }
+        offset = end;
+    }
+
+    Ok(())
+}
+
+/// Writes `coils` starting at `address`, using as many requests as the protocol
+/// limits need. The requests are sent in address order, so when one fails the coils
+/// before it have been written.
+pub fn write_coils<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    address: Address,
+    coils: &[bool],
+) -> Result<(), ChunkError> {
+    check_range(address, coils.len())?;
+
+    let mut data_buf = [0_u8; MAX_PDU_SIZE];
+    let mut res_buf = [0_u8; RESPONSE_BUF_SIZE];
+    let mut offset = 0;
+    for (chunk_address, quantity) in chunks(address, coils.len(), MAX_WRITE_COILS) {
+        let chunk_error = |error| ChunkError {
+            address: chunk_address,
+            quantity: quantity as usize,
+            error,
+        };
+
+        let end = offset + quantity as usize;
+        let data_coils = DataCoils::from_coils(&coils[offset..end], &mut data_buf);
+        let req = PduRequest::WriteMultipleCoils(chunk_address, data_coils);
+        match client.request(unit_id, req, &mut res_buf) {
+            Ok(PduResponse::WriteMultipleCoils(res_address, res_quantity))
+                if res_address == chunk_address && res_quantity == quantity => {}
+            Ok(_) => return Err(chunk_error(Error::UnexpectedResponse)),
+            Err(err) => return Err(chunk_error(err)),
+        }
+        offset = end;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{vec, vec::Vec};
+
+    use crate::{
+        client::{Client, Error},
+        exception_code::ExceptionCode,
+        pdu::{
+            Address, DataCoils, DataWords, Quantity, RegisterType, function_code::FunctionCode,
+            request::Request as PduRequest, response::Response as PduResponse,
+        },
+    };
+
+    use super::{chunks, read_bits, read_registers, write_coils, write_registers};
+
+    /// Client answering from memory, with 0x10000 registers and coils
+    pub(crate) struct MockDevice {
+        pub(crate) registers: Vec<u16>,
+        pub(crate) coils: Vec<bool>,
+        pub(crate) requests: Vec<(FunctionCode, Address, Quantity)>,
+        /// Requests starting at this address fail with IllegalDataAddress
+        pub(crate) fail_address: Option<Address>,
+    }
+
+    impl MockDevice {
+        pub(crate) fn new() -> Self {
+            Self {
+                registers: (0..=u16::MAX).collect(),
+                coils: (0..=u16::MAX).map(|i| i % 3 == 0).collect(),
+                requests: vec![],
+                fail_address: None,
+            }
+        }
+    }
+
+    impl Client for MockDevice {
+        fn request<'b>(
+            &mut self,
+            _unit_id: u8,
+            req: PduRequest<'_>,
+            buf: &'b mut [u8],
+        ) -> Result<PduResponse<'b>, Error> {
+            let fn_code = FunctionCode::from(&req);
+            let (address, quantity) = match &req {
+                PduRequest::ReadCoils(address, quantity)
+                | PduRequest::ReadDiscreteInput(address, quantity)
+                | PduRequest::ReadHoldingRegisters(address, quantity)
+                | PduRequest::ReadInputRegisters(address, quantity) => (*address, *quantity),
+                PduRequest::WriteMultipleCoils(address, coils) => {
+                    (*address, coils.quantity() as Quantity)
+                }
+                PduRequest::WriteMultipleRegisters(address, words) => {
+                    (*address, words.quantity() as Quantity)
+                }
+                PduRequest::WriteSingleCoil(address, _)
+                | PduRequest::WriteSingleRegister(address, _) => (*address, 1),
+                _ => return Err(Error::Exception(fn_code, ExceptionCode::IllegalFunction)),
+            };
+            self.requests.push((fn_code, address, quantity));
+            if self.fail_address == Some(address) {
+                return Err(Error::Exception(fn_code, ExceptionCode::IllegalDataAddress));
+            }
+
+            let range = address as usize..address as usize + quantity as usize;
+            let res = match req {
+                PduRequest::ReadCoils(_, _) => {
+                    PduResponse::ReadCoils(DataCoils::from_coils(&self.coils[range], buf))
+                }
+                PduRequest::ReadDiscreteInput(_, _) => {
+                    PduResponse::ReadDiscreteInput(DataCoils::from_coils(&self.coils[range], buf))
+                }
+                PduRequest::ReadHoldingRegisters(_, _) => PduResponse::ReadHoldingRegisters(
+                    DataWords::from_words(&self.registers[range], buf),
+                ),
+                PduRequest::ReadInputRegisters(_, _) => PduResponse::ReadInputRegisters(
+                    DataWords::from_words(&self.registers[range], buf),
+                ),
+                PduRequest::WriteMultipleCoils(_, coils) => {
+                    coils.copy_coils_to(&mut self.coils[range]);
+                    PduResponse::WriteMultipleCoils(address, quantity)
+                }
+                PduRequest::WriteMultipleRegisters(_, words) => {
+                    words.copy_words_to(&mut self.registers[range]);
+                    PduResponse::WriteMultipleRegisters(address, quantity)
+                }
+                PduRequest::WriteSingleCoil(_, coil) => {
+                    self.coils[address as usize] = coil;
+                    PduResponse::WriteSingleCoil(address, coil)
+                }
+                PduRequest::WriteSingleRegister(_, word) => {
+                    self.registers[address as usize] = word;
+                    PduResponse::WriteSingleRegister(address, word)
+                }
+                _ => return Err(Error::Exception(fn_code, ExceptionCode::IllegalFunction)),
+            };
+            Ok(res)
+        }
+    }
+
+    #[test]
+    fn chunks_of_range() {
+        assert_eq!(
+            chunks(0, 300, 125).collect::<Vec<_>>(),
+            &[(0, 125), (125, 125), (250, 50)]
+        );
+        assert_eq!(chunks(65530, 6, 125).collect::<Vec<_>>(), &[(65530, 6)]);
+        assert_eq!(chunks(10, 0, 125).count(), 0);
+    }
+
+    #[test]
+    fn read_many_registers() {
+        let mut device = MockDevice::new();
+        let mut words = vec![0_u16; 1000];
+        read_registers(&mut device, 1, RegisterType::HoldingRegister, 0, &mut words).unwrap();
+
+        assert_eq!(words, (0..1000).collect::<Vec<u16>>());
+        assert_eq!(device.requests.len(), 8);
+        assert!(
+            device
+                .requests
+                .iter()
+                .enumerate()
+                .all(|(i, req)| *req == (FunctionCode::ReadHoldingRegisters, i as u16 * 125, 125))
+        );
+    }
+
+    #[test]
+    fn read_many_bits() {
+        let mut device = MockDevice::new();
+        let mut bits = vec![false; 2100];
+        read_bits(&mut device, 1, RegisterType::DiscreteInput, 10, &mut bits).unwrap();
+
+        assert_eq!(bits, &device.coils[10..2110]);
+        assert_eq!(
+            device.requests,
+            &[
+                (FunctionCode::ReadDiscreteInput, 10, 2000),
+                (FunctionCode::ReadDiscreteInput, 2010, 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn write_many_registers_and_coils() {
+        let mut device = MockDevice::new();
+        let words = vec![7_u16; 300];
+        write_registers(&mut device, 1, 100, &words).unwrap();
+        assert_eq!(&device.registers[100..400], words.as_slice());
+        assert_eq!(device.registers[400], 400);
+
+        let coils = vec![true; 2000];
+        write_coils(&mut device, 1, 0, &coils).unwrap();
+        assert_eq!(&device.coils[..2000], coils.as_slice());
+
+        assert_eq!(
+            device.requests,
+            &[
+                (FunctionCode::WriteMultipleRegisters, 100, 123),
+                (FunctionCode::WriteMultipleRegisters, 223, 123),
+                (FunctionCode::WriteMultipleRegisters, 346, 54),
+                (FunctionCode::WriteMultipleCoils, 0, 1968),
+                (FunctionCode::WriteMultipleCoils, 1968, 32),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_failed_range() {
+        let mut device = MockDevice::new();
+        device.fail_address = Some(250);
+        let mut words = vec![0_u16; 1000];
+        let err =
+            read_registers(&mut device, 1, RegisterType::InputRegister, 0, &mut words).unwrap_err();
+
+        assert_eq!((err.address, err.quantity), (250, 125));
+        assert!(matches!(
+            err.error,
+            Error::Exception(
+                FunctionCode::ReadInputRegisters,
+                ExceptionCode::IllegalDataAddress
+            )
+        ));
+        assert_eq!(device.requests.len(), 3);
+        assert_eq!(&words[..250], &device.registers[..250]);
+    }
+
+    #[test]
+    fn rejects_range_beyond_address_space() {
+        let mut device = MockDevice::new();
+        let err = write_registers(&mut device, 1, 65500, &[0; 100]).unwrap_err();
+        assert_eq!((err.address, err.quantity), (65500, 100));
+        assert!(matches!(err.error, Error::AddressOutOfRange));
+        assert!(device.requests.is_empty());
+    }
+}
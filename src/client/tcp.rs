@@ -0,0 +1,330 @@
+use std::{
+    io,
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    adu::tcp::{
+        MAX_ADU_SIZE, header::Header, request::Request as AduRequest,
+        response::Response as AduResponse,
+    },
+    error::DecodeError,
+    instrument::{Outcome, Role, Transaction},
+    pdu::{
+        function_code::FunctionCode, request::Request as PduRequest,
+        response::Response as PduResponse,
+    },
+    transport::Transport,
+};
+
+use super::{Client, Error};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TcpConfig {
+    /// Max time between sending a request and receiving the complete response
+    pub response_timeout: Duration,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        Self {
+            response_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Modbus TCP client, sending one request at a time
+#[derive(Debug)]
+pub struct TcpClient<T> {
+    transport: T,
+    config: TcpConfig,
+    transaction_id: u16,
+}
+
+impl TcpClient<TcpStream> {
+    /// Connects to the first address of `addr` accepting the connection within
+    /// `connect_timeout`
+    pub fn connect(
+        addr: impl ToSocketAddrs,
+        connect_timeout: Duration,
+        config: TcpConfig,
+    ) -> io::Result<Self> {
+        let mut last_err = None;
+        for addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, connect_timeout) {
+                Ok(stream) => {
+                    stream.set_nodelay(true)?;
+                    return Ok(Self::new(stream, config));
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to connect to")))
+    }
+}
+
+impl<T: Transport> TcpClient<T> {
+    pub fn new(transport: T, config: TcpConfig) -> Self {
+        Self {
+            transport,
+            config,
+            transaction_id: 0,
+        }
+    }
+
+    pub fn config(&self) -> &TcpConfig {
+        &self.config
+    }
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Transaction id of the last request sent
+    pub fn transaction_id(&self) -> u16 {
+        self.transaction_id
+    }
+
+    /// Sends the request and waits for its response, which is decoded from `buf`.
+    /// Late responses to earlier requests, recognized by their transaction id, are skipped.
+    pub fn send<'b>(
+        &mut self,
+        unit_id: u8,
+        pdu_req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, Error> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let fn_code = FunctionCode::from(&pdu_req);
+        let transaction = Transaction::start(
+            Role::Client,
+            unit_id,
+            fn_code.into(),
+            Some(self.transaction_id),
+        );
+        let res = self.transact(unit_id, pdu_req, buf);
+        transaction.finish(Outcome::of(&res));
+        res
+    }
+
+    fn transact<'b>(
+        &mut self,
+        unit_id: u8,
+        pdu_req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, Error> {
+        let fn_code = FunctionCode::from(&pdu_req);
+        let req = AduRequest::new(self.transaction_id, unit_id, pdu_req);
+
+        let mut req_buf = [0_u8; MAX_ADU_SIZE];
+        let req_len = req.encode(&mut req_buf)?;
+        self.transport.write_all(&req_buf[..req_len])?;
+        self.transport.flush()?;
+
+        let adu_len = self.read_frame(buf)?;
+        let res = AduResponse::decode(&buf[..adu_len])?;
+        if *res.header().unit_id() != unit_id {
+            return Err(Error::UnexpectedResponse);
+        }
+        match res.into_pdu() {
+            Ok(pdu) if FunctionCode::from(&pdu) == fn_code => Ok(pdu),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Reads until the frame with the current transaction id is at the start of `buf`
+    /// and returns its length
+    fn read_frame(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let deadline = Instant::now() + self.config.response_timeout;
+        let mut pos = 0;
+
+        loop {
+            if pos >= Header::size() {
+                let header = Header::decode(&buf[..pos])?;
+                // unit_id is included in the header.length and header.size
+                let adu_len = *header.length() as usize + Header::size() - 1;
+                if *header.protocol_id() != 0 || adu_len > buf.len() {
+                    return Err(Error::UnexpectedResponse);
+                }
+                if adu_len <= pos {
+                    if *header.transaction_id() == self.transaction_id {
+                        return Ok(adu_len);
+                    }
+                    buf.copy_within(adu_len..pos, 0);
+                    pos -= adu_len;
+                    continue;
+                }
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Timeout);
+            }
+            if pos == buf.len() {
+                return Err(Error::Decode(DecodeError::IncompleteBuffer {
+                    current_size: pos,
+                    min_needed_size: pos + 1,
+                }));
+            }
+
+            self.transport.set_read_timeout(Some(remaining))?;
+            match self.transport.read(&mut buf[pos..]) {
+                Ok(0) => return Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+                Ok(bytes_read) => pos += bytes_read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+        }
+    }
+}
+
+impl<T: Transport> Client for TcpClient<T> {
+    fn request<'b>(
+        &mut self,
+        unit_id: u8,
+        req: PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, Error> {
+        self.send(unit_id, req, buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io,
+        net::{SocketAddr, TcpListener},
+        time::Duration,
+    };
+
+    use crate::{
+        client::{Error, split, test::MockTransport},
+        exception_code::ExceptionCode,
+        pdu::{
+            DataWords, function_code::FunctionCode, request::Request as PduRequest,
+            response::Response as PduResponse,
+        },
+    };
+
+    use super::{TcpClient, TcpConfig};
+
+    #[test]
+    fn read_input_registers() {
+        let transport = MockTransport::new(&[&[0, 1, 0, 0, 0, 7, 1], &[4, 4, 0, 1, 0, 2]]);
+        let mut client = TcpClient::new(transport, TcpConfig::default());
+
+        let mut buf = [0_u8; 260];
+        let res = client.send(1, PduRequest::ReadInputRegisters(2, 2), &mut buf);
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadInputRegisters(DataWords::new(&[0, 1, 0, 2], 2))
+        );
+        assert_eq!(
+            client.transport().written,
+            &[0, 1, 0, 0, 0, 6, 1, 4, 0, 2, 0, 2]
+        );
+    }
+
+    #[test]
+    fn skips_late_responses() {
+        let transport = MockTransport::new(&[
+            &[0, 1, 0, 0, 0, 6, 1, 6, 0, 1, 0, 3],
+            &[0, 2, 0, 0, 0, 6, 1, 6, 0, 1, 0, 4],
+        ]);
+        let mut client = TcpClient::new(transport, TcpConfig::default());
+        client.transaction_id = 1;
+
+        let mut buf = [0_u8; 260];
+        let res = client.send(1, PduRequest::WriteSingleRegister(1, 4), &mut buf);
+        assert_eq!(res.unwrap(), PduResponse::WriteSingleRegister(1, 4));
+        assert_eq!(client.transaction_id(), 2);
+    }
+
+    #[test]
+    fn connects_to_first_address_accepting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        // Refused once the listener is dropped
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let addrs = [closed, listener.local_addr().unwrap()];
+
+        let timeout = Duration::from_secs(1);
+        let client = TcpClient::connect(&addrs[..], timeout, TcpConfig::default()).unwrap();
+        assert_eq!(client.transport().peer_addr().unwrap(), addrs[1]);
+        let no_addrs: &[SocketAddr] = &[];
+        let err = TcpClient::connect(no_addrs, timeout, TcpConfig::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn write_multiple_requests() {
+        let transport = MockTransport::new(&[
+            &[0, 1, 0, 0, 0, 6, 1, 0x0f, 0, 0x13, 0, 3],
+            &[0, 2, 0, 0, 0, 6, 1, 0x10, 0, 1, 0, 2],
+            &[
+                0, 3, 0, 0, 0, 15, 1, 0x17, 12, 0, 0xfe, 0x0a, 0xcd, 0, 1, 0, 3, 0, 0x0d, 0, 0xff,
+            ],
+        ]);
+        let mut client = TcpClient::new(transport, TcpConfig::default());
+
+        split::write_coils(&mut client, 1, 0x13, &[true, false, true]).unwrap();
+        split::write_registers(&mut client, 1, 1, &[0x000a, 0x0102]).unwrap();
+        let mut data = [0_u8; 6];
+        let words = DataWords::from_words(&[0x00ff, 0x00ff, 0x00ff], &mut data);
+        let mut buf = [0_u8; 260];
+        let res = client.send(
+            1,
+            PduRequest::ReadWriteMultipleRegisters(3, 6, 0x0e, words),
+            &mut buf,
+        );
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadWriteMultipleRegisters(DataWords::new(
+                &[0, 0xfe, 0x0a, 0xcd, 0, 1, 0, 3, 0, 0x0d, 0, 0xff],
+                6
+            ))
+        );
+
+        let written: &[&[u8]] = &[
+            &[0, 1, 0, 0, 0, 8, 1, 0x0f, 0, 0x13, 0, 3, 1, 0x05],
+            &[0, 2, 0, 0, 0, 11, 1, 0x10, 0, 1, 0, 2, 4, 0, 0x0a, 1, 2],
+            &[
+                0, 3, 0, 0, 0, 17, 1, 0x17, 0, 3, 0, 6, 0, 0x0e, 0, 3, 6, 0, 0xff, 0, 0xff, 0, 0xff,
+            ],
+        ];
+        assert_eq!(client.transport().written, written.concat());
+    }
+
+    #[test]
+    fn exception_response() {
+        let transport = MockTransport::new(&[&[0, 1, 0, 0, 0, 3, 1, 0x83, 0x02]]);
+        let mut client = TcpClient::new(transport, TcpConfig::default());
+
+        let mut buf = [0_u8; 260];
+        let res = client.send(1, PduRequest::ReadHoldingRegisters(0, 1), &mut buf);
+        assert!(matches!(
+            res,
+            Err(Error::Exception(
+                FunctionCode::ReadHoldingRegisters,
+                ExceptionCode::IllegalDataAddress
+            ))
+        ));
+    }
+
+    #[test]
+    fn response_timeout() {
+        let mut client = TcpClient::new(MockTransport::default(), TcpConfig::default());
+
+        let mut buf = [0_u8; 260];
+        let res = client.send(1, PduRequest::ReadHoldingRegisters(0, 1), &mut buf);
+        assert!(matches!(res, Err(Error::Timeout)));
+    }
+}
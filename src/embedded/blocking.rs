@@ -0,0 +1,389 @@
+//! Clients and servers over the blocking [`embedded_io`] traits
+
+use embedded_io::{Read, Write};
+
+use crate::{
+    adu::{
+        rtu::{self, crc::check_crc, request::Request as RtuRequest},
+        tcp::{self, request::Request as TcpRequest},
+    },
+    pdu::{
+        function_code::FunctionCode, request::Request as PduRequest,
+        response::Response as PduResponse,
+    },
+    server::{Handler, rtu::RtuServer as RtuProcessor, tcp::process_frame},
+};
+
+use super::{Error, check_unit_id, rtu_response, rtu_response_len, tcp_frame, tcp_response};
+
+/// Reads at least one byte
+fn read<T: Read>(io: &mut T, buf: &mut [u8]) -> Result<usize, Error<T::Error>> {
+    match io.read(buf).map_err(Error::Io)? {
+        0 => Err(Error::Eof),
+        bytes_read => Ok(bytes_read),
+    }
+}
+
+fn write<T: Write>(io: &mut T, buf: &[u8]) -> Result<(), Error<T::Error>> {
+    io.write_all(buf).map_err(Error::Io)?;
+    io.flush().map_err(Error::Io)
+}
+
+/// Modbus TCP client, sending one request at a time. Its frames fit `N` bytes,
+/// [`tcp::MAX_ADU_SIZE`] fitting every frame.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TcpClient<T, const N: usize = { tcp::MAX_ADU_SIZE }> {
+    io: T,
+    transaction_id: u16,
+    buf: [u8; N],
+}
+
+impl<T: Read + Write, const N: usize> TcpClient<T, N> {
+    pub fn new(io: T) -> Self {
+        Self {
+            io,
+            transaction_id: 0,
+            buf: [0; N],
+        }
+    }
+
+    pub fn io(&self) -> &T {
+        &self.io
+    }
+    pub fn io_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// Transaction id of the last request sent
+    pub fn transaction_id(&self) -> u16 {
+        self.transaction_id
+    }
+
+    /// Sends the request and waits for its response. Late responses to earlier
+    /// requests, recognized by their transaction id, are skipped.
+    pub fn request(
+        &mut self,
+        unit_id: u8,
+        pdu_req: PduRequest<'_>,
+    ) -> Result<PduResponse<'_>, Error<T::Error>> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let fn_code = FunctionCode::from(&pdu_req);
+        let req_len =
+            TcpRequest::new(self.transaction_id, unit_id, pdu_req).encode(&mut self.buf)?;
+        write(&mut self.io, &self.buf[..req_len])?;
+
+        let mut pos = 0;
+        let adu_len = loop {
+            match tcp_frame(&self.buf[..pos], N)? {
+                Some((transaction_id, adu_len)) if transaction_id == self.transaction_id => {
+                    break adu_len;
+                }
+                Some((_, adu_len)) => {
+                    self.buf.copy_within(adu_len..pos, 0);
+                    pos -= adu_len;
+                }
+                None => pos += read(&mut self.io, &mut self.buf[pos..])?,
+            }
+        };
+        tcp_response(&self.buf[..adu_len], unit_id, fn_code)
+    }
+}
+
+/// RTU master on a serial line. Its frames fit `N` bytes, [`rtu::MAX_ADU_SIZE`]
+/// fitting every frame.
+///
+/// Broadcasts have no response, so they can't be sent. Frames of custom function codes
+/// have no known length, so their response is whatever was received by the first read.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RtuClient<T, const N: usize = { rtu::MAX_ADU_SIZE }> {
+    io: T,
+    buf: [u8; N],
+}
+
+impl<T: Read + Write, const N: usize> RtuClient<T, N> {
+    pub fn new(io: T) -> Self {
+        Self { io, buf: [0; N] }
+    }
+
+    pub fn io(&self) -> &T {
+        &self.io
+    }
+    pub fn io_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// Sends the request and waits for its response
+    pub fn request(
+        &mut self,
+        unit_id: u8,
+        pdu_req: PduRequest<'_>,
+    ) -> Result<PduResponse<'_>, Error<T::Error>> {
+        let fn_code = FunctionCode::from(&pdu_req);
+        check_unit_id(unit_id, fn_code)?;
+        let req_len = RtuRequest::new(unit_id, pdu_req).encode(&mut self.buf)?;
+        write(&mut self.io, &self.buf[..req_len])?;
+
+        let mut pos = 0;
+        let adu_len = loop {
+            if pos == N {
+                return Err(Error::InvalidFrame);
+            }
+            pos += read(&mut self.io, &mut self.buf[pos..])?;
+            if let Some(adu_len) = rtu_response_len(&self.buf[..pos])? {
+                break adu_len;
+            }
+        };
+        rtu_response(&self.buf[..adu_len], unit_id, fn_code)
+    }
+}
+
+/// Modbus TCP server answering the requests of a connection with a [`Handler`]. The
+/// requests and responses fit `N` bytes, [`tcp::MAX_ADU_SIZE`] fitting every frame.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TcpServer<H, const N: usize = { tcp::MAX_ADU_SIZE }> {
+    handler: H,
+    req_buf: [u8; N],
+    res_buf: [u8; N],
+}
+
+impl<H: Handler, const N: usize> TcpServer<H, N> {
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler,
+            req_buf: [0; N],
+            res_buf: [0; N],
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+    pub fn into_inner(self) -> H {
+        self.handler
+    }
+
+    /// Serves the requests of one connection until the peer closes it. Frames that
+    /// can't be delimited end it with [`Error::InvalidFrame`].
+    pub fn serve<S: Read + Write>(&mut self, mut stream: S) -> Result<(), Error<S::Error>> {
+        let mut len = 0;
+        loop {
+            // A read may contain several pipelined requests
+            while let Some((_, adu_len)) = tcp_frame(&self.req_buf[..len], N)? {
+                let frame = &self.req_buf[..adu_len];
+                if let Some(res_len) = process_frame(frame, &mut self.res_buf, &mut self.handler) {
+                    write(&mut stream, &self.res_buf[..res_len])?;
+                }
+                self.req_buf.copy_within(adu_len..len, 0);
+                len -= adu_len;
+            }
+
+            match read(&mut stream, &mut self.req_buf[len..]) {
+                Ok(bytes_read) => len += bytes_read,
+                Err(Error::Eof) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// RTU server for one unit id on a serial line, see [`crate::server::rtu::RtuServer`].
+/// The requests fit `N` bytes, [`rtu::MAX_ADU_SIZE`] fitting every frame.
+///
+/// There is no timer to tell the silent interval ending a frame, so a frame ends once
+/// the length its function code gives was received. Frames of custom function codes
+/// end with the read they start in, and what was received after a frame with an
+/// invalid CRC is dropped.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RtuServer<H, const N: usize = { rtu::MAX_ADU_SIZE }> {
+    processor: RtuProcessor<H>,
+    req_buf: [u8; N],
+    res_buf: [u8; rtu::MAX_ADU_SIZE],
+}
+
+impl<H: Handler, const N: usize> RtuServer<H, N> {
+    pub fn new(unit_id: u8, handler: H) -> Self {
+        Self {
+            processor: RtuProcessor::new(unit_id, handler),
+            req_buf: [0; N],
+            res_buf: [0; rtu::MAX_ADU_SIZE],
+        }
+    }
+
+    /// Server processing the frames, with the handler and the diagnostic counters
+    pub fn processor(&self) -> &RtuProcessor<H> {
+        &self.processor
+    }
+    pub fn processor_mut(&mut self) -> &mut RtuProcessor<H> {
+        &mut self.processor
+    }
+
+    /// Serves requests until the serial line fails or reaches EOF
+    pub fn serve<T: Read + Write>(&mut self, mut io: T) -> Result<(), Error<T::Error>> {
+        let mut len = 0;
+        loop {
+            if len == N {
+                len = 0;
+            }
+            match read(&mut io, &mut self.req_buf[len..]) {
+                Ok(bytes_read) => len += bytes_read,
+                Err(Error::Eof) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+
+            while let Some(adu_len) = rtu::request::frame_len(&self.req_buf[..len]) {
+                let frame = &self.req_buf[..adu_len];
+                let res_len = self.processor.process_frame(frame, &mut self.res_buf);
+                if check_crc(frame).is_err() {
+                    len = 0;
+                } else {
+                    self.req_buf.copy_within(adu_len..len, 0);
+                    len -= adu_len;
+                }
+                if let Some(res_len) = res_len {
+                    write(&mut io, &self.res_buf[..res_len])?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        adu::rtu::{
+            MAX_ADU_SIZE, request::Request as RtuRequest, response::Response as RtuResponse,
+        },
+        embedded::{Error, test::MockIo},
+        error::Error as ModbusError,
+        pdu::{DataWords, request::Request as PduRequest, response::Response as PduResponse},
+        server::test::RegistersHandler,
+    };
+
+    use super::{RtuClient, RtuServer, TcpClient, TcpServer};
+
+    #[test]
+    fn tcp_client_skips_late_responses() {
+        let chunks: &[&[u8]] = &[
+            // Late response to transaction 0, then the start of the response
+            &[0, 0, 0, 0, 0, 7, 1, 0x03, 4, 0, 1, 0, 2, 0, 1, 0, 0],
+            &[0, 7, 1, 0x03, 4, 0, 6, 0, 5],
+        ];
+        let mut client = TcpClient::<_>::new(MockIo::new(chunks));
+
+        let res = client.request(1, PduRequest::ReadHoldingRegisters(0, 2));
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadHoldingRegisters(DataWords::new(&[0, 6, 0, 5], 2))
+        );
+        assert_eq!(
+            client.io().written(),
+            &[0, 1, 0, 0, 0, 6, 1, 0x03, 0, 0, 0, 2]
+        );
+
+        let res = client.request(1, PduRequest::ReadHoldingRegisters(0, 2));
+        assert!(matches!(res, Err(Error::Eof)));
+    }
+
+    #[test]
+    fn rtu_client_reads_split_response() {
+        let chunks: &[&[u8]] = &[&[0x01, 0x03, 0x04, 0x00], &[0x06, 0x00, 0x05, 0xda, 0x31]];
+        let mut client = RtuClient::<_>::new(MockIo::new(chunks));
+
+        let res = client.request(1, PduRequest::ReadHoldingRegisters(0, 2));
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadHoldingRegisters(DataWords::new(&[0, 6, 0, 5], 2))
+        );
+        assert_eq!(
+            client.io().written(),
+            &[0x01, 0x03, 0x00, 0x00, 0x00, 0x02, 0xc4, 0x0b]
+        );
+
+        let res = client.request(0, PduRequest::WriteSingleRegister(1, 3));
+        assert!(matches!(
+            res,
+            Err(Error::Request(ModbusError::BroadcastNotAllowed(_)))
+        ));
+    }
+
+    #[test]
+    fn tcp_server_serves_pipelined_requests() {
+        let chunks: &[&[u8]] = &[
+            // Write single register 1 = 0x0102, then the start of a read
+            &[0, 1, 0, 0, 0, 6, 1, 0x06, 0, 1, 1, 2, 0, 2, 0, 0],
+            // Read holding registers 0..2
+            &[0, 6, 1, 0x03, 0, 0, 0, 2],
+            // Read coils, not supported by the handler
+            &[0, 3, 0, 0, 0, 6, 1, 0x01, 0, 0, 0, 1],
+        ];
+        let mut server = TcpServer::<_>::new(RegistersHandler::default());
+        let mut io = MockIo::new(chunks);
+
+        server.serve(&mut io).unwrap();
+        assert_eq!(
+            io.written(),
+            &[
+                0, 1, 0, 0, 0, 6, 1, 0x06, 0, 1, 1, 2, // Write single register
+                0, 2, 0, 0, 0, 7, 1, 0x03, 4, 0, 0, 1, 2, // Read holding registers
+                0, 3, 0, 0, 0, 3, 1, 0x81, 0x01, // Exception
+            ]
+        );
+
+        let mut io = MockIo::new(&[&[0, 1, 0, 1, 0, 6, 1, 0x03, 0, 0, 0, 1]]);
+        assert!(matches!(server.serve(&mut io), Err(Error::InvalidFrame)));
+    }
+
+    #[test]
+    fn rtu_server_delimits_frames() {
+        let mut write = [0_u8; MAX_ADU_SIZE];
+        let write_len = RtuRequest::new(1, PduRequest::WriteSingleRegister(1, 0x0102))
+            .encode(&mut write)
+            .unwrap();
+        let mut read = [0_u8; MAX_ADU_SIZE];
+        let read_len = RtuRequest::new(1, PduRequest::ReadHoldingRegisters(0, 2))
+            .encode(&mut read)
+            .unwrap();
+        let mut corrupt = read;
+        corrupt[read_len - 1] ^= 0xff;
+        let mut request = [0_u8; 32];
+        request[..write_len].copy_from_slice(&write[..write_len]);
+        request[write_len..write_len + 3].copy_from_slice(&read[..3]);
+
+        let chunks: &[&[u8]] = &[
+            &request[..write_len + 3],
+            &read[3..read_len],
+            // Dropped with the corrupt frame
+            &corrupt[..read_len + 2],
+            &read[..read_len],
+        ];
+        let mut server = RtuServer::<_>::new(1, RegistersHandler::default());
+        let mut io = MockIo::new(chunks);
+        server.serve(&mut io).unwrap();
+
+        let mut expected = [0_u8; 64];
+        let mut len = RtuResponse::new(1, Ok(PduResponse::WriteSingleRegister(1, 0x0102)))
+            .encode(&mut expected)
+            .unwrap();
+        let words = PduResponse::ReadHoldingRegisters(DataWords::new(&[0, 0, 1, 2], 2));
+        let read_res_len = RtuResponse::new(1, Ok(words))
+            .encode(&mut expected[len..])
+            .unwrap();
+        expected.copy_within(len..len + read_res_len, len + read_res_len);
+        len += 2 * read_res_len;
+        assert_eq!(io.written(), &expected[..len]);
+        assert_eq!(server.processor().counters().bus_communication_error, 1);
+    }
+}
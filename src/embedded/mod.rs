@@ -0,0 +1,248 @@
+//! Clients and servers over the [`embedded_io`] traits, behind the `embedded-io`
+//! feature, and their async counterparts over [`embedded_io_async`], behind the
+//! `embedded-io-async` feature.
+//!
+//! They work over anything implementing the traits, like a UART of a HAL for RTU or a
+//! TCP socket of embassy-net or smoltcp, and keep their frames in buffers of `N` bytes
+//! instead of allocating. There are no timers: timeouts are up to the caller, for
+//! example by dropping the future of an async request after a while.
+
+#[cfg(feature = "embedded-io-async")]
+pub mod asynch;
+pub mod blocking;
+
+use core::fmt;
+
+use crate::{
+    adu::{
+        rtu::{BROADCAST_UNIT_ID, response::Response as RtuResponse},
+        tcp::{header::Header, response::Response as TcpResponse},
+    },
+    error::{DecodeError, EncodeError, Error as ModbusError},
+    pdu::{
+        exception_response::ExceptionResponse, function_code::FunctionCode,
+        response::Response as PduResponse,
+    },
+};
+
+/// Error of a client request or of a server, `E` being the one of the transport
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    Io(E),
+    /// The transport reached EOF, like a TCP socket closed by the peer
+    Eof,
+    /// The frame received can't be delimited: its MBAP header is invalid, or it doesn't
+    /// fit the buffer
+    InvalidFrame,
+    /// The request failed, like it would with the std clients
+    Request(ModbusError),
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err:?}"),
+            Error::Eof => write!(f, "unexpected EOF"),
+            Error::InvalidFrame => write!(f, "invalid frame"),
+            Error::Request(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Request(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<E> From<ModbusError> for Error<E> {
+    fn from(err: ModbusError) -> Self {
+        Error::Request(err)
+    }
+}
+
+impl<E> From<EncodeError> for Error<E> {
+    fn from(err: EncodeError) -> Self {
+        Error::Request(err.into())
+    }
+}
+
+impl<E> From<DecodeError> for Error<E> {
+    fn from(err: DecodeError) -> Self {
+        Error::Request(err.into())
+    }
+}
+
+/// Transaction id and length of the MBAP frame at the start of `buf`, `None` if it
+/// isn't complete yet
+fn tcp_frame<E>(buf: &[u8], max_len: usize) -> Result<Option<(u16, usize)>, Error<E>> {
+    let Ok(header) = Header::decode(buf) else {
+        return Ok(None);
+    };
+    // unit_id is included in the header.length and header.size
+    let adu_len = *header.length() as usize + Header::size() - 1;
+    if *header.protocol_id() != 0 || *header.length() < 2 || adu_len > max_len {
+        return Err(Error::InvalidFrame);
+    }
+    Ok((adu_len <= buf.len()).then_some((*header.transaction_id(), adu_len)))
+}
+
+/// Length of the RTU response at the start of `buf`, `None` if it isn't complete yet
+fn rtu_response_len<E>(buf: &[u8]) -> Result<Option<usize>, Error<E>> {
+    match RtuResponse::decode(buf) {
+        Ok(res) => Ok(Some(res.adu_len())),
+        Err(DecodeError::IncompleteBuffer { .. }) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn tcp_response<E>(
+    frame: &[u8],
+    unit_id: u8,
+    fn_code: FunctionCode,
+) -> Result<PduResponse<'_>, Error<E>> {
+    let res = TcpResponse::decode(frame)?;
+    if *res.header().unit_id() != unit_id {
+        return Err(ModbusError::UnexpectedResponse.into());
+    }
+    response_pdu(res.into_pdu(), fn_code)
+}
+
+fn rtu_response<E>(
+    frame: &[u8],
+    unit_id: u8,
+    fn_code: FunctionCode,
+) -> Result<PduResponse<'_>, Error<E>> {
+    let res = RtuResponse::decode(frame)?;
+    if *res.unit_id() != unit_id {
+        return Err(ModbusError::UnexpectedResponse.into());
+    }
+    response_pdu(res.into_pdu(), fn_code)
+}
+
+fn response_pdu<E>(
+    pdu: Result<PduResponse<'_>, ExceptionResponse>,
+    fn_code: FunctionCode,
+) -> Result<PduResponse<'_>, Error<E>> {
+    match pdu {
+        Ok(pdu) if FunctionCode::from(&pdu) == fn_code => Ok(pdu),
+        _ => Err(ModbusError::UnexpectedResponse.into()),
+    }
+}
+
+/// Checks the unit id of a request, broadcasts having no response
+fn check_unit_id<E>(unit_id: u8, fn_code: FunctionCode) -> Result<(), Error<E>> {
+    match unit_id {
+        BROADCAST_UNIT_ID => Err(ModbusError::BroadcastNotAllowed(fn_code).into()),
+        248.. => Err(ModbusError::InvalidUnitId(unit_id).into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use core::convert::Infallible;
+
+    /// Transport answering reads with the queued chunks, then EOF, and keeping what is
+    /// written
+    #[derive(Debug)]
+    pub(crate) struct MockIo<'a> {
+        chunks: &'a [&'a [u8]],
+        offset: usize,
+        written: [u8; 1024],
+        written_len: usize,
+    }
+
+    impl<'a> MockIo<'a> {
+        pub(crate) fn new(chunks: &'a [&'a [u8]]) -> Self {
+            Self {
+                chunks,
+                offset: 0,
+                written: [0; 1024],
+                written_len: 0,
+            }
+        }
+
+        pub(crate) fn written(&self) -> &[u8] {
+            &self.written[..self.written_len]
+        }
+
+        pub(crate) fn read(&mut self, buf: &mut [u8]) -> usize {
+            let Some(chunk) = self.chunks.first() else {
+                return 0;
+            };
+            let len = (chunk.len() - self.offset).min(buf.len());
+            buf[..len].copy_from_slice(&chunk[self.offset..self.offset + len]);
+            self.offset += len;
+            if self.offset == chunk.len() {
+                self.chunks = &self.chunks[1..];
+                self.offset = 0;
+            }
+            len
+        }
+
+        pub(crate) fn write(&mut self, buf: &[u8]) -> usize {
+            self.written[self.written_len..self.written_len + buf.len()].copy_from_slice(buf);
+            self.written_len += buf.len();
+            buf.len()
+        }
+    }
+
+    impl embedded_io::ErrorType for MockIo<'_> {
+        type Error = Infallible;
+    }
+
+    impl embedded_io::Read for MockIo<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::read(self, buf))
+        }
+    }
+
+    impl embedded_io::Write for MockIo<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::write(self, buf))
+        }
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    impl embedded_io_async::Read for MockIo<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::read(self, buf))
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    impl embedded_io_async::Write for MockIo<'_> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::write(self, buf))
+        }
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// Polls the future to completion, which it must reach without waiting
+    #[cfg(feature = "embedded-io-async")]
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        use core::{
+            pin::pin,
+            task::{Context, Poll, Waker},
+        };
+
+        let mut future = pin!(future);
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future is pending"),
+        }
+    }
+}
@@ -1,17 +1,111 @@
-use crate::{exception_code::ExceptionCode, pdu::function_code::FunctionCode};
+//! Errors of the codecs, transports and clients.
+//!
+//! [`Error`] is the root of the hierarchy, what a client request fails with. It wraps
+//! the [`EncodeError`] and [`DecodeError`] of the frames, themselves carrying the
+//! [`ExceptionError`] a server answers a request it can't decode with.
 
+#[cfg(feature = "std")]
+use std::io;
+
+use core::fmt;
+
+use crate::{
+    exception_code::ExceptionCode,
+    pdu::{Address, function_code::FunctionCode},
+};
+
+/// Field of a PDU
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Field {
+    Quantity,
+    ReadQuantity,
+    WriteQuantity,
+    /// The byte count doesn't match the quantity
+    ByteCount,
+    Value,
+    /// The PDU is shorter than its function code requires
+    Length,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Field::Quantity => "quantity",
+            Field::ReadQuantity => "read quantity",
+            Field::WriteQuantity => "write quantity",
+            Field::ByteCount => "byte count",
+            Field::Value => "value",
+            Field::Length => "length",
+        })
+    }
+}
+
+/// Request a server answers with an exception, following the checks of the server
+/// state diagrams of the spec
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ExceptionError {
+    /// The function code is the one of an exception response
+    IllegalFunction,
+    /// The addresses starting at this one go beyond the 16 bit address space
     IllegalDataAddress(u16),
-    IllegalDataValue,
+    /// The field is out of the range the function code allows
+    IllegalDataValue(Field),
+}
+
+impl fmt::Display for ExceptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExceptionError::IllegalFunction => write!(f, "illegal function"),
+            ExceptionError::IllegalDataAddress(address) => {
+                write!(f, "illegal data address {address}")
+            }
+            ExceptionError::IllegalDataValue(field) => write!(f, "illegal {field}"),
+        }
+    }
+}
+
+impl core::error::Error for ExceptionError {}
+
+impl From<ExceptionError> for ExceptionCode {
+    fn from(err: ExceptionError) -> Self {
+        match err {
+            ExceptionError::IllegalFunction => ExceptionCode::IllegalFunction,
+            ExceptionError::IllegalDataAddress(_) => ExceptionCode::IllegalDataAddress,
+            ExceptionError::IllegalDataValue(_) => ExceptionCode::IllegalDataValue,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum EncodeError {
-    InvalidBufferSize,
+    /// The buffer is smaller than the frame. When encoding an iterator, `needed` is
+    /// only the size of the items up to the one that didn't fit.
+    InvalidBufferSize { needed: usize, available: usize },
+    /// More items than one request can hold
+    InvalidQuantity { quantity: usize, max: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::InvalidBufferSize { needed, available } => write!(
+                f,
+                "buffer too small: {needed} bytes needed, {available} available"
+            ),
+            EncodeError::InvalidQuantity { quantity, max } => {
+                write!(f, "{quantity} items, more than the {max} of one request")
+            }
+        }
+    }
 }
 
+impl core::error::Error for EncodeError {}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DecodeError {
     IncompleteBuffer {
         current_size: usize,
@@ -21,5 +115,216 @@ pub enum DecodeError {
     /// Returned when the function code is valid, but the response is an error
     ModbusExceptionError(FunctionCode, ExceptionError),
     /// Returned when the function code is an error itself
-    ModbusExceptionCode(FunctionCode, Result<ExceptionCode, u8>),
+    ModbusExceptionCode(FunctionCode, ExceptionCode),
+    /// Returned when the CRC of a RTU frame doesn't match its content
+    InvalidCrc { expected: u16, actual: u16 },
+    /// Returned when the LRC of an ASCII frame doesn't match its content
+    InvalidLrc { expected: u8, actual: u8 },
+    /// Returned when an ASCII frame doesn't start with `:`, has an odd number of
+    /// characters or characters that aren't hex digits
+    InvalidAsciiFrame,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::IncompleteBuffer {
+                current_size,
+                min_needed_size,
+            } => write!(
+                f,
+                "incomplete frame: {min_needed_size} bytes needed, {current_size} available"
+            ),
+            DecodeError::ModbusExceptionError(fn_code, err) => write!(f, "{fn_code}: {err}"),
+            DecodeError::ModbusExceptionCode(fn_code, exception_code) => {
+                write!(f, "{fn_code}: exception {exception_code}")
+            }
+            DecodeError::InvalidCrc { expected, actual } => {
+                write!(f, "invalid CRC {actual:#06x}, expected {expected:#06x}")
+            }
+            DecodeError::InvalidLrc { expected, actual } => {
+                write!(f, "invalid LRC {actual:#04x}, expected {expected:#04x}")
+            }
+            DecodeError::InvalidAsciiFrame => write!(f, "invalid ASCII frame"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            DecodeError::ModbusExceptionError(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Exception code a server answers a request with when its complete frame fails to
+/// decode, like the spec requires. The errors returned back aren't answered: the frame
+/// is corrupt, has no function code, or is an exception response itself.
+impl TryFrom<DecodeError> for ExceptionCode {
+    type Error = DecodeError;
+
+    fn try_from(err: DecodeError) -> Result<Self, Self::Error> {
+        match err {
+            DecodeError::ModbusExceptionError(_, err) => Ok(err.into()),
+            DecodeError::IncompleteBuffer { .. }
+            | DecodeError::ModbusExceptionCode(_, _)
+            | DecodeError::InvalidCrc { .. }
+            | DecodeError::InvalidLrc { .. }
+            | DecodeError::InvalidAsciiFrame => Err(err),
+        }
+    }
+}
+
+/// Error of a request to a server
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    #[cfg(feature = "std")]
+    Io(#[cfg_attr(feature = "defmt", defmt(Display2Format))] io::Error),
+    /// No complete response arrived within the response timeout
+    Timeout,
+    Encode(EncodeError),
+    Decode(DecodeError),
+    /// The server answered with an exception response
+    Exception(FunctionCode, ExceptionCode),
+    /// Broadcasts are never answered, so requests reading data can't be broadcast
+    BroadcastNotAllowed(FunctionCode),
+    InvalidUnitId(u8),
+    /// The response doesn't belong to the request that was sent
+    UnexpectedResponse,
+    /// The addresses don't fit in the 16 bit address space
+    AddressOutOfRange,
+    /// The register read back after a read-modify-write isn't the value written, another
+    /// client wrote it too
+    ConcurrentWrite(Address),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Timeout => write!(f, "timed out"),
+            Error::Encode(err) => write!(f, "{err}"),
+            Error::Decode(err) => write!(f, "{err}"),
+            Error::Exception(fn_code, exception_code) => {
+                write!(f, "{fn_code}: exception {exception_code}")
+            }
+            Error::BroadcastNotAllowed(fn_code) => write!(f, "{fn_code} can't be broadcast"),
+            Error::InvalidUnitId(unit_id) => write!(f, "invalid unit id {unit_id}"),
+            Error::UnexpectedResponse => write!(f, "unexpected response"),
+            Error::AddressOutOfRange => write!(f, "address out of range"),
+            Error::ConcurrentWrite(address) => {
+                write!(f, "register {address} was written concurrently")
+            }
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            Error::Encode(err) => Some(err),
+            Error::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Error::Timeout,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<EncodeError> for Error {
+    fn from(err: EncodeError) -> Self {
+        Error::Encode(err)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Self {
+        match err {
+            DecodeError::ModbusExceptionCode(fn_code, exception_code) => {
+                Error::Exception(fn_code, exception_code)
+            }
+            err => Error::Decode(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exception_codes_of_decode_errors() {
+        let err = DecodeError::ModbusExceptionError(
+            FunctionCode::ReadCoils,
+            ExceptionError::IllegalDataValue(Field::Quantity),
+        );
+        assert_eq!(
+            ExceptionCode::try_from(err),
+            Ok(ExceptionCode::IllegalDataValue)
+        );
+        assert_eq!(
+            ExceptionCode::from(ExceptionError::IllegalDataAddress(7)),
+            ExceptionCode::IllegalDataAddress
+        );
+        let err = DecodeError::IncompleteBuffer {
+            current_size: 0,
+            min_needed_size: 1,
+        };
+        assert_eq!(ExceptionCode::try_from(err), Err(err));
+        let err = DecodeError::InvalidCrc {
+            expected: 1,
+            actual: 2,
+        };
+        assert_eq!(ExceptionCode::try_from(err), Err(err));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn display_errors() {
+        extern crate alloc;
+        use alloc::string::ToString;
+
+        assert_eq!(
+            EncodeError::InvalidBufferSize {
+                needed: 12,
+                available: 8
+            }
+            .to_string(),
+            "buffer too small: 12 bytes needed, 8 available"
+        );
+        let err = DecodeError::ModbusExceptionError(
+            FunctionCode::WriteMultipleRegisters,
+            ExceptionError::IllegalDataValue(Field::Quantity),
+        );
+        assert_eq!(
+            err.to_string(),
+            "FC10 WriteMultipleRegisters: illegal quantity"
+        );
+        assert_eq!(
+            Error::from(DecodeError::ModbusExceptionCode(
+                FunctionCode::ReadHoldingRegisters,
+                ExceptionCode::IllegalDataAddress
+            ))
+            .to_string(),
+            "FC03 ReadHoldingRegisters: exception IllegalDataAddress"
+        );
+        assert_eq!(
+            Error::Exception(FunctionCode::ReadCoils, ExceptionCode::Other(0x42)).to_string(),
+            "FC01 ReadCoils: exception 0x42"
+        );
+    }
 }
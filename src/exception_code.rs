@@ -1,31 +1,147 @@
+use core::fmt;
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ExceptionCode {
-    IllegalFunction = 0x01,
-    IllegalDataAddress = 0x02,
-    IllegalDataValue = 0x03,
-    ServerDeviceFailure = 0x04,
-    Acknowledge = 0x05,
-    ServerDeviceBusy = 0x06,
-    MemoryParityError = 0x08,
-    GatewayPathUnavailable = 0x0a,
-    GatewayTargetDeviceFailedToRespond = 0x0b,
+    IllegalFunction,
+    IllegalDataAddress,
+    IllegalDataValue,
+    ServerDeviceFailure,
+    Acknowledge,
+    ServerDeviceBusy,
+    /// The device can't perform the program function, used by older devices
+    NegativeAcknowledge,
+    MemoryParityError,
+    GatewayPathUnavailable,
+    GatewayTargetDeviceFailedToRespond,
+    /// Code the spec doesn't define, like vendor specific ones. Decoding never gives
+    /// it the code of another variant.
+    Other(u8),
 }
 
-impl TryFrom<u8> for ExceptionCode {
-    type Error = u8;
-
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
+impl From<u8> for ExceptionCode {
+    fn from(value: u8) -> Self {
         match value {
-            0x01 => Ok(Self::IllegalFunction),
-            0x02 => Ok(Self::IllegalDataAddress),
-            0x03 => Ok(Self::IllegalDataValue),
-            0x04 => Ok(Self::ServerDeviceFailure),
-            0x05 => Ok(Self::Acknowledge),
-            0x06 => Ok(Self::ServerDeviceBusy),
-            0x08 => Ok(Self::MemoryParityError),
-            0x0a => Ok(Self::GatewayPathUnavailable),
-            0x0b => Ok(Self::GatewayTargetDeviceFailedToRespond),
-            v => Err(v),
+            0x01 => Self::IllegalFunction,
+            0x02 => Self::IllegalDataAddress,
+            0x03 => Self::IllegalDataValue,
+            0x04 => Self::ServerDeviceFailure,
+            0x05 => Self::Acknowledge,
+            0x06 => Self::ServerDeviceBusy,
+            0x07 => Self::NegativeAcknowledge,
+            0x08 => Self::MemoryParityError,
+            0x0a => Self::GatewayPathUnavailable,
+            0x0b => Self::GatewayTargetDeviceFailedToRespond,
+            v => Self::Other(v),
+        }
+    }
+}
+
+impl From<ExceptionCode> for u8 {
+    fn from(code: ExceptionCode) -> Self {
+        match code {
+            ExceptionCode::IllegalFunction => 0x01,
+            ExceptionCode::IllegalDataAddress => 0x02,
+            ExceptionCode::IllegalDataValue => 0x03,
+            ExceptionCode::ServerDeviceFailure => 0x04,
+            ExceptionCode::Acknowledge => 0x05,
+            ExceptionCode::ServerDeviceBusy => 0x06,
+            ExceptionCode::NegativeAcknowledge => 0x07,
+            ExceptionCode::MemoryParityError => 0x08,
+            ExceptionCode::GatewayPathUnavailable => 0x0a,
+            ExceptionCode::GatewayTargetDeviceFailedToRespond => 0x0b,
+            ExceptionCode::Other(code) => code,
+        }
+    }
+}
+
+/// `IllegalDataAddress`, or `0x42` for other codes
+impl fmt::Display for ExceptionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExceptionCode::Other(code) => write!(f, "{code:#04x}"),
+            code => write!(f, "{code:?}"),
         }
     }
 }
+
+#[cfg(feature = "serde")]
+const NAMES: [(&str, ExceptionCode); 10] = [
+    ("IllegalFunction", ExceptionCode::IllegalFunction),
+    ("IllegalDataAddress", ExceptionCode::IllegalDataAddress),
+    ("IllegalDataValue", ExceptionCode::IllegalDataValue),
+    ("ServerDeviceFailure", ExceptionCode::ServerDeviceFailure),
+    ("Acknowledge", ExceptionCode::Acknowledge),
+    ("ServerDeviceBusy", ExceptionCode::ServerDeviceBusy),
+    ("NegativeAcknowledge", ExceptionCode::NegativeAcknowledge),
+    ("MemoryParityError", ExceptionCode::MemoryParityError),
+    ("GatewayPathUnavailable", ExceptionCode::GatewayPathUnavailable),
+    (
+        "GatewayTargetDeviceFailedToRespond",
+        ExceptionCode::GatewayTargetDeviceFailedToRespond,
+    ),
+];
+
+/// Name of the code, like `IllegalDataAddress`, or its number for other codes. Only the
+/// number in formats that aren't human readable.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ExceptionCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::serde_names::serialize(*self, &NAMES, serializer)
+    }
+}
+
+/// Name or number of the code
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ExceptionCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::serde_names::deserialize(deserializer, &NAMES, "an exception code name or number")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ExceptionCode;
+
+    #[test]
+    fn converts_codes() {
+        for code in 0..=u8::MAX {
+            assert_eq!(u8::from(ExceptionCode::from(code)), code);
+        }
+        assert_eq!(
+            ExceptionCode::from(0x07),
+            ExceptionCode::NegativeAcknowledge
+        );
+        assert_eq!(ExceptionCode::from(0x09), ExceptionCode::Other(0x09));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let codes = [ExceptionCode::ServerDeviceBusy, ExceptionCode::Other(0x42)];
+        let json = serde_json::to_string(&codes).unwrap();
+        assert_eq!(json, r#"["ServerDeviceBusy",66]"#);
+        assert_eq!(serde_json::from_str::<[ExceptionCode; 2]>(&json).unwrap(), codes);
+        assert_eq!(
+            serde_json::from_str::<ExceptionCode>("2").unwrap(),
+            ExceptionCode::IllegalDataAddress
+        );
+        assert!(serde_json::from_str::<ExceptionCode>("256").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_compact() {
+        use serde_test::{Configure, Token, assert_tokens};
+
+        assert_tokens(
+            &ExceptionCode::ServerDeviceBusy.compact(),
+            &[Token::U8(0x06)],
+        );
+        assert_tokens(&ExceptionCode::Other(0x42).compact(), &[Token::U8(0x42)]);
+        assert_tokens(
+            &ExceptionCode::ServerDeviceBusy.readable(),
+            &[Token::Str("ServerDeviceBusy")],
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Spans and metrics of the transactions of the clients and servers, behind the
+//! `tracing` and `metrics` features. Without them a [`Transaction`] is empty and its
+//! methods do nothing, so they compile to nothing.
+//!
+//! Each transaction runs in a `modbus_transaction` debug span with the `role`
+//! (`client` or `server`), `unit`, `function_code` and `transaction_id` (TCP only)
+//! fields. The metrics are labeled with the `role` and `unit`:
+//!
+//! - `modbus_requests_total`, also labeled with the `function_code`
+//! - `modbus_exceptions_total`, also labeled with the `function_code` and
+//!   `exception_code`
+//! - `modbus_decode_errors_total`, responses a client can't decode, or requests a
+//!   server can't
+//! - `modbus_timeouts_total`
+//! - `modbus_request_duration_seconds`, histogram of the time a client waits for a
+//!   response or a server takes to handle a request
+
+// Only the clients with `std` and the `tracing` feature use everything
+#![cfg_attr(
+    not(all(feature = "std", feature = "tracing")),
+    allow(dead_code, unused_variables)
+)]
+
+use core::fmt;
+
+#[cfg(feature = "metrics")]
+use std::{
+    format,
+    string::{String, ToString},
+    time::Instant,
+};
+
+#[cfg(feature = "std")]
+use crate::error::Error;
+use crate::{exception_code::ExceptionCode, pdu::exception_response::ExceptionResponse};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Role {
+    Client,
+    Server,
+}
+
+impl Role {
+    fn name(&self) -> &'static str {
+        match self {
+            Role::Client => "client",
+            Role::Server => "server",
+        }
+    }
+}
+
+/// How a transaction ended
+#[derive(Copy, Clone)]
+pub(crate) enum Outcome<'a> {
+    Response,
+    /// The request was broadcast, or isn't answered by the server
+    NoResponse,
+    Exception(ExceptionCode),
+    DecodeError(&'a dyn fmt::Display),
+    Timeout,
+    /// Any other error, like an I/O one
+    Failed(&'a dyn fmt::Display),
+}
+
+impl<'a> Outcome<'a> {
+    /// Outcome of a client request
+    #[cfg(feature = "std")]
+    pub(crate) fn of<T>(res: &'a Result<T, Error>) -> Self {
+        match res {
+            Ok(_) => Outcome::Response,
+            Err(Error::Exception(_, exception_code)) => Outcome::Exception(*exception_code),
+            Err(Error::Decode(err)) => Outcome::DecodeError(err),
+            Err(Error::Timeout) => Outcome::Timeout,
+            Err(err) => Outcome::Failed(err),
+        }
+    }
+
+    /// Outcome of a request a server answers
+    pub(crate) fn answer<T>(res: &Result<T, ExceptionResponse>) -> Self {
+        match res {
+            Ok(_) => Outcome::Response,
+            Err(res) => Outcome::Exception(*res.exception_code()),
+        }
+    }
+}
+
+/// A request and its response, from the request being sent or received to the
+/// response being received or sent
+pub(crate) struct Transaction {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
+}
+
+impl Transaction {
+    #[inline]
+    pub(crate) fn start(
+        role: Role,
+        unit_id: u8,
+        function_code: u8,
+        transaction_id: Option<u16>,
+    ) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            _span: tracing::debug_span!(
+                "modbus_transaction",
+                role = role.name(),
+                unit = unit_id,
+                function_code,
+                transaction_id
+            )
+            .entered(),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::start(role, unit_id, function_code),
+        }
+    }
+
+    /// Records a request a server can't decode, before answering it or not
+    #[inline]
+    pub(crate) fn decode_error(&self, err: &dyn fmt::Display) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = %err, "decode error");
+        #[cfg(feature = "metrics")]
+        self.metrics.decode_error();
+    }
+
+    #[inline]
+    pub(crate) fn finish(self, outcome: Outcome<'_>) {
+        #[cfg(feature = "tracing")]
+        match outcome {
+            Outcome::Response => tracing::debug!("response"),
+            Outcome::NoResponse => tracing::debug!("no response"),
+            Outcome::Exception(exception_code) => {
+                tracing::debug!(%exception_code, "exception response")
+            }
+            Outcome::DecodeError(err) => tracing::warn!(error = %err, "decode error"),
+            Outcome::Timeout => tracing::warn!("timeout"),
+            Outcome::Failed(err) => tracing::warn!(error = %err, "failed"),
+        }
+        #[cfg(feature = "metrics")]
+        self.metrics.finish(outcome);
+    }
+}
+
+#[cfg(feature = "metrics")]
+struct Metrics {
+    role: &'static str,
+    unit: String,
+    function_code: String,
+    start: Instant,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    fn start(role: Role, unit_id: u8, function_code: u8) -> Self {
+        let metrics = Self {
+            role: role.name(),
+            unit: unit_id.to_string(),
+            function_code: format!("{function_code:#04x}"),
+            start: Instant::now(),
+        };
+        metrics::counter!(
+            "modbus_requests_total",
+            "role" => metrics.role,
+            "unit" => metrics.unit.clone(),
+            "function_code" => metrics.function_code.clone()
+        )
+        .increment(1);
+        metrics
+    }
+
+    fn decode_error(&self) {
+        metrics::counter!(
+            "modbus_decode_errors_total",
+            "role" => self.role,
+            "unit" => self.unit.clone()
+        )
+        .increment(1);
+    }
+
+    fn finish(self, outcome: Outcome<'_>) {
+        if let Outcome::DecodeError(_) = outcome {
+            self.decode_error();
+            return;
+        }
+        let Self {
+            role,
+            unit,
+            function_code,
+            start,
+        } = self;
+        match outcome {
+            Outcome::Response | Outcome::NoResponse | Outcome::Failed(_) => {}
+            Outcome::Exception(exception_code) => metrics::counter!(
+                "modbus_exceptions_total",
+                "role" => role,
+                "unit" => unit.clone(),
+                "function_code" => function_code,
+                "exception_code" => exception_code.to_string()
+            )
+            .increment(1),
+            Outcome::DecodeError(_) => unreachable!(),
+            Outcome::Timeout => {
+                metrics::counter!("modbus_timeouts_total", "role" => role, "unit" => unit)
+                    .increment(1);
+                return;
+            }
+        }
+        // Only the transactions answered have a duration
+        if let Outcome::Response | Outcome::Exception(_) = outcome {
+            metrics::histogram!(
+                "modbus_request_duration_seconds",
+                "role" => role,
+                "unit" => unit
+            )
+            .record(start.elapsed().as_secs_f64());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod test {
+    use std::{
+        format,
+        string::String,
+        sync::{Arc, Mutex},
+        vec,
+        vec::Vec,
+    };
+
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+
+    use crate::{
+        client::{
+            Error,
+            tcp::{TcpClient, TcpConfig},
+            test::MockTransport,
+        },
+        pdu::request::Request as PduRequest,
+        server::{tcp::process_frame, test::RegistersHandler},
+    };
+
+    /// Recorder keeping the `name{labels}` of the counters incremented or set and of the
+    /// histograms recorded
+    #[derive(Debug, Default, Clone)]
+    struct TestRecorder(Arc<Mutex<Vec<String>>>);
+
+    struct Handle {
+        key: String,
+        recorded: TestRecorder,
+    }
+
+    impl CounterFn for Handle {
+        fn increment(&self, value: u64) {
+            let key = format!("{} {value}", self.key);
+            self.recorded.0.lock().unwrap().push(key);
+        }
+        fn absolute(&self, value: u64) {
+            let key = format!("{} ={value}", self.key);
+            self.recorded.0.lock().unwrap().push(key);
+        }
+    }
+
+    impl HistogramFn for Handle {
+        fn record(&self, _value: f64) {
+            self.recorded.0.lock().unwrap().push(self.key.clone());
+        }
+    }
+
+    impl TestRecorder {
+        fn handle(&self, key: &Key) -> Arc<Handle> {
+            let labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            Arc::new(Handle {
+                key: format!("{}{{{}}}", key.name(), labels.join(",")),
+                recorded: self.clone(),
+            })
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.handle(key))
+        }
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.handle(key))
+        }
+    }
+
+    #[test]
+    fn records_client_metrics() {
+        let transport = MockTransport::new(&[
+            &[0, 1, 0, 0, 0, 5, 1, 3, 2, 0, 7],
+            &[0, 2, 0, 0, 0, 3, 1, 0x83, 0x02],
+        ]);
+        let mut client = TcpClient::new(transport, TcpConfig::default());
+        let recorder = TestRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || {
+            let mut buf = [0; 260];
+            let req = PduRequest::ReadHoldingRegisters(0, 1);
+            assert!(client.send(1, req, &mut buf).is_ok());
+            let req = PduRequest::ReadHoldingRegisters(100, 1);
+            assert!(matches!(
+                client.send(1, req, &mut buf),
+                Err(Error::Exception(_, _))
+            ));
+            let req = PduRequest::ReadHoldingRegisters(0, 1);
+            assert!(matches!(client.send(1, req, &mut buf), Err(Error::Timeout)));
+        });
+
+        let requests = "modbus_requests_total{role=client,unit=1,function_code=0x03} 1";
+        let duration = "modbus_request_duration_seconds{role=client,unit=1}";
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                requests,
+                duration,
+                requests,
+                "modbus_exceptions_total{role=client,unit=1,function_code=0x03,\
+                 exception_code=IllegalDataAddress} 1",
+                duration,
+                requests,
+                "modbus_timeouts_total{role=client,unit=1} 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn records_server_metrics() {
+        let mut handler = RegistersHandler::default();
+        let recorder = TestRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || {
+            let mut buf = [0; 260];
+            let frames: [&[u8]; 3] = [
+                &[0, 1, 0, 0, 0, 6, 1, 3, 0, 0, 0, 1],
+                &[0, 2, 0, 0, 0, 6, 1, 3, 0, 100, 0, 1],
+                // Quantity 0
+                &[0, 3, 0, 0, 0, 6, 1, 3, 0, 0, 0, 0],
+            ];
+            for frame in frames {
+                assert!(process_frame(frame, &mut buf, &mut handler).is_some());
+            }
+        });
+
+        let requests = "modbus_requests_total{role=server,unit=1,function_code=0x03} 1";
+        let duration = "modbus_request_duration_seconds{role=server,unit=1}";
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                requests,
+                duration,
+                requests,
+                "modbus_exceptions_total{role=server,unit=1,function_code=0x03,\
+                 exception_code=IllegalDataAddress} 1",
+                duration,
+                requests,
+                "modbus_decode_errors_total{role=server,unit=1} 1",
+                "modbus_exceptions_total{role=server,unit=1,function_code=0x03,\
+                 exception_code=IllegalDataValue} 1",
+                duration,
+            ]
+        );
+    }
+}
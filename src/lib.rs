@@ -1,7 +1,26 @@
 #![warn(rust_2018_idioms)]
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 pub mod adu;
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+#[cfg(feature = "std")]
+pub mod client;
 pub mod error;
 pub mod exception_code;
+mod instrument;
 pub mod pdu;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+#[cfg(feature = "profiles")]
+pub mod profile;
+#[cfg(feature = "serde")]
+mod serde_names;
+pub mod server;
+#[cfg(feature = "std")]
+pub mod sunspec;
+#[cfg(feature = "std")]
+pub mod transport;
@@ -0,0 +1,437 @@
+//! Readers of the pcap and pcapng capture formats
+
+use std::{
+    io::{self, ErrorKind, Read},
+    time::Duration,
+    vec,
+    vec::Vec,
+};
+
+use super::PcapError;
+
+const PCAP_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_NANOS: u32 = 0xa1b2_3c4d;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d_0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const IF_TSRESOL: u16 = 9;
+
+/// Blocks bigger than this are considered corrupt rather than allocated
+const MAX_BLOCK_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// Time since the Unix epoch it was captured at
+    pub at: Duration,
+    /// Link layer header type, as in <https://www.tcpdump.org/linktypes.html>
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        match self {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// Timestamp resolution of a pcapng interface
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Resolution {
+    /// Units of 10^-n seconds
+    Decimal(u32),
+    /// Units of 2^-n seconds
+    Binary(u32),
+}
+
+impl Resolution {
+    fn duration(&self, ts: u64) -> Duration {
+        let nanos = match *self {
+            Resolution::Decimal(n) if n <= 9 => ts as u128 * 10_u128.pow(9 - n),
+            Resolution::Decimal(n) => ts as u128 / 10_u128.pow((n - 9).min(38)),
+            Resolution::Binary(n) => (ts as u128 * 1_000_000_000) >> n.min(127),
+        };
+        Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        )
+    }
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap {
+        endian: Endian,
+        nanos: bool,
+        link_type: u32,
+    },
+    PcapNg {
+        endian: Endian,
+        /// Link type and resolution of the interfaces of the current section
+        interfaces: Vec<(u32, Resolution)>,
+    },
+}
+
+/// Reads the packets of a pcap or pcapng capture, telling them apart from their first
+/// bytes.
+///
+/// Only the enhanced packet blocks of pcapng carry packets with their interface and
+/// timestamp, the other blocks are skipped.
+#[derive(Debug)]
+pub struct PacketReader<R> {
+    reader: R,
+    format: Format,
+}
+
+impl<R: Read> PacketReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, PcapError> {
+        let mut magic = [0_u8; 4];
+        reader.read_exact(&mut magic).map_err(truncated)?;
+
+        if u32::from_le_bytes(magic) == SECTION_HEADER_BLOCK {
+            let mut reader = Self {
+                reader,
+                format: Format::PcapNg {
+                    endian: Endian::Little,
+                    interfaces: vec![],
+                },
+            };
+            reader.read_section_header()?;
+            return Ok(reader);
+        }
+
+        let (endian, nanos) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MICROS, _) => (Endian::Little, false),
+            (PCAP_NANOS, _) => (Endian::Little, true),
+            (_, PCAP_MICROS) => (Endian::Big, false),
+            (_, PCAP_NANOS) => (Endian::Big, true),
+            _ => return Err(PcapError::UnknownFormat),
+        };
+        // Version, time zone, accuracy and snap length, then the link type
+        let mut header = [0_u8; 20];
+        reader.read_exact(&mut header).map_err(truncated)?;
+        Ok(Self {
+            reader,
+            format: Format::Pcap {
+                endian,
+                nanos,
+                link_type: endian.u32(&header[16..]) & 0x0fff_ffff,
+            },
+        })
+    }
+
+    /// Next packet, `None` at the end of the capture
+    pub fn next_packet(&mut self) -> Result<Option<Packet>, PcapError> {
+        match self.format {
+            Format::Pcap {
+                endian,
+                nanos,
+                link_type,
+            } => {
+                let mut header = [0_u8; 16];
+                if !self.read_or_eof(&mut header)? {
+                    return Ok(None);
+                }
+                let secs = endian.u32(&header[0..]) as u64;
+                let frac = endian.u32(&header[4..]);
+                let len = endian.u32(&header[8..]) as usize;
+                if len > MAX_BLOCK_SIZE {
+                    return Err(PcapError::Corrupt);
+                }
+                let mut data = vec![0_u8; len];
+                self.reader.read_exact(&mut data).map_err(truncated)?;
+
+                let nanos = if nanos {
+                    frac
+                } else {
+                    frac.saturating_mul(1000)
+                };
+                Ok(Some(Packet {
+                    at: Duration::from_secs(secs) + Duration::from_nanos(nanos as u64),
+                    link_type,
+                    data,
+                }))
+            }
+            Format::PcapNg { .. } => loop {
+                let mut header = [0_u8; 8];
+                if !self.read_or_eof(&mut header)? {
+                    return Ok(None);
+                }
+                if u32::from_le_bytes([header[0], header[1], header[2], header[3]])
+                    == SECTION_HEADER_BLOCK
+                {
+                    self.read_section_header_body(header[4..].try_into().unwrap())?;
+                    continue;
+                }
+                let Format::PcapNg { endian, .. } = self.format else {
+                    unreachable!();
+                };
+                let block_type = endian.u32(&header[0..]);
+                let body = self.read_block_body(endian.u32(&header[4..]))?;
+                if let Some(packet) = self.process_block(block_type, &body)? {
+                    return Ok(Some(packet));
+                }
+            },
+        }
+    }
+
+    /// Reads `buf` whole, or returns `false` at EOF before its first byte
+    fn read_or_eof(&mut self, buf: &mut [u8]) -> Result<bool, PcapError> {
+        let mut len = 0;
+        while len < buf.len() {
+            match self.reader.read(&mut buf[len..]) {
+                Ok(0) if len == 0 => return Ok(false),
+                Ok(0) => return Err(PcapError::Truncated),
+                Ok(bytes_read) => len += bytes_read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(PcapError::Io(err)),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads the rest of a section header block whose type was already read
+    fn read_section_header(&mut self) -> Result<(), PcapError> {
+        let mut len = [0_u8; 4];
+        self.reader.read_exact(&mut len).map_err(truncated)?;
+        self.read_section_header_body(len)
+    }
+
+    fn read_section_header_body(&mut self, len: [u8; 4]) -> Result<(), PcapError> {
+        // The byte order magic tells the endianness of the length before it
+        let mut magic = [0_u8; 4];
+        self.reader.read_exact(&mut magic).map_err(truncated)?;
+        let endian = match u32::from_le_bytes(magic) {
+            BYTE_ORDER_MAGIC => Endian::Little,
+            _ if u32::from_be_bytes(magic) == BYTE_ORDER_MAGIC => Endian::Big,
+            _ => return Err(PcapError::Corrupt),
+        };
+        let len = endian.u32(&len);
+        // The magic is part of the body
+        let body_len = len.checked_sub(4).ok_or(PcapError::Corrupt)?;
+        self.read_block_body(body_len)?;
+        self.format = Format::PcapNg {
+            endian,
+            interfaces: vec![],
+        };
+        Ok(())
+    }
+
+    /// Reads the body and trailing length of a block of `len` bytes whose type and
+    /// length were already read
+    fn read_block_body(&mut self, len: u32) -> Result<Vec<u8>, PcapError> {
+        let len = len as usize;
+        if len < 12 || !len.is_multiple_of(4) || len > MAX_BLOCK_SIZE {
+            return Err(PcapError::Corrupt);
+        }
+        let mut body = vec![0_u8; len - 8];
+        self.reader.read_exact(&mut body).map_err(truncated)?;
+        body.truncate(len - 12);
+        Ok(body)
+    }
+
+    fn process_block(&mut self, block_type: u32, body: &[u8]) -> Result<Option<Packet>, PcapError> {
+        let Format::PcapNg { endian, interfaces } = &mut self.format else {
+            unreachable!();
+        };
+        let endian = *endian;
+        match block_type {
+            INTERFACE_DESCRIPTION_BLOCK => {
+                if body.len() < 8 {
+                    return Err(PcapError::Corrupt);
+                }
+                let link_type = endian.u16(body) as u32;
+                let mut resolution = Resolution::Decimal(6);
+                let mut options = &body[8..];
+                while options.len() >= 4 {
+                    let code = endian.u16(options);
+                    let len = endian.u16(&options[2..]) as usize;
+                    let value = options.get(4..4 + len).ok_or(PcapError::Corrupt)?;
+                    if code == IF_TSRESOL && len == 1 {
+                        resolution = match value[0] {
+                            n if n & 0x80 == 0 => Resolution::Decimal(n as u32),
+                            n => Resolution::Binary((n & 0x7f) as u32),
+                        };
+                    }
+                    options = options
+                        .get(4 + len.next_multiple_of(4)..)
+                        .unwrap_or_default();
+                }
+                interfaces.push((link_type, resolution));
+                Ok(None)
+            }
+            ENHANCED_PACKET_BLOCK => {
+                if body.len() < 20 {
+                    return Err(PcapError::Corrupt);
+                }
+                let interface = endian.u32(body) as usize;
+                let &(link_type, resolution) =
+                    interfaces.get(interface).ok_or(PcapError::Corrupt)?;
+                let ts = ((endian.u32(&body[4..]) as u64) << 32) | endian.u32(&body[8..]) as u64;
+                let len = endian.u32(&body[12..]) as usize;
+                let data = body.get(20..20 + len).ok_or(PcapError::Corrupt)?;
+                Ok(Some(Packet {
+                    at: resolution.duration(ts),
+                    link_type,
+                    data: data.to_vec(),
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<R: Read> Iterator for PacketReader<R> {
+    type Item = Result<Packet, PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet().transpose()
+    }
+}
+
+fn truncated(err: io::Error) -> PcapError {
+    match err.kind() {
+        ErrorKind::UnexpectedEof => PcapError::Truncated,
+        _ => PcapError::Io(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{time::Duration, vec, vec::Vec};
+
+    use super::*;
+    use crate::pcap::test::pcap;
+
+    /// pcapng block with its lengths
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let len = (body.len().next_multiple_of(4) + 12) as u32;
+        let mut block = vec![];
+        block.extend_from_slice(&block_type.to_le_bytes());
+        block.extend_from_slice(&len.to_le_bytes());
+        block.extend_from_slice(body);
+        block.resize(len as usize - 4, 0);
+        block.extend_from_slice(&len.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn reads_pcap() {
+        let file = pcap(&[
+            (Duration::new(10, 5_000), vec![1, 2, 3]),
+            (Duration::new(11, 0), vec![4]),
+        ]);
+        let packets: Vec<_> = PacketReader::new(&file[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            packets,
+            [
+                Packet {
+                    at: Duration::new(10, 5_000),
+                    link_type: 1,
+                    data: vec![1, 2, 3],
+                },
+                Packet {
+                    at: Duration::new(11, 0),
+                    link_type: 1,
+                    data: vec![4],
+                },
+            ]
+        );
+
+        // Cut in the middle of the second packet
+        let mut reader = PacketReader::new(&file[..file.len() - 1]).unwrap();
+        assert!(reader.next_packet().unwrap().is_some());
+        assert!(matches!(reader.next_packet(), Err(PcapError::Truncated)));
+    }
+
+    #[test]
+    fn reads_big_endian_nanosecond_pcap() {
+        let mut file = vec![];
+        for field in [PCAP_NANOS, 0x0004_0002, 0, 0, 65535, 101] {
+            file.extend_from_slice(&field.to_be_bytes());
+        }
+        for field in [7_u32, 123, 2, 2] {
+            file.extend_from_slice(&field.to_be_bytes());
+        }
+        file.extend_from_slice(&[0x45, 0]);
+
+        let mut reader = PacketReader::new(&file[..]).unwrap();
+        let packet = reader.next_packet().unwrap().unwrap();
+        assert_eq!(packet.at, Duration::new(7, 123));
+        assert_eq!(packet.link_type, 101);
+        assert_eq!(packet.data, [0x45, 0]);
+        assert!(reader.next_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn reads_pcapng() {
+        let mut file = vec![];
+        // Byte order magic, version and unknown section length
+        let mut shb = vec![0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0];
+        shb.extend_from_slice(&[0xff; 8]);
+        file.extend(block(SECTION_HEADER_BLOCK, &shb));
+        // Ethernet with the default microseconds, then raw IP in milliseconds
+        file.extend(block(
+            INTERFACE_DESCRIPTION_BLOCK,
+            &[1, 0, 0, 0, 0, 0, 0, 0],
+        ));
+        file.extend(block(
+            INTERFACE_DESCRIPTION_BLOCK,
+            &[101, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0],
+        ));
+        // A name resolution block
+        file.extend(block(4, &[0, 0, 0, 0]));
+        let mut epb = vec![
+            1, 0, 0, 0, 0, 0, 0, 0, 0xd2, 0x04, 0, 0, 3, 0, 0, 0, 3, 0, 0, 0,
+        ];
+        epb.extend_from_slice(&[0xaa, 0xbb, 0xcc]);
+        file.extend(block(ENHANCED_PACKET_BLOCK, &epb));
+
+        let packets: Vec<_> = PacketReader::new(&file[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            packets,
+            [Packet {
+                at: Duration::from_millis(1234),
+                link_type: 101,
+                data: vec![0xaa, 0xbb, 0xcc],
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_format() {
+        assert!(matches!(
+            PacketReader::new(&b"not a capture"[..]),
+            Err(PcapError::UnknownFormat)
+        ));
+        assert!(matches!(
+            PacketReader::new(&[0xd4, 0xc3][..]),
+            Err(PcapError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,729 @@
+//! Offline dissection of Modbus TCP traffic captured by Wireshark or tcpdump.
+//!
+//! The TCP streams of a pcap or pcapng capture are put back together, split into
+//! ADUs and decoded with [`crate::adu::tcp`]. Requests are matched with their
+//! responses by transaction id, and anything not following the protocol is reported
+//! along with the exceptions and latencies per unit and function code.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, format, io,
+    io::Read,
+    net::SocketAddr,
+    string::String,
+    time::Duration,
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    adu::{
+        Framing,
+        tcp::{header::Header, request::Request, response::Response},
+    },
+    error::DecodeError,
+    exception_code::ExceptionCode,
+    pdu::function_code::FunctionCode,
+};
+
+pub mod file;
+pub mod net;
+pub mod reassembly;
+
+use file::{Packet, PacketReader};
+use reassembly::Stream;
+
+#[derive(Debug)]
+pub enum PcapError {
+    Io(io::Error),
+    /// The file is neither a pcap nor a pcapng capture
+    UnknownFormat,
+    /// The file ends in the middle of a packet or block
+    Truncated,
+    /// A packet or block has an invalid length or refers to an unknown interface
+    Corrupt,
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcapError::Io(err) => write!(f, "{err}"),
+            PcapError::UnknownFormat => write!(f, "not a pcap or pcapng capture"),
+            PcapError::Truncated => write!(f, "truncated capture"),
+            PcapError::Corrupt => write!(f, "corrupt capture"),
+        }
+    }
+}
+
+impl std::error::Error for PcapError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DissectorConfig {
+    /// Ports the servers listen on
+    pub ports: Vec<u16>,
+}
+
+impl Default for DissectorConfig {
+    fn default() -> Self {
+        Self { ports: vec![502] }
+    }
+}
+
+/// A request and its response, if the capture has it.
+///
+/// Times are relative to the first packet of the capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub transaction_id: u16,
+    pub unit_id: u8,
+    pub function_code: u8,
+    pub request_at: Duration,
+    pub request: Vec<u8>,
+    pub response_at: Option<Duration>,
+    pub response: Option<Vec<u8>>,
+    /// Exception code of the response, if it's an exception
+    pub exception: Option<ExceptionCode>,
+}
+
+impl Transaction {
+    /// Time between the request and its response
+    pub fn latency(&self) -> Option<Duration> {
+        Some(self.response_at?.saturating_sub(self.request_at))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The protocol id isn't 0 or the length doesn't fit a PDU. The rest of the
+    /// stream is dropped until the next segment.
+    InvalidHeader,
+    InvalidRequest(DecodeError),
+    InvalidResponse(DecodeError),
+    /// A response with this transaction id, which no request pending has
+    UnexpectedResponse(u16),
+    /// A request with this transaction id while another one with it is pending
+    DuplicateTransactionId(u16),
+    /// The unit id of a response isn't the one of its request
+    UnitMismatch,
+    /// The function code of a response isn't the one of its request
+    FunctionMismatch,
+    /// Bytes of the stream are missing from the capture
+    MissingData,
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationKind::InvalidHeader => write!(f, "invalid MBAP header"),
+            ViolationKind::InvalidRequest(err) => write!(f, "invalid request: {err}"),
+            ViolationKind::InvalidResponse(err) => write!(f, "invalid response: {err}"),
+            ViolationKind::UnexpectedResponse(id) => {
+                write!(f, "response to no pending request, transaction {id}")
+            }
+            ViolationKind::DuplicateTransactionId(id) => {
+                write!(f, "transaction {id} reused while pending")
+            }
+            ViolationKind::UnitMismatch => write!(f, "response from another unit"),
+            ViolationKind::FunctionMismatch => write!(f, "response to another function"),
+            ViolationKind::MissingData => write!(f, "bytes missing from the capture"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub at: Duration,
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.6} {} -> {}: {}",
+            self.at.as_secs_f64(),
+            self.client,
+            self.server,
+            self.kind
+        )
+    }
+}
+
+/// Counts and latencies of the transactions of a unit and function code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub requests: usize,
+    pub responses: usize,
+    pub exceptions: usize,
+    pub min_latency: Option<Duration>,
+    pub max_latency: Option<Duration>,
+    pub total_latency: Duration,
+}
+
+impl Stats {
+    pub fn mean_latency(&self) -> Option<Duration> {
+        (self.responses > 0).then(|| self.total_latency / self.responses as u32)
+    }
+
+    fn add(&mut self, transaction: &Transaction) {
+        self.requests += 1;
+        if let Some(latency) = transaction.latency() {
+            self.responses += 1;
+            self.min_latency = Some(self.min_latency.map_or(latency, |min| min.min(latency)));
+            self.max_latency = Some(self.max_latency.map_or(latency, |max| max.max(latency)));
+            self.total_latency += latency;
+        }
+        if transaction.exception.is_some() {
+            self.exceptions += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// In the order of their requests
+    pub transactions: Vec<Transaction>,
+    /// In the order they were seen
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Stats by unit id and function code
+    pub fn stats(&self) -> BTreeMap<(u8, u8), Stats> {
+        let mut stats = BTreeMap::<_, Stats>::new();
+        for transaction in &self.transactions {
+            stats
+                .entry((transaction.unit_id, transaction.function_code))
+                .or_default()
+                .add(transaction);
+        }
+        stats
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>4}  {:<32}  {:>8}  {:>9}  {:>10}  {:>9}  {:>9}  {:>9}",
+            "unit",
+            "function",
+            "requests",
+            "responses",
+            "exceptions",
+            "min ms",
+            "mean ms",
+            "max ms"
+        )?;
+        for ((unit_id, function_code), stats) in self.stats() {
+            let function = match FunctionCode::try_from(function_code) {
+                Ok(FunctionCode::Custom(_)) | Err(_) => format!("{function_code:#04x}"),
+                Ok(function) => format!("{function_code:#04x} {function:?}"),
+            };
+            writeln!(
+                f,
+                "{unit_id:>4}  {function:<32}  {:>8}  {:>9}  {:>10}  {:>9}  {:>9}  {:>9}",
+                stats.requests,
+                stats.responses,
+                stats.exceptions,
+                millis(stats.min_latency),
+                millis(stats.mean_latency()),
+                millis(stats.max_latency),
+            )?;
+        }
+        if !self.violations.is_empty() {
+            writeln!(f, "{} violations:", self.violations.len())?;
+            for violation in &self.violations {
+                writeln!(f, "{violation}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn millis(latency: Option<Duration>) -> String {
+    latency.map_or_else(
+        || "-".into(),
+        |latency| format!("{:.3}", latency.as_secs_f64() * 1000.0),
+    )
+}
+
+/// The two streams of a connection and its pending requests
+#[derive(Debug, Default)]
+struct Connection {
+    to_server: Stream,
+    to_client: Stream,
+    /// Index in the transactions by transaction id
+    pending: HashMap<u16, usize>,
+}
+
+/// Dissects the packets of a capture fed one by one
+#[derive(Debug, Default)]
+pub struct Dissector {
+    config: DissectorConfig,
+    /// Time of the first packet
+    start: Option<Duration>,
+    last_at: Duration,
+    /// By client and server address
+    connections: HashMap<(SocketAddr, SocketAddr), Connection>,
+    report: Report,
+}
+
+impl Dissector {
+    pub fn new(config: DissectorConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &DissectorConfig {
+        &self.config
+    }
+
+    /// Dissects a packet, ignoring it unless it's a TCP segment to or from one of the
+    /// configured ports
+    pub fn feed(&mut self, packet: &Packet) {
+        let start = *self.start.get_or_insert(packet.at);
+        let at = packet.at.saturating_sub(start);
+        self.last_at = at;
+
+        let Some(segment) = net::tcp_segment(packet.link_type, &packet.data) else {
+            return;
+        };
+        let (client, server, to_server) = if self.config.ports.contains(&segment.dst.port()) {
+            (segment.src, segment.dst, true)
+        } else if self.config.ports.contains(&segment.src.port()) {
+            (segment.dst, segment.src, false)
+        } else {
+            return;
+        };
+
+        let connection = self.connections.entry((client, server)).or_default();
+        let stream = match to_server {
+            true => &mut connection.to_server,
+            false => &mut connection.to_client,
+        };
+        let gap = stream.push(segment.seq, segment.syn, segment.payload);
+        if gap {
+            self.violation(at, client, server, ViolationKind::MissingData);
+        }
+        self.process_stream(at, client, server, to_server);
+    }
+
+    /// Reports what's left of the streams and returns the report
+    pub fn finish(mut self) -> Report {
+        let at = self.last_at;
+        let mut keys: Vec<_> = self.connections.keys().copied().collect();
+        keys.sort();
+        for (client, server) in keys {
+            for to_server in [true, false] {
+                let connection = self.connections.get_mut(&(client, server)).unwrap();
+                let stream = match to_server {
+                    true => &mut connection.to_server,
+                    false => &mut connection.to_client,
+                };
+                if stream.finish() {
+                    self.violation(at, client, server, ViolationKind::MissingData);
+                }
+                self.process_stream(at, client, server, to_server);
+
+                // The capture stopped in the middle of an ADU
+                let connection = &self.connections[&(client, server)];
+                let stream = match to_server {
+                    true => &connection.to_server,
+                    false => &connection.to_client,
+                };
+                if !stream.data().is_empty() {
+                    self.violation(at, client, server, ViolationKind::MissingData);
+                }
+            }
+        }
+        self.report
+    }
+
+    /// Processes the complete ADUs of one stream of a connection
+    fn process_stream(
+        &mut self,
+        at: Duration,
+        client: SocketAddr,
+        server: SocketAddr,
+        to_server: bool,
+    ) {
+        let connection = self.connections.get_mut(&(client, server)).unwrap();
+        let stream = match to_server {
+            true => &mut connection.to_server,
+            false => &mut connection.to_client,
+        };
+        let (frames, valid) = split_frames(stream);
+        if !valid {
+            self.violation(at, client, server, ViolationKind::InvalidHeader);
+        }
+        for frame in frames {
+            match to_server {
+                true => self.process_request(at, client, server, frame),
+                false => self.process_response(at, client, server, frame),
+            }
+        }
+    }
+
+    fn process_request(
+        &mut self,
+        at: Duration,
+        client: SocketAddr,
+        server: SocketAddr,
+        frame: Vec<u8>,
+    ) {
+        // `split_frames` checked the length
+        let header = Header::decode(&frame).unwrap();
+        if let Err(err) = Request::decode(&frame) {
+            self.violation(at, client, server, ViolationKind::InvalidRequest(err));
+        }
+        let transaction_id = *header.transaction_id();
+        let index = self.report.transactions.len();
+        self.report.transactions.push(Transaction {
+            client,
+            server,
+            transaction_id,
+            unit_id: *header.unit_id(),
+            function_code: frame[Header::size()],
+            request_at: at,
+            request: frame,
+            response_at: None,
+            response: None,
+            exception: None,
+        });
+
+        let connection = self.connections.get_mut(&(client, server)).unwrap();
+        if connection.pending.insert(transaction_id, index).is_some() {
+            self.violation(
+                at,
+                client,
+                server,
+                ViolationKind::DuplicateTransactionId(transaction_id),
+            );
+        }
+    }
+
+    fn process_response(
+        &mut self,
+        at: Duration,
+        client: SocketAddr,
+        server: SocketAddr,
+        frame: Vec<u8>,
+    ) {
+        let header = Header::decode(&frame).unwrap();
+        let transaction_id = *header.transaction_id();
+        let connection = self.connections.get_mut(&(client, server)).unwrap();
+        let Some(index) = connection.pending.remove(&transaction_id) else {
+            self.violation(
+                at,
+                client,
+                server,
+                ViolationKind::UnexpectedResponse(transaction_id),
+            );
+            return;
+        };
+
+        let exception = match Response::decode(&frame) {
+            Ok(_) => None,
+            Err(DecodeError::ModbusExceptionCode(_, code)) => Some(code),
+            Err(err) => {
+                self.violation(at, client, server, ViolationKind::InvalidResponse(err));
+                None
+            }
+        };
+        let transaction = &mut self.report.transactions[index];
+        let unit_mismatch = *header.unit_id() != transaction.unit_id;
+        let function_mismatch = frame[Header::size()] & 0x7f != transaction.function_code;
+        transaction.response_at = Some(at);
+        transaction.response = Some(frame);
+        transaction.exception = exception;
+
+        if unit_mismatch {
+            self.violation(at, client, server, ViolationKind::UnitMismatch);
+        }
+        if function_mismatch {
+            self.violation(at, client, server, ViolationKind::FunctionMismatch);
+        }
+    }
+
+    fn violation(
+        &mut self,
+        at: Duration,
+        client: SocketAddr,
+        server: SocketAddr,
+        kind: ViolationKind,
+    ) {
+        self.report.violations.push(Violation {
+            at,
+            client,
+            server,
+            kind,
+        });
+    }
+}
+
+/// Takes the complete ADUs out of a stream. Returns `false` with them if the bytes
+/// after them don't start with a valid MBAP header, in which case they are dropped.
+fn split_frames(stream: &mut Stream) -> (Vec<Vec<u8>>, bool) {
+    let mut frames = vec![];
+    while stream.data().len() >= Header::size() {
+        let header = Header::decode(stream.data()).unwrap();
+        // The unit id and at least a function code, at most a PDU
+        if *header.protocol_id() != 0 || !(2..=254).contains(header.length()) {
+            stream.clear();
+            return (frames, false);
+        }
+        let Some(len) = Framing::Tcp.frame_len(stream.data()) else {
+            break;
+        };
+        frames.push(stream.data()[..len].to_vec());
+        stream.consume(len);
+    }
+    (frames, true)
+}
+
+/// Dissects a whole pcap or pcapng capture
+pub fn dissect<R: Read>(reader: R, config: DissectorConfig) -> Result<Report, PcapError> {
+    let mut dissector = Dissector::new(config);
+    for packet in PacketReader::new(reader)? {
+        dissector.feed(&packet?);
+    }
+    Ok(dissector.finish())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{net::SocketAddr, string::ToString, time::Duration, vec, vec::Vec};
+
+    use super::{file::Packet, net::LINKTYPE_ETHERNET, *};
+
+    pub(crate) const SYN: u8 = 0x02;
+    pub(crate) const PSH_ACK: u8 = 0x18;
+
+    /// IPv4 packet carrying a TCP segment
+    pub(crate) fn tcp_ipv4(src: &str, dst: &str, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let (SocketAddr::V4(src), SocketAddr::V4(dst)) =
+            (src.parse().unwrap(), dst.parse().unwrap())
+        else {
+            panic!("not IPv4 addresses");
+        };
+        let total_len = (40 + payload.len()) as u16;
+        let mut ip = vec![0x45, 0];
+        ip.extend_from_slice(&total_len.to_be_bytes());
+        // Id, don't fragment, TTL, protocol and checksum
+        ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+        ip.extend_from_slice(&src.ip().octets());
+        ip.extend_from_slice(&dst.ip().octets());
+        ip.extend_from_slice(&src.port().to_be_bytes());
+        ip.extend_from_slice(&dst.port().to_be_bytes());
+        ip.extend_from_slice(&seq.to_be_bytes());
+        // Ack, data offset, flags, window, checksum and urgent pointer
+        ip.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        ip.extend_from_slice(payload);
+        ip
+    }
+
+    pub(crate) fn ethernet(ethertype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Little-endian pcap capture of Ethernet frames with microsecond timestamps
+    pub(crate) fn pcap(packets: &[(Duration, Vec<u8>)]) -> Vec<u8> {
+        let mut file = vec![];
+        for field in [0xa1b2_c3d4_u32, 0x0004_0002, 0, 0, 65535, LINKTYPE_ETHERNET] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        for (at, data) in packets {
+            let len = data.len() as u32;
+            for field in [at.as_secs() as u32, at.subsec_micros(), len, len] {
+                file.extend_from_slice(&field.to_le_bytes());
+            }
+            file.extend_from_slice(data);
+        }
+        file
+    }
+
+    fn packet(millis: u64, src: &str, dst: &str, seq: u32, flags: u8, payload: &[u8]) -> Packet {
+        Packet {
+            at: Duration::from_secs(1_700_000_000) + Duration::from_millis(millis),
+            link_type: LINKTYPE_ETHERNET,
+            data: ethernet(0x0800, &tcp_ipv4(src, dst, seq, flags, payload)),
+        }
+    }
+
+    const CLIENT: &str = "10.0.0.1:40000";
+    const SERVER: &str = "10.0.0.2:502";
+
+    #[test]
+    fn dissects_capture() {
+        let read_2 = [0, 1, 0, 0, 0, 6, 1, 3, 0, 100, 0, 2];
+        let mut pipelined = vec![0, 2, 0, 0, 0, 6, 1, 3, 0, 0, 0, 1];
+        pipelined.extend_from_slice(&[0, 3, 0, 0, 0, 6, 1, 6, 0, 1, 0, 5]);
+        let packets = [
+            packet(0, CLIENT, SERVER, 1000, SYN, &[]),
+            // Split across segments
+            packet(1, CLIENT, SERVER, 1001, PSH_ACK, &read_2[..5]),
+            packet(2, CLIENT, SERVER, 1006, PSH_ACK, &read_2[5..]),
+            packet(3, CLIENT, SERVER, 1013, PSH_ACK, &pipelined),
+            packet(
+                10,
+                SERVER,
+                CLIENT,
+                5000,
+                PSH_ACK,
+                &[0, 1, 0, 0, 0, 7, 1, 3, 4, 0, 1, 0, 2],
+            ),
+            packet(
+                12,
+                SERVER,
+                CLIENT,
+                5013,
+                PSH_ACK,
+                &[0, 3, 0, 0, 0, 3, 1, 0x86, 2],
+            ),
+            packet(
+                15,
+                SERVER,
+                CLIENT,
+                5022,
+                PSH_ACK,
+                &[0, 2, 0, 0, 0, 5, 1, 3, 2, 0, 9],
+            ),
+            packet(
+                20,
+                SERVER,
+                CLIENT,
+                5033,
+                PSH_ACK,
+                &[0, 9, 0, 0, 0, 5, 1, 3, 2, 0, 9],
+            ),
+        ];
+        let file = pcap(&packets.map(|packet| (packet.at, packet.data)));
+        let report = dissect(&file[..], DissectorConfig::default()).unwrap();
+
+        let transactions = &report.transactions;
+        assert_eq!(transactions.len(), 3);
+        assert_eq!(transactions[0].client, CLIENT.parse().unwrap());
+        assert_eq!(transactions[0].server, SERVER.parse().unwrap());
+        assert_eq!(transactions[0].request, read_2);
+        assert_eq!(transactions[0].latency(), Some(Duration::from_millis(8)));
+        assert_eq!(transactions[1].transaction_id, 2);
+        assert_eq!(transactions[1].latency(), Some(Duration::from_millis(12)));
+        assert_eq!(transactions[2].function_code, 6);
+        assert_eq!(
+            transactions[2].exception,
+            Some(ExceptionCode::IllegalDataAddress)
+        );
+        assert_eq!(transactions[2].latency(), Some(Duration::from_millis(9)));
+
+        assert_eq!(
+            report.violations,
+            [Violation {
+                at: Duration::from_millis(20),
+                client: CLIENT.parse().unwrap(),
+                server: SERVER.parse().unwrap(),
+                kind: ViolationKind::UnexpectedResponse(9),
+            }]
+        );
+
+        let stats = report.stats();
+        let reads = stats[&(1, 3)];
+        assert_eq!(
+            (reads.requests, reads.responses, reads.exceptions),
+            (2, 2, 0)
+        );
+        assert_eq!(reads.min_latency, Some(Duration::from_millis(8)));
+        assert_eq!(reads.mean_latency(), Some(Duration::from_millis(10)));
+        assert_eq!(reads.max_latency, Some(Duration::from_millis(12)));
+        let writes = stats[&(1, 6)];
+        assert_eq!(
+            (writes.requests, writes.responses, writes.exceptions),
+            (1, 1, 1)
+        );
+
+        let text = report.to_string();
+        assert!(text.contains("0x03 ReadHoldingRegisters"));
+        assert!(text.contains("response to no pending request, transaction 9"));
+    }
+
+    #[test]
+    fn reports_violations() {
+        let server = "10.0.0.2:1502";
+        let mut dissector = Dissector::new(DissectorConfig { ports: vec![1502] });
+        // Not on a configured port
+        dissector.feed(&packet(
+            0,
+            CLIENT,
+            SERVER,
+            1,
+            PSH_ACK,
+            &[0, 1, 0, 0, 0, 6, 1, 3, 0, 0, 0, 1],
+        ));
+        // Protocol id 1
+        dissector.feed(&packet(
+            1,
+            CLIENT,
+            server,
+            1,
+            PSH_ACK,
+            &[0, 1, 0, 1, 0, 6, 1, 3, 0, 0, 0, 1],
+        ));
+        dissector.feed(&packet(
+            2,
+            CLIENT,
+            server,
+            13,
+            PSH_ACK,
+            &[0, 2, 0, 0, 0, 6, 1, 3, 0, 0, 0, 1],
+        ));
+        // Pending when the same id is sent again
+        dissector.feed(&packet(
+            3,
+            CLIENT,
+            server,
+            25,
+            PSH_ACK,
+            &[0, 2, 0, 0, 0, 6, 1, 3, 0, 0, 0, 1],
+        ));
+        // Response from unit 2 to a request to unit 1
+        dissector.feed(&packet(
+            4,
+            server,
+            CLIENT,
+            1,
+            PSH_ACK,
+            &[0, 2, 0, 0, 0, 5, 2, 3, 2, 0, 0],
+        ));
+        // The bytes 37..49 are missing, and the capture ends in the middle of an ADU
+        dissector.feed(&packet(5, CLIENT, server, 49, PSH_ACK, &[0, 4, 0, 0, 0, 6]));
+        let report = dissector.finish();
+
+        let kinds: Vec<_> = report.violations.iter().map(|v| v.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ViolationKind::InvalidHeader,
+                ViolationKind::DuplicateTransactionId(2),
+                ViolationKind::UnitMismatch,
+                ViolationKind::MissingData,
+                ViolationKind::MissingData,
+            ]
+        );
+        assert_eq!(report.transactions.len(), 2);
+        assert_eq!(report.transactions[0].latency(), None);
+        assert_eq!(
+            report.transactions[1].latency(),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(report.stats()[&(1, 3)].responses, 1);
+    }
+}
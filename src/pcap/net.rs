@@ -0,0 +1,243 @@
+//! Link, IP and TCP headers of captured packets
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+pub const LINKTYPE_NULL: u32 = 0;
+pub const LINKTYPE_ETHERNET: u32 = 1;
+pub const LINKTYPE_RAW: u32 = 101;
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+pub const LINKTYPE_IPV4: u32 = 228;
+pub const LINKTYPE_IPV6: u32 = 229;
+pub const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IPPROTO_TCP: u8 = 6;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub seq: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub payload: &'a [u8],
+}
+
+/// TCP segment of a packet, `None` if it carries anything else, a fragment or a link
+/// type that isn't supported
+pub fn tcp_segment(link_type: u32, data: &[u8]) -> Option<Segment<'_>> {
+    let (ethertype, ip) = match link_type {
+        LINKTYPE_NULL => {
+            // The address family, in the byte order of the capturing host
+            let family = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+            let family = if family > 0xffff {
+                family.swap_bytes()
+            } else {
+                family
+            };
+            match family {
+                2 => (ETHERTYPE_IPV4, &data[4..]),
+                // BSDs don't agree on the value of AF_INET6
+                24 | 28 | 30 => (ETHERTYPE_IPV6, &data[4..]),
+                _ => return None,
+            }
+        }
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = be16(data.get(12..14)?);
+            let mut offset = 14;
+            while matches!(ethertype, ETHERTYPE_VLAN | ETHERTYPE_QINQ) {
+                ethertype = be16(data.get(offset + 2..offset + 4)?);
+                offset += 4;
+            }
+            (ethertype, data.get(offset..)?)
+        }
+        LINKTYPE_RAW => match data.first()? >> 4 {
+            4 => (ETHERTYPE_IPV4, data),
+            6 => (ETHERTYPE_IPV6, data),
+            _ => return None,
+        },
+        LINKTYPE_LINUX_SLL => (be16(data.get(14..16)?), data.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => (be16(data.get(..2)?), data.get(20..)?),
+        LINKTYPE_IPV4 => (ETHERTYPE_IPV4, data),
+        LINKTYPE_IPV6 => (ETHERTYPE_IPV6, data),
+        _ => return None,
+    };
+
+    let (src, dst, tcp) = match ethertype {
+        ETHERTYPE_IPV4 => ipv4(ip)?,
+        ETHERTYPE_IPV6 => ipv6(ip)?,
+        _ => return None,
+    };
+
+    if tcp.len() < 20 {
+        return None;
+    }
+    let data_offset = (tcp[12] >> 4) as usize * 4;
+    if data_offset < 20 {
+        return None;
+    }
+    let flags = tcp[13];
+    Some(Segment {
+        src: SocketAddr::new(src, be16(&tcp[0..2])),
+        dst: SocketAddr::new(dst, be16(&tcp[2..4])),
+        seq: u32::from_be_bytes(tcp[4..8].try_into().unwrap()),
+        syn: flags & TCP_SYN != 0,
+        fin: flags & TCP_FIN != 0,
+        rst: flags & TCP_RST != 0,
+        payload: tcp.get(data_offset..)?,
+    })
+}
+
+/// Addresses and TCP header and payload of an IPv4 packet
+fn ipv4(ip: &[u8]) -> Option<(IpAddr, IpAddr, &[u8])> {
+    if ip.len() < 20 {
+        return None;
+    }
+    let header_len = (ip[0] & 0x0f) as usize * 4;
+    let total_len = be16(&ip[2..4]) as usize;
+    // More fragments, or an offset
+    let fragment = be16(&ip[6..8]) & 0x3fff;
+    if ip[0] >> 4 != 4 || header_len < 20 || fragment != 0 || ip[9] != IPPROTO_TCP {
+        return None;
+    }
+    let src: [u8; 4] = ip[12..16].try_into().ok()?;
+    let dst: [u8; 4] = ip[16..20].try_into().ok()?;
+    // Ethernet pads short frames, and captures may be cut
+    let tcp = ip.get(header_len..total_len.min(ip.len()))?;
+    Some((Ipv4Addr::from(src).into(), Ipv4Addr::from(dst).into(), tcp))
+}
+
+/// Addresses and TCP header and payload of an IPv6 packet, skipping its extension
+/// headers
+fn ipv6(ip: &[u8]) -> Option<(IpAddr, IpAddr, &[u8])> {
+    if *ip.first()? >> 4 != 6 {
+        return None;
+    }
+    let payload_len = be16(ip.get(4..6)?) as usize;
+    let mut next_header = *ip.get(6)?;
+    let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+    let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+    let mut payload = &ip[40..(40 + payload_len).min(ip.len())];
+
+    loop {
+        match next_header {
+            IPPROTO_TCP => break,
+            // Hop-by-hop, routing and destination options
+            0 | 43 | 60 => {
+                let len = (*payload.get(1)? as usize + 1) * 8;
+                next_header = payload[0];
+                payload = payload.get(len..)?;
+            }
+            // Fragments and anything else
+            _ => return None,
+        }
+    }
+    Some((
+        Ipv6Addr::from(src).into(),
+        Ipv6Addr::from(dst).into(),
+        payload,
+    ))
+}
+
+fn be16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec;
+
+    use super::*;
+    use crate::pcap::test::{PSH_ACK, SYN, ethernet, tcp_ipv4};
+
+    #[test]
+    fn parses_ethernet_ipv4() {
+        let mut frame = ethernet(
+            0x0800,
+            &tcp_ipv4("10.0.0.1:40000", "10.0.0.2:502", 7, SYN, &[]),
+        );
+        // Ethernet padding after the IP packet
+        frame.extend_from_slice(&[0; 6]);
+        let segment = tcp_segment(LINKTYPE_ETHERNET, &frame).unwrap();
+        assert_eq!(segment.src, "10.0.0.1:40000".parse().unwrap());
+        assert_eq!(segment.dst, "10.0.0.2:502".parse().unwrap());
+        assert_eq!(segment.seq, 7);
+        assert!(segment.syn && !segment.fin && !segment.rst);
+        assert!(segment.payload.is_empty());
+    }
+
+    #[test]
+    fn parses_vlan_and_linux_cooked() {
+        let ip = tcp_ipv4("10.0.0.1:40000", "10.0.0.2:502", 1, PSH_ACK, &[1, 2]);
+        let mut tagged = vec![0, 1, 0x08, 0x00];
+        tagged.extend_from_slice(&ip);
+        let frame = ethernet(0x8100, &tagged);
+        assert_eq!(
+            tcp_segment(LINKTYPE_ETHERNET, &frame).unwrap().payload,
+            [1, 2]
+        );
+
+        let mut sll = vec![0; 14];
+        sll.extend_from_slice(&[0x08, 0x00]);
+        sll.extend_from_slice(&ip);
+        assert_eq!(
+            tcp_segment(LINKTYPE_LINUX_SLL, &sll).unwrap().payload,
+            [1, 2]
+        );
+        assert_eq!(tcp_segment(LINKTYPE_RAW, &ip).unwrap().payload, [1, 2]);
+    }
+
+    #[test]
+    fn parses_ipv6() {
+        let tcp = &tcp_ipv4("10.0.0.1:40000", "10.0.0.2:502", 1, PSH_ACK, &[9])[20..];
+        // With hop-by-hop options
+        let mut ip = vec![0x60, 0, 0, 0, 0, (tcp.len() + 8) as u8, 0, 64];
+        ip.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip.extend_from_slice(&[IPPROTO_TCP, 0, 0, 0, 0, 0, 0, 0]);
+        ip.extend_from_slice(tcp);
+
+        let segment = tcp_segment(LINKTYPE_IPV6, &ip).unwrap();
+        assert_eq!(segment.src, "[::1]:40000".parse().unwrap());
+        assert_eq!(segment.dst, "[::1]:502".parse().unwrap());
+        assert_eq!(segment.payload, [9]);
+    }
+
+    #[test]
+    fn ignores_other_packets() {
+        let mut ip = tcp_ipv4("10.0.0.1:40000", "10.0.0.2:502", 1, PSH_ACK, &[9]);
+        assert!(tcp_segment(LINKTYPE_ETHERNET, &ethernet(0x0806, &ip)).is_none());
+        assert!(tcp_segment(147, &ip).is_none());
+        // More fragments
+        ip[6] = 0x20;
+        assert!(tcp_segment(LINKTYPE_RAW, &ip).is_none());
+        // UDP
+        ip[6] = 0;
+        ip[9] = 17;
+        assert!(tcp_segment(LINKTYPE_RAW, &ip).is_none());
+        assert!(tcp_segment(LINKTYPE_RAW, &ip[..30]).is_none());
+
+        // Truncated IP and TCP headers
+        let ip = tcp_ipv4("10.0.0.1:40000", "10.0.0.2:502", 1, PSH_ACK, &[]);
+        assert!(tcp_segment(LINKTYPE_RAW, &ip).is_some());
+        for len in 0..40 {
+            assert!(tcp_segment(LINKTYPE_RAW, &ip[..len]).is_none());
+        }
+        let mut ip = vec![0x60, 0, 0, 0, 0, 20, IPPROTO_TCP, 64];
+        ip.extend_from_slice(&[0; 32]);
+        ip.extend_from_slice(&tcp_ipv4("10.0.0.1:40000", "10.0.0.2:502", 1, PSH_ACK, &[])[20..]);
+        assert!(tcp_segment(LINKTYPE_RAW, &ip).is_some());
+        for len in 0..60 {
+            assert!(tcp_segment(LINKTYPE_RAW, &ip[..len]).is_none());
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, vec::Vec};
+
+/// Segments kept waiting for a missing one before giving up on it, which happens when
+/// the capture dropped it
+const MAX_PENDING_BYTES: usize = 64 * 1024;
+
+/// One direction of a TCP connection, putting the payloads of its segments back in
+/// order.
+///
+/// Retransmitted bytes are dropped. When the capture misses a segment, the bytes
+/// buffered before it are dropped too and the stream carries on after the gap.
+#[derive(Debug, Default)]
+pub struct Stream {
+    /// Sequence number of the next byte in order, unknown before the first segment
+    next_seq: Option<u32>,
+    /// Segments received ahead of `next_seq`, by sequence number
+    pending: BTreeMap<u32, Vec<u8>>,
+    pending_len: usize,
+    data: Vec<u8>,
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes in order, not consumed yet
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn consume(&mut self, len: usize) {
+        self.data.drain(..len);
+    }
+
+    /// Drops the bytes not consumed yet
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// Adds the payload of a segment. Returns `true` when bytes before it are missing
+    /// from the capture, in which case the unconsumed data was dropped.
+    pub fn push(&mut self, seq: u32, syn: bool, payload: &[u8]) -> bool {
+        if syn {
+            // The SYN takes a sequence number
+            self.next_seq = Some(seq.wrapping_add(1));
+            self.pending.clear();
+            self.pending_len = 0;
+            self.data.clear();
+            return false;
+        }
+        if payload.is_empty() {
+            return false;
+        }
+        // Captures may start in the middle of a connection
+        let next_seq = *self.next_seq.get_or_insert(seq);
+
+        if offset(next_seq, seq) > 0 {
+            self.pending_len += payload.len();
+            if let Some(retransmitted) = self.pending.insert(seq, payload.to_vec()) {
+                self.pending_len -= retransmitted.len();
+            }
+        } else {
+            self.append(seq, payload);
+        }
+        self.drain_pending(MAX_PENDING_BYTES)
+    }
+
+    /// Gives up on the missing segments at the end of the capture, returning `true` if
+    /// there were any
+    pub fn finish(&mut self) -> bool {
+        self.drain_pending(0)
+    }
+
+    /// Appends the part of a segment starting at or before `next_seq` not seen yet
+    fn append(&mut self, seq: u32, payload: &[u8]) {
+        let next_seq = self.next_seq.unwrap();
+        let seen = (-offset(next_seq, seq)) as usize;
+        if seen < payload.len() {
+            self.data.extend_from_slice(&payload[seen..]);
+            self.next_seq = Some(seq.wrapping_add(payload.len() as u32));
+        }
+    }
+
+    /// Appends the pending segments now in order, skipping missing ones when more than
+    /// `max_pending_bytes` are waiting for them
+    fn drain_pending(&mut self, max_pending_bytes: usize) -> bool {
+        let mut gap = false;
+        let Some(mut next_seq) = self.next_seq else {
+            return gap;
+        };
+        loop {
+            // The closest segment, sequence numbers wrapping around
+            let Some(seq) = self
+                .pending
+                .keys()
+                .copied()
+                .min_by_key(|seq| offset(next_seq, *seq))
+            else {
+                return gap;
+            };
+            if offset(next_seq, seq) > 0 {
+                if self.pending_len <= max_pending_bytes {
+                    return gap;
+                }
+                // Skips the missing bytes
+                gap = true;
+                self.data.clear();
+                self.next_seq = Some(seq);
+            }
+            let payload = self.pending.remove(&seq).unwrap();
+            self.pending_len -= payload.len();
+            self.append(seq, &payload);
+            next_seq = self.next_seq.unwrap();
+        }
+    }
+}
+
+/// Distance from `next_seq` to `seq`, negative when `seq` is behind
+fn offset(next_seq: u32, seq: u32) -> i32 {
+    seq.wrapping_sub(next_seq) as i32
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec;
+
+    use super::*;
+
+    #[test]
+    fn reorders_segments() {
+        let mut stream = Stream::new();
+        assert!(!stream.push(99, true, &[]));
+        assert!(!stream.push(103, false, &[4, 5]));
+        assert!(!stream.push(105, false, &[6]));
+        assert!(stream.data().is_empty());
+        assert!(!stream.push(100, false, &[1, 2, 3]));
+        assert_eq!(stream.data(), [1, 2, 3, 4, 5, 6]);
+
+        stream.consume(4);
+        assert_eq!(stream.data(), [5, 6]);
+        assert!(!stream.finish());
+    }
+
+    #[test]
+    fn drops_retransmissions() {
+        let mut stream = Stream::new();
+        // Starting in the middle of a connection, around the wrap of sequence numbers
+        assert!(!stream.push(u32::MAX - 1, false, &[1, 2]));
+        assert!(!stream.push(u32::MAX - 1, false, &[1, 2]));
+        assert!(!stream.push(u32::MAX, false, &[2, 3, 4]));
+        assert!(!stream.push(2, false, &[5]));
+        assert!(!stream.push(2, false, &[5]));
+        assert!(!stream.push(1, false, &[4, 5]));
+        assert_eq!(stream.data(), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn skips_missing_segments() {
+        let mut stream = Stream::new();
+        assert!(!stream.push(0, false, &[1, 2]));
+        assert!(!stream.push(10, false, &[3]));
+        assert!(stream.finish());
+        assert_eq!(stream.data(), [3]);
+
+        // Giving up once too much is waiting
+        let mut stream = Stream::new();
+        assert!(!stream.push(0, false, &[1]));
+        assert!(!stream.push(10, false, &vec![2; MAX_PENDING_BYTES]));
+        assert!(stream.push(10 + MAX_PENDING_BYTES as u32, false, &[3]));
+        assert_eq!(stream.data().len(), MAX_PENDING_BYTES + 1);
+    }
+}
@@ -0,0 +1,196 @@
+use super::{
+    DataWords,
+    value::{DataType, Value, WordOrder},
+};
+
+/// Value of the 4 BCD digits of a register, `None` if a nibble isn't a digit
+pub fn decode_bcd16(word: u16) -> Option<u16> {
+    let mut value = 0;
+    for shift in [12, 8, 4, 0] {
+        let digit = (word >> shift) & 0xf;
+        if digit > 9 {
+            return None;
+        }
+        value = value * 10 + digit;
+    }
+    Some(value)
+}
+
+/// BCD digits of a value up to 9999
+pub fn encode_bcd16(value: u16) -> Option<u16> {
+    if value > 9999 {
+        return None;
+    }
+    Some(
+        ((value / 1000) << 12)
+            | ((value / 100 % 10) << 8)
+            | ((value / 10 % 10) << 4)
+            | (value % 10),
+    )
+}
+
+/// Value of 8 BCD digits, `None` if a nibble isn't a digit
+pub fn decode_bcd32(value: u32) -> Option<u32> {
+    let high = decode_bcd16((value >> 16) as u16)?;
+    let low = decode_bcd16(value as u16)?;
+    Some(high as u32 * 10000 + low as u32)
+}
+
+/// BCD digits of a value up to 99999999
+pub fn encode_bcd32(value: u32) -> Option<u32> {
+    if value > 99_999_999 {
+        return None;
+    }
+    let high = encode_bcd16((value / 10000) as u16)?;
+    let low = encode_bcd16((value % 10000) as u16)?;
+    Some(((high as u32) << 16) | low as u32)
+}
+
+fn decode_bcd8(byte: u8) -> Option<u8> {
+    let (high, low) = (byte >> 4, byte & 0xf);
+    (high <= 9 && low <= 9).then_some(high * 10 + low)
+}
+
+fn encode_bcd8(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// Date and time packed as BCD bytes in 3 registers: `YY MM`, `DD hh`, `mm ss`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BcdDateTime {
+    /// 2000 to 2099
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl BcdDateTime {
+    /// Number of registers
+    pub const QUANTITY: usize = 3;
+
+    fn is_valid(&self) -> bool {
+        (2000..=2099).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    /// `None` if a byte isn't BCD or a field is out of its range
+    pub fn decode(words: [u16; 3]) -> Option<Self> {
+        let [yy, mm, dd, hh, min, ss] = [
+            (words[0] >> 8) as u8,
+            words[0] as u8,
+            (words[1] >> 8) as u8,
+            words[1] as u8,
+            (words[2] >> 8) as u8,
+            words[2] as u8,
+        ]
+        .map(decode_bcd8);
+        let date_time = Self {
+            year: 2000 + yy? as u16,
+            month: mm?,
+            day: dd?,
+            hour: hh?,
+            minute: min?,
+            second: ss?,
+        };
+        date_time.is_valid().then_some(date_time)
+    }
+
+    /// `None` if a field is out of its range
+    pub fn encode(&self) -> Option<[u16; 3]> {
+        if !self.is_valid() {
+            return None;
+        }
+        let pair = |high: u8, low: u8| ((encode_bcd8(high) as u16) << 8) | encode_bcd8(low) as u16;
+        Some([
+            pair((self.year - 2000) as u8, self.month),
+            pair(self.day, self.hour),
+            pair(self.minute, self.second),
+        ])
+    }
+}
+
+impl DataWords<'_> {
+    /// BCD value of the register `index`
+    pub fn bcd16(&self, index: usize) -> Option<u16> {
+        match self.value(index, DataType::U16, WordOrder::Abcd)? {
+            Value::U16(word) => decode_bcd16(word),
+            _ => None,
+        }
+    }
+
+    /// BCD value of 2 registers from `index`
+    pub fn bcd32(&self, index: usize, order: WordOrder) -> Option<u32> {
+        match self.value(index, DataType::U32, order)? {
+            Value::U32(value) => decode_bcd32(value),
+            _ => None,
+        }
+    }
+
+    /// Date and time of 3 registers from `index`
+    pub fn bcd_date_time(&self, index: usize) -> Option<BcdDateTime> {
+        let word = |i| match self.value(index + i, DataType::U16, WordOrder::Abcd) {
+            Some(Value::U16(word)) => Some(word),
+            _ => None,
+        };
+        BcdDateTime::decode([word(0)?, word(1)?, word(2)?])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pdu::{DataWords, value::WordOrder};
+
+    use super::{BcdDateTime, decode_bcd16, encode_bcd16, encode_bcd32};
+
+    #[test]
+    fn bcd_values() {
+        assert_eq!(decode_bcd16(0x1234), Some(1234));
+        assert_eq!(decode_bcd16(0x12a4), None);
+        assert_eq!(encode_bcd16(9999), Some(0x9999));
+        assert_eq!(encode_bcd16(10000), None);
+        assert_eq!(encode_bcd32(12345678), Some(0x1234_5678));
+        assert_eq!(encode_bcd32(100_000_000), None);
+
+        let words = DataWords::new(&[0x56, 0x78, 0x12, 0x34, 0x00, 0xf0], 3);
+        assert_eq!(words.bcd16(1), Some(1234));
+        assert_eq!(words.bcd16(2), None);
+        assert_eq!(words.bcd32(0, WordOrder::Abcd), Some(56781234));
+        assert_eq!(words.bcd32(0, WordOrder::Cdab), Some(12345678));
+        assert_eq!(words.bcd32(2, WordOrder::Abcd), None);
+    }
+
+    #[test]
+    fn bcd_date_time() {
+        let date_time = BcdDateTime {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hour: 23,
+            minute: 5,
+            second: 59,
+        };
+        assert_eq!(date_time.encode(), Some([0x2402, 0x2923, 0x0559]));
+
+        let words = DataWords::new(&[0x24, 0x02, 0x29, 0x23, 0x05, 0x59], 3);
+        assert_eq!(words.bcd_date_time(0), Some(date_time));
+        assert_eq!(BcdDateTime::decode([0x2413, 0x0100, 0x0000]), None);
+        assert_eq!(BcdDateTime::decode([0x2401, 0x0124, 0x0000]), None);
+        assert_eq!(BcdDateTime::decode([0x2401, 0x0100, 0x000a]), None);
+        assert_eq!(
+            BcdDateTime {
+                year: 1999,
+                ..date_time
+            }
+            .encode(),
+            None
+        );
+    }
+}
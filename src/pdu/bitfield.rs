@@ -0,0 +1,158 @@
+use super::{
+    DataWords,
+    value::{DataType, Value, WordOrder},
+};
+
+/// Named bit of a status word
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Flag<'a> {
+    pub name: &'a str,
+    pub bit: u8,
+}
+
+impl<'a> Flag<'a> {
+    pub const fn new(name: &'a str, bit: u8) -> Self {
+        Self { name, bit }
+    }
+
+    fn mask(&self) -> u32 {
+        1 << self.bit
+    }
+}
+
+/// Names of the bits of 16 or 32 bit status words, usually a `const`:
+///
+/// ```
+/// use modbus::pdu::bitfield::{BitfieldMap, Flag};
+///
+/// const STATUS: BitfieldMap<'_> =
+///     BitfieldMap::new(16, &[Flag::new("running", 0), Flag::new("fault", 3)]);
+/// assert_eq!(STATUS.flags(0b1001).collect::<Vec<_>>(), ["running", "fault"]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BitfieldMap<'a> {
+    bits: u8,
+    flags: &'a [Flag<'a>],
+}
+
+impl<'a> BitfieldMap<'a> {
+    /// Panics if `bits` isn't 16 or 32, or a flag is beyond it
+    pub const fn new(bits: u8, flags: &'a [Flag<'a>]) -> Self {
+        assert!(bits == 16 || bits == 32);
+        let mut i = 0;
+        while i < flags.len() {
+            assert!(flags[i].bit < bits);
+            i += 1;
+        }
+        Self { bits, flags }
+    }
+
+    pub fn bits(&self) -> &u8 {
+        &self.bits
+    }
+    pub fn flags_defined(&self) -> &'a [Flag<'a>] {
+        self.flags
+    }
+
+    /// Names of the flags set in `value`
+    pub fn flags(&self, value: u32) -> impl Iterator<Item = &'a str> + use<'a> {
+        self.flags
+            .iter()
+            .filter(move |flag| value & flag.mask() != 0)
+            .map(|flag| flag.name)
+    }
+
+    /// Whether the flag is set, `None` for an unknown name
+    pub fn is_set(&self, value: u32, name: &str) -> Option<bool> {
+        let flag = self.flags.iter().find(|flag| flag.name == name)?;
+        Some(value & flag.mask() != 0)
+    }
+
+    /// Bits set in `value` without a name
+    pub fn unknown_bits(&self, value: u32) -> u32 {
+        self.flags
+            .iter()
+            .fold(value, |value, flag| value & !flag.mask())
+    }
+
+    /// Value with the named flags set, `None` for an unknown name
+    pub fn encode<'n>(&self, names: impl IntoIterator<Item = &'n str>) -> Option<u32> {
+        names.into_iter().try_fold(0, |value, name| {
+            let flag = self.flags.iter().find(|flag| flag.name == name)?;
+            Some(value | flag.mask())
+        })
+    }
+
+    /// Writes `value` to the start of `words`, 1 or 2 registers, and returns how many
+    pub fn encode_words(&self, value: u32, order: WordOrder, words: &mut [u16]) -> usize {
+        if self.bits == 16 {
+            Value::U16(value as u16).encode_words(order, words)
+        } else {
+            Value::U32(value).encode_words(order, words)
+        }
+    }
+
+    /// Status word starting at the register `index`
+    pub fn decode(&self, words: &DataWords<'_>, index: usize, order: WordOrder) -> Option<u32> {
+        let data_type = if self.bits == 16 {
+            DataType::U16
+        } else {
+            DataType::U32
+        };
+        match words.value(index, data_type, order)? {
+            Value::U16(value) => Some(value as u32),
+            Value::U32(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pdu::{DataWords, value::WordOrder};
+
+    use super::{BitfieldMap, Flag};
+
+    const STATUS: BitfieldMap<'_> = BitfieldMap::new(
+        32,
+        &[
+            Flag::new("running", 0),
+            Flag::new("fault", 3),
+            Flag::new("grid_lost", 17),
+        ],
+    );
+
+    #[test]
+    fn names_flags() {
+        let value = 0b1001 | 1 << 17 | 1 << 20;
+        let mut flags = STATUS.flags(value);
+        assert_eq!(flags.next(), Some("running"));
+        assert_eq!(flags.next(), Some("fault"));
+        assert_eq!(flags.next(), Some("grid_lost"));
+        assert_eq!(flags.next(), None);
+        assert_eq!(STATUS.is_set(value, "fault"), Some(true));
+        assert_eq!(STATUS.is_set(0, "fault"), Some(false));
+        assert_eq!(STATUS.is_set(0, "other"), None);
+        assert_eq!(STATUS.unknown_bits(value), 1 << 20);
+    }
+
+    #[test]
+    fn encodes_and_decodes_words() {
+        let value = STATUS.encode(["fault", "grid_lost"]).unwrap();
+        assert_eq!(value, 1 << 3 | 1 << 17);
+        assert_eq!(STATUS.encode(["fault", "other"]), None);
+
+        let mut words = [0; 2];
+        assert_eq!(STATUS.encode_words(value, WordOrder::Cdab, &mut words), 2);
+        assert_eq!(words, [0x0008, 0x0002]);
+
+        let words = DataWords::new(&[0x00, 0x08, 0x00, 0x02], 2);
+        assert_eq!(STATUS.decode(&words, 0, WordOrder::Cdab), Some(value));
+        let flags = [Flag::new("on", 15)];
+        let map = BitfieldMap::new(16, &flags);
+        assert_eq!(map.decode(&words, 1, WordOrder::Abcd), Some(2));
+        assert_eq!(map.decode(&words, 2, WordOrder::Abcd), None);
+    }
+}
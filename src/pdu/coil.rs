@@ -1,4 +1,9 @@
+use core::{fmt, iter::FusedIterator};
+
+use crate::error::EncodeError;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct DataCoils<'a> {
     data: &'a [u8],
     quantity: usize,
@@ -40,20 +45,134 @@ impl<'a> DataCoils<'a> {
     pub fn data_len(&self) -> usize {
         self.data.len()
     }
+
+    /// Coils of the iterator packed into `buf`, which must hold a byte per 8 coils
+    pub fn from_coils_iter(
+        coils: impl IntoIterator<Item = bool>,
+        buf: &'a mut [u8],
+    ) -> Result<Self, EncodeError> {
+        let available = buf.len();
+        let mut quantity = 0;
+        for coil in coils {
+            let byte = buf
+                .get_mut(quantity / 8)
+                .ok_or(EncodeError::InvalidBufferSize {
+                    needed: quantity / 8 + 1,
+                    available,
+                })?;
+            if quantity % 8 == 0 {
+                *byte = 0;
+            }
+            *byte |= (coil as u8) << (quantity % 8);
+            quantity += 1;
+        }
+
+        Ok(Self {
+            data: &buf[..quantity.div_ceil(8)],
+            quantity,
+        })
+    }
+
+    /// Coil at `index`, `None` if it's beyond the quantity
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.quantity {
+            return None;
+        }
+        let byte = self.data.get(index / 8)?;
+        Some(byte & (1 << (index % 8)) != 0)
+    }
+
+    /// Coils read from the borrowed bytes
+    pub fn iter(&self) -> Coils<'a> {
+        Coils {
+            data: self.data,
+            start: 0,
+            end: self.quantity.min(self.data.len() * 8),
+        }
+    }
+}
+
+/// Iterator over the coils of [`DataCoils`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Coils<'a> {
+    data: &'a [u8],
+    start: usize,
+    end: usize,
+}
+
+impl Coils<'_> {
+    fn bit(&self, index: usize) -> bool {
+        self.data[index / 8] & (1 << (index % 8)) != 0
+    }
+}
+
+impl Iterator for Coils<'_> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.start += 1;
+        Some(self.bit(self.start - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Coils<'_> {
+    fn next_back(&mut self) -> Option<bool> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.bit(self.end))
+    }
+}
+
+impl ExactSizeIterator for Coils<'_> {}
+impl FusedIterator for Coils<'_> {}
+
+impl<'a> IntoIterator for DataCoils<'a> {
+    type Item = bool;
+    type IntoIter = Coils<'a>;
+
+    fn into_iter(self) -> Coils<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &DataCoils<'a> {
+    type Item = bool;
+    type IntoIter = Coils<'a>;
+
+    fn into_iter(self) -> Coils<'a> {
+        self.iter()
+    }
 }
 
 impl<'a, 'b> DataCoils<'a> {
     pub fn copy_coils_to(&self, coils: &'b mut [bool]) -> &'b [bool] {
-        for (i, coil) in coils.iter_mut().enumerate().take(self.quantity) {
-            let byte = self.data[i / 8];
-            let bit_pos = i % 8;
-            *coil = (byte & (1 << (bit_pos))) != 0;
+        for (coil, value) in coils.iter_mut().zip(self.iter()) {
+            *coil = value;
         }
 
         &coils[..self.quantity]
     }
 }
 
+/// One digit per coil, the first one first: `1011`
+impl fmt::Display for DataCoils<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter()
+            .try_for_each(|coil| f.write_str(if coil { "1" } else { "0" }))
+    }
+}
+
 #[cfg(feature = "alloc")]
 extern crate alloc;
 #[cfg(feature = "alloc")]
@@ -61,16 +180,22 @@ use alloc::vec::Vec;
 #[cfg(feature = "alloc")]
 impl From<DataCoils<'_>> for Vec<bool> {
     fn from(data_coils: DataCoils<'_>) -> Self {
-        Vec::from_iter((0..data_coils.quantity).map(|i| {
-            let byte = data_coils.data[i / 8];
-            let bit_pos = i % 8;
-            (byte & (1 << (bit_pos))) != 0
-        }))
+        Vec::from_iter(data_coils)
+    }
+}
+
+/// The coils as an array of bools, not their bytes
+#[cfg(feature = "serde")]
+impl serde::Serialize for DataCoils<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
     }
 }
 
 #[cfg(test)]
 pub mod test {
+    use crate::error::EncodeError;
+
     use super::DataCoils;
 
     #[test]
@@ -140,6 +265,48 @@ pub mod test {
         assert_eq!(coils_buf, [false, true, true, true, true, true, true, true]);
     }
 
+    #[test]
+    fn iterates_data_coils() {
+        let data_coils = DataCoils::new(&[0xcd, 0x01], 10);
+        assert_eq!(data_coils.get(0), Some(true));
+        assert_eq!(data_coils.get(1), Some(false));
+        assert_eq!(data_coils.get(8), Some(true));
+        assert_eq!(data_coils.get(10), None);
+
+        let mut coils = data_coils.iter();
+        assert_eq!(coils.len(), 10);
+        assert_eq!(coils.next_back(), Some(false));
+        assert_eq!(coils.next_back(), Some(true));
+        assert_eq!(coils.len(), 8);
+        assert_eq!(
+            coils.filter(|coil| *coil).count(),
+            0xcd_u8.count_ones() as usize
+        );
+        assert_eq!((&data_coils).into_iter().filter(|coil| *coil).count(), 6);
+    }
+
+    #[test]
+    fn data_coils_from_iter() {
+        let mut buf = [0xff_u8; 3];
+        let data_coils = DataCoils::from_coils_iter((0..10).map(|i| i % 3 == 0), &mut buf);
+        assert_eq!(
+            data_coils,
+            Ok(DataCoils {
+                data: &[0x49, 0x02],
+                quantity: 10
+            })
+        );
+
+        let mut buf = [0_u8; 1];
+        assert_eq!(
+            DataCoils::from_coils_iter([true; 9], &mut buf),
+            Err(EncodeError::InvalidBufferSize {
+                needed: 2,
+                available: 1
+            })
+        );
+    }
+
     #[cfg(feature = "alloc")]
     extern crate alloc;
     #[cfg(feature = "alloc")]
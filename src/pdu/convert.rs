@@ -0,0 +1,278 @@
+//! Conversion of raw register values to engineering units and back
+//!
+//! A [`Pipeline`] applies its steps in order to read values, and undoes them in
+//! reverse order for writes:
+//!
+//! ```
+//! use modbus::pdu::{
+//!     convert::{Pipeline, Step},
+//!     value::{DataType, Value},
+//! };
+//!
+//! // Tenths of a degree Celsius to Fahrenheit
+//! const TEMPERATURE: Pipeline<'_> = Pipeline::new(
+//!     DataType::I16,
+//!     &[Step::Scale(0.18), Step::Offset(32.0), Step::Clamp { min: -40.0, max: 212.0 }],
+//! );
+//! assert_eq!(TEMPERATURE.to_engineering(Value::I16(1000)), Ok(212.0));
+//! assert_eq!(TEMPERATURE.to_raw(68.0), Ok(Value::I16(200)));
+//! assert!(TEMPERATURE.to_raw(68.01).is_err());
+//! ```
+
+use core::fmt;
+
+use super::value::{DataType, Value, round};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Step<'a> {
+    /// Multiplies
+    Scale(f64),
+    /// Adds
+    Offset(f64),
+    /// Limits read values to the range, and refuses writes outside it
+    Clamp { min: f64, max: f64 },
+    /// Replaces values by the second value of the pair starting with them. Values not
+    /// in the table are refused.
+    Lookup(&'a [(f64, f64)]),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConvertError {
+    /// The value is outside a clamp range, or the range of the data type
+    OutOfRange(f64),
+    /// The raw value isn't an integer, and would be written rounded
+    PrecisionLoss { raw: f64, rounded: f64 },
+    /// The value isn't in a lookup table
+    Unmapped(f64),
+    /// The label isn't in the labels
+    UnknownLabel,
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::OutOfRange(value) => write!(f, "{value} is out of range"),
+            ConvertError::PrecisionLoss { raw, rounded } => {
+                write!(f, "{raw} would be rounded to {rounded}")
+            }
+            ConvertError::Unmapped(value) => write!(f, "{value} isn't mapped"),
+            ConvertError::UnknownLabel => write!(f, "unknown label"),
+        }
+    }
+}
+
+impl core::error::Error for ConvertError {}
+
+/// Raw value to write, with how much it was rounded
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rounded {
+    pub raw: Value,
+    /// Exact raw value minus the written one, 0 if it is written exactly
+    pub precision_loss: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Pipeline<'a> {
+    /// Type of the raw values written
+    pub data_type: DataType,
+    pub steps: &'a [Step<'a>],
+    /// Names of engineering values, for enumerations
+    pub labels: &'a [(f64, &'a str)],
+}
+
+/// Whether `raw` and `rounded` only differ by float errors of the steps, such as
+/// `23.4 / 0.1` being `233.99999999999997`
+fn is_exact(raw: f64, rounded: f64) -> bool {
+    (raw - rounded).abs() <= 1e-9 * rounded.abs().max(1.0)
+}
+
+impl<'a> Pipeline<'a> {
+    pub const fn new(data_type: DataType, steps: &'a [Step<'a>]) -> Self {
+        Self {
+            data_type,
+            steps,
+            labels: &[],
+        }
+    }
+
+    pub const fn with_labels(mut self, labels: &'a [(f64, &'a str)]) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Engineering value of a raw value
+    pub fn to_engineering(&self, raw: Value) -> Result<f64, ConvertError> {
+        self.steps
+            .iter()
+            .try_fold(raw.as_f64(), |value, step| match *step {
+                Step::Scale(scale) => Ok(value * scale),
+                Step::Offset(offset) => Ok(value + offset),
+                Step::Clamp { min, max } => Ok(value.clamp(min, max)),
+                Step::Lookup(table) => table
+                    .iter()
+                    .find(|(from, _)| *from == value)
+                    .map(|(_, to)| *to)
+                    .ok_or(ConvertError::Unmapped(value)),
+            })
+    }
+
+    /// Label of the engineering value of a raw value, `Ok(None)` if it has none
+    pub fn to_label(&self, raw: Value) -> Result<Option<&'a str>, ConvertError> {
+        let value = self.to_engineering(raw)?;
+        Ok(self
+            .labels
+            .iter()
+            .find(|(label_value, _)| *label_value == value)
+            .map(|(_, label)| *label))
+    }
+
+    /// Raw value of an engineering value, rounded to the nearest integer for integer
+    /// data types
+    pub fn to_raw_rounded(&self, value: f64) -> Result<Rounded, ConvertError> {
+        let raw = self
+            .steps
+            .iter()
+            .rev()
+            .try_fold(value, |value, step| match *step {
+                Step::Scale(scale) => Ok(value / scale),
+                Step::Offset(offset) => Ok(value - offset),
+                Step::Clamp { min, max } if value >= min && value <= max => Ok(value),
+                Step::Clamp { .. } => Err(ConvertError::OutOfRange(value)),
+                Step::Lookup(table) => table
+                    .iter()
+                    .find(|(_, to)| *to == value)
+                    .map(|(from, _)| *from)
+                    .ok_or(ConvertError::Unmapped(value)),
+            })?;
+
+        let rounded = match self.data_type {
+            DataType::F32 | DataType::F64 => raw,
+            _ => round(raw),
+        };
+        let written =
+            Value::from_f64(self.data_type, rounded).ok_or(ConvertError::OutOfRange(raw))?;
+        Ok(Rounded {
+            raw: written,
+            precision_loss: if is_exact(raw, rounded) {
+                0.0
+            } else {
+                raw - rounded
+            },
+        })
+    }
+
+    /// Raw value of an engineering value, refused if it would be rounded
+    pub fn to_raw(&self, value: f64) -> Result<Value, ConvertError> {
+        let rounded = self.to_raw_rounded(value)?;
+        if rounded.precision_loss != 0.0 {
+            return Err(ConvertError::PrecisionLoss {
+                raw: rounded.raw.as_f64() + rounded.precision_loss,
+                rounded: rounded.raw.as_f64(),
+            });
+        }
+        Ok(rounded.raw)
+    }
+
+    /// Raw value of a label
+    pub fn label_to_raw(&self, label: &str) -> Result<Value, ConvertError> {
+        let (value, _) = self
+            .labels
+            .iter()
+            .find(|(_, name)| *name == label)
+            .ok_or(ConvertError::UnknownLabel)?;
+        self.to_raw(*value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pdu::value::{DataType, Value};
+
+    use super::{ConvertError, Pipeline, Rounded, Step};
+
+    #[test]
+    fn scales_offsets_and_clamps() {
+        let pipeline = Pipeline::new(
+            DataType::U16,
+            &[
+                Step::Scale(0.1),
+                Step::Offset(-10.0),
+                Step::Clamp {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            ],
+        );
+        assert_eq!(pipeline.to_engineering(Value::U16(334)), Ok(23.4));
+        assert_eq!(pipeline.to_engineering(Value::U16(50)), Ok(0.0));
+        assert_eq!(pipeline.to_engineering(Value::U16(2000)), Ok(100.0));
+
+        assert_eq!(pipeline.to_raw(23.4), Ok(Value::U16(334)));
+        assert_eq!(pipeline.to_raw(100.5), Err(ConvertError::OutOfRange(100.5)));
+        assert!(matches!(
+            pipeline.to_raw(f64::NAN),
+            Err(ConvertError::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn refuses_lossy_and_out_of_range_writes() {
+        let pipeline = Pipeline::new(DataType::I16, &[Step::Scale(0.5)]);
+        let Err(ConvertError::PrecisionLoss { raw, rounded }) = pipeline.to_raw(1.3) else {
+            panic!("expected a precision loss");
+        };
+        assert!((raw - 2.6).abs() < 1e-9);
+        assert_eq!(rounded, 3.0);
+
+        let Rounded {
+            raw,
+            precision_loss,
+        } = pipeline.to_raw_rounded(1.3).unwrap();
+        assert_eq!(raw, Value::I16(3));
+        assert!((precision_loss + 0.4).abs() < 1e-9);
+        assert_eq!(
+            pipeline.to_raw_rounded(-1.5),
+            Ok(Rounded {
+                raw: Value::I16(-3),
+                precision_loss: 0.0
+            })
+        );
+
+        assert_eq!(
+            pipeline.to_raw(20000.0),
+            Err(ConvertError::OutOfRange(40000.0))
+        );
+        let pipeline = Pipeline::new(DataType::U16, &[Step::Scale(0.0)]);
+        assert_eq!(
+            pipeline.to_raw(1.0),
+            Err(ConvertError::OutOfRange(f64::INFINITY))
+        );
+
+        let pipeline = Pipeline::new(DataType::F32, &[Step::Scale(0.5)]);
+        assert_eq!(pipeline.to_raw(1.3), Ok(Value::F32(2.6)));
+    }
+
+    #[test]
+    fn maps_lookup_tables_and_labels() {
+        let pipeline = Pipeline::new(DataType::U16, &[Step::Lookup(&[(0.0, 1.0), (4.0, 2.0)])])
+            .with_labels(&[(1.0, "off"), (2.0, "on")]);
+        assert_eq!(pipeline.to_engineering(Value::U16(4)), Ok(2.0));
+        assert_eq!(pipeline.to_label(Value::U16(0)), Ok(Some("off")));
+        assert_eq!(
+            pipeline.to_label(Value::U16(1)),
+            Err(ConvertError::Unmapped(1.0))
+        );
+
+        assert_eq!(pipeline.to_raw(2.0), Ok(Value::U16(4)));
+        assert_eq!(pipeline.to_raw(3.0), Err(ConvertError::Unmapped(3.0)));
+        assert_eq!(pipeline.label_to_raw("off"), Ok(Value::U16(0)));
+        assert_eq!(
+            pipeline.label_to_raw("standby"),
+            Err(ConvertError::UnknownLabel)
+        );
+    }
+}
@@ -1,8 +1,15 @@
-use crate::{error::EncodeError, exception_code::ExceptionCode};
+use core::fmt;
+
+use crate::{
+    error::{DecodeError, EncodeError},
+    exception_code::ExceptionCode,
+};
 
 use super::function_code::FunctionCode;
 
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExceptionResponse {
     function_code: FunctionCode,
     exception_code: ExceptionCode,
@@ -16,17 +23,111 @@ impl ExceptionResponse {
         }
     }
 
+    pub fn function_code(&self) -> &FunctionCode {
+        &self.function_code
+    }
+    pub fn exception_code(&self) -> &ExceptionCode {
+        &self.exception_code
+    }
+
     pub fn pdu_len(&self) -> usize {
         2
     }
 
     pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
         if self.pdu_len() > buf.len() {
-            return Err(EncodeError::InvalidBufferSize);
+            return Err(EncodeError::InvalidBufferSize {
+                needed: self.pdu_len(),
+                available: buf.len(),
+            });
         }
         buf[0] = u8::from(self.function_code) | 0x80;
-        buf[1] = self.exception_code as u8;
+        buf[1] = self.exception_code.into();
 
         Ok(self.pdu_len())
     }
 }
+
+/// `FC03 ReadHoldingRegisters exception=IllegalDataAddress`
+impl fmt::Display for ExceptionResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} exception={}",
+            self.function_code, self.exception_code
+        )
+    }
+}
+
+/// Response a server sends for a request that failed to decode, like the quantity of a
+/// read being out of range. The other errors are given back.
+impl TryFrom<DecodeError> for ExceptionResponse {
+    type Error = DecodeError;
+
+    fn try_from(err: DecodeError) -> Result<Self, Self::Error> {
+        match err {
+            DecodeError::ModbusExceptionError(fn_code, err) => Ok(Self::new(fn_code, err.into())),
+            err => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::{DecodeError, ExceptionError, Field},
+        exception_code::ExceptionCode,
+        pdu::{function_code::FunctionCode, request::Request},
+    };
+
+    use super::ExceptionResponse;
+
+    #[test]
+    fn exception_responses_of_decode_errors() {
+        let err = Request::decode(&[0x03, 0, 0, 0, 0x7e]).unwrap_err();
+        assert_eq!(
+            err,
+            DecodeError::ModbusExceptionError(
+                FunctionCode::ReadHoldingRegisters,
+                ExceptionError::IllegalDataValue(Field::Quantity)
+            )
+        );
+        let res = ExceptionResponse::try_from(err).unwrap();
+        let buf = &mut [0; 2];
+        assert_eq!(res.encode(buf), Ok(2));
+        assert_eq!(buf, &[0x83, 0x03]);
+
+        let res =
+            ExceptionResponse::try_from(Request::decode(&[0x01, 0xff, 0xff, 0, 2]).unwrap_err());
+        assert_eq!(
+            res,
+            Ok(ExceptionResponse::new(
+                FunctionCode::ReadCoils,
+                ExceptionCode::IllegalDataAddress
+            ))
+        );
+        // The PDU of a complete frame is too short for its function code
+        let res = ExceptionResponse::try_from(Request::decode(&[0x06, 0, 1]).unwrap_err());
+        assert_eq!(
+            res,
+            Ok(ExceptionResponse::new(
+                FunctionCode::WriteSingleRegister,
+                ExceptionCode::IllegalDataValue
+            ))
+        );
+        let res = ExceptionResponse::try_from(Request::decode(&[0x86, 0x02]).unwrap_err());
+        assert_eq!(
+            res,
+            Ok(ExceptionResponse::new(
+                FunctionCode::Custom(0x86),
+                ExceptionCode::IllegalFunction
+            ))
+        );
+
+        let err = DecodeError::InvalidCrc {
+            expected: 1,
+            actual: 2,
+        };
+        assert_eq!(ExceptionResponse::try_from(err), Err(err));
+    }
+}
@@ -1,6 +1,9 @@
+use core::fmt;
+
 use super::{request::Request as PduRequest, response::Response as PduResponse};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum FunctionCode {
     ReadCoils,
     ReadDiscreteInput,
@@ -56,6 +59,17 @@ impl From<FunctionCode> for u8 {
     }
 }
 
+/// `FC03 ReadHoldingRegisters`
+impl fmt::Display for FunctionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FC{:02X} ", u8::from(*self))?;
+        match self {
+            FunctionCode::Custom(_) => f.write_str("Custom"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
 impl<'a> From<&PduResponse<'a>> for FunctionCode {
     fn from(value: &PduResponse<'a>) -> Self {
         match value {
@@ -94,6 +108,41 @@ impl<'a> From<&PduRequest<'a>> for FunctionCode {
     }
 }
 
+#[cfg(feature = "serde")]
+const NAMES: [(&str, FunctionCode); 10] = [
+    ("ReadCoils", FunctionCode::ReadCoils),
+    ("ReadDiscreteInput", FunctionCode::ReadDiscreteInput),
+    ("ReadHoldingRegisters", FunctionCode::ReadHoldingRegisters),
+    ("ReadInputRegisters", FunctionCode::ReadInputRegisters),
+    ("WriteSingleCoil", FunctionCode::WriteSingleCoil),
+    ("WriteSingleRegister", FunctionCode::WriteSingleRegister),
+    ("WriteMultipleCoils", FunctionCode::WriteMultipleCoils),
+    ("WriteMultipleRegisters", FunctionCode::WriteMultipleRegisters),
+    ("MaskWriteRegister", FunctionCode::MaskWriteRegister),
+    ("ReadWriteMultipleRegisters", FunctionCode::ReadWriteMultipleRegisters),
+];
+
+/// Name of the code, like `ReadCoils`, or its number for custom codes. Only the number
+/// in formats that aren't human readable.
+#[cfg(feature = "serde")]
+impl serde::Serialize for FunctionCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::serde_names::serialize(*self, &NAMES, serializer)
+    }
+}
+
+/// Name or number of the code, custom codes only having a number
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FunctionCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::serde_names::deserialize(
+            deserializer,
+            &NAMES,
+            "a function code name or a number below 0x80",
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -110,4 +159,41 @@ mod test {
         assert_eq!(FunctionCode::try_from(0x9a), Err(0x9a));
         assert_eq!(FunctionCode::try_from(0xff), Err(0xff));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let json = serde_json::to_string(&[FunctionCode::ReadCoils, FunctionCode::Custom(0x41)]);
+        assert_eq!(json.unwrap(), r#"["ReadCoils",65]"#);
+
+        let codes: [FunctionCode; 3] =
+            serde_json::from_str(r#"["MaskWriteRegister", 65, 3]"#).unwrap();
+        assert_eq!(
+            codes,
+            [
+                FunctionCode::MaskWriteRegister,
+                FunctionCode::Custom(0x41),
+                FunctionCode::ReadHoldingRegisters
+            ]
+        );
+        assert!(serde_json::from_str::<FunctionCode>("128").is_err());
+        assert!(serde_json::from_str::<FunctionCode>(r#""Custom""#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_compact() {
+        use serde_test::{Configure, Token, assert_de_tokens_error, assert_tokens};
+
+        assert_tokens(&FunctionCode::ReadCoils.compact(), &[Token::U8(0x01)]);
+        assert_tokens(&FunctionCode::Custom(0x41).compact(), &[Token::U8(0x41)]);
+        assert_tokens(
+            &FunctionCode::ReadCoils.readable(),
+            &[Token::Str("ReadCoils")],
+        );
+        assert_de_tokens_error::<serde_test::Compact<FunctionCode>>(
+            &[Token::U8(0x81)],
+            "invalid value: integer `129`, expected a function code name or a number below 0x80",
+        );
+    }
 }
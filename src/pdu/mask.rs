@@ -0,0 +1,111 @@
+use super::{Address, request::Request};
+
+/// Register value after a MaskWriteRegister request, as servers compute it
+pub fn mask_write(current: u16, and_mask: u16, or_mask: u16) -> u16 {
+    (current & and_mask) | (or_mask & !and_mask)
+}
+
+/// Bits of a register to set and clear, leaving the others as they are:
+///
+/// ```
+/// use modbus::pdu::{mask::BitMask, request::Request};
+///
+/// let mask = BitMask::new().set(0).clear(4);
+/// assert_eq!(mask.apply(0x00f0), 0x00e1);
+/// assert_eq!(mask.request(10), Request::MaskWriteRegister(10, 0xffee, 0x0001));
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BitMask {
+    set: u16,
+    clear: u16,
+}
+
+impl BitMask {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the bit, 0 being the least significant. Panics if it's above 15.
+    pub fn set(self, bit: u8) -> Self {
+        self.write(bit, true)
+    }
+
+    /// Clears the bit, 0 being the least significant. Panics if it's above 15.
+    pub fn clear(self, bit: u8) -> Self {
+        self.write(bit, false)
+    }
+
+    /// Sets or clears the bit, replacing an earlier change of it
+    pub fn write(self, bit: u8, value: bool) -> Self {
+        assert!(bit < 16, "bit {bit} of a register");
+        let mask = 1 << bit;
+        if value {
+            Self {
+                set: self.set | mask,
+                clear: self.clear & !mask,
+            }
+        } else {
+            Self {
+                set: self.set & !mask,
+                clear: self.clear | mask,
+            }
+        }
+    }
+
+    pub fn set_bits(&self) -> &u16 {
+        &self.set
+    }
+    pub fn clear_bits(&self) -> &u16 {
+        &self.clear
+    }
+
+    /// Whether no bit is changed
+    pub fn is_empty(&self) -> bool {
+        self.set == 0 && self.clear == 0
+    }
+
+    /// Bits kept from the current value
+    pub fn and_mask(&self) -> u16 {
+        !(self.set | self.clear)
+    }
+
+    pub fn or_mask(&self) -> u16 {
+        self.set
+    }
+
+    /// Value of the register after the change
+    pub fn apply(&self, current: u16) -> u16 {
+        mask_write(current, self.and_mask(), self.or_mask())
+    }
+
+    pub fn request(&self, address: Address) -> Request<'static> {
+        Request::MaskWriteRegister(address, self.and_mask(), self.or_mask())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BitMask, mask_write};
+
+    #[test]
+    fn server_formula() {
+        // Example of the specification
+        assert_eq!(mask_write(0x12, 0xf2, 0x25), 0x17);
+        assert_eq!(mask_write(0xabcd, 0xffff, 0x1234), 0xabcd);
+        assert_eq!(mask_write(0xabcd, 0x0000, 0x1234), 0x1234);
+    }
+
+    #[test]
+    fn builds_masks() {
+        let mask = BitMask::new().set(0).set(15).clear(3).clear(0);
+        assert_eq!(*mask.set_bits(), 0x8000);
+        assert_eq!(*mask.clear_bits(), 0x0009);
+        assert_eq!(mask.and_mask(), 0x7ff6);
+        assert_eq!(mask.or_mask(), 0x8000);
+        assert_eq!(mask.apply(0x000f), 0x8006);
+        assert_eq!(mask.apply(0xffff), 0xfff6);
+        assert!(BitMask::new().is_empty());
+        assert_eq!(BitMask::new().apply(0x1234), 0x1234);
+    }
+}
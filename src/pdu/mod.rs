@@ -1,16 +1,88 @@
+pub mod bcd;
+pub mod bitfield;
 pub mod coil;
+pub mod convert;
 pub mod exception_response;
 pub mod function_code;
+pub mod mask;
+#[cfg(feature = "alloc")]
+pub mod owned;
 pub mod request;
 pub mod response;
+pub mod string;
+pub mod value;
 pub mod word;
 
+use core::fmt;
+
 pub use coil::DataCoils;
 pub use word::DataWords;
 
+use request::Request;
+
 pub type Address = u16;
 pub type Quantity = u16;
 
+/// Max size of a PDU, limited by the 256 bytes of a RTU frame
+pub const MAX_PDU_SIZE: usize = 253;
+
+pub const MAX_READ_COILS: Quantity = 0x07d0;
+pub const MAX_READ_REGISTERS: Quantity = 0x7d;
+pub const MAX_WRITE_COILS: Quantity = 0x07b0;
+pub const MAX_WRITE_REGISTERS: Quantity = 0x7b;
+
+/// The four tables of the Modbus data model
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RegisterType {
+    Coil,
+    DiscreteInput,
+    InputRegister,
+    HoldingRegister,
+}
+
+impl RegisterType {
+    /// Whether the items are single bits instead of 16 bit words
+    pub fn is_bit(&self) -> bool {
+        matches!(self, RegisterType::Coil | RegisterType::DiscreteInput)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        matches!(self, RegisterType::Coil | RegisterType::HoldingRegister)
+    }
+
+    /// Max quantity of a single read request
+    pub fn max_read_quantity(&self) -> Quantity {
+        if self.is_bit() {
+            MAX_READ_COILS
+        } else {
+            MAX_READ_REGISTERS
+        }
+    }
+
+    pub fn read_request<'a>(&self, address: Address, quantity: Quantity) -> Request<'a> {
+        match self {
+            RegisterType::Coil => Request::ReadCoils(address, quantity),
+            RegisterType::DiscreteInput => Request::ReadDiscreteInput(address, quantity),
+            RegisterType::InputRegister => Request::ReadInputRegisters(address, quantity),
+            RegisterType::HoldingRegister => Request::ReadHoldingRegisters(address, quantity),
+        }
+    }
+}
+
+/// Writes `bytes` as lowercase hex digits
+pub(crate) fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+}
+
+/// `on` or `off`
+fn coil_name(coil: bool) -> &'static str {
+    match coil {
+        true => "on",
+        false => "off",
+    }
+}
+
 pub fn u16_coil_to_coil(u16_coil: u16) -> Option<bool> {
     match u16_coil {
         0x0000 => Some(false),
@@ -0,0 +1,291 @@
+//! Requests and responses owning their data, to keep them beyond the buffer they
+//! were decoded from. With the `serde` feature they serialize like the borrowed ones.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use crate::error::EncodeError;
+
+use super::{
+    Address, DataCoils, DataWords, MAX_PDU_SIZE, Quantity, function_code::FunctionCode,
+    request::Request, response::Response,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedRequest {
+    ReadCoils(Address, Quantity),
+    ReadDiscreteInput(Address, Quantity),
+    ReadHoldingRegisters(Address, Quantity),
+    ReadInputRegisters(Address, Quantity),
+    WriteSingleCoil(Address, bool),
+    WriteSingleRegister(Address, u16),
+    WriteMultipleCoils(Address, Vec<bool>),
+    WriteMultipleRegisters(Address, Vec<u16>),
+    MaskWriteRegister(Address, u16, u16),
+    ReadWriteMultipleRegisters(Address, Quantity, Address, Vec<u16>),
+    Custom(FunctionCode, Vec<u8>),
+}
+
+impl OwnedRequest {
+    /// Borrowed request, its coils or registers packed into `buf`
+    pub fn as_request<'a>(&'a self, buf: &'a mut [u8]) -> Result<Request<'a>, EncodeError> {
+        Ok(match self {
+            OwnedRequest::ReadCoils(address, quantity) => Request::ReadCoils(*address, *quantity),
+            OwnedRequest::ReadDiscreteInput(address, quantity) => {
+                Request::ReadDiscreteInput(*address, *quantity)
+            }
+            OwnedRequest::ReadHoldingRegisters(address, quantity) => {
+                Request::ReadHoldingRegisters(*address, *quantity)
+            }
+            OwnedRequest::ReadInputRegisters(address, quantity) => {
+                Request::ReadInputRegisters(*address, *quantity)
+            }
+            OwnedRequest::WriteSingleCoil(address, coil) => {
+                Request::WriteSingleCoil(*address, *coil)
+            }
+            OwnedRequest::WriteSingleRegister(address, word) => {
+                Request::WriteSingleRegister(*address, *word)
+            }
+            OwnedRequest::WriteMultipleCoils(address, coils) => Request::WriteMultipleCoils(
+                *address,
+                DataCoils::from_coils_iter(coils.iter().copied(), buf)?,
+            ),
+            OwnedRequest::WriteMultipleRegisters(address, words) => {
+                Request::WriteMultipleRegisters(
+                    *address,
+                    DataWords::from_words_iter(words.iter().copied(), buf)?,
+                )
+            }
+            OwnedRequest::MaskWriteRegister(address, and_mask, or_mask) => {
+                Request::MaskWriteRegister(*address, *and_mask, *or_mask)
+            }
+            OwnedRequest::ReadWriteMultipleRegisters(read_address, quantity, address, words) => {
+                Request::ReadWriteMultipleRegisters(
+                    *read_address,
+                    *quantity,
+                    *address,
+                    DataWords::from_words_iter(words.iter().copied(), buf)?,
+                )
+            }
+            OwnedRequest::Custom(fn_code, data) => Request::Custom(*fn_code, data),
+        })
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut data_buf = [0_u8; MAX_PDU_SIZE];
+        self.as_request(&mut data_buf)?.encode(buf)
+    }
+}
+
+impl From<&Request<'_>> for OwnedRequest {
+    fn from(req: &Request<'_>) -> Self {
+        match req {
+            Request::ReadCoils(address, quantity) => OwnedRequest::ReadCoils(*address, *quantity),
+            Request::ReadDiscreteInput(address, quantity) => {
+                OwnedRequest::ReadDiscreteInput(*address, *quantity)
+            }
+            Request::ReadHoldingRegisters(address, quantity) => {
+                OwnedRequest::ReadHoldingRegisters(*address, *quantity)
+            }
+            Request::ReadInputRegisters(address, quantity) => {
+                OwnedRequest::ReadInputRegisters(*address, *quantity)
+            }
+            Request::WriteSingleCoil(address, coil) => {
+                OwnedRequest::WriteSingleCoil(*address, *coil)
+            }
+            Request::WriteSingleRegister(address, word) => {
+                OwnedRequest::WriteSingleRegister(*address, *word)
+            }
+            Request::WriteMultipleCoils(address, coils) => {
+                OwnedRequest::WriteMultipleCoils(*address, coils.iter().collect())
+            }
+            Request::WriteMultipleRegisters(address, words) => {
+                OwnedRequest::WriteMultipleRegisters(*address, words.iter().collect())
+            }
+            Request::MaskWriteRegister(address, and_mask, or_mask) => {
+                OwnedRequest::MaskWriteRegister(*address, *and_mask, *or_mask)
+            }
+            Request::ReadWriteMultipleRegisters(read_address, quantity, address, words) => {
+                OwnedRequest::ReadWriteMultipleRegisters(
+                    *read_address,
+                    *quantity,
+                    *address,
+                    words.iter().collect(),
+                )
+            }
+            Request::Custom(fn_code, data) => OwnedRequest::Custom(*fn_code, data.to_vec()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedResponse {
+    ReadCoils(Vec<bool>),
+    ReadDiscreteInput(Vec<bool>),
+    ReadHoldingRegisters(Vec<u16>),
+    ReadInputRegisters(Vec<u16>),
+    WriteSingleCoil(Address, bool),
+    WriteSingleRegister(Address, u16),
+    WriteMultipleCoils(Address, Quantity),
+    WriteMultipleRegisters(Address, Quantity),
+    MaskWriteRegister(Address, u16, u16),
+    ReadWriteMultipleRegisters(Vec<u16>),
+    Custom(FunctionCode, Vec<u8>),
+}
+
+impl OwnedResponse {
+    /// Borrowed response, its coils or registers packed into `buf`
+    pub fn as_response<'a>(&'a self, buf: &'a mut [u8]) -> Result<Response<'a>, EncodeError> {
+        Ok(match self {
+            OwnedResponse::ReadCoils(coils) => {
+                Response::ReadCoils(DataCoils::from_coils_iter(coils.iter().copied(), buf)?)
+            }
+            OwnedResponse::ReadDiscreteInput(coils) => {
+                Response::ReadDiscreteInput(DataCoils::from_coils_iter(coils.iter().copied(), buf)?)
+            }
+            OwnedResponse::ReadHoldingRegisters(words) => Response::ReadHoldingRegisters(
+                DataWords::from_words_iter(words.iter().copied(), buf)?,
+            ),
+            OwnedResponse::ReadInputRegisters(words) => Response::ReadInputRegisters(
+                DataWords::from_words_iter(words.iter().copied(), buf)?,
+            ),
+            OwnedResponse::WriteSingleCoil(address, coil) => {
+                Response::WriteSingleCoil(*address, *coil)
+            }
+            OwnedResponse::WriteSingleRegister(address, word) => {
+                Response::WriteSingleRegister(*address, *word)
+            }
+            OwnedResponse::WriteMultipleCoils(address, quantity) => {
+                Response::WriteMultipleCoils(*address, *quantity)
+            }
+            OwnedResponse::WriteMultipleRegisters(address, quantity) => {
+                Response::WriteMultipleRegisters(*address, *quantity)
+            }
+            OwnedResponse::MaskWriteRegister(address, and_mask, or_mask) => {
+                Response::MaskWriteRegister(*address, *and_mask, *or_mask)
+            }
+            OwnedResponse::ReadWriteMultipleRegisters(words) => {
+                Response::ReadWriteMultipleRegisters(DataWords::from_words_iter(
+                    words.iter().copied(),
+                    buf,
+                )?)
+            }
+            OwnedResponse::Custom(fn_code, data) => Response::Custom(*fn_code, data),
+        })
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut data_buf = [0_u8; MAX_PDU_SIZE];
+        self.as_response(&mut data_buf)?.encode(buf)
+    }
+}
+
+impl From<&Response<'_>> for OwnedResponse {
+    fn from(res: &Response<'_>) -> Self {
+        match res {
+            Response::ReadCoils(coils) => OwnedResponse::ReadCoils(coils.iter().collect()),
+            Response::ReadDiscreteInput(coils) => {
+                OwnedResponse::ReadDiscreteInput(coils.iter().collect())
+            }
+            Response::ReadHoldingRegisters(words) => {
+                OwnedResponse::ReadHoldingRegisters(words.iter().collect())
+            }
+            Response::ReadInputRegisters(words) => {
+                OwnedResponse::ReadInputRegisters(words.iter().collect())
+            }
+            Response::WriteSingleCoil(address, coil) => {
+                OwnedResponse::WriteSingleCoil(*address, *coil)
+            }
+            Response::WriteSingleRegister(address, word) => {
+                OwnedResponse::WriteSingleRegister(*address, *word)
+            }
+            Response::WriteMultipleCoils(address, quantity) => {
+                OwnedResponse::WriteMultipleCoils(*address, *quantity)
+            }
+            Response::WriteMultipleRegisters(address, quantity) => {
+                OwnedResponse::WriteMultipleRegisters(*address, *quantity)
+            }
+            Response::MaskWriteRegister(address, and_mask, or_mask) => {
+                OwnedResponse::MaskWriteRegister(*address, *and_mask, *or_mask)
+            }
+            Response::ReadWriteMultipleRegisters(words) => {
+                OwnedResponse::ReadWriteMultipleRegisters(words.iter().collect())
+            }
+            Response::Custom(fn_code, data) => OwnedResponse::Custom(*fn_code, data.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::alloc::vec;
+
+    use crate::pdu::{
+        DataCoils, DataWords, function_code::FunctionCode, request::Request, response::Response,
+    };
+
+    use super::{OwnedRequest, OwnedResponse};
+
+    #[test]
+    fn converts_requests() {
+        let coils = Request::WriteMultipleCoils(3, DataCoils::new(&[0b101], 3));
+        let owned = OwnedRequest::from(&coils);
+        assert_eq!(
+            owned,
+            OwnedRequest::WriteMultipleCoils(3, vec![true, false, true])
+        );
+        let mut buf = [0_u8; 1];
+        assert_eq!(owned.as_request(&mut buf).unwrap(), coils);
+
+        let owned = OwnedRequest::WriteMultipleRegisters(1, vec![0x1234, 5]);
+        let mut buf = [0_u8; 3];
+        assert!(owned.as_request(&mut buf).is_err());
+        let mut buf = [0_u8; 16];
+        assert_eq!(owned.encode(&mut buf), Ok(10));
+        assert_eq!(buf[..10], [0x10, 0, 1, 0, 2, 4, 0x12, 0x34, 0, 5]);
+    }
+
+    #[test]
+    fn converts_responses() {
+        let words = Response::ReadHoldingRegisters(DataWords::new(&[0, 7, 1, 0], 2));
+        let owned = OwnedResponse::from(&words);
+        assert_eq!(owned, OwnedResponse::ReadHoldingRegisters(vec![7, 0x100]));
+        let mut buf = [0_u8; 4];
+        assert_eq!(owned.as_response(&mut buf).unwrap(), words);
+
+        let custom = Response::Custom(FunctionCode::Custom(0x41), &[1, 2]);
+        assert_eq!(
+            OwnedResponse::from(&custom),
+            OwnedResponse::Custom(FunctionCode::Custom(0x41), vec![1, 2])
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let req = Request::WriteMultipleCoils(3, DataCoils::new(&[0b101], 3));
+        let json = serde_json::to_string(&req).unwrap();
+        assert_eq!(json, r#"{"WriteMultipleCoils":[3,[true,false,true]]}"#);
+        let owned: OwnedRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(owned, OwnedRequest::from(&req));
+
+        let res = Response::ReadInputRegisters(DataWords::new(&[0, 7, 1, 0], 2));
+        let json = serde_json::to_string(&res).unwrap();
+        assert_eq!(json, r#"{"ReadInputRegisters":[7,256]}"#);
+        let owned: OwnedResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(owned, OwnedResponse::from(&res));
+
+        let custom = OwnedResponse::Custom(FunctionCode::Custom(0x41), vec![1, 2]);
+        let json = serde_json::to_string(&custom).unwrap();
+        assert_eq!(json, r#"{"Custom":[65,[1,2]]}"#);
+        assert_eq!(
+            serde_json::from_str::<OwnedResponse>(&json).unwrap(),
+            custom
+        );
+    }
+}
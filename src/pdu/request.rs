@@ -1,14 +1,16 @@
-use crate::{
-    error::{DecodeError, EncodeError, ExceptionError},
-    exception_code::ExceptionCode,
-};
+use core::fmt;
+
+use crate::error::{DecodeError, EncodeError, ExceptionError, Field};
 
 use super::{
-    coil_to_u16_coil, function_code::FunctionCode, u16_coil_to_coil, Address, DataCoils, DataWords,
-    Quantity,
+    coil_name, coil_to_u16_coil, function_code::FunctionCode, u16_coil_to_coil, write_hex, Address,
+    DataCoils, DataWords, Quantity, MAX_READ_COILS, MAX_READ_REGISTERS, MAX_WRITE_COILS,
+    MAX_WRITE_REGISTERS,
 };
 
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Request<'a> {
     ReadCoils(Address, Quantity),
     ReadDiscreteInput(Address, Quantity),
@@ -42,7 +44,10 @@ impl<'a> Request<'a> {
 
     pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
         if self.pdu_len() > buf.len() {
-            return Err(EncodeError::InvalidBufferSize);
+            return Err(EncodeError::InvalidBufferSize {
+                needed: self.pdu_len(),
+                available: buf.len(),
+            });
         }
 
         buf[0] = FunctionCode::from(self).into();
@@ -66,13 +71,13 @@ impl<'a> Request<'a> {
             }
             Request::WriteMultipleCoils(address, coils) => {
                 buf[1..3].copy_from_slice(&address.to_be_bytes());
-                buf[3..5].copy_from_slice(&coils.quantity().to_be_bytes());
+                buf[3..5].copy_from_slice(&(coils.quantity() as u16).to_be_bytes());
                 buf[5] = coils.data().len() as u8;
                 buf[6..coils.data().len() + 6].copy_from_slice(coils.data());
             }
             Request::WriteMultipleRegisters(address, words) => {
                 buf[1..3].copy_from_slice(&address.to_be_bytes());
-                buf[3..5].copy_from_slice(&words.quantity().to_be_bytes());
+                buf[3..5].copy_from_slice(&(words.quantity() as u16).to_be_bytes());
                 buf[5] = words.data().len() as u8;
                 buf[6..words.data().len() + 6].copy_from_slice(words.data());
             }
@@ -90,8 +95,9 @@ impl<'a> Request<'a> {
                 buf[1..3].copy_from_slice(&read_address.to_be_bytes());
                 buf[3..5].copy_from_slice(&read_quantity.to_be_bytes());
                 buf[5..7].copy_from_slice(&write_address.to_be_bytes());
-                buf[7..9].copy_from_slice(&write_words.quantity().to_be_bytes());
-                buf[9..write_words.data().len() + 9].copy_from_slice(write_words.data());
+                buf[7..9].copy_from_slice(&(write_words.quantity() as u16).to_be_bytes());
+                buf[9] = write_words.data().len() as u8;
+                buf[10..write_words.data().len() + 10].copy_from_slice(write_words.data());
             }
             Request::Custom(_, data) => {
                 buf[1..1 + data.len()].copy_from_slice(data);
@@ -109,18 +115,12 @@ impl<'a> Request<'a> {
             });
         }
 
+        // A request can't have the function code of an exception response
         let fn_code: FunctionCode = buf[0].try_into().map_err(|c| {
-            if buf.len() > 1 {
-                DecodeError::ModbusExceptionCode(
-                    FunctionCode::try_from(c & 0x7f).unwrap(),
-                    ExceptionCode::try_from(buf[1]),
-                )
-            } else {
-                DecodeError::IncompleteBuffer {
-                    current_size: 1,
-                    min_needed_size: 2,
-                }
-            }
+            DecodeError::ModbusExceptionError(
+                FunctionCode::Custom(c),
+                ExceptionError::IllegalFunction,
+            )
         })?;
 
         let request = match fn_code {
@@ -128,62 +128,54 @@ impl<'a> Request<'a> {
             | FunctionCode::ReadDiscreteInput
             | FunctionCode::ReadHoldingRegisters
             | FunctionCode::ReadInputRegisters => {
-                if 5 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: 5,
-                    });
-                }
+                check_len(fn_code, buf, 5)?;
                 let address = u16::from_be_bytes(buf[1..3].try_into().unwrap());
                 let quantity = u16::from_be_bytes(buf[3..5].try_into().unwrap());
 
-                match fn_code {
+                let request = match fn_code {
                     FunctionCode::ReadCoils => {
-                        if quantity == 0 || quantity > 0x07d0 {
+                        if quantity == 0 || quantity > MAX_READ_COILS {
                             return Err(DecodeError::ModbusExceptionError(
                                 fn_code,
-                                ExceptionError::IllegalDataValue,
+                                ExceptionError::IllegalDataValue(Field::Quantity),
                             ));
                         }
                         Request::ReadCoils(address, quantity)
                     }
                     FunctionCode::ReadDiscreteInput => {
-                        if quantity == 0 || quantity > 0x07d0 {
+                        if quantity == 0 || quantity > MAX_READ_COILS {
                             return Err(DecodeError::ModbusExceptionError(
                                 fn_code,
-                                ExceptionError::IllegalDataValue,
+                                ExceptionError::IllegalDataValue(Field::Quantity),
                             ));
                         }
                         Request::ReadDiscreteInput(address, quantity)
                     }
                     FunctionCode::ReadHoldingRegisters => {
-                        if quantity == 0 || quantity > 0x7d {
+                        if quantity == 0 || quantity > MAX_READ_REGISTERS {
                             return Err(DecodeError::ModbusExceptionError(
                                 fn_code,
-                                ExceptionError::IllegalDataValue,
+                                ExceptionError::IllegalDataValue(Field::Quantity),
                             ));
                         }
                         Request::ReadHoldingRegisters(address, quantity)
                     }
                     FunctionCode::ReadInputRegisters => {
-                        if quantity == 0 || quantity > 0x7d {
+                        if quantity == 0 || quantity > MAX_READ_REGISTERS {
                             return Err(DecodeError::ModbusExceptionError(
                                 fn_code,
-                                ExceptionError::IllegalDataValue,
+                                ExceptionError::IllegalDataValue(Field::Quantity),
                             ));
                         }
                         Request::ReadInputRegisters(address, quantity)
                     }
                     _ => unreachable!(),
-                }
+                };
+                check_range(fn_code, address, quantity)?;
+                request
             }
             FunctionCode::WriteSingleCoil | FunctionCode::WriteSingleRegister => {
-                if 5 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: 5,
-                    });
-                }
+                check_len(fn_code, buf, 5)?;
                 let address = u16::from_be_bytes(buf[1..3].try_into().unwrap());
                 let value = u16::from_be_bytes(buf[3..5].try_into().unwrap());
 
@@ -192,7 +184,7 @@ impl<'a> Request<'a> {
                         let Some(coil_bool) = u16_coil_to_coil(value) else {
                             return Err(DecodeError::ModbusExceptionError(
                                 fn_code,
-                                ExceptionError::IllegalDataValue,
+                                ExceptionError::IllegalDataValue(Field::Value),
                             ));
                         };
                         Request::WriteSingleCoil(address, coil_bool)
@@ -204,80 +196,54 @@ impl<'a> Request<'a> {
                 }
             }
             FunctionCode::WriteMultipleCoils => {
-                if 6 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: 6,
-                    });
-                }
+                check_len(fn_code, buf, 6)?;
                 let address = u16::from_be_bytes(buf[1..3].try_into().unwrap());
                 let quantity = u16::from_be_bytes(buf[3..5].try_into().unwrap());
-                if quantity == 0 || quantity > 0x07b0 {
+                if quantity == 0 || quantity > MAX_WRITE_COILS {
                     return Err(DecodeError::ModbusExceptionError(
                         fn_code,
-                        ExceptionError::IllegalDataValue,
+                        ExceptionError::IllegalDataValue(Field::Quantity),
                     ));
                 }
                 let byte_count = buf[5] as usize;
-                if byte_count + 6 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: byte_count + 6,
-                    });
-                }
+                check_byte_count(fn_code, byte_count, (quantity as usize).div_ceil(8))?;
+                check_len(fn_code, buf, byte_count + 6)?;
+                check_range(fn_code, address, quantity)?;
                 let data = &buf[6..byte_count + 6];
                 Request::WriteMultipleCoils(address, DataCoils::new(data, quantity as usize))
             }
             FunctionCode::WriteMultipleRegisters => {
-                if 6 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: 6,
-                    });
-                }
+                check_len(fn_code, buf, 6)?;
                 let address = u16::from_be_bytes(buf[1..3].try_into().unwrap());
                 let quantity = u16::from_be_bytes(buf[3..5].try_into().unwrap());
-                if quantity == 0 || quantity > 0x7b {
+                if quantity == 0 || quantity > MAX_WRITE_REGISTERS {
                     return Err(DecodeError::ModbusExceptionError(
                         fn_code,
-                        ExceptionError::IllegalDataValue,
+                        ExceptionError::IllegalDataValue(Field::Quantity),
                     ));
                 }
                 let byte_count = buf[5] as usize;
-                if byte_count + 6 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: byte_count + 6,
-                    });
-                }
+                check_byte_count(fn_code, byte_count, quantity as usize * 2)?;
+                check_len(fn_code, buf, byte_count + 6)?;
+                check_range(fn_code, address, quantity)?;
                 let data = &buf[6..byte_count + 6];
                 Request::WriteMultipleRegisters(address, DataWords::new(data, quantity as usize))
             }
             FunctionCode::MaskWriteRegister => {
-                if 7 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: 7,
-                    });
-                }
+                check_len(fn_code, buf, 7)?;
                 let reference_address = u16::from_be_bytes(buf[1..3].try_into().unwrap());
                 let and_mask = u16::from_be_bytes(buf[3..5].try_into().unwrap());
                 let or_mask = u16::from_be_bytes(buf[5..7].try_into().unwrap());
                 Request::MaskWriteRegister(reference_address, and_mask, or_mask)
             }
             FunctionCode::ReadWriteMultipleRegisters => {
-                if 10 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: 10,
-                    });
-                }
+                check_len(fn_code, buf, 10)?;
                 let read_address = u16::from_be_bytes(buf[1..3].try_into().unwrap());
                 let read_quantity = u16::from_be_bytes(buf[3..5].try_into().unwrap());
-                if read_quantity == 0 || read_quantity > 0x7d {
+                if read_quantity == 0 || read_quantity > MAX_READ_REGISTERS {
                     return Err(DecodeError::ModbusExceptionError(
                         fn_code,
-                        ExceptionError::IllegalDataValue,
+                        ExceptionError::IllegalDataValue(Field::ReadQuantity),
                     ));
                 }
                 let write_address = u16::from_be_bytes(buf[5..7].try_into().unwrap());
@@ -285,16 +251,14 @@ impl<'a> Request<'a> {
                 if write_quantity == 0 || write_quantity > 0x7d {
                     return Err(DecodeError::ModbusExceptionError(
                         fn_code,
-                        ExceptionError::IllegalDataValue,
+                        ExceptionError::IllegalDataValue(Field::WriteQuantity),
                     ));
                 }
                 let write_byte_count = buf[9] as usize;
-                if write_byte_count + 10 > buf.len() {
-                    return Err(DecodeError::IncompleteBuffer {
-                        current_size: buf.len(),
-                        min_needed_size: write_byte_count + 10,
-                    });
-                }
+                check_byte_count(fn_code, write_byte_count, write_quantity as usize * 2)?;
+                check_len(fn_code, buf, write_byte_count + 10)?;
+                check_range(fn_code, read_address, read_quantity)?;
+                check_range(fn_code, write_address, write_quantity)?;
                 let data = &buf[10..write_byte_count + 10];
                 Request::ReadWriteMultipleRegisters(
                     read_address,
@@ -310,6 +274,94 @@ impl<'a> Request<'a> {
     }
 }
 
+/// Checks the PDU has the `len` bytes its function code requires. The frame it came in
+/// is complete, so a shorter PDU is answered with an exception.
+fn check_len(fn_code: FunctionCode, buf: &[u8], len: usize) -> Result<(), DecodeError> {
+    if len > buf.len() {
+        return Err(DecodeError::ModbusExceptionError(
+            fn_code,
+            ExceptionError::IllegalDataValue(Field::Length),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the `quantity` items starting at `address` fit in the 16 bit address space
+fn check_range(
+    fn_code: FunctionCode,
+    address: Address,
+    quantity: Quantity,
+) -> Result<(), DecodeError> {
+    if address as u32 + quantity as u32 > 0x10000 {
+        return Err(DecodeError::ModbusExceptionError(
+            fn_code,
+            ExceptionError::IllegalDataAddress(address),
+        ));
+    }
+    Ok(())
+}
+
+fn check_byte_count(
+    fn_code: FunctionCode,
+    byte_count: usize,
+    expected: usize,
+) -> Result<(), DecodeError> {
+    if byte_count != expected {
+        return Err(DecodeError::ModbusExceptionError(
+            fn_code,
+            ExceptionError::IllegalDataValue(Field::ByteCount),
+        ));
+    }
+    Ok(())
+}
+
+/// `FC03 ReadHoldingRegisters addr=100 qty=10`
+impl fmt::Display for Request<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", FunctionCode::from(self))?;
+        match self {
+            Request::ReadCoils(address, quantity)
+            | Request::ReadDiscreteInput(address, quantity)
+            | Request::ReadHoldingRegisters(address, quantity)
+            | Request::ReadInputRegisters(address, quantity) => {
+                write!(f, " addr={address} qty={quantity}")
+            }
+            Request::WriteSingleCoil(address, coil) => {
+                write!(f, " addr={address} value={}", coil_name(*coil))
+            }
+            Request::WriteSingleRegister(address, word) => {
+                write!(f, " addr={address} value={word}")
+            }
+            Request::WriteMultipleCoils(address, coils) => {
+                write!(f, " addr={address} qty={} values={coils}", coils.quantity())
+            }
+            Request::WriteMultipleRegisters(address, words) => {
+                write!(f, " addr={address} qty={} values={words}", words.quantity())
+            }
+            Request::MaskWriteRegister(address, and_mask, or_mask) => {
+                write!(f, " addr={address} and={and_mask:#06x} or={or_mask:#06x}")
+            }
+            Request::ReadWriteMultipleRegisters(
+                read_address,
+                read_quantity,
+                write_address,
+                words,
+            ) => {
+                write!(f, " read_addr={read_address} read_qty={read_quantity}")?;
+                write!(
+                    f,
+                    " write_addr={write_address} write_qty={} values={words}",
+                    words.quantity()
+                )
+            }
+            Request::Custom(_, data) => {
+                f.write_str(" data=")?;
+                write_hex(f, data)
+            }
+        }
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for Request<'a> {
     type Error = DecodeError;
 
@@ -321,9 +373,8 @@ impl<'a> TryFrom<&'a [u8]> for Request<'a> {
 #[cfg(test)]
 mod test {
     use crate::{
-        error::ExceptionError,
-        exception_code::ExceptionCode,
-        pdu::{function_code::FunctionCode, DataCoils},
+        error::{ExceptionError, Field},
+        pdu::{function_code::FunctionCode, DataCoils, DataWords},
     };
 
     use super::{DecodeError, Request};
@@ -342,17 +393,9 @@ mod test {
         let buf: &[u8] = &[0x81, 0x01];
         assert_eq!(
             Request::try_from(buf),
-            Err(DecodeError::ModbusExceptionCode(
-                FunctionCode::ReadCoils,
-                Ok(ExceptionCode::IllegalFunction)
-            ))
-        );
-        let buf: &[u8] = &[0x90, 0x02];
-        assert_eq!(
-            Request::try_from(buf),
-            Err(DecodeError::ModbusExceptionCode(
-                FunctionCode::WriteMultipleRegisters,
-                Ok(ExceptionCode::IllegalDataAddress)
+            Err(DecodeError::ModbusExceptionError(
+                FunctionCode::Custom(0x81),
+                ExceptionError::IllegalFunction
             ))
         );
 
@@ -363,10 +406,18 @@ mod test {
             Request::try_from(buf),
             Err(DecodeError::ModbusExceptionError(
                 FunctionCode::ReadCoils,
-                ExceptionError::IllegalDataValue,
+                ExceptionError::IllegalDataValue(Field::Quantity),
             ))
         );
 
+        let buf: &[u8] = &[0x0f, 0x00, 0x13, 0x00, 0x0a, 0x02, 0xcd];
+        assert_eq!(
+            Request::try_from(buf),
+            Err(DecodeError::ModbusExceptionError(
+                FunctionCode::WriteMultipleCoils,
+                ExceptionError::IllegalDataValue(Field::Length),
+            ))
+        );
         let buf: &[u8] = &[0x0f, 0x00, 0x13, 0x00, 0x0a, 0x02, 0xcd, 0x01];
         assert_eq!(
             Request::try_from(buf),
@@ -375,6 +426,25 @@ mod test {
                 DataCoils::new(&[0xcd, 0x01], 0x0a)
             ))
         );
+        let buf: &[u8] = &[0x0f, 0x00, 0x13, 0x00, 0x0a, 0x01, 0xcd];
+        assert_eq!(
+            Request::try_from(buf),
+            Err(DecodeError::ModbusExceptionError(
+                FunctionCode::WriteMultipleCoils,
+                ExceptionError::IllegalDataValue(Field::ByteCount),
+            ))
+        );
+
+        let buf: &[u8] = &[0x10, 0xff, 0xff, 0x00, 0x02, 0x04, 0, 1, 0, 2];
+        assert_eq!(
+            Request::try_from(buf),
+            Err(DecodeError::ModbusExceptionError(
+                FunctionCode::WriteMultipleRegisters,
+                ExceptionError::IllegalDataAddress(0xffff),
+            ))
+        );
+        let buf: &[u8] = &[0x04, 0xff, 0x83, 0x00, 0x7d];
+        assert_eq!(Request::try_from(buf), Ok(Request::ReadInputRegisters(0xff83, 0x7d)));
     }
 
     #[test]
@@ -384,5 +454,65 @@ mod test {
         let pdu_len = res.encode(buf);
         assert_eq!(pdu_len, Ok(5));
         assert_eq!(buf, &[0x01, 0x03, 0xe8, 0x01, 0x23]);
+
+        let req = Request::WriteMultipleCoils(0x13, DataCoils::new(&[0xcd, 0x01], 0x0a));
+        let buf: &mut [u8] = &mut [0; 8];
+        assert_eq!(req.encode(buf), Ok(8));
+        assert_eq!(buf, &[0x0f, 0x00, 0x13, 0x00, 0x0a, 0x02, 0xcd, 0x01]);
+
+        let req = Request::WriteMultipleRegisters(1, DataWords::new(&[0, 0x0a, 0x01, 0x02], 2));
+        let buf: &mut [u8] = &mut [0; 10];
+        assert_eq!(req.encode(buf), Ok(10));
+        assert_eq!(buf, &[0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0a, 0x01, 0x02]);
+
+        let req = Request::ReadWriteMultipleRegisters(3, 6, 0x0e, DataWords::new(&[0, 0xff], 1));
+        let buf: &mut [u8] = &mut [0; 12];
+        assert_eq!(req.encode(buf), Ok(12));
+        assert_eq!(
+            buf,
+            &[0x17, 0x00, 0x03, 0x00, 0x06, 0x00, 0x0e, 0x00, 0x01, 0x02, 0x00, 0xff]
+        );
+        assert_eq!(Request::try_from(&buf[..]), Ok(req));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn display_request() {
+        extern crate alloc;
+        use alloc::format;
+
+        assert_eq!(
+            format!("{}", Request::ReadHoldingRegisters(100, 10)),
+            "FC03 ReadHoldingRegisters addr=100 qty=10"
+        );
+        assert_eq!(
+            format!("{}", Request::WriteSingleCoil(5, true)),
+            "FC05 WriteSingleCoil addr=5 value=on"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                Request::WriteMultipleCoils(1, DataCoils::new(&[0b101], 3))
+            ),
+            "FC0F WriteMultipleCoils addr=1 qty=3 values=101"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                Request::ReadWriteMultipleRegisters(0, 2, 10, DataWords::new(&[0, 7, 1, 0], 2))
+            ),
+            "FC17 ReadWriteMultipleRegisters read_addr=0 read_qty=2 write_addr=10 write_qty=2 values=[7, 256]"
+        );
+        assert_eq!(
+            format!("{}", Request::MaskWriteRegister(4, 0xf2, 0x25)),
+            "FC16 MaskWriteRegister addr=4 and=0x00f2 or=0x0025"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                Request::Custom(FunctionCode::Custom(0x41), &[1, 0xab])
+            ),
+            "FC41 Custom data=01ab"
+        );
     }
 }
@@ -1,13 +1,18 @@
+use core::fmt;
+
 use crate::{
-    error::{DecodeError, EncodeError, ExceptionError},
+    error::{DecodeError, EncodeError, ExceptionError, Field},
     exception_code::ExceptionCode,
 };
 
 use super::{
-    coil_to_u16_coil, function_code::FunctionCode, Address, DataCoils, DataWords, Quantity,
+    coil_name, coil_to_u16_coil, function_code::FunctionCode, write_hex, Address, DataCoils,
+    DataWords, Quantity,
 };
 
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Response<'a> {
     ReadCoils(DataCoils<'a>),
     ReadDiscreteInput(DataCoils<'a>),
@@ -42,7 +47,10 @@ impl<'a> Response<'a> {
 
     pub fn encode(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
         if self.pdu_len() > buf.len() {
-            return Err(EncodeError::InvalidBufferSize);
+            return Err(EncodeError::InvalidBufferSize {
+                needed: self.pdu_len(),
+                available: buf.len(),
+            });
         }
 
         buf[0] = FunctionCode::from(self).into();
@@ -93,7 +101,7 @@ impl<'a> Response<'a> {
             if buf.len() > 1 {
                 DecodeError::ModbusExceptionCode(
                     FunctionCode::try_from(c & 0x7f).unwrap(),
-                    ExceptionCode::try_from(buf[1]),
+                    ExceptionCode::from(buf[1]),
                 )
             } else {
                 DecodeError::IncompleteBuffer {
@@ -178,7 +186,7 @@ impl<'a> Response<'a> {
                             _ => {
                                 return Err(DecodeError::ModbusExceptionError(
                                     fn_code,
-                                    ExceptionError::IllegalDataValue,
+                                    ExceptionError::IllegalDataValue(Field::Value),
                                 ))
                             }
                         };
@@ -193,7 +201,7 @@ impl<'a> Response<'a> {
                         } else {
                             return Err(DecodeError::ModbusExceptionError(
                                 fn_code,
-                                ExceptionError::IllegalDataValue,
+                                ExceptionError::IllegalDataValue(Field::Quantity),
                             ));
                         }
                     }
@@ -203,7 +211,7 @@ impl<'a> Response<'a> {
                         } else {
                             return Err(DecodeError::ModbusExceptionError(
                                 fn_code,
-                                ExceptionError::IllegalDataValue,
+                                ExceptionError::IllegalDataValue(Field::Quantity),
                             ));
                         }
                     }
@@ -229,6 +237,40 @@ impl<'a> Response<'a> {
     }
 }
 
+/// `FC03 ReadHoldingRegisters qty=2 values=[1, 2]`
+impl fmt::Display for Response<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", FunctionCode::from(self))?;
+        match self {
+            Response::ReadCoils(coils) | Response::ReadDiscreteInput(coils) => {
+                write!(f, " qty={} values={coils}", coils.quantity())
+            }
+            Response::ReadHoldingRegisters(words)
+            | Response::ReadInputRegisters(words)
+            | Response::ReadWriteMultipleRegisters(words) => {
+                write!(f, " qty={} values={words}", words.quantity())
+            }
+            Response::WriteSingleCoil(address, coil) => {
+                write!(f, " addr={address} value={}", coil_name(*coil))
+            }
+            Response::WriteSingleRegister(address, word) => {
+                write!(f, " addr={address} value={word}")
+            }
+            Response::WriteMultipleCoils(address, quantity)
+            | Response::WriteMultipleRegisters(address, quantity) => {
+                write!(f, " addr={address} qty={quantity}")
+            }
+            Response::MaskWriteRegister(address, and_mask, or_mask) => {
+                write!(f, " addr={address} and={and_mask:#06x} or={or_mask:#06x}")
+            }
+            Response::Custom(_, data) => {
+                f.write_str(" data=")?;
+                write_hex(f, data)
+            }
+        }
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for Response<'a> {
     type Error = DecodeError;
 
@@ -262,7 +304,7 @@ mod test {
             Response::try_from(buf),
             Err(DecodeError::ModbusExceptionCode(
                 FunctionCode::ReadCoils,
-                Ok(ExceptionCode::IllegalFunction)
+                ExceptionCode::IllegalFunction
             ))
         );
         let buf: &[u8] = &[0x90, 0x02];
@@ -270,7 +312,23 @@ mod test {
             Response::try_from(buf),
             Err(DecodeError::ModbusExceptionCode(
                 FunctionCode::WriteMultipleRegisters,
-                Ok(ExceptionCode::IllegalDataAddress)
+                ExceptionCode::IllegalDataAddress
+            ))
+        );
+        let buf: &[u8] = &[0x83, 0x07];
+        assert_eq!(
+            Response::try_from(buf),
+            Err(DecodeError::ModbusExceptionCode(
+                FunctionCode::ReadHoldingRegisters,
+                ExceptionCode::NegativeAcknowledge
+            ))
+        );
+        let buf: &[u8] = &[0x83, 0x42];
+        assert_eq!(
+            Response::try_from(buf),
+            Err(DecodeError::ModbusExceptionCode(
+                FunctionCode::ReadHoldingRegisters,
+                ExceptionCode::Other(0x42)
             ))
         );
 
@@ -386,4 +444,27 @@ mod test {
         assert_eq!(pdu_len, Ok(4));
         assert_eq!(buf, &[0x01, 0x02, 0xff, 0x7f]);
     }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn display_response() {
+        extern crate alloc;
+        use alloc::format;
+
+        assert_eq!(
+            format!(
+                "{}",
+                Response::ReadInputRegisters(DataWords::new(&[0, 1, 0xff, 0xff], 2))
+            ),
+            "FC04 ReadInputRegisters qty=2 values=[1, 65535]"
+        );
+        assert_eq!(
+            format!("{}", Response::ReadCoils(DataCoils::new(&[0b11], 8))),
+            "FC01 ReadCoils qty=8 values=11000000"
+        );
+        assert_eq!(
+            format!("{}", Response::WriteMultipleRegisters(3, 2)),
+            "FC10 WriteMultipleRegisters addr=3 qty=2"
+        );
+    }
 }
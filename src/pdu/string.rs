@@ -0,0 +1,140 @@
+use crate::error::EncodeError;
+
+use super::DataWords;
+
+/// Order of the two characters in a register
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ByteOrder {
+    /// First character in the high byte
+    #[default]
+    HighFirst,
+    LowFirst,
+}
+
+/// Filler after the end of strings shorter than their registers
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Padding {
+    #[default]
+    Null,
+    Space,
+}
+
+impl Padding {
+    fn byte(&self) -> u8 {
+        match self {
+            Padding::Null => 0,
+            Padding::Space => b' ',
+        }
+    }
+}
+
+impl DataWords<'_> {
+    /// String of `len` registers from the register `index`, copied to `buf` and cut at
+    /// the first null with trailing spaces trimmed. `None` if it doesn't fit the words
+    /// or `buf`, or isn't UTF-8.
+    pub fn string<'b>(
+        &self,
+        index: usize,
+        len: usize,
+        order: ByteOrder,
+        buf: &'b mut [u8],
+    ) -> Option<&'b str> {
+        if index + len > self.quantity() || len * 2 > buf.len() {
+            return None;
+        }
+        let data = &self.data()[index * 2..(index + len) * 2];
+        for (chars, word) in buf.chunks_exact_mut(2).zip(data.chunks_exact(2)) {
+            match order {
+                ByteOrder::HighFirst => chars.copy_from_slice(word),
+                ByteOrder::LowFirst => {
+                    chars[0] = word[1];
+                    chars[1] = word[0];
+                }
+            }
+        }
+
+        let bytes = &buf[..len * 2];
+        let end = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(bytes.len());
+        let s = core::str::from_utf8(&bytes[..end]).ok()?;
+        Some(s.trim_end_matches(' '))
+    }
+}
+
+/// Writes `s` to all of `words`, two bytes per register, padded after its end. Fails
+/// if it's longer than the words.
+pub fn encode_string(
+    s: &str,
+    order: ByteOrder,
+    padding: Padding,
+    words: &mut [u16],
+) -> Result<(), EncodeError> {
+    let bytes = s.as_bytes();
+    if bytes.len() > words.len() * 2 {
+        return Err(EncodeError::InvalidBufferSize {
+            needed: bytes.len(),
+            available: words.len() * 2,
+        });
+    }
+    let byte = |i: usize| bytes.get(i).copied().unwrap_or(padding.byte());
+    for (i, word) in words.iter_mut().enumerate() {
+        let (first, second) = (byte(i * 2), byte(i * 2 + 1));
+        *word = match order {
+            ByteOrder::HighFirst => u16::from_be_bytes([first, second]),
+            ByteOrder::LowFirst => u16::from_be_bytes([second, first]),
+        };
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{error::EncodeError, pdu::DataWords};
+
+    use super::{ByteOrder, Padding, encode_string};
+
+    #[test]
+    fn decodes_strings() {
+        let data = b"\x00\x01ABCD E \x00\x00\x00";
+        let words = DataWords::new(data, 6);
+        let mut buf = [0_u8; 16];
+        assert_eq!(
+            words.string(1, 5, ByteOrder::HighFirst, &mut buf),
+            Some("ABCD E")
+        );
+        assert_eq!(
+            words.string(1, 2, ByteOrder::LowFirst, &mut buf),
+            Some("BADC")
+        );
+        assert_eq!(words.string(1, 6, ByteOrder::HighFirst, &mut buf), None);
+        assert_eq!(words.string(1, 5, ByteOrder::HighFirst, &mut [0; 9]), None);
+    }
+
+    #[test]
+    fn encodes_strings() {
+        let mut words = [0xffff; 3];
+        encode_string("ABC", ByteOrder::HighFirst, Padding::Space, &mut words).unwrap();
+        assert_eq!(words, [0x4142, 0x4320, 0x2020]);
+        encode_string("ABC", ByteOrder::LowFirst, Padding::Null, &mut words).unwrap();
+        assert_eq!(words, [0x4241, 0x0043, 0x0000]);
+        assert_eq!(
+            encode_string("ABCDEFG", ByteOrder::HighFirst, Padding::Null, &mut words),
+            Err(EncodeError::InvalidBufferSize {
+                needed: 7,
+                available: 6
+            })
+        );
+
+        let mut data = [0_u8; 6];
+        let data_words = DataWords::from_words(&words, &mut data);
+        let mut buf = [0_u8; 6];
+        assert_eq!(
+            data_words.string(0, 3, ByteOrder::LowFirst, &mut buf),
+            Some("ABC")
+        );
+    }
+}
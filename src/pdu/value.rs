@@ -0,0 +1,476 @@
+use core::str::FromStr;
+
+use crate::error::EncodeError;
+
+use super::{Address, DataCoils, DataWords, MAX_WRITE_REGISTERS, RegisterType, request::Request};
+
+/// Order of the bytes of values spanning multiple registers, named after the
+/// big endian bytes `abcd` of a 32 bit value
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WordOrder {
+    /// Big endian
+    #[default]
+    Abcd,
+    /// Big endian bytes with the least significant word first
+    Cdab,
+    /// Most significant word first with its bytes swapped
+    Badc,
+    /// Little endian
+    Dcba,
+}
+
+impl WordOrder {
+    fn swaps_words(&self) -> bool {
+        matches!(self, WordOrder::Cdab | WordOrder::Dcba)
+    }
+    fn swaps_bytes(&self) -> bool {
+        matches!(self, WordOrder::Badc | WordOrder::Dcba)
+    }
+
+    /// Words of big endian `bytes` in this order
+    fn bytes_to_words(&self, bytes: &[u8], words: &mut [u16]) {
+        let len = bytes.len() / 2;
+        for (i, word) in words.iter_mut().enumerate().take(len) {
+            let j = if self.swaps_words() { len - 1 - i } else { i };
+            let [high, low] = [bytes[j * 2], bytes[j * 2 + 1]];
+            *word = if self.swaps_bytes() {
+                u16::from_be_bytes([low, high])
+            } else {
+                u16::from_be_bytes([high, low])
+            };
+        }
+    }
+
+    /// Big endian bytes from words in this order
+    fn words_to_bytes(&self, words: impl Fn(usize) -> u16, bytes: &mut [u8]) {
+        let len = bytes.len() / 2;
+        for i in 0..len {
+            let j = if self.swaps_words() { len - 1 - i } else { i };
+            let [high, low] = words(i).to_be_bytes();
+            let (high, low) = if self.swaps_bytes() {
+                (low, high)
+            } else {
+                (high, low)
+            };
+            bytes[j * 2] = high;
+            bytes[j * 2 + 1] = low;
+        }
+    }
+}
+
+impl FromStr for WordOrder {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abcd" | "ABCD" => Ok(WordOrder::Abcd),
+            "cdab" | "CDAB" => Ok(WordOrder::Cdab),
+            "badc" | "BADC" => Ok(WordOrder::Badc),
+            "dcba" | "DCBA" => Ok(WordOrder::Dcba),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataType {
+    /// A coil or discrete input
+    Bool,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+}
+
+impl DataType {
+    /// Number of registers, or bits for [`DataType::Bool`]
+    pub fn quantity(&self) -> usize {
+        match self {
+            DataType::Bool | DataType::U16 | DataType::I16 => 1,
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+            DataType::U64 | DataType::I64 | DataType::F64 => 4,
+        }
+    }
+
+    /// Whether values of this type can be stored in the register type
+    pub fn fits(&self, register_type: RegisterType) -> bool {
+        (*self == DataType::Bool) == register_type.is_bit()
+    }
+}
+
+impl FromStr for DataType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bool" => Ok(DataType::Bool),
+            "u16" => Ok(DataType::U16),
+            "i16" => Ok(DataType::I16),
+            "u32" => Ok(DataType::U32),
+            "i32" => Ok(DataType::I32),
+            "f32" => Ok(DataType::F32),
+            "u64" => Ok(DataType::U64),
+            "i64" => Ok(DataType::I64),
+            "f64" => Ok(DataType::F64),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for RegisterType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "coil" => Ok(RegisterType::Coil),
+            "discrete_input" => Ok(RegisterType::DiscreteInput),
+            "input_register" => Ok(RegisterType::InputRegister),
+            "holding_register" => Ok(RegisterType::HoldingRegister),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Bool(bool),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+}
+
+/// Rounds half away from zero, as `f64::round` needs std
+pub(super) fn round(value: f64) -> f64 {
+    // Floats this large are integers already, or NaN or infinite
+    if value.is_nan() || value.abs() >= 4_503_599_627_370_496.0 {
+        return value;
+    }
+    let truncated = value as i64 as f64;
+    match value - truncated {
+        fraction if fraction >= 0.5 => truncated + 1.0,
+        fraction if fraction <= -0.5 => truncated - 1.0,
+        _ => truncated,
+    }
+}
+
+impl Value {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Value::Bool(_) => DataType::Bool,
+            Value::U16(_) => DataType::U16,
+            Value::I16(_) => DataType::I16,
+            Value::U32(_) => DataType::U32,
+            Value::I32(_) => DataType::I32,
+            Value::F32(_) => DataType::F32,
+            Value::U64(_) => DataType::U64,
+            Value::I64(_) => DataType::I64,
+            Value::F64(_) => DataType::F64,
+        }
+    }
+
+    /// Value as a float, `true` being 1
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Value::Bool(value) => value as u8 as f64,
+            Value::U16(value) => value as f64,
+            Value::I16(value) => value as f64,
+            Value::U32(value) => value as f64,
+            Value::I32(value) => value as f64,
+            Value::F32(value) => value as f64,
+            Value::U64(value) => value as f64,
+            Value::I64(value) => value as f64,
+            Value::F64(value) => value,
+        }
+    }
+
+    /// Value of the type from a float, `None` if it's out of the range of the type.
+    /// Integers are rounded to the nearest.
+    pub fn from_f64(data_type: DataType, value: f64) -> Option<Value> {
+        fn int(value: f64, min: f64, max: f64) -> Option<f64> {
+            let value = round(value);
+            (value >= min && value <= max).then_some(value)
+        }
+
+        Some(match data_type {
+            DataType::Bool if value == 0.0 => Value::Bool(false),
+            DataType::Bool if value == 1.0 => Value::Bool(true),
+            DataType::Bool => return None,
+            DataType::U16 => Value::U16(int(value, 0.0, u16::MAX as f64)? as u16),
+            DataType::I16 => Value::I16(int(value, i16::MIN as f64, i16::MAX as f64)? as i16),
+            DataType::U32 => Value::U32(int(value, 0.0, u32::MAX as f64)? as u32),
+            DataType::I32 => Value::I32(int(value, i32::MIN as f64, i32::MAX as f64)? as i32),
+            DataType::F32 if value.is_finite() && value.abs() > f32::MAX as f64 => return None,
+            DataType::F32 => Value::F32(value as f32),
+            // The max values aren't representable as f64, the rounded value is one above
+            DataType::U64 => Value::U64(
+                int(value, 0.0, u64::MAX as f64).filter(|v| *v < u64::MAX as f64)? as u64,
+            ),
+            DataType::I64 => Value::I64(
+                int(value, i64::MIN as f64, i64::MAX as f64).filter(|v| *v < i64::MAX as f64)?
+                    as i64,
+            ),
+            DataType::F64 => Value::F64(value),
+        })
+    }
+
+    fn to_be_bytes(self, bytes: &mut [u8; 8]) -> usize {
+        fn put<const N: usize>(src: [u8; N], bytes: &mut [u8; 8]) -> usize {
+            bytes[..N].copy_from_slice(&src);
+            N
+        }
+
+        match self {
+            Value::Bool(value) => put((value as u16).to_be_bytes(), bytes),
+            Value::U16(value) => put(value.to_be_bytes(), bytes),
+            Value::I16(value) => put(value.to_be_bytes(), bytes),
+            Value::U32(value) => put(value.to_be_bytes(), bytes),
+            Value::I32(value) => put(value.to_be_bytes(), bytes),
+            Value::F32(value) => put(value.to_be_bytes(), bytes),
+            Value::U64(value) => put(value.to_be_bytes(), bytes),
+            Value::I64(value) => put(value.to_be_bytes(), bytes),
+            Value::F64(value) => put(value.to_be_bytes(), bytes),
+        }
+    }
+
+    fn from_be_bytes(data_type: DataType, bytes: &[u8]) -> Value {
+        fn take<const N: usize>(bytes: &[u8]) -> [u8; N] {
+            bytes[..N].try_into().unwrap()
+        }
+
+        match data_type {
+            DataType::Bool => Value::Bool(u16::from_be_bytes(take(bytes)) != 0),
+            DataType::U16 => Value::U16(u16::from_be_bytes(take(bytes))),
+            DataType::I16 => Value::I16(i16::from_be_bytes(take(bytes))),
+            DataType::U32 => Value::U32(u32::from_be_bytes(take(bytes))),
+            DataType::I32 => Value::I32(i32::from_be_bytes(take(bytes))),
+            DataType::F32 => Value::F32(f32::from_be_bytes(take(bytes))),
+            DataType::U64 => Value::U64(u64::from_be_bytes(take(bytes))),
+            DataType::I64 => Value::I64(i64::from_be_bytes(take(bytes))),
+            DataType::F64 => Value::F64(f64::from_be_bytes(take(bytes))),
+        }
+    }
+
+    /// Writes the registers of the value to the start of `words` and returns how many
+    /// were written. Bools are written as 0 or 1.
+    pub fn encode_words(&self, order: WordOrder, words: &mut [u16]) -> usize {
+        let mut bytes = [0_u8; 8];
+        let len = self.to_be_bytes(&mut bytes);
+        order.bytes_to_words(&bytes[..len], words);
+        len / 2
+    }
+
+    /// Value of the type from the start of `words`, `None` if there aren't enough words
+    pub fn decode_words(data_type: DataType, order: WordOrder, words: &[u16]) -> Option<Value> {
+        let quantity = data_type.quantity();
+        if words.len() < quantity {
+            return None;
+        }
+        let mut bytes = [0_u8; 8];
+        order.words_to_bytes(|i| words[i], &mut bytes[..quantity * 2]);
+        Some(Value::from_be_bytes(data_type, &bytes))
+    }
+}
+
+/// Struct stored in a block of registers, usually derived with `modbus-derive`
+pub trait ModbusRegisters: Sized {
+    /// Number of registers from the start of the block to the end of the last field
+    const SPAN: usize;
+
+    /// Decodes the struct from registers read from the start of the block, `None` if
+    /// there are less than [`Self::SPAN`]
+    fn decode(words: &DataWords<'_>) -> Option<Self>;
+
+    /// Writes the registers of the fields to `words`, which holds the whole block.
+    /// Registers between fields are left as they are.
+    fn encode(&self, words: &mut [u16]);
+
+    /// Request writing the whole block at `address`, with the registers between fields
+    /// set to 0. Fails if the block is larger than one write, which allows fewer
+    /// registers than one read.
+    fn write_request<'a>(
+        &self,
+        address: Address,
+        buf: &'a mut [u8],
+    ) -> Result<Request<'a>, EncodeError> {
+        if Self::SPAN > MAX_WRITE_REGISTERS as usize {
+            return Err(EncodeError::InvalidQuantity {
+                quantity: Self::SPAN,
+                max: MAX_WRITE_REGISTERS as usize,
+            });
+        }
+        let mut words = [0_u16; MAX_WRITE_REGISTERS as usize];
+        self.encode(&mut words[..Self::SPAN]);
+        let words = DataWords::from_words_iter(words[..Self::SPAN].iter().copied(), buf)?;
+        Ok(Request::WriteMultipleRegisters(address, words))
+    }
+}
+
+impl DataWords<'_> {
+    /// Value of the type starting at the register `index`, `None` if it doesn't fit
+    pub fn value(&self, index: usize, data_type: DataType, order: WordOrder) -> Option<Value> {
+        let quantity = data_type.quantity();
+        if index + quantity > self.quantity() {
+            return None;
+        }
+        let data = &self.data()[index * 2..];
+        let mut bytes = [0_u8; 8];
+        order.words_to_bytes(
+            |i| u16::from_be_bytes([data[i * 2], data[i * 2 + 1]]),
+            &mut bytes[..quantity * 2],
+        );
+        Some(Value::from_be_bytes(data_type, &bytes))
+    }
+}
+
+impl<'a> DataWords<'a> {
+    /// Consecutive values of the type, ignoring the registers left after the last whole
+    /// value
+    pub fn values(&self, data_type: DataType, order: WordOrder) -> Values<'a> {
+        Values {
+            words: *self,
+            index: 0,
+            data_type,
+            order,
+        }
+    }
+}
+
+/// Iterator over the typed values of [`DataWords`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Values<'a> {
+    words: DataWords<'a>,
+    index: usize,
+    data_type: DataType,
+    order: WordOrder,
+}
+
+impl Iterator for Values<'_> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let value = self.words.value(self.index, self.data_type, self.order)?;
+        self.index += self.data_type.quantity();
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.words.quantity().saturating_sub(self.index) / self.data_type.quantity();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Values<'_> {}
+
+impl DataCoils<'_> {
+    /// Coil at `index`, `None` if it's beyond the quantity
+    pub fn value(&self, index: usize) -> Option<Value> {
+        self.get(index).map(Value::Bool)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pdu::{DataCoils, DataWords};
+
+    use super::{DataType, Value, WordOrder};
+
+    #[test]
+    fn decodes_word_orders() {
+        let data = [0x00, 0x00, 0x12, 0x34, 0x56, 0x78];
+        let words = DataWords::new(&data, 3);
+        let value = |order| words.value(1, DataType::U32, order);
+        assert_eq!(value(WordOrder::Abcd), Some(Value::U32(0x12345678)));
+        assert_eq!(value(WordOrder::Cdab), Some(Value::U32(0x56781234)));
+        assert_eq!(value(WordOrder::Badc), Some(Value::U32(0x34127856)));
+        assert_eq!(value(WordOrder::Dcba), Some(Value::U32(0x78563412)));
+        assert_eq!(words.value(2, DataType::U32, WordOrder::Abcd), None);
+        assert_eq!(
+            words.value(1, DataType::I16, WordOrder::Abcd),
+            Some(Value::I16(0x1234))
+        );
+    }
+
+    #[test]
+    fn iterates_values() {
+        let data = [0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0xff];
+        let words = DataWords::new(&data, 4);
+        let mut values = words.values(DataType::U32, WordOrder::Cdab);
+        assert_eq!(values.len(), 2);
+        assert_eq!(values.next(), Some(Value::U32(1)));
+        assert_eq!(values.next(), Some(Value::U32(2)));
+        assert_eq!(values.next(), None);
+        assert_eq!(words.values(DataType::U64, WordOrder::Abcd).len(), 1);
+        assert_eq!(words.values(DataType::F64, WordOrder::Abcd).nth(1), None);
+    }
+
+    #[test]
+    fn encodes_and_decodes_words() {
+        let values = [
+            Value::F32(-1.5),
+            Value::I64(-2),
+            Value::U64(0x0102030405060708),
+            Value::F64(1e300),
+            Value::I16(-3),
+        ];
+        for order in [
+            WordOrder::Abcd,
+            WordOrder::Cdab,
+            WordOrder::Badc,
+            WordOrder::Dcba,
+        ] {
+            for value in values {
+                let mut words = [0; 4];
+                let len = value.encode_words(order, &mut words);
+                assert_eq!(len, value.data_type().quantity());
+                assert_eq!(
+                    Value::decode_words(value.data_type(), order, &words[..len]),
+                    Some(value)
+                );
+            }
+        }
+
+        let mut words = [0; 4];
+        Value::U64(0x0102030405060708).encode_words(WordOrder::Cdab, &mut words);
+        assert_eq!(words, [0x0708, 0x0506, 0x0304, 0x0102]);
+        Value::F32(1.0).encode_words(WordOrder::Abcd, &mut words);
+        assert_eq!(words[..2], [0x3f80, 0x0000]);
+    }
+
+    #[test]
+    fn decodes_coils() {
+        let coils = DataCoils::new(&[0b0000_0101, 0b1], 9);
+        assert_eq!(coils.value(2), Some(Value::Bool(true)));
+        assert_eq!(coils.value(3), Some(Value::Bool(false)));
+        assert_eq!(coils.value(8), Some(Value::Bool(true)));
+        assert_eq!(coils.value(9), None);
+    }
+
+    #[test]
+    fn from_f64_checks_range() {
+        assert_eq!(
+            Value::from_f64(DataType::U16, 65535.4),
+            Some(Value::U16(65535))
+        );
+        assert_eq!(Value::from_f64(DataType::U16, 65535.6), None);
+        assert_eq!(Value::from_f64(DataType::I16, -2.6), Some(Value::I16(-3)));
+        assert_eq!(Value::from_f64(DataType::U32, -1.0), None);
+        assert_eq!(Value::from_f64(DataType::U64, u64::MAX as f64), None);
+        assert_eq!(Value::from_f64(DataType::Bool, 0.5), None);
+        assert_eq!(Value::from_f64(DataType::F32, 1e39), None);
+        assert_eq!(Value::from_f64(DataType::U16, f64::NAN), None);
+    }
+}
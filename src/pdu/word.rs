@@ -1,4 +1,9 @@
-#[derive(Debug, PartialEq, Eq)]
+use core::{fmt, iter::FusedIterator, slice::ChunksExact};
+
+use crate::error::EncodeError;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct DataWords<'a> {
     data: &'a [u8],
     quantity: usize,
@@ -30,20 +35,124 @@ impl<'a> DataWords<'a> {
             quantity: words.len(),
         }
     }
+
+    /// Words of the iterator written to `buf`, which must hold 2 bytes per word
+    pub fn from_words_iter(
+        words: impl IntoIterator<Item = u16>,
+        buf: &'a mut [u8],
+    ) -> Result<Self, EncodeError> {
+        let available = buf.len();
+        let mut quantity = 0;
+        for word in words {
+            let bytes = buf
+                .get_mut(quantity * 2..quantity * 2 + 2)
+                .ok_or(EncodeError::InvalidBufferSize {
+                    needed: quantity * 2 + 2,
+                    available,
+                })?;
+            bytes.copy_from_slice(&word.to_be_bytes());
+            quantity += 1;
+        }
+
+        Ok(Self {
+            data: &buf[..quantity * 2],
+            quantity,
+        })
+    }
+
+    /// Word at `index`, `None` if it's beyond the quantity
+    pub fn get(&self, index: usize) -> Option<u16> {
+        if index >= self.quantity {
+            return None;
+        }
+        let bytes = self.data.get(index * 2..index * 2 + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Words read from the borrowed bytes
+    pub fn iter(&self) -> Words<'a> {
+        let len = (self.quantity * 2).min(self.data.len());
+        Words {
+            chunks: self.data[..len].chunks_exact(2),
+        }
+    }
+}
+
+/// Iterator over the words of [`DataWords`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Words<'a> {
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    chunks: ChunksExact<'a, u8>,
+}
+
+impl Iterator for Words<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        self.chunks
+            .next()
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Words<'_> {
+    fn next_back(&mut self) -> Option<u16> {
+        self.chunks
+            .next_back()
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+impl ExactSizeIterator for Words<'_> {}
+impl FusedIterator for Words<'_> {}
+
+impl<'a> IntoIterator for DataWords<'a> {
+    type Item = u16;
+    type IntoIter = Words<'a>;
+
+    fn into_iter(self) -> Words<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &DataWords<'a> {
+    type Item = u16;
+    type IntoIter = Words<'a>;
+
+    fn into_iter(self) -> Words<'a> {
+        self.iter()
+    }
 }
 
 impl<'a, 'b> DataWords<'a> {
     pub fn copy_words_to(&self, words: &'b mut [u16]) -> &'b [u16] {
-        for (i, word) in words.iter_mut().enumerate().take(self.quantity) {
-            let high = self.data[i * 2];
-            let low = self.data[i * 2 + 1];
-            *word = u16::from_be_bytes([high, low]);
+        for (word, value) in words.iter_mut().zip(self.iter()) {
+            *word = value;
         }
 
         &words[..self.quantity]
     }
 }
 
+/// `[1, 2, 3]`
+impl fmt::Display for DataWords<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, word) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{word}")?;
+        }
+        f.write_str("]")
+    }
+}
+
 #[cfg(feature = "alloc")]
 extern crate alloc;
 #[cfg(feature = "alloc")]
@@ -51,16 +160,22 @@ use alloc::vec::Vec;
 #[cfg(feature = "alloc")]
 impl From<DataWords<'_>> for Vec<u16> {
     fn from(data_words: DataWords<'_>) -> Self {
-        Vec::from_iter((0..data_words.quantity).map(|i| {
-            let high = data_words.data[i * 2];
-            let low = data_words.data[i * 2 + 1];
-            u16::from_be_bytes([high, low])
-        }))
+        Vec::from_iter(data_words)
+    }
+}
+
+/// The words as an array of `u16`, not their bytes
+#[cfg(feature = "serde")]
+impl serde::Serialize for DataWords<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::error::EncodeError;
+
     use super::DataWords;
 
     #[test]
@@ -92,6 +207,48 @@ mod test {
         assert_eq!(words_buf, [0xffff, 0x0900, 0, 0]);
     }
 
+    #[test]
+    fn iterates_data_words() {
+        let data_words = DataWords::new(&[0xff, 0xff, 0x09, 0, 0x12, 0x34], 3);
+        assert_eq!(data_words.get(1), Some(0x0900));
+        assert_eq!(data_words.get(3), None);
+
+        let mut words = data_words.iter();
+        assert_eq!(words.len(), 3);
+        assert_eq!(words.next(), Some(0xffff));
+        assert_eq!(words.next_back(), Some(0x1234));
+        assert_eq!(words.next(), Some(0x0900));
+        assert_eq!(words.next(), None);
+
+        let mut sum = 0_u32;
+        for word in &data_words {
+            sum += word as u32;
+        }
+        assert_eq!(sum, 0xffff + 0x0900 + 0x1234);
+    }
+
+    #[test]
+    fn data_words_from_iter() {
+        let mut buf = [0_u8; 6];
+        let data_words = DataWords::from_words_iter((1..=3).map(|i| i * 0x101), &mut buf);
+        assert_eq!(
+            data_words,
+            Ok(DataWords {
+                data: &[1, 1, 2, 2, 3, 3],
+                quantity: 3
+            })
+        );
+
+        let mut buf = [0_u8; 5];
+        assert_eq!(
+            DataWords::from_words_iter([1, 2, 3], &mut buf),
+            Err(EncodeError::InvalidBufferSize {
+                needed: 6,
+                available: 5
+            })
+        );
+    }
+
     #[cfg(feature = "alloc")]
     extern crate alloc;
     #[cfg(feature = "alloc")]
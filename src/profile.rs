@@ -0,0 +1,680 @@
+//! Register maps of devices described in TOML or JSON files
+//!
+//! ```toml
+//! name = "Meter"
+//!
+//! [[points]]
+//! name = "voltage"
+//! register_type = "input_register"
+//! address = 0
+//! data_type = "f32"
+//! word_order = "cdab"
+//! unit = "V"
+//!
+//! [[points]]
+//! name = "current_limit"
+//! register_type = "holding_register"
+//! address = 10
+//! data_type = "u16"
+//! scale = 0.1
+//! access = "read_write"
+//! ```
+
+use std::{
+    fmt, fs, io,
+    path::Path,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+use crate::{
+    client::{
+        Client, Error, RESPONSE_BUF_SIZE,
+        coalesce::{self, CoalesceConfig, PlanError, ReadPlan, ReadValues, Tag},
+    },
+    pdu::{
+        Address, DataWords, Quantity, RegisterType,
+        request::Request as PduRequest,
+        response::Response as PduResponse,
+        value::{DataType, Value, WordOrder},
+    },
+};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    #[default]
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    pub fn is_readable(&self) -> bool {
+        matches!(self, Access::Read | Access::ReadWrite)
+    }
+    pub fn is_writable(&self) -> bool {
+        matches!(self, Access::Write | Access::ReadWrite)
+    }
+}
+
+/// Named value of a device. Its engineering value is `raw * scale + offset`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Point {
+    pub name: String,
+    #[serde(deserialize_with = "from_str")]
+    pub register_type: RegisterType,
+    pub address: Address,
+    #[serde(deserialize_with = "from_str")]
+    pub data_type: DataType,
+    #[serde(default, deserialize_with = "from_str")]
+    pub word_order: WordOrder,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub access: Access,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn from_str<'de, D: Deserializer<'de>, T: core::str::FromStr>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse()
+        .map_err(|_| D::Error::custom(std::format!("invalid value `{s}`")))
+}
+
+impl Point {
+    /// Number of registers, or bits, of the point
+    pub fn quantity(&self) -> usize {
+        self.data_type.quantity()
+    }
+
+    fn end(&self) -> usize {
+        self.address as usize + self.quantity()
+    }
+
+    /// Engineering value of a raw value
+    pub fn to_engineering(&self, raw: Value) -> f64 {
+        raw.as_f64() * self.scale + self.offset
+    }
+
+    /// Raw value of an engineering value, `None` if it doesn't fit the data type
+    pub fn to_raw(&self, value: f64) -> Option<Value> {
+        Value::from_f64(self.data_type, (value - self.offset) / self.scale)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub points: Vec<Point>,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// The file extension is neither `toml` nor `json`
+    UnknownFormat,
+    DuplicateName(String),
+    /// Two points share registers
+    Overlap(String, String),
+    /// The data type can't be stored in the register type of the point
+    InvalidDataType(String),
+    /// The point goes beyond the 16 bit address space
+    OutOfRange(String),
+    /// The point is writable, but its register type is read only
+    NotWritable(String),
+    /// The scale is zero or not finite, or the offset not finite
+    InvalidScale(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(err) => write!(f, "{err}"),
+            ProfileError::Toml(err) => write!(f, "{err}"),
+            ProfileError::Json(err) => write!(f, "{err}"),
+            ProfileError::UnknownFormat => write!(f, "unknown profile format"),
+            ProfileError::DuplicateName(name) => write!(f, "duplicate point `{name}`"),
+            ProfileError::Overlap(a, b) => write!(f, "points `{a}` and `{b}` overlap"),
+            ProfileError::InvalidDataType(name) => {
+                write!(f, "invalid data type for the register type of `{name}`")
+            }
+            ProfileError::OutOfRange(name) => write!(f, "point `{name}` is out of range"),
+            ProfileError::NotWritable(name) => write!(f, "point `{name}` can't be written"),
+            ProfileError::InvalidScale(name) => {
+                write!(f, "invalid scale or offset of `{name}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+#[derive(Debug)]
+pub enum PointError {
+    UnknownPoint,
+    /// The access mode of the point doesn't allow the operation
+    AccessDenied,
+    /// The value doesn't fit the data type of the point
+    OutOfRange,
+    /// The raw value has another data type than the point
+    TypeMismatch,
+    Client(Error),
+}
+
+impl fmt::Display for PointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointError::UnknownPoint => write!(f, "unknown point"),
+            PointError::AccessDenied => write!(f, "access denied"),
+            PointError::OutOfRange => write!(f, "value out of range"),
+            PointError::TypeMismatch => write!(f, "value doesn't match the data type"),
+            PointError::Client(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for PointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PointError::Client(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for PointError {
+    fn from(err: Error) -> Self {
+        PointError::Client(err)
+    }
+}
+
+impl Profile {
+    /// Validated profile
+    pub fn new(name: String, points: Vec<Point>) -> Result<Self, ProfileError> {
+        let profile = Self { name, points };
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self, ProfileError> {
+        let profile: Self = toml::from_str(s).map_err(ProfileError::Toml)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, ProfileError> {
+        let profile: Self = serde_json::from_str(s).map_err(ProfileError::Json)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Loads a `.toml` or `.json` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProfileError> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).map_err(ProfileError::Io)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&s),
+            Some("json") => Self::from_json(&s),
+            _ => Err(ProfileError::UnknownFormat),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        for (i, point) in self.points.iter().enumerate() {
+            let name = || point.name.to_string();
+            if self.points[..i]
+                .iter()
+                .any(|other| other.name == point.name)
+            {
+                return Err(ProfileError::DuplicateName(name()));
+            }
+            if !point.data_type.fits(point.register_type) {
+                return Err(ProfileError::InvalidDataType(name()));
+            }
+            if point.end() > 0x10000 {
+                return Err(ProfileError::OutOfRange(name()));
+            }
+            if point.access.is_writable() && !point.register_type.is_writable() {
+                return Err(ProfileError::NotWritable(name()));
+            }
+            if point.scale == 0.0 || !point.scale.is_finite() || !point.offset.is_finite() {
+                return Err(ProfileError::InvalidScale(name()));
+            }
+            if let Some(other) = self.points[..i].iter().find(|other| {
+                other.register_type == point.register_type
+                    && (other.address as usize) < point.end()
+                    && (point.address as usize) < other.end()
+            }) {
+                return Err(ProfileError::Overlap(other.name.clone(), name()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn point(&self, name: &str) -> Option<&Point> {
+        self.points.iter().find(|point| point.name == name)
+    }
+
+    /// Reads the raw value of the point
+    pub fn read_raw<C: Client>(
+        &self,
+        client: &mut C,
+        unit_id: u8,
+        name: &str,
+    ) -> Result<Value, PointError> {
+        let point = self.point(name).ok_or(PointError::UnknownPoint)?;
+        if !point.access.is_readable() {
+            return Err(PointError::AccessDenied);
+        }
+
+        let req = point
+            .register_type
+            .read_request(point.address, point.quantity() as u16);
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+        let value = match client.request(unit_id, req, &mut buf)? {
+            PduResponse::ReadCoils(coils) | PduResponse::ReadDiscreteInput(coils) => coils.value(0),
+            PduResponse::ReadHoldingRegisters(words) | PduResponse::ReadInputRegisters(words) => {
+                words.value(0, point.data_type, point.word_order)
+            }
+            _ => None,
+        };
+        value.ok_or(PointError::Client(Error::UnexpectedResponse))
+    }
+
+    /// Reads the engineering value of the point, bools being 0 or 1
+    pub fn read<C: Client>(
+        &self,
+        client: &mut C,
+        unit_id: u8,
+        name: &str,
+    ) -> Result<f64, PointError> {
+        let raw = self.read_raw(client, unit_id, name)?;
+        Ok(self.point(name).unwrap().to_engineering(raw))
+    }
+
+    /// Writes the raw value of the point, which must have the point's data type.
+    ///
+    /// Fails with [`Error::UnexpectedResponse`] unless the response echoes the write.
+    pub fn write_raw<C: Client>(
+        &self,
+        client: &mut C,
+        unit_id: u8,
+        name: &str,
+        value: Value,
+    ) -> Result<(), PointError> {
+        let point = self.point(name).ok_or(PointError::UnknownPoint)?;
+        if !point.access.is_writable() {
+            return Err(PointError::AccessDenied);
+        }
+        if value.data_type() != point.data_type {
+            return Err(PointError::TypeMismatch);
+        }
+
+        let mut words = [0_u16; 4];
+        let mut data_buf = [0_u8; 8];
+        let len = value.encode_words(point.word_order, &mut words);
+        let (req, echo) = match value {
+            Value::Bool(coil) => (
+                PduRequest::WriteSingleCoil(point.address, coil),
+                PduResponse::WriteSingleCoil(point.address, coil),
+            ),
+            _ if len == 1 => (
+                PduRequest::WriteSingleRegister(point.address, words[0]),
+                PduResponse::WriteSingleRegister(point.address, words[0]),
+            ),
+            _ => (
+                PduRequest::WriteMultipleRegisters(
+                    point.address,
+                    DataWords::from_words(&words[..len], &mut data_buf),
+                ),
+                PduResponse::WriteMultipleRegisters(point.address, len as Quantity),
+            ),
+        };
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+        if client.request(unit_id, req, &mut buf)? != echo {
+            return Err(PointError::Client(Error::UnexpectedResponse));
+        }
+        Ok(())
+    }
+
+    /// Writes the engineering value of the point
+    pub fn write<C: Client>(
+        &self,
+        client: &mut C,
+        unit_id: u8,
+        name: &str,
+        value: f64,
+    ) -> Result<(), PointError> {
+        let point = self.point(name).ok_or(PointError::UnknownPoint)?;
+        let raw = point.to_raw(value).ok_or(PointError::OutOfRange)?;
+        self.write_raw(client, unit_id, name, raw)
+    }
+}
+
+/// Reads of every readable point of a profile, merged into as few requests as the
+/// default [`CoalesceConfig`] allows
+#[derive(Debug, Clone)]
+pub struct ProfileReader {
+    /// Readable points, in the order of the tags of the plan
+    points: Vec<Point>,
+    plan: ReadPlan,
+}
+
+impl ProfileReader {
+    pub fn new(profile: Profile) -> Result<Self, PlanError> {
+        let points: Vec<Point> = profile
+            .points
+            .into_iter()
+            .filter(|point| point.access.is_readable())
+            .collect();
+        let tags: Vec<Tag> = points
+            .iter()
+            .map(|point| {
+                Tag::new(
+                    point.register_type,
+                    point.address,
+                    point.quantity() as Quantity,
+                )
+            })
+            .collect();
+        let plan = coalesce::plan(&tags, &CoalesceConfig::default())?;
+        Ok(Self { points, plan })
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Reads the raw value of every readable point
+    pub fn read<C: Client>(&self, client: &mut C, unit_id: u8) -> Readings<'_> {
+        let read = self.plan.read(client, unit_id);
+        let values = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let raw = if point.register_type.is_bit() {
+                    read.bits(i)
+                        .map(|bits| bits.map(|bits| Value::Bool(bits[0])))
+                } else {
+                    read.words(i).map(|words| {
+                        words.and_then(|words| {
+                            Value::decode_words(point.data_type, point.word_order, words)
+                        })
+                    })
+                };
+                (point, raw.ok().flatten())
+            })
+            .collect();
+        Readings { values, read }
+    }
+}
+
+/// Values read by a [`ProfileReader`]
+#[derive(Debug)]
+pub struct Readings<'p> {
+    /// Raw value of every readable point, `None` when its read failed
+    pub values: Vec<(&'p Point, Option<Value>)>,
+    read: ReadValues<'p>,
+}
+
+impl Readings<'_> {
+    /// Errors of the failed requests, in the order they were sent
+    pub fn errors(&self) -> impl Iterator<Item = &Error> {
+        self.read
+            .blocks()
+            .iter()
+            .filter_map(|block| block.as_ref().err())
+    }
+
+    /// Whether a request failed with an I/O error, after which the connection should be
+    /// reopened
+    pub fn is_disconnected(&self) -> bool {
+        self.errors().any(|err| matches!(err, Error::Io(_)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{string::ToString, vec::Vec};
+
+    use crate::{
+        client::{Client, Error, split::test::MockDevice},
+        pdu::{
+            RegisterType,
+            function_code::FunctionCode,
+            request::Request as PduRequest,
+            response::Response as PduResponse,
+            value::{DataType, Value, WordOrder},
+        },
+    };
+
+    use super::{Access, PointError, Profile, ProfileError, ProfileReader};
+
+    const METER: &str = r#"
+        name = "Meter"
+
+        [[points]]
+        name = "energy"
+        register_type = "input_register"
+        address = 0
+        data_type = "u32"
+        word_order = "cdab"
+        scale = 0.1
+        unit = "kWh"
+
+        [[points]]
+        name = "limit"
+        register_type = "holding_register"
+        address = 2
+        data_type = "i16"
+        scale = 0.5
+        offset = -10
+        access = "read_write"
+
+        [[points]]
+        name = "relay"
+        register_type = "coil"
+        address = 2
+        data_type = "bool"
+        access = "read_write"
+    "#;
+
+    #[test]
+    fn loads_toml_and_json() {
+        let profile = Profile::from_toml(METER).unwrap();
+        assert_eq!(profile.name, "Meter");
+        let energy = profile.point("energy").unwrap();
+        assert_eq!(energy.register_type, RegisterType::InputRegister);
+        assert_eq!(energy.word_order, WordOrder::Cdab);
+        assert_eq!(energy.unit.as_deref(), Some("kWh"));
+        assert_eq!(energy.access, Access::Read);
+        assert_eq!(profile.point("limit").unwrap().word_order, WordOrder::Abcd);
+
+        let json = r#"{"name": "Meter", "points": [
+            {"name": "energy", "register_type": "input_register", "address": 0,
+             "data_type": "u32", "word_order": "cdab", "scale": 0.1, "unit": "kWh"}
+        ]}"#;
+        assert_eq!(
+            Profile::from_json(json).unwrap().points[0],
+            profile.points[0]
+        );
+    }
+
+    #[test]
+    fn validates_points() {
+        let profile = |points: &str| Profile::from_toml(&("name = \"a\"\n".to_string() + points));
+        let point = |name: &str, register_type: &str, address: u16, data_type: &str| {
+            std::format!(
+                "[[points]]\nname = \"{name}\"\nregister_type = \"{register_type}\"\n\
+                 address = {address}\ndata_type = \"{data_type}\"\n"
+            )
+        };
+
+        let points =
+            point("a", "holding_register", 0, "f32") + &point("b", "holding_register", 1, "u16");
+        assert!(
+            matches!(profile(&points), Err(ProfileError::Overlap(a, b)) if a == "a" && b == "b")
+        );
+        let points =
+            point("a", "holding_register", 0, "f32") + &point("b", "input_register", 1, "u16");
+        assert!(profile(&points).is_ok());
+        let points = point("a", "coil", 0, "bool") + &point("a", "coil", 1, "bool");
+        assert!(matches!(
+            profile(&points),
+            Err(ProfileError::DuplicateName(_))
+        ));
+        assert!(matches!(
+            profile(&point("a", "coil", 0, "u16")),
+            Err(ProfileError::InvalidDataType(_))
+        ));
+        assert!(matches!(
+            profile(&point("a", "input_register", 0, "bool")),
+            Err(ProfileError::InvalidDataType(_))
+        ));
+        assert!(matches!(
+            profile(&point("a", "input_register", 65534, "u64")),
+            Err(ProfileError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            profile(&(point("a", "input_register", 0, "u16") + "access = \"write\"\n")),
+            Err(ProfileError::NotWritable(_))
+        ));
+        assert!(matches!(
+            profile(&(point("a", "input_register", 0, "u16") + "scale = 0\n")),
+            Err(ProfileError::InvalidScale(_))
+        ));
+        assert!(matches!(
+            profile(&point("a", "input_register", 0, "u128")),
+            Err(ProfileError::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn reads_and_writes_points() {
+        let profile = Profile::from_toml(METER).unwrap();
+        let mut device = MockDevice::new();
+        device.registers[0] = 0x0002;
+        device.registers[1] = 0x0001;
+
+        assert_eq!(
+            profile.read_raw(&mut device, 1, "energy").unwrap(),
+            Value::U32(0x0001_0002)
+        );
+        assert_eq!(profile.read(&mut device, 1, "energy").unwrap(), 6553.8);
+        assert_eq!(profile.read(&mut device, 1, "limit").unwrap(), -9.0);
+        assert_eq!(profile.read(&mut device, 1, "relay").unwrap(), 0.0);
+
+        profile.write(&mut device, 1, "limit", 20.0).unwrap();
+        assert_eq!(device.registers[2], 60);
+        profile.write(&mut device, 1, "limit", -30.0).unwrap();
+        assert_eq!(device.registers[2], (-40_i16) as u16);
+        profile.write(&mut device, 1, "relay", 1.0).unwrap();
+        assert!(device.coils[2]);
+
+        assert!(matches!(
+            profile.write(&mut device, 1, "limit", 20000.0),
+            Err(PointError::OutOfRange)
+        ));
+        assert!(matches!(
+            profile.write(&mut device, 1, "energy", 1.0),
+            Err(PointError::AccessDenied)
+        ));
+        assert!(matches!(
+            profile.write_raw(&mut device, 1, "relay", Value::U16(1)),
+            Err(PointError::TypeMismatch)
+        ));
+        assert!(matches!(
+            profile.read(&mut device, 1, "missing"),
+            Err(PointError::UnknownPoint)
+        ));
+        assert_eq!(profile.point("relay").unwrap().data_type, DataType::Bool);
+        assert_eq!(device.requests.len(), 7);
+    }
+
+    /// Client answering every request with the same write echo
+    struct WrongEcho;
+
+    impl Client for WrongEcho {
+        fn request<'b>(
+            &mut self,
+            _unit_id: u8,
+            _req: PduRequest<'_>,
+            _buf: &'b mut [u8],
+        ) -> Result<PduResponse<'b>, Error> {
+            Ok(PduResponse::WriteSingleRegister(0, 0))
+        }
+    }
+
+    #[test]
+    fn checks_write_echo() {
+        let mut profile = Profile::from_toml(METER).unwrap();
+        profile.points[0].access = Access::ReadWrite;
+        let mut device = MockDevice::new();
+        profile
+            .write_raw(&mut device, 1, "energy", Value::U32(0x0001_0002))
+            .unwrap();
+        assert_eq!(device.registers[..2], [0x0002, 0x0001]);
+
+        for name in ["energy", "limit", "relay"] {
+            assert!(matches!(
+                profile.write(&mut WrongEcho, 1, name, 1.0),
+                Err(PointError::Client(Error::UnexpectedResponse))
+            ));
+        }
+    }
+
+    #[test]
+    fn reads_every_readable_point() {
+        let mut profile = Profile::from_toml(METER).unwrap();
+        profile.points[1].access = Access::Write;
+        let reader = ProfileReader::new(profile).unwrap();
+        assert_eq!(reader.points().len(), 2);
+
+        let mut device = MockDevice::new();
+        device.registers[0] = 0x0002;
+        device.registers[1] = 0x0001;
+        let readings = reader.read(&mut device, 1);
+        let values: Vec<_> = readings
+            .values
+            .iter()
+            .map(|(point, value)| (point.name.as_str(), *value))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("energy", Some(Value::U32(0x0001_0002))),
+                ("relay", Some(Value::Bool(false)))
+            ]
+        );
+        assert_eq!(readings.errors().count(), 0);
+        assert!(!readings.is_disconnected());
+
+        device.fail_address = Some(0);
+        let readings = reader.read(&mut device, 1);
+        assert_eq!(readings.values[0].1, None);
+        assert_eq!(readings.values[1].1, Some(Value::Bool(false)));
+        assert!(matches!(
+            readings.errors().collect::<Vec<_>>()[..],
+            [Error::Exception(FunctionCode::ReadInputRegisters, _)]
+        ));
+        assert!(!readings.is_disconnected());
+    }
+}
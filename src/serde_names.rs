@@ -0,0 +1,72 @@
+//! Serde representation of the function and exception codes. Human readable formats
+//! get the name of the code, like `ReadCoils`, or its number when it has none. The
+//! other formats get its number.
+
+use core::fmt;
+
+use serde::{
+    Deserializer, Serializer,
+    de::{Error, Unexpected, Visitor},
+};
+
+pub(crate) fn serialize<T, S>(
+    code: T,
+    names: &[(&str, T)],
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: Copy + PartialEq + Into<u8>,
+    S: Serializer,
+{
+    match names.iter().find(|(_, named)| *named == code) {
+        Some((name, _)) if serializer.is_human_readable() => serializer.serialize_str(name),
+        _ => serializer.serialize_u8(code.into()),
+    }
+}
+
+/// Takes a name or a number in human readable formats, only a number in the others.
+/// `expecting` describes what is accepted, for the errors.
+pub(crate) fn deserialize<'de, T, D>(
+    deserializer: D,
+    names: &'static [(&'static str, T)],
+    expecting: &'static str,
+) -> Result<T, D::Error>
+where
+    T: Copy + TryFrom<u8>,
+    D: Deserializer<'de>,
+{
+    let visitor = CodeVisitor { names, expecting };
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(visitor)
+    } else {
+        deserializer.deserialize_u8(visitor)
+    }
+}
+
+struct CodeVisitor<T: 'static> {
+    names: &'static [(&'static str, T)],
+    expecting: &'static str,
+}
+
+impl<T: Copy + TryFrom<u8>> Visitor<'_> for CodeVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_u64<E: Error>(self, value: u64) -> Result<T, E> {
+        u8::try_from(value)
+            .ok()
+            .and_then(|code| T::try_from(code).ok())
+            .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_str<E: Error>(self, value: &str) -> Result<T, E> {
+        self.names
+            .iter()
+            .find(|(name, _)| *name == value)
+            .map(|(_, code)| *code)
+            .ok_or_else(|| E::invalid_value(Unexpected::Str(value), &self))
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(feature = "alloc")]
+pub mod registers;
+pub mod rtu;
+pub mod tcp;
+
+use crate::{
+    exception_code::ExceptionCode,
+    pdu::{request::Request as PduRequest, response::Response as PduResponse},
+};
+
+/// Application side of a server, shared by the RTU and TCP runtimes
+pub trait Handler {
+    /// Handles a request addressed to `unit_id` (0 for RTU broadcasts). The data of
+    /// the response, like the words read, can be encoded into `buf`.
+    fn handle<'b>(
+        &mut self,
+        unit_id: u8,
+        req: &PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, ExceptionCode>;
+}
+
+impl<H: Handler + ?Sized> Handler for &mut H {
+    fn handle<'b>(
+        &mut self,
+        unit_id: u8,
+        req: &PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, ExceptionCode> {
+        (**self).handle(unit_id, req, buf)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::{
+        exception_code::ExceptionCode,
+        pdu::{DataWords, request::Request as PduRequest, response::Response as PduResponse},
+    };
+
+    use super::Handler;
+
+    /// Handler with 16 holding registers
+    #[derive(Debug, Default)]
+    pub(crate) struct RegistersHandler {
+        pub(crate) registers: [u16; 16],
+    }
+
+    impl RegistersHandler {
+        fn range(&self, address: u16, quantity: usize) -> Result<(usize, usize), ExceptionCode> {
+            let start = address as usize;
+            let end = start + quantity;
+            if end > self.registers.len() {
+                return Err(ExceptionCode::IllegalDataAddress);
+            }
+            Ok((start, end))
+        }
+    }
+
+    impl Handler for RegistersHandler {
+        fn handle<'b>(
+            &mut self,
+            _unit_id: u8,
+            req: &PduRequest<'_>,
+            buf: &'b mut [u8],
+        ) -> Result<PduResponse<'b>, ExceptionCode> {
+            match req {
+                PduRequest::ReadHoldingRegisters(address, quantity) => {
+                    let (start, end) = self.range(*address, *quantity as usize)?;
+                    Ok(PduResponse::ReadHoldingRegisters(DataWords::from_words(
+                        &self.registers[start..end],
+                        buf,
+                    )))
+                }
+                PduRequest::WriteSingleRegister(address, value) => {
+                    let (start, _) = self.range(*address, 1)?;
+                    self.registers[start] = *value;
+                    Ok(PduResponse::WriteSingleRegister(*address, *value))
+                }
+                PduRequest::WriteMultipleRegisters(address, words) => {
+                    let (start, end) = self.range(*address, words.quantity())?;
+                    words.copy_words_to(&mut self.registers[start..end]);
+                    Ok(PduResponse::WriteMultipleRegisters(
+                        *address,
+                        words.quantity() as u16,
+                    ))
+                }
+                _ => Err(ExceptionCode::IllegalFunction),
+            }
+        }
+    }
+}
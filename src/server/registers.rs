@@ -0,0 +1,349 @@
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{fmt, num::ParseIntError};
+
+use crate::{
+    exception_code::ExceptionCode,
+    pdu::{
+        Address, DataCoils, DataWords, MAX_READ_COILS, MAX_READ_REGISTERS, Quantity, RegisterType,
+        mask::mask_write, request::Request as PduRequest, response::Response as PduResponse,
+    },
+};
+
+use super::Handler;
+
+/// Handler serving the four tables from memory. Only the addresses set exist, reading
+/// or writing any other answers `IllegalDataAddress`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RegisterMap {
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    coils: BTreeMap<Address, bool>,
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    discrete_inputs: BTreeMap<Address, bool>,
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    input_registers: BTreeMap<Address, u16>,
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    holding_registers: BTreeMap<Address, u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DumpError {
+    /// The line, counted from 1, isn't `<table> <address>: <values>`
+    InvalidLine(usize),
+    InvalidNumber(
+        usize,
+        #[cfg_attr(feature = "defmt", defmt(Display2Format))] ParseIntError,
+    ),
+    /// The values of the line go beyond the 16 bit address space
+    OutOfRange(usize),
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DumpError::InvalidLine(line) => write!(f, "invalid line {line}"),
+            DumpError::InvalidNumber(line, err) => write!(f, "line {line}: {err}"),
+            DumpError::OutOfRange(line) => write!(f, "line {line}: address out of range"),
+        }
+    }
+}
+
+impl core::error::Error for DumpError {}
+
+impl RegisterMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a register dump, one block of consecutive items per line:
+    ///
+    /// ```text
+    /// # Comment
+    /// hr 40000: 5375 6e53 0001 0042
+    /// co 0: 1 0 1
+    /// ```
+    ///
+    /// Tables are `co`, `di`, `ir` and `hr`, addresses are decimal, register values
+    /// hexadecimal and bits `0` or `1`.
+    pub fn from_dump(dump: &str) -> Result<Self, DumpError> {
+        let mut map = Self::new();
+        for (i, line) in dump.lines().enumerate() {
+            let line_nr = i + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (head, values) = line
+                .split_once(':')
+                .ok_or(DumpError::InvalidLine(line_nr))?;
+            let (table, address) = head
+                .trim()
+                .split_once(' ')
+                .ok_or(DumpError::InvalidLine(line_nr))?;
+            let register_type = match table {
+                "co" => RegisterType::Coil,
+                "di" => RegisterType::DiscreteInput,
+                "ir" => RegisterType::InputRegister,
+                "hr" => RegisterType::HoldingRegister,
+                _ => return Err(DumpError::InvalidLine(line_nr)),
+            };
+            let address: Address = address
+                .trim()
+                .parse()
+                .map_err(|err| DumpError::InvalidNumber(line_nr, err))?;
+
+            for (offset, value) in values.split_whitespace().enumerate() {
+                let address = Address::try_from(address as usize + offset)
+                    .map_err(|_| DumpError::OutOfRange(line_nr))?;
+                let radix = if register_type.is_bit() { 2 } else { 16 };
+                let value = u16::from_str_radix(value, radix)
+                    .map_err(|err| DumpError::InvalidNumber(line_nr, err))?;
+                map.set(register_type, address, value);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Sets a register, or a bit to `value != 0`
+    pub fn set(&mut self, register_type: RegisterType, address: Address, value: u16) {
+        match register_type {
+            RegisterType::Coil => _ = self.coils.insert(address, value != 0),
+            RegisterType::DiscreteInput => _ = self.discrete_inputs.insert(address, value != 0),
+            RegisterType::InputRegister => _ = self.input_registers.insert(address, value),
+            RegisterType::HoldingRegister => _ = self.holding_registers.insert(address, value),
+        }
+    }
+
+    /// Register, or bit as 0 or 1, `None` if it isn't set
+    pub fn get(&self, register_type: RegisterType, address: Address) -> Option<u16> {
+        match register_type {
+            RegisterType::Coil => self.coils.get(&address).map(|bit| *bit as u16),
+            RegisterType::DiscreteInput => {
+                self.discrete_inputs.get(&address).map(|bit| *bit as u16)
+            }
+            RegisterType::InputRegister => self.input_registers.get(&address).copied(),
+            RegisterType::HoldingRegister => self.holding_registers.get(&address).copied(),
+        }
+    }
+
+    /// Sets consecutive registers from `address`
+    pub fn set_registers(&mut self, register_type: RegisterType, address: Address, values: &[u16]) {
+        for (address, value) in (address..=Address::MAX).zip(values) {
+            self.set(register_type, address, *value);
+        }
+    }
+
+    /// The items of the range, if they are all set
+    fn read(
+        &self,
+        register_type: RegisterType,
+        address: Address,
+        quantity: Quantity,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        let max = if register_type.is_bit() {
+            MAX_READ_COILS
+        } else {
+            MAX_READ_REGISTERS
+        };
+        if quantity == 0 || quantity > max {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        (address as u32..address as u32 + quantity as u32)
+            .map(|address| {
+                Address::try_from(address)
+                    .ok()
+                    .and_then(|address| self.get(register_type, address))
+                    .ok_or(ExceptionCode::IllegalDataAddress)
+            })
+            .collect()
+    }
+
+    fn check_set(
+        &self,
+        register_type: RegisterType,
+        address: Address,
+        quantity: usize,
+    ) -> Result<(), ExceptionCode> {
+        let all_set = (address as usize..address as usize + quantity).all(|address| {
+            Address::try_from(address)
+                .is_ok_and(|address| self.get(register_type, address).is_some())
+        });
+        if all_set {
+            Ok(())
+        } else {
+            Err(ExceptionCode::IllegalDataAddress)
+        }
+    }
+}
+
+impl Handler for RegisterMap {
+    fn handle<'b>(
+        &mut self,
+        _unit_id: u8,
+        req: &PduRequest<'_>,
+        buf: &'b mut [u8],
+    ) -> Result<PduResponse<'b>, ExceptionCode> {
+        match req {
+            PduRequest::ReadCoils(address, quantity) => {
+                let bits = self.read(RegisterType::Coil, *address, *quantity)?;
+                let bits: Vec<bool> = bits.iter().map(|bit| *bit != 0).collect();
+                Ok(PduResponse::ReadCoils(DataCoils::from_coils(&bits, buf)))
+            }
+            PduRequest::ReadDiscreteInput(address, quantity) => {
+                let bits = self.read(RegisterType::DiscreteInput, *address, *quantity)?;
+                let bits: Vec<bool> = bits.iter().map(|bit| *bit != 0).collect();
+                Ok(PduResponse::ReadDiscreteInput(DataCoils::from_coils(
+                    &bits, buf,
+                )))
+            }
+            PduRequest::ReadInputRegisters(address, quantity) => {
+                let words = self.read(RegisterType::InputRegister, *address, *quantity)?;
+                Ok(PduResponse::ReadInputRegisters(DataWords::from_words(
+                    &words, buf,
+                )))
+            }
+            PduRequest::ReadHoldingRegisters(address, quantity) => {
+                let words = self.read(RegisterType::HoldingRegister, *address, *quantity)?;
+                Ok(PduResponse::ReadHoldingRegisters(DataWords::from_words(
+                    &words, buf,
+                )))
+            }
+            PduRequest::WriteSingleCoil(address, coil) => {
+                self.check_set(RegisterType::Coil, *address, 1)?;
+                self.set(RegisterType::Coil, *address, *coil as u16);
+                Ok(PduResponse::WriteSingleCoil(*address, *coil))
+            }
+            PduRequest::WriteSingleRegister(address, word) => {
+                self.check_set(RegisterType::HoldingRegister, *address, 1)?;
+                self.set(RegisterType::HoldingRegister, *address, *word);
+                Ok(PduResponse::WriteSingleRegister(*address, *word))
+            }
+            PduRequest::WriteMultipleCoils(address, coils) => {
+                self.check_set(RegisterType::Coil, *address, coils.quantity())?;
+                let bits = Vec::from(*coils);
+                for (address, bit) in (*address..).zip(bits) {
+                    self.set(RegisterType::Coil, address, bit as u16);
+                }
+                Ok(PduResponse::WriteMultipleCoils(
+                    *address,
+                    coils.quantity() as Quantity,
+                ))
+            }
+            PduRequest::WriteMultipleRegisters(address, words) => {
+                self.check_set(RegisterType::HoldingRegister, *address, words.quantity())?;
+                let mut values = alloc::vec![0; words.quantity()];
+                words.copy_words_to(&mut values);
+                self.set_registers(RegisterType::HoldingRegister, *address, &values);
+                Ok(PduResponse::WriteMultipleRegisters(
+                    *address,
+                    words.quantity() as Quantity,
+                ))
+            }
+            PduRequest::MaskWriteRegister(address, and_mask, or_mask) => {
+                let current = self
+                    .get(RegisterType::HoldingRegister, *address)
+                    .ok_or(ExceptionCode::IllegalDataAddress)?;
+                let value = mask_write(current, *and_mask, *or_mask);
+                self.set(RegisterType::HoldingRegister, *address, value);
+                Ok(PduResponse::MaskWriteRegister(
+                    *address, *and_mask, *or_mask,
+                ))
+            }
+            PduRequest::ReadWriteMultipleRegisters(
+                read_address,
+                quantity,
+                write_address,
+                words,
+            ) => {
+                // The write is done before the read
+                self.check_set(
+                    RegisterType::HoldingRegister,
+                    *write_address,
+                    words.quantity(),
+                )?;
+                self.read(RegisterType::HoldingRegister, *read_address, *quantity)?;
+                let values: Vec<u16> = words.iter().collect();
+                self.set_registers(RegisterType::HoldingRegister, *write_address, &values);
+                let words = self.read(RegisterType::HoldingRegister, *read_address, *quantity)?;
+                Ok(PduResponse::ReadWriteMultipleRegisters(
+                    DataWords::from_words(&words, buf),
+                ))
+            }
+            _ => Err(ExceptionCode::IllegalFunction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        exception_code::ExceptionCode,
+        pdu::{
+            DataWords, RegisterType, request::Request as PduRequest,
+            response::Response as PduResponse,
+        },
+        server::Handler,
+    };
+
+    use super::{DumpError, RegisterMap};
+
+    #[test]
+    fn parses_dumps() {
+        let map = RegisterMap::from_dump(
+            "# Device\n\
+             hr 40000: 5375 6E53 0001\n\
+             \n\
+             co 3: 1 0 1  # relays\n",
+        )
+        .unwrap();
+        assert_eq!(map.get(RegisterType::HoldingRegister, 40001), Some(0x6e53));
+        assert_eq!(map.get(RegisterType::HoldingRegister, 40003), None);
+        assert_eq!(map.get(RegisterType::Coil, 5), Some(1));
+        assert_eq!(map.get(RegisterType::InputRegister, 40000), None);
+
+        assert_eq!(
+            RegisterMap::from_dump("hr 0 1 2"),
+            Err(DumpError::InvalidLine(1))
+        );
+        assert!(matches!(
+            RegisterMap::from_dump("\nco 0: 2"),
+            Err(DumpError::InvalidNumber(2, _))
+        ));
+        assert_eq!(
+            RegisterMap::from_dump("ir 65535: 1 2"),
+            Err(DumpError::OutOfRange(1))
+        );
+    }
+
+    #[test]
+    fn serves_only_set_addresses() {
+        let mut map = RegisterMap::new();
+        map.set_registers(RegisterType::HoldingRegister, 10, &[1, 2, 3]);
+        let mut buf = [0_u8; 8];
+
+        let res = map.handle(1, &PduRequest::ReadHoldingRegisters(10, 3), &mut buf);
+        assert_eq!(
+            res,
+            Ok(PduResponse::ReadHoldingRegisters(DataWords::new(
+                &[0, 1, 0, 2, 0, 3],
+                3
+            )))
+        );
+        let res = map.handle(1, &PduRequest::ReadHoldingRegisters(11, 3), &mut buf);
+        assert_eq!(res, Err(ExceptionCode::IllegalDataAddress));
+
+        let mut data = [0_u8; 4];
+        let words = DataWords::from_words(&[7, 8], &mut data);
+        let res = map.handle(1, &PduRequest::WriteMultipleRegisters(11, words), &mut buf);
+        assert_eq!(res, Ok(PduResponse::WriteMultipleRegisters(11, 2)));
+        assert_eq!(map.get(RegisterType::HoldingRegister, 12), Some(8));
+        let res = map.handle(1, &PduRequest::WriteSingleRegister(13, 1), &mut buf);
+        assert_eq!(res, Err(ExceptionCode::IllegalDataAddress));
+        let res = map.handle(1, &PduRequest::ReadInputRegisters(10, 1), &mut buf);
+        assert_eq!(res, Err(ExceptionCode::IllegalDataAddress));
+    }
+}
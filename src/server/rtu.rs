@@ -0,0 +1,463 @@
+use crate::{
+    adu::rtu::{BROADCAST_UNIT_ID, crc::check_crc, response::Response as AduResponse},
+    exception_code::ExceptionCode,
+    instrument::{Outcome, Role, Transaction},
+    pdu::{
+        MAX_PDU_SIZE, exception_response::ExceptionResponse, function_code::FunctionCode,
+        request::Request as PduRequest, response::Response as PduResponse,
+    },
+};
+
+use super::Handler;
+
+const DIAGNOSTICS: u8 = 0x08;
+
+const RETURN_QUERY_DATA: u16 = 0x00;
+const RESTART_COMMUNICATIONS: u16 = 0x01;
+const RETURN_DIAGNOSTIC_REGISTER: u16 = 0x02;
+const FORCE_LISTEN_ONLY_MODE: u16 = 0x04;
+const CLEAR_COUNTERS: u16 = 0x0a;
+const RETURN_BUS_MESSAGE_COUNT: u16 = 0x0b;
+const RETURN_BUS_COMMUNICATION_ERROR_COUNT: u16 = 0x0c;
+const RETURN_BUS_EXCEPTION_ERROR_COUNT: u16 = 0x0d;
+const RETURN_SERVER_MESSAGE_COUNT: u16 = 0x0e;
+const RETURN_SERVER_NO_RESPONSE_COUNT: u16 = 0x0f;
+
+/// Serial line diagnostic counters, returned by the Diagnostics (0x08) sub-functions
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Counters {
+    /// Frames with a valid CRC seen on the bus, whatever unit they are for
+    pub bus_message: u16,
+    /// Frames with an invalid CRC or too short to be a frame
+    pub bus_communication_error: u16,
+    /// Exception responses sent
+    pub bus_exception_error: u16,
+    /// Frames addressed to this unit, broadcasts included
+    pub server_message: u16,
+    /// Frames addressed to this unit that weren't answered
+    pub server_no_response: u16,
+}
+
+/// RTU server (slave) for one unit id on a serial line.
+///
+/// It works on complete frames, delimiting them is up to the caller. With `std`,
+/// [`RtuServer::serve`] does that using the silent interval between frames.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RtuServer<H> {
+    unit_id: u8,
+    handler: H,
+    counters: Counters,
+    listen_only: bool,
+}
+
+impl<H: Handler> RtuServer<H> {
+    pub fn new(unit_id: u8, handler: H) -> Self {
+        Self {
+            unit_id,
+            handler,
+            counters: Counters::default(),
+            listen_only: false,
+        }
+    }
+
+    pub fn unit_id(&self) -> &u8 {
+        &self.unit_id
+    }
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+    pub fn is_listen_only(&self) -> bool {
+        self.listen_only
+    }
+
+    /// Processes a received frame and encodes the response frame into `res_buf`,
+    /// which should fit [`crate::adu::rtu::MAX_ADU_SIZE`] bytes.
+    ///
+    /// Returns the size of the response frame, or `None` when nothing should be sent:
+    /// the frame is for another unit, is a broadcast, has an invalid CRC or the
+    /// server is in listen only mode.
+    pub fn process_frame(&mut self, frame: &[u8], res_buf: &mut [u8]) -> Option<usize> {
+        if frame.len() < 4 || check_crc(frame).is_err() {
+            self.counters.bus_communication_error =
+                self.counters.bus_communication_error.wrapping_add(1);
+            return None;
+        }
+        self.counters.bus_message = self.counters.bus_message.wrapping_add(1);
+
+        let unit_id = frame[0];
+        let is_broadcast = unit_id == BROADCAST_UNIT_ID;
+        if unit_id != self.unit_id && !is_broadcast {
+            return None;
+        }
+        self.counters.server_message = self.counters.server_message.wrapping_add(1);
+
+        let transaction = Transaction::start(Role::Server, unit_id, frame[1], None);
+        let mut data_buf = [0_u8; MAX_PDU_SIZE];
+        let pdu_buf = &frame[1..frame.len() - 2];
+        let pdu_res = self
+            .process_pdu(&transaction, unit_id, pdu_buf, &mut data_buf)
+            .filter(|_| !is_broadcast);
+
+        let Some(pdu_res) = pdu_res else {
+            self.counters.server_no_response = self.counters.server_no_response.wrapping_add(1);
+            transaction.finish(Outcome::NoResponse);
+            return None;
+        };
+        transaction.finish(Outcome::answer(&pdu_res));
+        if pdu_res.is_err() {
+            self.counters.bus_exception_error = self.counters.bus_exception_error.wrapping_add(1);
+        }
+
+        AduResponse::new(self.unit_id, pdu_res).encode(res_buf).ok()
+    }
+
+    fn process_pdu<'b>(
+        &mut self,
+        transaction: &Transaction,
+        unit_id: u8,
+        pdu_buf: &[u8],
+        buf: &'b mut [u8],
+    ) -> Option<Result<PduResponse<'b>, ExceptionResponse>> {
+        let req = match PduRequest::decode(pdu_buf) {
+            Ok(req) => req,
+            Err(err) => {
+                transaction.decode_error(&err);
+                if self.listen_only {
+                    return None;
+                }
+                return ExceptionResponse::try_from(err).ok().map(Err);
+            }
+        };
+
+        if let PduRequest::Custom(FunctionCode::Custom(DIAGNOSTICS), data) = req {
+            return self.diagnostics(data, buf);
+        }
+        if self.listen_only {
+            return None;
+        }
+        // Broadcasts are never answered, so there is no point in reading anything
+        if unit_id == BROADCAST_UNIT_ID && is_read_request(&req) {
+            return None;
+        }
+
+        Some(
+            self.handler
+                .handle(unit_id, &req, buf)
+                .map_err(|code| ExceptionResponse::new(FunctionCode::from(&req), code)),
+        )
+    }
+
+    fn diagnostics<'b>(
+        &mut self,
+        data: &[u8],
+        buf: &'b mut [u8],
+    ) -> Option<Result<PduResponse<'b>, ExceptionResponse>> {
+        let fn_code = FunctionCode::Custom(DIAGNOSTICS);
+        let exception = |code| Some(Err(ExceptionResponse::new(fn_code, code)));
+
+        if data.len() < 2 {
+            if self.listen_only {
+                return None;
+            }
+            return exception(ExceptionCode::IllegalDataValue);
+        }
+        let sub_function = u16::from_be_bytes([data[0], data[1]]);
+        if self.listen_only && sub_function != RESTART_COMMUNICATIONS {
+            return None;
+        }
+
+        let echo = |buf: &'b mut [u8]| {
+            buf[..data.len()].copy_from_slice(data);
+            Some(Ok(PduResponse::Custom(fn_code, &buf[..data.len()])))
+        };
+
+        let value = match sub_function {
+            RETURN_QUERY_DATA => return echo(buf),
+            RESTART_COMMUNICATIONS => {
+                let was_listen_only = self.listen_only;
+                self.listen_only = false;
+                self.counters = Counters::default();
+                if was_listen_only {
+                    return None;
+                }
+                return echo(buf);
+            }
+            CLEAR_COUNTERS => {
+                self.counters = Counters::default();
+                return echo(buf);
+            }
+            FORCE_LISTEN_ONLY_MODE => {
+                self.listen_only = true;
+                return None;
+            }
+            RETURN_DIAGNOSTIC_REGISTER => 0,
+            RETURN_BUS_MESSAGE_COUNT => self.counters.bus_message,
+            RETURN_BUS_COMMUNICATION_ERROR_COUNT => self.counters.bus_communication_error,
+            RETURN_BUS_EXCEPTION_ERROR_COUNT => self.counters.bus_exception_error,
+            RETURN_SERVER_MESSAGE_COUNT => self.counters.server_message,
+            RETURN_SERVER_NO_RESPONSE_COUNT => self.counters.server_no_response,
+            _ => return exception(ExceptionCode::IllegalFunction),
+        };
+
+        buf[0..2].copy_from_slice(&sub_function.to_be_bytes());
+        buf[2..4].copy_from_slice(&value.to_be_bytes());
+        Some(Ok(PduResponse::Custom(fn_code, &buf[..4])))
+    }
+}
+
+fn is_read_request(req: &PduRequest<'_>) -> bool {
+    matches!(
+        req,
+        PduRequest::ReadCoils(_, _)
+            | PduRequest::ReadDiscreteInput(_, _)
+            | PduRequest::ReadHoldingRegisters(_, _)
+            | PduRequest::ReadInputRegisters(_, _)
+            | PduRequest::ReadWriteMultipleRegisters(_, _, _, _)
+    )
+}
+
+#[cfg(feature = "std")]
+impl<H: Handler> RtuServer<H> {
+    /// Serves requests until the transport fails or reaches EOF. A frame ends when
+    /// nothing was received for `frame_gap`, which should be 3.5 character times.
+    pub fn serve<T: crate::transport::Transport>(
+        &mut self,
+        mut transport: T,
+        frame_gap: std::time::Duration,
+    ) -> std::io::Result<()> {
+        use crate::adu::rtu::MAX_ADU_SIZE;
+        use std::io::ErrorKind;
+
+        let mut frame_buf = [0_u8; MAX_ADU_SIZE];
+        let mut res_buf = [0_u8; MAX_ADU_SIZE];
+
+        loop {
+            let mut frame_len = 0;
+            transport.set_read_timeout(None)?;
+            while frame_len < frame_buf.len() {
+                match transport.read(&mut frame_buf[frame_len..]) {
+                    Ok(0) => return Ok(()),
+                    Ok(bytes_read) => {
+                        frame_len += bytes_read;
+                        transport.set_read_timeout(Some(frame_gap))?;
+                    }
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err)
+                        if matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) =>
+                    {
+                        break;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            if frame_len == 0 {
+                continue;
+            }
+
+            if let Some(res_len) = self.process_frame(&frame_buf[..frame_len], &mut res_buf) {
+                transport.write_all(&res_buf[..res_len])?;
+                transport.flush()?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        adu::rtu::{
+            MAX_ADU_SIZE, request::Request as AduRequest, response::Response as AduResponse,
+        },
+        error::DecodeError,
+        exception_code::ExceptionCode,
+        pdu::{
+            DataWords, function_code::FunctionCode, request::Request as PduRequest,
+            response::Response as PduResponse,
+        },
+        server::test::RegistersHandler,
+    };
+
+    use super::{Counters, RtuServer};
+
+    fn frame(unit_id: u8, pdu_req: PduRequest<'_>, buf: &mut [u8]) -> usize {
+        AduRequest::new(unit_id, pdu_req).encode(buf).unwrap()
+    }
+
+    fn diagnostics(server: &mut RtuServer<RegistersHandler>, sub_function: u16) -> Option<u16> {
+        let mut req_buf = [0_u8; MAX_ADU_SIZE];
+        let mut res_buf = [0_u8; MAX_ADU_SIZE];
+        let data = [(sub_function >> 8) as u8, sub_function as u8, 0, 0];
+        let len = frame(
+            1,
+            PduRequest::Custom(FunctionCode::Custom(0x08), &data),
+            &mut req_buf,
+        );
+        let res_len = server.process_frame(&req_buf[..len], &mut res_buf)?;
+        let res = AduResponse::decode(&res_buf[..res_len]).unwrap();
+        match res.pdu() {
+            Ok(PduResponse::Custom(_, data)) => Some(u16::from_be_bytes([data[2], data[3]])),
+            res => panic!("unexpected response {res:?}"),
+        }
+    }
+
+    #[test]
+    fn answers_own_unit_id() {
+        let mut server = RtuServer::new(1, RegistersHandler::default());
+        server.handler_mut().registers[2] = 0x1234;
+
+        let mut req_buf = [0_u8; MAX_ADU_SIZE];
+        let mut res_buf = [0_u8; MAX_ADU_SIZE];
+        let len = frame(1, PduRequest::ReadHoldingRegisters(2, 1), &mut req_buf);
+        let res_len = server.process_frame(&req_buf[..len], &mut res_buf).unwrap();
+        assert_eq!(
+            AduResponse::decode(&res_buf[..res_len]),
+            Ok(AduResponse::new(
+                1,
+                Ok(PduResponse::ReadHoldingRegisters(DataWords::new(
+                    &[0x12, 0x34],
+                    1
+                )))
+            ))
+        );
+
+        let len = frame(2, PduRequest::ReadHoldingRegisters(2, 1), &mut req_buf);
+        assert_eq!(server.process_frame(&req_buf[..len], &mut res_buf), None);
+
+        assert_eq!(
+            server.counters(),
+            &Counters {
+                bus_message: 2,
+                server_message: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn executes_broadcast_writes_silently() {
+        let mut server = RtuServer::new(1, RegistersHandler::default());
+
+        let mut req_buf = [0_u8; MAX_ADU_SIZE];
+        let mut res_buf = [0_u8; MAX_ADU_SIZE];
+        let len = frame(0, PduRequest::WriteSingleRegister(3, 7), &mut req_buf);
+        assert_eq!(server.process_frame(&req_buf[..len], &mut res_buf), None);
+        assert_eq!(server.handler().registers[3], 7);
+        assert_eq!(server.counters().server_no_response, 1);
+    }
+
+    #[test]
+    fn drops_frames_with_invalid_crc() {
+        let mut server = RtuServer::new(1, RegistersHandler::default());
+
+        let mut res_buf = [0_u8; MAX_ADU_SIZE];
+        let req = &[0x01, 0x06, 0x00, 0x03, 0x00, 0x07, 0x00, 0x00];
+        assert_eq!(server.process_frame(req, &mut res_buf), None);
+        assert_eq!(server.process_frame(&[0x01, 0x06], &mut res_buf), None);
+        assert_eq!(server.handler().registers[3], 0);
+        assert_eq!(diagnostics(&mut server, 0x0c), Some(2));
+    }
+
+    #[test]
+    fn answers_exceptions() {
+        let mut server = RtuServer::new(1, RegistersHandler::default());
+
+        let mut req_buf = [0_u8; MAX_ADU_SIZE];
+        let mut res_buf = [0_u8; MAX_ADU_SIZE];
+        let len = frame(1, PduRequest::ReadHoldingRegisters(15, 2), &mut req_buf);
+        let res_len = server.process_frame(&req_buf[..len], &mut res_buf).unwrap();
+        assert_eq!(
+            AduResponse::decode(&res_buf[..res_len]),
+            Err(DecodeError::ModbusExceptionCode(
+                FunctionCode::ReadHoldingRegisters,
+                ExceptionCode::IllegalDataAddress
+            ))
+        );
+
+        // Quantity of 0 fails when decoding the request
+        let len = frame(1, PduRequest::ReadHoldingRegisters(0, 0), &mut req_buf);
+        let res_len = server.process_frame(&req_buf[..len], &mut res_buf).unwrap();
+        assert_eq!(
+            AduResponse::decode(&res_buf[..res_len]),
+            Err(DecodeError::ModbusExceptionCode(
+                FunctionCode::ReadHoldingRegisters,
+                ExceptionCode::IllegalDataValue
+            ))
+        );
+
+        assert_eq!(diagnostics(&mut server, 0x0d), Some(2));
+        assert_eq!(diagnostics(&mut server, 0x0b), Some(4));
+        assert_eq!(diagnostics(&mut server, 0x0e), Some(5));
+    }
+
+    #[test]
+    fn listen_only_mode() {
+        let mut server = RtuServer::new(1, RegistersHandler::default());
+
+        assert_eq!(diagnostics(&mut server, 0x04), None);
+        assert!(server.is_listen_only());
+
+        let mut req_buf = [0_u8; MAX_ADU_SIZE];
+        let mut res_buf = [0_u8; MAX_ADU_SIZE];
+        let len = frame(1, PduRequest::WriteSingleRegister(3, 7), &mut req_buf);
+        assert_eq!(server.process_frame(&req_buf[..len], &mut res_buf), None);
+        assert_eq!(server.handler().registers[3], 0);
+        assert_eq!(diagnostics(&mut server, 0x0b), None);
+
+        // Restarting communications leaves listen only mode without answering
+        assert_eq!(diagnostics(&mut server, 0x01), None);
+        assert!(!server.is_listen_only());
+        assert_eq!(server.process_frame(&req_buf[..len], &mut res_buf), Some(8));
+        assert_eq!(server.handler().registers[3], 7);
+    }
+
+    #[cfg(all(feature = "serial", target_os = "linux"))]
+    #[test]
+    fn serve_on_pty() {
+        use std::{
+            io::{Read, Write},
+            thread,
+            time::Duration,
+        };
+
+        use crate::transport::serial::{SerialConfig, SerialPort, test::open_pty};
+
+        let (mut master, _slave, path) = open_pty();
+        let port = SerialPort::open(&path, SerialConfig::new(19200)).unwrap();
+        let frame_gap = port.config().frame_gap();
+        thread::spawn(move || {
+            let mut server = RtuServer::new(1, RegistersHandler::default());
+            let _ = server.serve(port, frame_gap);
+        });
+
+        let mut req_buf = [0_u8; MAX_ADU_SIZE];
+        let len = frame(1, PduRequest::WriteSingleRegister(3, 7), &mut req_buf);
+        master.write_all(&req_buf[..len]).unwrap();
+        let mut res_buf = [0_u8; 8];
+        master.read_exact(&mut res_buf).unwrap();
+        assert_eq!(&res_buf, &req_buf[..len]);
+
+        thread::sleep(Duration::from_millis(10));
+        let len = frame(1, PduRequest::ReadHoldingRegisters(3, 1), &mut req_buf);
+        master.write_all(&req_buf[..len]).unwrap();
+        let mut res_buf = [0_u8; 7];
+        master.read_exact(&mut res_buf).unwrap();
+        assert_eq!(
+            AduResponse::decode(&res_buf),
+            Ok(AduResponse::new(
+                1,
+                Ok(PduResponse::ReadHoldingRegisters(DataWords::new(
+                    &[0x00, 0x07],
+                    1
+                )))
+            ))
+        );
+    }
+}
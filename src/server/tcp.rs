@@ -0,0 +1,168 @@
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+use crate::{
+    adu::tcp::{header::Header, request::Request as AduRequest, response::Response as AduResponse},
+    instrument::{Outcome, Role, Transaction},
+    pdu::{MAX_PDU_SIZE, exception_response::ExceptionResponse, function_code::FunctionCode},
+};
+
+use super::Handler;
+
+/// Serves the requests of one TCP connection until the peer closes it.
+///
+/// Frames with a protocol id other than 0 or a length beyond the max ADU size
+/// close the connection with [`io::ErrorKind::InvalidData`].
+#[cfg(feature = "std")]
+pub fn serve_connection<S: Read + Write, H: Handler>(
+    mut stream: S,
+    handler: &mut H,
+) -> io::Result<()> {
+    use crate::adu::tcp::MAX_ADU_SIZE;
+
+    let mut req_buf = [0_u8; MAX_ADU_SIZE];
+    let mut res_buf = [0_u8; MAX_ADU_SIZE];
+    let mut buf_len = 0;
+
+    loop {
+        let bytes_read = match stream.read(&mut req_buf[buf_len..]) {
+            Ok(0) => return Ok(()),
+            Ok(bytes_read) => bytes_read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        buf_len += bytes_read;
+
+        // A read may contain several pipelined requests
+        while buf_len >= Header::size() {
+            let header = Header::decode(&req_buf[..buf_len]).unwrap();
+            // unit_id is included in the header.length and header.size
+            let adu_len = *header.length() as usize + Header::size() - 1;
+            if *header.protocol_id() != 0 || *header.length() < 2 || adu_len > MAX_ADU_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid MBAP header",
+                ));
+            }
+            if adu_len > buf_len {
+                break;
+            }
+
+            if let Some(res_len) = process_frame(&req_buf[..adu_len], &mut res_buf, handler) {
+                stream.write_all(&res_buf[..res_len])?;
+                stream.flush()?;
+            }
+
+            req_buf.copy_within(adu_len..buf_len, 0);
+            buf_len -= adu_len;
+        }
+    }
+}
+
+/// Processes one complete request frame and encodes the response into `res_buf`.
+/// Returns the size of the response, or `None` when nothing should be sent.
+pub fn process_frame<H: Handler>(
+    frame: &[u8],
+    res_buf: &mut [u8],
+    handler: &mut H,
+) -> Option<usize> {
+    let header = Header::decode(frame).ok()?;
+    let transaction_id = *header.transaction_id();
+    let unit_id = *header.unit_id();
+    let fn_code = frame.get(Header::size()).copied().unwrap_or_default();
+    let transaction = Transaction::start(Role::Server, unit_id, fn_code, Some(transaction_id));
+
+    let mut data_buf = [0_u8; MAX_PDU_SIZE];
+    let pdu_res = match AduRequest::decode(frame) {
+        Ok(req) => handler
+            .handle(unit_id, req.pdu(), &mut data_buf)
+            .map_err(|code| ExceptionResponse::new(FunctionCode::from(req.pdu()), code)),
+        Err(err) => {
+            transaction.decode_error(&err);
+            match ExceptionResponse::try_from(err) {
+                Ok(res) => Err(res),
+                Err(_) => {
+                    transaction.finish(Outcome::NoResponse);
+                    return None;
+                }
+            }
+        }
+    };
+    transaction.finish(Outcome::answer(&pdu_res));
+
+    AduResponse::new(transaction_id, unit_id, pdu_res)
+        .encode(res_buf)
+        .ok()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::{
+        io::{self, Cursor, Read, Write},
+        vec::Vec,
+    };
+
+    use crate::server::test::RegistersHandler;
+
+    use super::serve_connection;
+
+    struct Stream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn serves_pipelined_requests() {
+        let mut handler = RegistersHandler::default();
+        let mut input = Vec::new();
+        // Write single register 1 = 0x0102
+        input.extend_from_slice(&[0, 1, 0, 0, 0, 6, 1, 0x06, 0, 1, 1, 2]);
+        // Read holding registers 0..2
+        input.extend_from_slice(&[0, 2, 0, 0, 0, 6, 1, 0x03, 0, 0, 0, 2]);
+        // Read holding registers 15..17, out of range
+        input.extend_from_slice(&[0, 3, 0, 0, 0, 6, 1, 0x03, 0, 15, 0, 2]);
+        // Read coils, not supported by the handler
+        input.extend_from_slice(&[0, 4, 0, 0, 0, 6, 1, 0x01, 0, 0, 0, 1]);
+        let mut stream = Stream {
+            input: Cursor::new(input),
+            output: Vec::new(),
+        };
+
+        serve_connection(&mut stream, &mut handler).unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&[0, 1, 0, 0, 0, 6, 1, 0x06, 0, 1, 1, 2]);
+        expected.extend_from_slice(&[0, 2, 0, 0, 0, 7, 1, 0x03, 4, 0, 0, 1, 2]);
+        expected.extend_from_slice(&[0, 3, 0, 0, 0, 3, 1, 0x83, 0x02]);
+        expected.extend_from_slice(&[0, 4, 0, 0, 0, 3, 1, 0x81, 0x01]);
+        assert_eq!(stream.output, expected);
+    }
+
+    #[test]
+    fn closes_on_invalid_header() {
+        let mut handler = RegistersHandler::default();
+        let mut stream = Stream {
+            input: Cursor::new(Vec::from([0, 1, 0, 1, 0, 6, 1, 0x03, 0, 0, 0, 1])),
+            output: Vec::new(),
+        };
+
+        let err = serve_connection(&mut stream, &mut handler).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(stream.output.is_empty());
+    }
+}
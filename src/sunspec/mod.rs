@@ -0,0 +1,503 @@
+//! Discovery and decoding of SunSpec models
+//!
+//! A SunSpec device starts its holding registers with the `SunS` marker at one of the
+//! [`BASE_ADDRESSES`], followed by a chain of models, each starting with its id and
+//! length, until the end model with id `0xffff`.
+//!
+//! The common model 1, the inverter models 101 to 103, the MPPT model 160 and the DER
+//! models 701 to 705 are decoded: AC measurements, capacity, enter service, AC controls
+//! and volt-var curves. Other models are left as [`Model::Other`].
+
+pub mod model;
+
+use std::{fmt, vec, vec::Vec};
+
+use crate::{
+    client::{Client, Error as ClientError, split},
+    exception_code::ExceptionCode,
+    pdu::{Address, RegisterType},
+};
+
+use model::Model;
+
+/// Where the `SunS` marker is looked for, in order
+pub const BASE_ADDRESSES: [Address; 3] = [40000, 0, 50000];
+/// `SunS` in ASCII
+pub const MARKER: [u16; 2] = [0x5375, 0x6e53];
+const END_ID: u16 = 0xffff;
+
+#[derive(Debug)]
+pub enum Error {
+    Client(ClientError),
+    /// None of the base addresses has the marker
+    NotFound,
+    /// The model goes beyond the 16 bit address space, or is too short for its id
+    InvalidModel {
+        id: u16,
+        address: Address,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Client(err) => write!(f, "{err}"),
+            Error::NotFound => write!(f, "no SunSpec marker found"),
+            Error::InvalidModel { id, address } => {
+                write!(f, "invalid model {id} at address {address}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Client(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        Error::Client(err)
+    }
+}
+
+/// Location of a model in the chain
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModelHeader {
+    pub id: u16,
+    /// Address of the model id
+    pub address: Address,
+    /// Number of registers after the id and length
+    pub length: u16,
+}
+
+impl ModelHeader {
+    /// Address of the first register after the id and length
+    pub fn data_address(&self) -> Address {
+        self.address + 2
+    }
+}
+
+/// Models found on a device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    base_address: Address,
+    models: Vec<ModelHeader>,
+}
+
+fn is_illegal_address(err: &ClientError) -> bool {
+    matches!(
+        err,
+        ClientError::Exception(_, ExceptionCode::IllegalDataAddress)
+    )
+}
+
+fn read<C: Client>(
+    client: &mut C,
+    unit_id: u8,
+    address: Address,
+    words: &mut [u16],
+) -> Result<(), ClientError> {
+    split::read_registers(
+        client,
+        unit_id,
+        RegisterType::HoldingRegister,
+        address,
+        words,
+    )
+    .map_err(|err| err.error)
+}
+
+impl Device {
+    /// Finds the marker and walks the model chain. A chain without end model ends at
+    /// the first address answering `IllegalDataAddress`.
+    pub fn discover<C: Client>(client: &mut C, unit_id: u8) -> Result<Self, Error> {
+        let mut base_address = None;
+        for address in BASE_ADDRESSES {
+            let mut marker = [0; 2];
+            match read(client, unit_id, address, &mut marker) {
+                Ok(()) if marker == MARKER => {
+                    base_address = Some(address);
+                    break;
+                }
+                Ok(()) => {}
+                Err(err) if is_illegal_address(&err) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let base_address = base_address.ok_or(Error::NotFound)?;
+
+        let mut models = vec![];
+        let mut address = base_address as u32 + 2;
+        while address + 2 <= 0x10000 {
+            let mut header = [0; 2];
+            match read(client, unit_id, address as Address, &mut header) {
+                Ok(()) => {}
+                Err(err) if is_illegal_address(&err) => break,
+                Err(err) => return Err(err.into()),
+            }
+            let [id, length] = header;
+            if id == END_ID {
+                break;
+            }
+            if address + 2 + length as u32 > 0x10000 {
+                return Err(Error::InvalidModel {
+                    id,
+                    address: address as Address,
+                });
+            }
+            models.push(ModelHeader {
+                id,
+                address: address as Address,
+                length,
+            });
+            address += 2 + length as u32;
+        }
+
+        Ok(Self {
+            base_address,
+            models,
+        })
+    }
+
+    pub fn base_address(&self) -> &Address {
+        &self.base_address
+    }
+    pub fn models(&self) -> &[ModelHeader] {
+        &self.models
+    }
+
+    /// First model with the id
+    pub fn model(&self, id: u16) -> Option<&ModelHeader> {
+        self.models.iter().find(|model| model.id == id)
+    }
+
+    /// Reads and decodes a model of the chain
+    pub fn read_model<C: Client>(
+        &self,
+        client: &mut C,
+        unit_id: u8,
+        header: &ModelHeader,
+    ) -> Result<Model, Error> {
+        let mut data = vec![0; header.length as usize];
+        read(client, unit_id, header.data_address(), &mut data)?;
+        Model::decode(header.id, &data).ok_or(Error::InvalidModel {
+            id: header.id,
+            address: header.address,
+        })
+    }
+
+    /// Reads and decodes every model of the chain
+    pub fn read_models<C: Client>(&self, client: &mut C, unit_id: u8) -> Result<Vec<Model>, Error> {
+        self.models
+            .iter()
+            .map(|header| self.read_model(client, unit_id, header))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{vec, vec::Vec};
+
+    use crate::{client::loopback::Loopback, pdu::RegisterType, server::registers::RegisterMap};
+
+    use super::{
+        Device, Error, ModelHeader,
+        model::{
+            Common, DerAcControls, DerAcMeasurement, DerCapacity, DerEnterService, DerPowerFactor,
+            DerVoltVar, Inverter, Model, Mppt, VoltVarPoint,
+        },
+    };
+
+    const DUMP: &str = include_str!("../../testdata/sunspec-inverter.dump");
+
+    fn device() -> Loopback<RegisterMap> {
+        Loopback::new(RegisterMap::from_dump(DUMP).unwrap())
+    }
+
+    #[test]
+    fn discovers_model_chain() {
+        let mut client = device();
+        let device = Device::discover(&mut client, 1).unwrap();
+        assert_eq!(*device.base_address(), 40000);
+        let ids: Vec<(u16, u16, u16)> = device
+            .models()
+            .iter()
+            .map(|model| (model.id, model.address, model.length))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (1, 40002, 66),
+                (103, 40070, 50),
+                (160, 40122, 48),
+                (701, 40172, 153),
+                (702, 40327, 50),
+                (703, 40379, 17),
+                (704, 40398, 65),
+                (705, 40465, 47),
+                (64001, 40514, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn discovers_other_bases_and_chains_without_end() {
+        let mut map = RegisterMap::new();
+        map.set_registers(RegisterType::HoldingRegister, 0, &[0, 0]);
+        map.set_registers(
+            RegisterType::HoldingRegister,
+            50000,
+            &[0x5375, 0x6e53, 64000, 1, 7],
+        );
+        let mut client = Loopback::new(map);
+        let device = Device::discover(&mut client, 1).unwrap();
+        assert_eq!(*device.base_address(), 50000);
+        assert_eq!(
+            device.models(),
+            &[ModelHeader {
+                id: 64000,
+                address: 50002,
+                length: 1
+            }]
+        );
+
+        let mut client = Loopback::new(RegisterMap::new());
+        assert!(matches!(
+            Device::discover(&mut client, 1),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn decodes_common_and_inverter() {
+        let mut client = device();
+        let device = Device::discover(&mut client, 1).unwrap();
+
+        let Model::Common(common) = device
+            .read_model(&mut client, 1, device.model(Common::ID).unwrap())
+            .unwrap()
+        else {
+            panic!("expected the common model");
+        };
+        assert_eq!(common.manufacturer.as_deref(), Some("SunSpecTest"));
+        assert_eq!(common.model.as_deref(), Some("Inverter 5000"));
+        assert_eq!(common.options, None);
+        assert_eq!(common.version.as_deref(), Some("1.2.3"));
+        assert_eq!(common.serial_number.as_deref(), Some("SN12345"));
+        assert_eq!(common.device_address, Some(1));
+
+        let Model::Inverter(inverter) = device
+            .read_model(&mut client, 1, device.model(103).unwrap())
+            .unwrap()
+        else {
+            panic!("expected an inverter model");
+        };
+        assert_eq!(
+            inverter,
+            Inverter {
+                phases: 3,
+                current: Some(12.34),
+                phase_currents: [Some(4.11), Some(4.12), None],
+                line_voltages: [Some(400.0), Some(400.1), None],
+                phase_voltages: [Some(230.1), Some(230.2), Some(230.3)],
+                power: Some(5000.0),
+                frequency: Some(50.01),
+                apparent_power: Some(5100.0),
+                reactive_power: None,
+                power_factor: Some(98.0),
+                energy: Some(123456.0),
+                dc_current: Some(11.0),
+                dc_voltage: Some(450.0),
+                dc_power: Some(5200.0),
+                temperatures: [Some(45.0), None, None, None],
+                state: Some(4),
+                vendor_state: None,
+                events: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_mppt_and_der_models() {
+        let mut client = device();
+        let device = Device::discover(&mut client, 1).unwrap();
+        let models = device.read_models(&mut client, 1).unwrap();
+        assert_eq!(models.len(), 9);
+
+        let Model::Mppt(mppt) = &models[2] else {
+            panic!("expected the MPPT model");
+        };
+        assert_eq!(mppt.timestamp_period, None);
+        assert_eq!(mppt.modules.len(), 2);
+        assert_eq!(mppt.modules[0].name.as_deref(), Some("MPPT 1"));
+        assert_eq!(mppt.modules[0].dc_current, Some(5.5));
+        assert_eq!(mppt.modules[0].dc_energy, Some(1000.0));
+        assert_eq!(mppt.modules[0].timestamp, None);
+        assert_eq!(mppt.modules[1].dc_current, None);
+        assert_eq!(mppt.modules[1].dc_voltage, Some(440.0));
+        assert_eq!(models[2].id(), Mppt::ID);
+
+        let Model::DerAcMeasurement(der) = &models[3] else {
+            panic!("expected the DER AC measurement model");
+        };
+        assert_eq!(models[3].id(), DerAcMeasurement::ID);
+        assert_eq!(der.power, Some(5000.0));
+        assert_eq!(der.reactive_power, None);
+        assert_eq!(der.power_factor, Some(0.98));
+        assert_eq!(der.current, Some(123.4));
+        assert_eq!(der.phase_voltage, Some(230.0));
+        assert_eq!(der.frequency, Some(50.01));
+        assert_eq!(der.energy_injected, Some(123456.0));
+        assert_eq!(der.energy_absorbed, None);
+        assert_eq!(der.ambient_temperature, None);
+        assert_eq!(der.cabinet_temperature, Some(45.0));
+        assert_eq!(der.phases[0].power, Some(1700.0));
+        assert_eq!(der.phases[2].current, None);
+        assert_eq!(der.phases[2].line_voltage, None);
+
+        assert_eq!(
+            models[8],
+            Model::Other {
+                id: 64001,
+                data: vec![0x1234, 0x5678]
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_der_settings_models() {
+        let mut client = device();
+        let device = Device::discover(&mut client, 1).unwrap();
+        let read = |client: &mut _, id| {
+            device
+                .read_model(client, 1, device.model(id).unwrap())
+                .unwrap()
+        };
+
+        let Model::DerCapacity(capacity) = read(&mut client, DerCapacity::ID) else {
+            panic!("expected the DER capacity model");
+        };
+        assert_eq!(capacity.ratings.power, Some(5000.0));
+        assert_eq!(capacity.ratings.power_over_excited_pf, Some(0.9));
+        assert_eq!(capacity.ratings.charge_rate, None);
+        assert_eq!(capacity.ratings.nominal_voltage, Some(230.0));
+        assert_eq!(capacity.ratings.current, Some(25.0));
+        assert_eq!(capacity.ratings.min_pf_under_excited, Some(0.8));
+        assert_eq!(capacity.settings.power, Some(4000.0));
+        assert_eq!(capacity.settings.power_over_excited, None);
+        assert_eq!(capacity.settings.max_voltage, Some(253.0));
+        assert_eq!(capacity.settings.min_pf_over_excited, Some(0.85));
+        assert_eq!(capacity.reactive_susceptance, None);
+        assert_eq!(capacity.normal_category, Some(1));
+        assert_eq!(capacity.control_modes, Some(0x3f));
+        assert_eq!(capacity.island_categories, None);
+
+        let Model::DerEnterService(enter_service) = read(&mut client, DerEnterService::ID) else {
+            panic!("expected the DER enter service model");
+        };
+        assert_eq!(
+            enter_service,
+            DerEnterService {
+                enabled: Some(true),
+                voltage_high: Some(105.0),
+                voltage_low: Some(91.7),
+                frequency_high: Some(50.1),
+                frequency_low: Some(47.5),
+                delay: Some(300),
+                random_delay: None,
+                ramp_time: Some(300),
+                delay_remaining: Some(0),
+            }
+        );
+
+        let Model::DerAcControls(controls) = read(&mut client, DerAcControls::ID) else {
+            panic!("expected the DER AC controls model");
+        };
+        assert_eq!(
+            controls.pf_injection,
+            DerPowerFactor {
+                enabled: Some(true),
+                power_factor: Some(0.95),
+                excitation: Some(1),
+            }
+        );
+        assert_eq!(controls.pf_absorption.enabled, Some(false));
+        assert_eq!(controls.pf_absorption.power_factor, Some(0.9));
+        assert_eq!(controls.power_limit, Some(80.0));
+        assert_eq!(controls.power_setpoint_mode, Some(1));
+        assert_eq!(controls.power_setpoint, None);
+        assert_eq!(controls.power_setpoint_percent, None);
+        assert_eq!(controls.reactive_power_setpoint_enabled, Some(false));
+        assert_eq!(controls.reactive_power_setpoint, Some(-500.0));
+        assert_eq!(controls.anti_islanding_enabled, Some(true));
+
+        let Model::DerVoltVar(volt_var) = read(&mut client, DerVoltVar::ID) else {
+            panic!("expected the DER volt-var model");
+        };
+        assert_eq!(volt_var.enabled, Some(true));
+        assert_eq!(volt_var.revert_time, None);
+        assert_eq!(volt_var.curves.len(), 2);
+        let curve = &volt_var.curves[0];
+        assert_eq!(curve.reference_voltage, Some(100.0));
+        assert_eq!(curve.auto_reference_voltage, None);
+        assert_eq!(curve.response_time, Some(5.0));
+        assert_eq!(curve.read_only, Some(true));
+        let points: Vec<_> = curve
+            .points
+            .iter()
+            .map(|point| (point.voltage, point.reactive_power))
+            .collect();
+        assert_eq!(
+            points,
+            [
+                (Some(92.0), Some(30.0)),
+                (Some(98.0), Some(0.0)),
+                (Some(102.0), Some(0.0)),
+                (Some(108.0), Some(-30.0))
+            ]
+        );
+        assert_eq!(
+            volt_var.curves[1].points,
+            [
+                VoltVarPoint {
+                    voltage: Some(95.0),
+                    reactive_power: Some(20.0)
+                },
+                VoltVarPoint {
+                    voltage: Some(105.0),
+                    reactive_power: Some(-20.0)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_models() {
+        assert_eq!(Model::decode(103, &[0; 49]), None);
+        assert!(Model::decode(103, &[0; 50]).is_some());
+
+        // 2 curves of 1 point need 13 + 2 * 11 registers
+        let mut volt_var = [0; 35];
+        volt_var[3..5].copy_from_slice(&[1, 2]);
+        assert!(Model::decode(DerVoltVar::ID, &volt_var).is_some());
+        assert_eq!(Model::decode(DerVoltVar::ID, &volt_var[..34]), None);
+        assert_eq!(Model::decode(DerVoltVar::ID, &[0; 12]), None);
+    }
+
+    #[test]
+    fn keeps_other_models_raw() {
+        assert_eq!(
+            Model::decode(707, &[1, 2]),
+            Some(Model::Other {
+                id: 707,
+                data: vec![1, 2]
+            })
+        );
+    }
+}
@@ -0,0 +1,689 @@
+use std::{string::String, vec::Vec};
+
+/// Points of a model, with the "not implemented" values of the SunSpec types as `None`
+struct Points<'a> {
+    data: &'a [u16],
+}
+
+impl Points<'_> {
+    fn uint16(&self, offset: usize) -> Option<u16> {
+        Some(self.data[offset]).filter(|value| *value != 0xffff)
+    }
+    fn int16(&self, offset: usize) -> Option<i16> {
+        Some(self.data[offset] as i16).filter(|value| *value != i16::MIN)
+    }
+    fn raw32(&self, offset: usize) -> u32 {
+        (self.data[offset] as u32) << 16 | self.data[offset + 1] as u32
+    }
+    fn uint32(&self, offset: usize) -> Option<u32> {
+        Some(self.raw32(offset)).filter(|value| *value != 0xffff_ffff)
+    }
+    fn int32(&self, offset: usize) -> Option<i32> {
+        Some(self.raw32(offset) as i32).filter(|value| *value != i32::MIN)
+    }
+    fn acc32(&self, offset: usize) -> Option<u32> {
+        Some(self.raw32(offset)).filter(|value| *value != 0)
+    }
+    fn acc64(&self, offset: usize) -> Option<u64> {
+        let value = (self.raw32(offset) as u64) << 32 | self.raw32(offset + 2) as u64;
+        Some(value).filter(|value| *value != 0)
+    }
+    fn sunssf(&self, offset: usize) -> Option<i16> {
+        self.int16(offset)
+    }
+    /// Enum of the enable and read-only points, true when 1
+    fn flag(&self, offset: usize) -> Option<bool> {
+        self.uint16(offset).map(|value| value == 1)
+    }
+
+    /// ASCII string of `len` registers, trimmed of null and space padding
+    fn string(&self, offset: usize, len: usize) -> Option<String> {
+        let bytes: Vec<u8> = self.data[offset..offset + len]
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect();
+        let end = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(bytes.len());
+        let s = String::from_utf8_lossy(&bytes[..end]);
+        let s = s.trim_end();
+        (!s.is_empty()).then(|| s.into())
+    }
+
+    /// `value * 10^sf`, `None` if either isn't implemented
+    fn scaled(&self, value: Option<impl Into<f64>>, sf: Option<i16>) -> Option<f64> {
+        let (value, sf) = (value?.into(), sf? as i32);
+        // Dividing keeps values like 2302 * 10^-1 at the closest float to 230.2
+        if sf < 0 {
+            Some(value / 10_f64.powi(-sf))
+        } else {
+            Some(value * 10_f64.powi(sf))
+        }
+    }
+}
+
+/// Model 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Common {
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub options: Option<String>,
+    pub version: Option<String>,
+    pub serial_number: Option<String>,
+    pub device_address: Option<u16>,
+}
+
+impl Common {
+    pub const ID: u16 = 1;
+
+    fn decode(points: &Points<'_>) -> Self {
+        Self {
+            manufacturer: points.string(0, 16),
+            model: points.string(16, 16),
+            options: points.string(32, 8),
+            version: points.string(40, 8),
+            serial_number: points.string(48, 16),
+            device_address: points.uint16(64),
+        }
+    }
+}
+
+/// Models 101, 102 and 103 of single, split and three phase inverters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inverter {
+    pub phases: u8,
+    /// AC current in A
+    pub current: Option<f64>,
+    pub phase_currents: [Option<f64>; 3],
+    /// Line to line voltages AB, BC and CA in V
+    pub line_voltages: [Option<f64>; 3],
+    /// Line to neutral voltages in V
+    pub phase_voltages: [Option<f64>; 3],
+    /// AC power in W
+    pub power: Option<f64>,
+    /// Line frequency in Hz
+    pub frequency: Option<f64>,
+    /// Apparent power in VA
+    pub apparent_power: Option<f64>,
+    /// Reactive power in var
+    pub reactive_power: Option<f64>,
+    /// Power factor in percent
+    pub power_factor: Option<f64>,
+    /// AC energy in Wh
+    pub energy: Option<f64>,
+    pub dc_current: Option<f64>,
+    pub dc_voltage: Option<f64>,
+    pub dc_power: Option<f64>,
+    /// Cabinet, heat sink, transformer and other temperatures in °C
+    pub temperatures: [Option<f64>; 4],
+    pub state: Option<u16>,
+    pub vendor_state: Option<u16>,
+    pub events: Option<u32>,
+}
+
+impl Inverter {
+    pub const IDS: [u16; 3] = [101, 102, 103];
+    const LENGTH: usize = 50;
+
+    fn decode(phases: u8, p: &Points<'_>) -> Self {
+        let sf = |offset| p.sunssf(offset);
+        let (a_sf, v_sf, tmp_sf) = (sf(4), sf(11), sf(35));
+        Self {
+            phases,
+            current: p.scaled(p.uint16(0), a_sf),
+            phase_currents: [1, 2, 3].map(|offset| p.scaled(p.uint16(offset), a_sf)),
+            line_voltages: [5, 6, 7].map(|offset| p.scaled(p.uint16(offset), v_sf)),
+            phase_voltages: [8, 9, 10].map(|offset| p.scaled(p.uint16(offset), v_sf)),
+            power: p.scaled(p.int16(12), sf(13)),
+            frequency: p.scaled(p.uint16(14), sf(15)),
+            apparent_power: p.scaled(p.int16(16), sf(17)),
+            reactive_power: p.scaled(p.int16(18), sf(19)),
+            power_factor: p.scaled(p.int16(20), sf(21)),
+            energy: p.scaled(p.acc32(22), sf(24)),
+            dc_current: p.scaled(p.uint16(25), sf(26)),
+            dc_voltage: p.scaled(p.uint16(27), sf(28)),
+            dc_power: p.scaled(p.int16(29), sf(30)),
+            temperatures: [31, 32, 33, 34].map(|offset| p.scaled(p.int16(offset), tmp_sf)),
+            state: p.uint16(36),
+            vendor_state: p.uint16(37),
+            events: p.uint32(38),
+        }
+    }
+}
+
+/// Model 160 of inverters with multiple MPPT inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mppt {
+    pub events: Option<u32>,
+    /// Period of the timestamps in s
+    pub timestamp_period: Option<u16>,
+    pub modules: Vec<MpptModule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MpptModule {
+    pub id: Option<u16>,
+    pub name: Option<String>,
+    pub dc_current: Option<f64>,
+    pub dc_voltage: Option<f64>,
+    pub dc_power: Option<f64>,
+    /// Lifetime energy in Wh
+    pub dc_energy: Option<f64>,
+    pub timestamp: Option<u32>,
+    pub temperature: Option<i16>,
+    pub state: Option<u16>,
+    pub events: Option<u32>,
+}
+
+impl Mppt {
+    pub const ID: u16 = 160;
+    const FIXED_LENGTH: usize = 8;
+    const MODULE_LENGTH: usize = 20;
+
+    fn decode(p: &Points<'_>) -> Self {
+        let (a_sf, v_sf, w_sf, wh_sf) = (p.sunssf(0), p.sunssf(1), p.sunssf(2), p.sunssf(3));
+        let count = (p.data.len() - Self::FIXED_LENGTH) / Self::MODULE_LENGTH;
+        let modules = (0..count)
+            .map(|i| {
+                let m = Points {
+                    data: &p.data[Self::FIXED_LENGTH + i * Self::MODULE_LENGTH..],
+                };
+                MpptModule {
+                    id: m.uint16(0),
+                    name: m.string(1, 8),
+                    dc_current: m.scaled(m.uint16(9), a_sf),
+                    dc_voltage: m.scaled(m.uint16(10), v_sf),
+                    dc_power: m.scaled(m.uint16(11), w_sf),
+                    dc_energy: m.scaled(m.acc32(12), wh_sf),
+                    timestamp: m.uint32(14),
+                    temperature: m.int16(16),
+                    state: m.uint16(17),
+                    events: m.uint32(18),
+                }
+            })
+            .collect();
+
+        Self {
+            events: p.uint32(4),
+            timestamp_period: p.uint16(7),
+            modules,
+        }
+    }
+}
+
+/// Model 701 of the AC measurements of a DER (distributed energy resource)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerAcMeasurement {
+    pub ac_type: Option<u16>,
+    pub state: Option<u16>,
+    pub inverter_state: Option<u16>,
+    pub connection_state: Option<u16>,
+    pub alarms: Option<u32>,
+    /// Active power in W
+    pub power: Option<f64>,
+    pub apparent_power: Option<f64>,
+    pub reactive_power: Option<f64>,
+    pub power_factor: Option<f64>,
+    pub current: Option<f64>,
+    /// Average line to line voltage
+    pub line_voltage: Option<f64>,
+    /// Average line to neutral voltage
+    pub phase_voltage: Option<f64>,
+    pub frequency: Option<f64>,
+    /// Energy injected to the grid in Wh
+    pub energy_injected: Option<f64>,
+    /// Energy absorbed from the grid in Wh
+    pub energy_absorbed: Option<f64>,
+    pub ambient_temperature: Option<f64>,
+    pub cabinet_temperature: Option<f64>,
+    pub phases: [DerPhase; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerPhase {
+    pub power: Option<f64>,
+    pub apparent_power: Option<f64>,
+    pub reactive_power: Option<f64>,
+    pub power_factor: Option<f64>,
+    pub current: Option<f64>,
+    /// Voltage to the next phase
+    pub line_voltage: Option<f64>,
+    pub phase_voltage: Option<f64>,
+}
+
+impl DerAcMeasurement {
+    pub const ID: u16 = 701;
+    const LENGTH: usize = 153;
+    const PHASES_OFFSET: usize = 39;
+    const PHASE_LENGTH: usize = 23;
+
+    fn decode(p: &Points<'_>) -> Self {
+        let a_sf = p.sunssf(111);
+        let v_sf = p.sunssf(112);
+        let hz_sf = p.sunssf(113);
+        let w_sf = p.sunssf(114);
+        let pf_sf = p.sunssf(115);
+        let va_sf = p.sunssf(116);
+        let var_sf = p.sunssf(117);
+        let wh_sf = p.sunssf(118);
+        let tmp_sf = p.sunssf(120);
+
+        let phases = [0, 1, 2].map(|i| {
+            let offset = Self::PHASES_OFFSET + i * Self::PHASE_LENGTH;
+            DerPhase {
+                power: p.scaled(p.int16(offset), w_sf),
+                apparent_power: p.scaled(p.int16(offset + 1), va_sf),
+                reactive_power: p.scaled(p.int16(offset + 2), var_sf),
+                power_factor: p.scaled(p.int16(offset + 3), pf_sf),
+                current: p.scaled(p.int16(offset + 4), a_sf),
+                line_voltage: p.scaled(p.uint16(offset + 5), v_sf),
+                phase_voltage: p.scaled(p.uint16(offset + 6), v_sf),
+            }
+        });
+
+        Self {
+            ac_type: p.uint16(0),
+            state: p.uint16(1),
+            inverter_state: p.uint16(2),
+            connection_state: p.uint16(3),
+            alarms: p.uint32(4),
+            power: p.scaled(p.int16(8), w_sf),
+            apparent_power: p.scaled(p.int16(9), va_sf),
+            reactive_power: p.scaled(p.int16(10), var_sf),
+            power_factor: p.scaled(p.int16(11), pf_sf),
+            current: p.scaled(p.int16(12), a_sf),
+            line_voltage: p.scaled(p.uint16(13), v_sf),
+            phase_voltage: p.scaled(p.uint16(14), v_sf),
+            frequency: p.scaled(p.uint32(15), hz_sf),
+            energy_injected: p.scaled(p.acc64(17).map(|value| value as f64), wh_sf),
+            energy_absorbed: p.scaled(p.acc64(21).map(|value| value as f64), wh_sf),
+            ambient_temperature: p.scaled(p.int16(33), tmp_sf),
+            cabinet_temperature: p.scaled(p.int16(34), tmp_sf),
+            phases,
+        }
+    }
+}
+
+/// Ratings or settings of model 702
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerLimits {
+    /// Active power in W
+    pub power: Option<f64>,
+    /// Active power when over-excited
+    pub power_over_excited: Option<f64>,
+    /// Power factor at `power_over_excited`
+    pub power_over_excited_pf: Option<f64>,
+    /// Active power when under-excited
+    pub power_under_excited: Option<f64>,
+    /// Power factor at `power_under_excited`
+    pub power_under_excited_pf: Option<f64>,
+    /// Apparent power in VA
+    pub apparent_power: Option<f64>,
+    /// Injected reactive power in var
+    pub reactive_power_injected: Option<f64>,
+    /// Absorbed reactive power in var
+    pub reactive_power_absorbed: Option<f64>,
+    /// Charge rate in W
+    pub charge_rate: Option<f64>,
+    /// Discharge rate in W
+    pub discharge_rate: Option<f64>,
+    /// Charge rate in VA
+    pub apparent_charge_rate: Option<f64>,
+    /// Discharge rate in VA
+    pub apparent_discharge_rate: Option<f64>,
+    /// Nominal AC voltage in V
+    pub nominal_voltage: Option<f64>,
+    pub max_voltage: Option<f64>,
+    pub min_voltage: Option<f64>,
+    /// AC current in A
+    pub current: Option<f64>,
+    /// Minimum power factor when over-excited
+    pub min_pf_over_excited: Option<f64>,
+    /// Minimum power factor when under-excited
+    pub min_pf_under_excited: Option<f64>,
+}
+
+impl DerLimits {
+    fn decode(p: &Points<'_>, offset: usize, sfs: &[Option<i16>; 6]) -> Self {
+        let [w_sf, pf_sf, va_sf, var_sf, v_sf, a_sf] = *sfs;
+        let scaled = |i: usize, sf| p.scaled(p.uint16(offset + i), sf);
+        Self {
+            power: scaled(0, w_sf),
+            power_over_excited: scaled(1, w_sf),
+            power_over_excited_pf: scaled(2, pf_sf),
+            power_under_excited: scaled(3, w_sf),
+            power_under_excited_pf: scaled(4, pf_sf),
+            apparent_power: scaled(5, va_sf),
+            reactive_power_injected: scaled(6, var_sf),
+            reactive_power_absorbed: scaled(7, var_sf),
+            charge_rate: scaled(8, w_sf),
+            discharge_rate: scaled(9, w_sf),
+            apparent_charge_rate: scaled(10, va_sf),
+            apparent_discharge_rate: scaled(11, va_sf),
+            nominal_voltage: scaled(12, v_sf),
+            max_voltage: scaled(13, v_sf),
+            min_voltage: scaled(14, v_sf),
+            current: scaled(15, a_sf),
+            min_pf_over_excited: scaled(16, pf_sf),
+            min_pf_under_excited: scaled(17, pf_sf),
+        }
+    }
+}
+
+/// Model 702 of the capacity of a DER: its nameplate ratings and the settings limiting
+/// them
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerCapacity {
+    pub ratings: DerLimits,
+    pub settings: DerLimits,
+    /// Reactive susceptance rating in S
+    pub reactive_susceptance: Option<f64>,
+    /// Normal operating performance category
+    pub normal_category: Option<u16>,
+    /// Abnormal operating performance category
+    pub abnormal_category: Option<u16>,
+    /// Bitfield of the supported control modes
+    pub control_modes: Option<u32>,
+    /// Bitfield of the supported intentional island categories
+    pub island_categories_rating: Option<u16>,
+    /// Bitfield of the enabled intentional island categories
+    pub island_categories: Option<u16>,
+}
+
+impl DerCapacity {
+    pub const ID: u16 = 702;
+    const LENGTH: usize = 50;
+    const SETTINGS_OFFSET: usize = 24;
+
+    fn decode(p: &Points<'_>) -> Self {
+        let sfs = [43, 44, 45, 46, 47, 48].map(|offset| p.sunssf(offset));
+        Self {
+            ratings: DerLimits::decode(p, 0, &sfs),
+            settings: DerLimits::decode(p, Self::SETTINGS_OFFSET, &sfs),
+            reactive_susceptance: p.scaled(p.uint16(18), p.sunssf(49)),
+            normal_category: p.uint16(19),
+            abnormal_category: p.uint16(20),
+            control_modes: p.uint32(21),
+            island_categories_rating: p.uint16(23),
+            island_categories: p.uint16(42),
+        }
+    }
+}
+
+/// Model 703 of the conditions for a DER to enter service
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerEnterService {
+    pub enabled: Option<bool>,
+    /// Voltage range in percent of the nominal voltage
+    pub voltage_high: Option<f64>,
+    pub voltage_low: Option<f64>,
+    /// Frequency range in Hz
+    pub frequency_high: Option<f64>,
+    pub frequency_low: Option<f64>,
+    /// Delay in s before entering service
+    pub delay: Option<u32>,
+    /// Maximum random delay in s added to `delay`
+    pub random_delay: Option<u32>,
+    /// Time in s to ramp up to full power
+    pub ramp_time: Option<u32>,
+    /// Remaining delay in s
+    pub delay_remaining: Option<u32>,
+}
+
+impl DerEnterService {
+    pub const ID: u16 = 703;
+    const LENGTH: usize = 17;
+
+    fn decode(p: &Points<'_>) -> Self {
+        let (v_sf, hz_sf) = (p.sunssf(15), p.sunssf(16));
+        Self {
+            enabled: p.flag(0),
+            voltage_high: p.scaled(p.uint16(1), v_sf),
+            voltage_low: p.scaled(p.uint16(2), v_sf),
+            frequency_high: p.scaled(p.uint32(3), hz_sf),
+            frequency_low: p.scaled(p.uint32(5), hz_sf),
+            delay: p.uint32(7),
+            random_delay: p.uint32(9),
+            ramp_time: p.uint32(11),
+            delay_remaining: p.uint32(13),
+        }
+    }
+}
+
+/// Constant power factor setting of model 704
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerPowerFactor {
+    pub enabled: Option<bool>,
+    pub power_factor: Option<f64>,
+    /// 0 when over-excited, 1 when under-excited
+    pub excitation: Option<u16>,
+}
+
+/// Model 704 of the AC controls of a DER, without their revert settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerAcControls {
+    /// Power factor while injecting active power
+    pub pf_injection: DerPowerFactor,
+    /// Power factor while absorbing active power
+    pub pf_absorption: DerPowerFactor,
+    pub power_limit_enabled: Option<bool>,
+    /// Active power limit in percent of the max power setting
+    pub power_limit: Option<f64>,
+    pub power_setpoint_enabled: Option<bool>,
+    /// 0 when the setpoint is in W, 1 when in percent
+    pub power_setpoint_mode: Option<u16>,
+    /// Active power setpoint in W
+    pub power_setpoint: Option<f64>,
+    /// Active power setpoint in percent of the max power setting
+    pub power_setpoint_percent: Option<f64>,
+    pub reactive_power_setpoint_enabled: Option<bool>,
+    pub reactive_power_setpoint_mode: Option<u16>,
+    /// 0 when active power has priority, 1 when reactive power has
+    pub reactive_power_priority: Option<u16>,
+    /// Reactive power setpoint in var
+    pub reactive_power_setpoint: Option<f64>,
+    /// Reactive power setpoint in percent of the mode's reference
+    pub reactive_power_setpoint_percent: Option<f64>,
+    pub anti_islanding_enabled: Option<bool>,
+}
+
+impl DerAcControls {
+    pub const ID: u16 = 704;
+    const LENGTH: usize = 65;
+    const PF_OFFSET: usize = 57;
+
+    fn decode(p: &Points<'_>) -> Self {
+        let pf_sf = p.sunssf(51);
+        let power_factor = |enabled: usize, offset: usize| DerPowerFactor {
+            enabled: p.flag(enabled),
+            power_factor: p.scaled(p.uint16(offset), pf_sf),
+            excitation: p.uint16(offset + 1),
+        };
+        Self {
+            pf_injection: power_factor(0, Self::PF_OFFSET),
+            pf_absorption: power_factor(6, Self::PF_OFFSET + 4),
+            power_limit_enabled: p.flag(12),
+            power_limit: p.scaled(p.uint16(13), p.sunssf(52)),
+            power_setpoint_enabled: p.flag(20),
+            power_setpoint_mode: p.uint16(21),
+            power_setpoint: p.scaled(p.int32(22), p.sunssf(53)),
+            power_setpoint_percent: p.scaled(p.int16(26), p.sunssf(54)),
+            reactive_power_setpoint_enabled: p.flag(33),
+            reactive_power_setpoint_mode: p.uint16(34),
+            reactive_power_priority: p.uint16(35),
+            reactive_power_setpoint: p.scaled(p.int32(36), p.sunssf(55)),
+            reactive_power_setpoint_percent: p.scaled(p.int16(40), p.sunssf(56)),
+            anti_islanding_enabled: p.flag(50),
+        }
+    }
+}
+
+/// Model 705 of the volt-var curves of a DER
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerVoltVar {
+    pub enabled: Option<bool>,
+    /// Curve requested to be adopted
+    pub adopt_curve_request: Option<u16>,
+    /// Result of the last adoption
+    pub adopt_curve_result: Option<u16>,
+    /// Time in s after which the revert curve is adopted
+    pub revert_time: Option<u32>,
+    pub revert_remaining: Option<u32>,
+    pub revert_curve: Option<u16>,
+    pub curves: Vec<VoltVarCurve>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoltVarCurve {
+    /// What the reactive power of the points is in percent of
+    pub dependent_reference: Option<u16>,
+    /// 0 when active power has priority, 1 when reactive power has
+    pub priority: Option<u16>,
+    /// Reference voltage in percent of the nominal voltage
+    pub reference_voltage: Option<f64>,
+    /// Reference voltage adjusted automatically
+    pub auto_reference_voltage: Option<f64>,
+    pub auto_reference_enabled: Option<bool>,
+    /// Time constant in s of the automatic adjustment
+    pub auto_reference_time: Option<u16>,
+    /// Open loop response time in s
+    pub response_time: Option<f64>,
+    pub read_only: Option<bool>,
+    /// Active points of the curve
+    pub points: Vec<VoltVarPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoltVarPoint {
+    /// Voltage in percent of the nominal voltage
+    pub voltage: Option<f64>,
+    /// Reactive power in percent of the dependent reference
+    pub reactive_power: Option<f64>,
+}
+
+impl DerVoltVar {
+    pub const ID: u16 = 705;
+    const FIXED_LENGTH: usize = 13;
+    const CURVE_FIXED_LENGTH: usize = 9;
+
+    /// `None` if the curves don't fit the data
+    fn decode(p: &Points<'_>) -> Option<Self> {
+        let point_count = p.uint16(3).unwrap_or(0) as usize;
+        let curve_count = p.uint16(4).unwrap_or(0) as usize;
+        let curve_length = Self::CURVE_FIXED_LENGTH + 2 * point_count;
+        if p.data.len() < Self::FIXED_LENGTH + curve_count * curve_length {
+            return None;
+        }
+        let (v_sf, var_sf, time_sf) = (p.sunssf(10), p.sunssf(11), p.sunssf(12));
+
+        let curves = (0..curve_count)
+            .map(|i| {
+                let c = Points {
+                    data: &p.data[Self::FIXED_LENGTH + i * curve_length..],
+                };
+                let active = c
+                    .uint16(0)
+                    .map_or(point_count, |active| (active as usize).min(point_count));
+                let points = (0..active)
+                    .map(|j| {
+                        let offset = Self::CURVE_FIXED_LENGTH + 2 * j;
+                        VoltVarPoint {
+                            voltage: c.scaled(c.uint16(offset), v_sf),
+                            reactive_power: c.scaled(c.int16(offset + 1), var_sf),
+                        }
+                    })
+                    .collect();
+                VoltVarCurve {
+                    dependent_reference: c.uint16(1),
+                    priority: c.uint16(2),
+                    reference_voltage: c.scaled(c.uint16(3), v_sf),
+                    auto_reference_voltage: c.scaled(c.uint16(4), v_sf),
+                    auto_reference_enabled: c.flag(5),
+                    auto_reference_time: c.uint16(6),
+                    response_time: c.scaled(c.uint16(7), time_sf),
+                    read_only: c.flag(8),
+                    points,
+                }
+            })
+            .collect();
+
+        Some(Self {
+            enabled: p.flag(0),
+            adopt_curve_request: p.uint16(1),
+            adopt_curve_result: p.uint16(2),
+            revert_time: p.uint32(5),
+            revert_remaining: p.uint32(7),
+            revert_curve: p.uint16(9),
+            curves,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Model {
+    Common(Common),
+    Inverter(Inverter),
+    Mppt(Mppt),
+    DerAcMeasurement(DerAcMeasurement),
+    DerCapacity(DerCapacity),
+    DerEnterService(DerEnterService),
+    DerAcControls(DerAcControls),
+    DerVoltVar(DerVoltVar),
+    /// Model without a decoder, like vendor models, with its registers after the id and
+    /// length
+    Other {
+        id: u16,
+        data: Vec<u16>,
+    },
+}
+
+impl Model {
+    /// Decodes the registers of a model after its id and length. `None` if they are
+    /// too few for the model id.
+    pub fn decode(id: u16, data: &[u16]) -> Option<Model> {
+        let points = Points { data };
+        let model = match id {
+            Common::ID if data.len() >= 65 => Model::Common(Common::decode(&points)),
+            101..=103 if data.len() >= Inverter::LENGTH => {
+                Model::Inverter(Inverter::decode((id - 100) as u8, &points))
+            }
+            Mppt::ID if data.len() >= Mppt::FIXED_LENGTH => Model::Mppt(Mppt::decode(&points)),
+            DerAcMeasurement::ID if data.len() >= DerAcMeasurement::LENGTH => {
+                Model::DerAcMeasurement(DerAcMeasurement::decode(&points))
+            }
+            DerCapacity::ID if data.len() >= DerCapacity::LENGTH => {
+                Model::DerCapacity(DerCapacity::decode(&points))
+            }
+            DerEnterService::ID if data.len() >= DerEnterService::LENGTH => {
+                Model::DerEnterService(DerEnterService::decode(&points))
+            }
+            DerAcControls::ID if data.len() >= DerAcControls::LENGTH => {
+                Model::DerAcControls(DerAcControls::decode(&points))
+            }
+            DerVoltVar::ID if data.len() >= DerVoltVar::FIXED_LENGTH => {
+                Model::DerVoltVar(DerVoltVar::decode(&points)?)
+            }
+            Common::ID | 101..=103 | Mppt::ID | 701..=705 => return None,
+            _ => Model::Other {
+                id,
+                data: data.into(),
+            },
+        };
+        Some(model)
+    }
+
+    pub fn id(&self) -> u16 {
+        match self {
+            Model::Common(_) => Common::ID,
+            Model::Inverter(inverter) => 100 + inverter.phases as u16,
+            Model::Mppt(_) => Mppt::ID,
+            Model::DerAcMeasurement(_) => DerAcMeasurement::ID,
+            Model::DerCapacity(_) => DerCapacity::ID,
+            Model::DerEnterService(_) => DerEnterService::ID,
+            Model::DerAcControls(_) => DerAcControls::ID,
+            Model::DerVoltVar(_) => DerVoltVar::ID,
+            Model::Other { id, .. } => *id,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+pub mod record;
+pub mod replay;
+#[cfg(all(feature = "serial", target_os = "linux"))]
+pub mod serial;
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+/// Blocking byte stream the clients and servers run on.
+///
+/// Reads have to fail with [`io::ErrorKind::TimedOut`] or [`io::ErrorKind::WouldBlock`]
+/// once the read timeout has passed without receiving anything.
+pub trait Transport: Read + Write {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Discards what was received but not read yet, like a response that came after
+    /// its timeout. Does nothing by default.
+    fn clear_input(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for TcpStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(all(feature = "serial", target_os = "linux"))]
+impl Transport for serial::SerialPort {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        serial::SerialPort::set_read_timeout(self, timeout);
+        Ok(())
+    }
+
+    fn clear_input(&mut self) -> io::Result<()> {
+        self.clear()
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        (**self).set_read_timeout(timeout)
+    }
+
+    fn clear_input(&mut self) -> io::Result<()> {
+        (**self).clear_input()
+    }
+}
@@ -0,0 +1,555 @@
+//! Recording of the ADUs going through a transport, to replay them later with
+//! [`super::replay::Replay`].
+//!
+//! Recordings are text, a header then an ADU per line with the time since the start of
+//! the recording in seconds, its direction, its bytes in hex and its decoded form:
+//!
+//! ```text
+//! # modbus recording framing=tcp side=client
+//! 0.000000 tx 000100000006010300000002 tx=1 unit=1 FC03 ReadHoldingRegisters addr=0 qty=2
+//! 0.003187 rx 00010000000701030400070008 tx=1 unit=1 FC03 ReadHoldingRegisters qty=2 values=[7, 8]
+//! ```
+
+use std::{
+    fmt, format, fs,
+    io::{self, ErrorKind, Read, Write},
+    path::Path,
+    str::FromStr,
+    string::String,
+    time::{Duration, Instant},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    adu::{Framing, ascii, rtu, tcp},
+    pdu::write_hex,
+};
+
+use super::Transport;
+
+const HEADER: &str = "# modbus recording";
+
+/// Direction of an ADU, from the point of view of the recorded transport
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::Sent => "tx",
+            Direction::Received => "rx",
+        })
+    }
+}
+
+impl FromStr for Direction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tx" => Ok(Direction::Sent),
+            "rx" => Ok(Direction::Received),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Side of the link the recorded transport is on, which tells requests from responses
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Side {
+    Client,
+    Server,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Side::Client => "client",
+            Side::Server => "server",
+        })
+    }
+}
+
+impl Side {
+    /// Whether the ADUs going that way are requests
+    pub fn is_request(&self, direction: Direction) -> bool {
+        match self {
+            Side::Client => direction == Direction::Sent,
+            Side::Server => direction == Direction::Received,
+        }
+    }
+}
+
+impl FromStr for Side {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client" => Ok(Side::Client),
+            "server" => Ok(Side::Server),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Time since the start of the recording when its first byte went through
+    pub at: Duration,
+    pub direction: Direction,
+    pub frame: Vec<u8>,
+}
+
+/// A request and the response to it, if there was one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Exchange<'a> {
+    pub request: &'a Record,
+    pub response: Option<&'a Record>,
+}
+
+impl Exchange<'_> {
+    pub fn latency(&self) -> Option<Duration> {
+        self.response
+            .map(|response| response.at.saturating_sub(self.request.at))
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    /// The first line isn't `# modbus recording framing=<framing> side=<side>`
+    InvalidHeader,
+    /// The line, counted from 1, isn't `<seconds> <tx|rx> <hex>`
+    InvalidLine(usize),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io(err) => write!(f, "{err}"),
+            RecordingError::InvalidHeader => write!(f, "invalid recording header"),
+            RecordingError::InvalidLine(line) => write!(f, "invalid line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    pub framing: Framing,
+    pub side: Side,
+    pub records: Vec<Record>,
+}
+
+impl Recording {
+    pub fn parse(s: &str) -> Result<Self, RecordingError> {
+        let mut lines = s.lines().enumerate();
+        let (framing, side) = lines
+            .next()
+            .and_then(|(_, line)| parse_header(line))
+            .ok_or(RecordingError::InvalidHeader)?;
+
+        let mut records = vec![];
+        for (i, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let record = parse_record(line).ok_or(RecordingError::InvalidLine(i + 1))?;
+            records.push(record);
+        }
+
+        Ok(Self {
+            framing,
+            side,
+            records,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let s = fs::read_to_string(path).map_err(RecordingError::Io)?;
+        Self::parse(&s)
+    }
+
+    /// Pairs the requests with their responses: the one with the same transaction id
+    /// for TCP, the one before the next request otherwise
+    pub fn exchanges(&self) -> Vec<Exchange<'_>> {
+        self.records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| self.side.is_request(record.direction))
+            .map(|(i, request)| {
+                let mut next = self.records[i + 1..].iter();
+                let response = match self.framing {
+                    Framing::Tcp => next.find(|record| {
+                        !self.side.is_request(record.direction)
+                            && record.frame.get(..2) == request.frame.get(..2)
+                    }),
+                    Framing::Rtu | Framing::Ascii => next
+                        .next()
+                        .filter(|record| !self.side.is_request(record.direction)),
+                };
+                Exchange { request, response }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER} framing={} side={}", self.framing, self.side)?;
+        for record in &self.records {
+            let is_request = self.side.is_request(record.direction);
+            writeln!(f, "{}", Line::new(self.framing, is_request, record))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_header(line: &str) -> Option<(Framing, Side)> {
+    let mut framing = None;
+    let mut side = None;
+    for field in line.strip_prefix(HEADER)?.split_whitespace() {
+        match field.split_once('=')? {
+            ("framing", value) => framing = Some(value.parse().ok()?),
+            ("side", value) => side = Some(value.parse().ok()?),
+            _ => {}
+        }
+    }
+    Some((framing?, side?))
+}
+
+fn parse_record(line: &str) -> Option<Record> {
+    let mut fields = line.split_whitespace();
+    let secs: f64 = fields.next()?.parse().ok()?;
+    let direction = fields.next()?.parse().ok()?;
+    let hex = fields.next()?;
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let frame = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<_>>()?;
+    Some(Record {
+        at: Duration::try_from_secs_f64(secs).ok()?,
+        direction,
+        frame,
+    })
+}
+
+/// Line of a record in a recording
+struct Line<'a> {
+    framing: Framing,
+    is_request: bool,
+    record: &'a Record,
+}
+
+impl<'a> Line<'a> {
+    fn new(framing: Framing, is_request: bool, record: &'a Record) -> Self {
+        Self {
+            framing,
+            is_request,
+            record,
+        }
+    }
+}
+
+impl fmt::Display for Line<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let record = self.record;
+        write!(f, "{:.6} {} ", record.at.as_secs_f64(), record.direction)?;
+        write_hex(f, &record.frame)?;
+        write!(
+            f,
+            " {}",
+            describe(self.framing, self.is_request, &record.frame)
+        )
+    }
+}
+
+/// Decoded form of a frame, or why it can't be decoded
+pub(crate) fn describe(framing: Framing, is_request: bool, frame: &[u8]) -> String {
+    let mut buf = [0_u8; ascii::MAX_ADU_SIZE];
+    let description = match (framing, is_request) {
+        (Framing::Tcp, true) => tcp::request::Request::decode(frame).map(|adu| format!("{adu}")),
+        (Framing::Tcp, false) => tcp::response::Response::decode(frame).map(|adu| format!("{adu}")),
+        (Framing::Rtu, true) => rtu::request::Request::decode(frame).map(|adu| format!("{adu}")),
+        (Framing::Rtu, false) => rtu::response::Response::decode(frame).map(|adu| format!("{adu}")),
+        (Framing::Ascii, true) => {
+            ascii::request::Request::decode(frame, &mut buf).map(|adu| format!("{adu}"))
+        }
+        (Framing::Ascii, false) => {
+            ascii::response::Response::decode(frame, &mut buf).map(|adu| format!("{adu}"))
+        }
+    };
+    description.unwrap_or_else(|err| format!("undecodable: {err}"))
+}
+
+/// Bytes going one way, not recorded yet
+#[derive(Debug)]
+struct Pending {
+    at: Duration,
+    direction: Direction,
+    bytes: Vec<u8>,
+}
+
+/// Transport logging the ADUs going through it to `log`, in the format of
+/// [`Recording`].
+///
+/// The bytes going one way are an ADU when the framing tells their length. Otherwise,
+/// as for RTU, they are one once the other way is taken, a read times out or writes
+/// are flushed. Pending bytes are logged by [`Recorder::finish`] or when dropped.
+#[derive(Debug)]
+pub struct Recorder<T, W: Write> {
+    transport: T,
+    log: W,
+    framing: Framing,
+    side: Side,
+    started: Instant,
+    pending: Option<Pending>,
+}
+
+impl<T: Transport, W: Write> Recorder<T, W> {
+    /// Starts the recording, writing its header to `log`
+    pub fn new(transport: T, mut log: W, framing: Framing, side: Side) -> io::Result<Self> {
+        writeln!(log, "{HEADER} framing={framing} side={side}")?;
+        Ok(Self {
+            transport,
+            log,
+            framing,
+            side,
+            started: Instant::now(),
+            pending: None,
+        })
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+}
+
+impl<T, W: Write> Recorder<T, W> {
+    /// Logs the pending bytes and flushes the log
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.record(true)?;
+        self.log.flush()
+    }
+
+    fn push(&mut self, direction: Direction, bytes: &[u8]) -> io::Result<()> {
+        if self
+            .pending
+            .as_ref()
+            .is_some_and(|pending| pending.direction != direction)
+        {
+            self.record(true)?;
+        }
+        let at = self.started.elapsed();
+        self.pending
+            .get_or_insert_with(|| Pending {
+                at,
+                direction,
+                bytes: vec![],
+            })
+            .bytes
+            .extend_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Logs the complete frames of the pending bytes, and what remains with `all`
+    fn record(&mut self, all: bool) -> io::Result<()> {
+        let Some(mut pending) = self.pending.take() else {
+            return Ok(());
+        };
+        loop {
+            let len = match self.framing.frame_len(&pending.bytes) {
+                Some(len) => len,
+                None if all && !pending.bytes.is_empty() => pending.bytes.len(),
+                None => break,
+            };
+            let record = Record {
+                at: pending.at,
+                direction: pending.direction,
+                frame: pending.bytes.drain(..len).collect(),
+            };
+            let is_request = self.side.is_request(record.direction);
+            writeln!(self.log, "{}", Line::new(self.framing, is_request, &record))?;
+        }
+        if !pending.bytes.is_empty() {
+            self.pending = Some(pending);
+        }
+        Ok(())
+    }
+}
+
+impl<T, W: Write> Drop for Recorder<T, W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+impl<T: Transport, W: Write> Read for Recorder<T, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.transport.read(buf) {
+            Ok(0) => {
+                self.record(true)?;
+                Ok(0)
+            }
+            Ok(bytes_read) => {
+                self.push(Direction::Received, &buf[..bytes_read])?;
+                self.record(false)?;
+                Ok(bytes_read)
+            }
+            Err(err) if matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                self.record(true)?;
+                Err(err)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<T: Transport, W: Write> Write for Recorder<T, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes_written = self.transport.write(buf)?;
+        self.push(Direction::Sent, &buf[..bytes_written])?;
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.transport.flush()?;
+        self.record(true)
+    }
+}
+
+impl<T: Transport, W: Write> Transport for Recorder<T, W> {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.transport.set_read_timeout(timeout)
+    }
+
+    fn clear_input(&mut self) -> io::Result<()> {
+        self.transport.clear_input()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+
+    use crate::{
+        adu::Framing,
+        client::{
+            RESPONSE_BUF_SIZE,
+            rtu::{RtuClient, RtuConfig},
+            tcp::{TcpClient, TcpConfig},
+            test::MockTransport,
+        },
+        pdu::request::Request as PduRequest,
+    };
+
+    use super::{Direction, Recorder, Recording, RecordingError, Side};
+
+    #[test]
+    fn records_tcp_exchanges() {
+        let transport = MockTransport::new(&[
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x07, 0x01],
+            &[0x03, 0x04, 0x00, 0x07, 0x00, 0x08],
+        ]);
+        let mut log = vec![];
+        let recorder = Recorder::new(transport, &mut log, Framing::Tcp, Side::Client).unwrap();
+        let mut client = TcpClient::new(recorder, TcpConfig::default());
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+        client
+            .send(1, PduRequest::ReadHoldingRegisters(0, 2), &mut buf)
+            .unwrap();
+        drop(client);
+
+        let log = String::from_utf8(log).unwrap();
+        let recording = Recording::parse(&log).unwrap();
+        assert_eq!(recording.to_string(), log);
+        assert_eq!(
+            (recording.framing, recording.side),
+            (Framing::Tcp, Side::Client)
+        );
+        assert_eq!(recording.records.len(), 2);
+        assert_eq!(recording.records[0].direction, Direction::Sent);
+        assert_eq!(
+            recording.records[0].frame,
+            [
+                0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x02
+            ]
+        );
+        assert_eq!(recording.records[1].direction, Direction::Received);
+        assert_eq!(recording.records[1].frame.len(), 13);
+        assert!(log.contains("tx=1 unit=1 FC03 ReadHoldingRegisters addr=0 qty=2"));
+
+        let exchanges = recording.exchanges();
+        assert_eq!(exchanges.len(), 1);
+        assert_eq!(exchanges[0].response, Some(&recording.records[1]));
+    }
+
+    #[test]
+    fn records_rtu_frames_when_turning_around() {
+        let transport = MockTransport::new(&[
+            &[0x01, 0x03, 0x02],
+            &[0x00, 0x07, 0xf9, 0x86],
+            &[0x01, 0x83, 0x02, 0xc0, 0xf1],
+        ]);
+        let mut log = vec![];
+        let recorder = Recorder::new(transport, &mut log, Framing::Rtu, Side::Client).unwrap();
+        let mut client = RtuClient::new(recorder, RtuConfig::default());
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+        client
+            .send(1, PduRequest::ReadHoldingRegisters(0, 1), &mut buf)
+            .unwrap();
+        client
+            .send(1, PduRequest::ReadHoldingRegisters(0, 1), &mut buf)
+            .unwrap_err();
+        drop(client);
+
+        let recording = Recording::parse(&String::from_utf8(log).unwrap()).unwrap();
+        let frames: Vec<_> = recording
+            .records
+            .iter()
+            .map(|record| (record.direction, record.frame.len()))
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                (Direction::Sent, 8),
+                (Direction::Received, 7),
+                (Direction::Sent, 8),
+                (Direction::Received, 5)
+            ]
+        );
+        assert_eq!(recording.exchanges().len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_recordings() {
+        assert!(matches!(
+            Recording::parse("0.0 tx 0103"),
+            Err(RecordingError::InvalidHeader)
+        ));
+        assert!(matches!(
+            Recording::parse("# modbus recording framing=rtu side=server\n\n0.0 tx 013"),
+            Err(RecordingError::InvalidLine(3))
+        ));
+        let recording =
+            Recording::parse("# modbus recording framing=ascii side=server\n# note\n1.5 rx 3a30\n")
+                .unwrap();
+        assert_eq!(recording.records[0].frame, b":0");
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    fmt,
+    io::{self, ErrorKind},
+    thread,
+    time::Duration,
+    vec,
+    vec::Vec,
+};
+
+use crate::{adu::Framing, pdu::write_hex};
+
+use super::{
+    Transport,
+    record::{Recording, describe},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ReplayConfig {
+    /// Waits as long as the recorded device did before answering
+    pub timing: bool,
+    /// Silent interval ending a RTU frame, 3.5 character times
+    pub frame_gap: Duration,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            timing: true,
+            // 3.5 characters at 19200 bauds
+            frame_gap: Duration::from_micros(2005),
+        }
+    }
+}
+
+/// Request received by a [`Replay`] that isn't in its recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    framing: Framing,
+    index: usize,
+    frame: Vec<u8>,
+}
+
+impl Mismatch {
+    /// Position of the request among those received, from 0
+    pub fn index(&self) -> &usize {
+        &self.index
+    }
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request {}: ", self.index)?;
+        write_hex(f, &self.frame)?;
+        write!(
+            f,
+            " {} isn't in the recording",
+            describe(self.framing, true, &self.frame)
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    request: Vec<u8>,
+    response: Option<Vec<u8>>,
+    latency: Duration,
+}
+
+/// Server answering the requests found in a recording with the responses that were
+/// recorded, to reproduce the exchanges with a device.
+///
+/// Requests are looked up from the one after the last matched, so a device polled
+/// repeatedly answers the recorded values in turn. Requests recorded without a response
+/// aren't answered, and neither are those not in the recording, which are kept as
+/// [`Mismatch`]es. TCP transaction ids don't have to match.
+#[derive(Debug)]
+pub struct Replay {
+    framing: Framing,
+    config: ReplayConfig,
+    entries: Vec<Entry>,
+    next: usize,
+    received: usize,
+    mismatches: Vec<Mismatch>,
+}
+
+impl Replay {
+    pub fn new(recording: &Recording, config: ReplayConfig) -> Self {
+        let entries = recording
+            .exchanges()
+            .iter()
+            .map(|exchange| Entry {
+                request: exchange.request.frame.clone(),
+                response: exchange.response.map(|response| response.frame.clone()),
+                latency: exchange.latency().unwrap_or_default(),
+            })
+            .collect();
+        Self {
+            framing: recording.framing,
+            config,
+            entries,
+            next: 0,
+            received: 0,
+            mismatches: vec![],
+        }
+    }
+
+    pub fn config(&self) -> &ReplayConfig {
+        &self.config
+    }
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    /// Looks up a received request frame and copies the recorded response into
+    /// `res_buf`. Returns the size of the response with how long to wait before
+    /// sending it, or `None` when nothing should be sent.
+    pub fn process_frame(&mut self, frame: &[u8], res_buf: &mut [u8]) -> Option<(usize, Duration)> {
+        let index = self.received;
+        self.received += 1;
+
+        // Everything but the transaction id for TCP
+        let key = |frame: &[u8]| match self.framing {
+            Framing::Tcp => frame.get(2..).unwrap_or_default().to_vec(),
+            Framing::Rtu | Framing::Ascii => frame.to_vec(),
+        };
+        let req_key = key(frame);
+        let len = self.entries.len();
+        let found = (0..len)
+            .map(|i| (self.next + i) % len)
+            .find(|i| key(&self.entries[*i].request) == req_key);
+        let Some(i) = found else {
+            self.mismatches.push(Mismatch {
+                framing: self.framing,
+                index,
+                frame: frame.to_vec(),
+            });
+            return None;
+        };
+        self.next = i + 1;
+
+        let entry = &self.entries[i];
+        let response = entry.response.as_ref()?;
+        res_buf.get_mut(..response.len())?.copy_from_slice(response);
+        if self.framing == Framing::Tcp && response.len() >= 2 && frame.len() >= 2 {
+            res_buf[..2].copy_from_slice(&frame[..2]);
+        }
+        let delay = if self.config.timing {
+            entry.latency
+        } else {
+            Duration::ZERO
+        };
+        Some((response.len(), delay))
+    }
+
+    /// Serves requests until the transport fails or reaches EOF
+    pub fn serve<T: Transport>(&mut self, transport: T) -> io::Result<()> {
+        self.serve_with(transport, |_| {})
+    }
+
+    /// Serves requests like [`Replay::serve`], calling `on_mismatch` as soon as a
+    /// request isn't found in the recording
+    pub fn serve_with<T: Transport, F: FnMut(&Mismatch)>(
+        &mut self,
+        mut transport: T,
+        mut on_mismatch: F,
+    ) -> io::Result<()> {
+        let max_adu_size = self.framing.max_adu_size();
+        let mut req_buf = vec![0_u8; max_adu_size];
+        let mut res_buf = vec![0_u8; max_adu_size];
+        let mut buf_len = 0;
+
+        loop {
+            // A read may contain several pipelined requests
+            while let Some(frame_len) = self.framing.frame_len(&req_buf[..buf_len]) {
+                self.reply(
+                    &mut transport,
+                    &req_buf[..frame_len],
+                    &mut res_buf,
+                    &mut on_mismatch,
+                )?;
+                req_buf.copy_within(frame_len..buf_len, 0);
+                buf_len -= frame_len;
+            }
+            if buf_len == req_buf.len() {
+                if self.framing != Framing::Rtu {
+                    return Err(io::Error::new(ErrorKind::InvalidData, "frame too long"));
+                }
+                self.reply(
+                    &mut transport,
+                    &req_buf[..buf_len],
+                    &mut res_buf,
+                    &mut on_mismatch,
+                )?;
+                buf_len = 0;
+            }
+
+            let frame_gap =
+                (self.framing == Framing::Rtu && buf_len > 0).then_some(self.config.frame_gap);
+            transport.set_read_timeout(frame_gap)?;
+            match transport.read(&mut req_buf[buf_len..]) {
+                Ok(0) => return Ok(()),
+                Ok(bytes_read) => buf_len += bytes_read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err)
+                    if frame_gap.is_some()
+                        && matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) =>
+                {
+                    self.reply(
+                        &mut transport,
+                        &req_buf[..buf_len],
+                        &mut res_buf,
+                        &mut on_mismatch,
+                    )?;
+                    buf_len = 0;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn reply<T: Transport, F: FnMut(&Mismatch)>(
+        &mut self,
+        transport: &mut T,
+        frame: &[u8],
+        res_buf: &mut [u8],
+        on_mismatch: &mut F,
+    ) -> io::Result<()> {
+        let mismatches = self.mismatches.len();
+        let Some((res_len, delay)) = self.process_frame(frame, res_buf) else {
+            if let Some(mismatch) = self.mismatches.get(mismatches) {
+                on_mismatch(mismatch);
+            }
+            return Ok(());
+        };
+        thread::sleep(delay);
+        transport.write_all(&res_buf[..res_len])?;
+        transport.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::{TcpListener, TcpStream},
+        string::ToString,
+        thread,
+        time::{Duration, Instant},
+        vec,
+    };
+
+    use crate::{
+        client::{
+            Client, Error, RESPONSE_BUF_SIZE,
+            tcp::{TcpClient, TcpConfig},
+        },
+        pdu::{DataWords, request::Request as PduRequest, response::Response as PduResponse},
+        transport::record::Recording,
+    };
+
+    use super::{Replay, ReplayConfig};
+
+    // Two reads of the same register answered with different values, then a write
+    // that wasn't answered
+    const RECORDING: &str = "# modbus recording framing=tcp side=client
+0.000000 tx 000100000006010300000001
+0.010000 rx 000100000005010302000a
+0.020000 tx 000200000006010300000001
+0.030000 rx 000200000005010302000b
+0.040000 tx 000300000006010600000001
+";
+
+    #[test]
+    fn answers_recorded_responses_in_turn() {
+        let recording = Recording::parse(RECORDING).unwrap();
+        let config = ReplayConfig {
+            timing: false,
+            ..Default::default()
+        };
+        let mut replay = Replay::new(&recording, config);
+        let mut res_buf = [0_u8; 16];
+
+        let read = [
+            0x00, 0x09, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+        ];
+        for value in [0x0a, 0x0b, 0x0a] {
+            let (len, delay) = replay.process_frame(&read, &mut res_buf).unwrap();
+            assert_eq!(delay, Duration::ZERO);
+            assert_eq!(
+                res_buf[..len],
+                [
+                    0x00, 0x09, 0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x02, 0x00, value
+                ]
+            );
+        }
+
+        let write = [
+            0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x00, 0x00, 0x01,
+        ];
+        assert_eq!(replay.process_frame(&write, &mut res_buf), None);
+        assert!(replay.mismatches().is_empty());
+
+        let other = [
+            0x00, 0x0b, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x05, 0x00, 0x01,
+        ];
+        assert_eq!(replay.process_frame(&other, &mut res_buf), None);
+        let mismatches = replay.mismatches();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(*mismatches[0].index(), 4);
+        assert_eq!(mismatches[0].frame(), other);
+        let report = mismatches[0].to_string();
+        assert!(report.starts_with("request 4: 000b00000006010300050001 "));
+        assert!(report.ends_with(
+            "tx=11 unit=1 FC03 ReadHoldingRegisters addr=5 qty=1 isn't in the recording"
+        ));
+    }
+
+    #[test]
+    fn serves_recording_with_its_timing() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let recording = Recording::parse(RECORDING).unwrap();
+            let mut replay = Replay::new(&recording, ReplayConfig::default());
+            let (stream, _) = listener.accept().unwrap();
+            let mut reported = vec![];
+            replay
+                .serve_with(stream, |mismatch| reported.push(*mismatch.index()))
+                .unwrap();
+            (replay, reported)
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let config = TcpConfig {
+            response_timeout: Duration::from_millis(200),
+        };
+        let mut client = TcpClient::new(stream, config);
+        let mut buf = [0_u8; RESPONSE_BUF_SIZE];
+
+        let started = Instant::now();
+        let res = client.request(1, PduRequest::ReadHoldingRegisters(0, 1), &mut buf);
+        assert_eq!(
+            res.unwrap(),
+            PduResponse::ReadHoldingRegisters(DataWords::new(&[0x00, 0x0a], 1))
+        );
+        assert!(started.elapsed() >= Duration::from_millis(10));
+
+        let res = client.request(2, PduRequest::ReadHoldingRegisters(0, 1), &mut buf);
+        assert!(matches!(res, Err(Error::Timeout)));
+        drop(client);
+
+        let (replay, reported) = server.join().unwrap();
+        assert_eq!(replay.mismatches().len(), 1);
+        assert_eq!(reported, [1]);
+    }
+}
@@ -0,0 +1,571 @@
+use std::{
+    ffi::{CStr, CString, OsStr},
+    io::{self, Read, Write},
+    mem,
+    os::{
+        fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// Kernel RS-485 mode, see `Documentation/driver-api/serial/serial-rs485.rst`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rs485Config {
+    /// Logical level of RTS while sending
+    pub rts_on_send: bool,
+    /// Logical level of RTS after sending
+    pub rts_after_send: bool,
+    pub delay_rts_before_send: Duration,
+    pub delay_rts_after_send: Duration,
+    /// Keep receiving while sending (echo of the own frames)
+    pub rx_during_tx: bool,
+}
+
+impl Default for Rs485Config {
+    fn default() -> Self {
+        Self {
+            rts_on_send: true,
+            rts_after_send: false,
+            delay_rts_before_send: Duration::ZERO,
+            delay_rts_after_send: Duration::ZERO,
+            rx_during_tx: false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub parity: Parity,
+    pub data_bits: DataBits,
+    pub stop_bits: StopBits,
+    pub rs485: Option<Rs485Config>,
+}
+
+impl SerialConfig {
+    /// 8 data bits, even parity and 1 stop bit, the default of the Modbus serial line spec
+    pub fn new(baud_rate: u32) -> Self {
+        Self {
+            baud_rate,
+            parity: Parity::Even,
+            data_bits: DataBits::Eight,
+            stop_bits: StopBits::One,
+            rs485: None,
+        }
+    }
+
+    /// Number of bits on the line for one character (start, data, parity and stop bits)
+    pub fn char_bits(&self) -> u32 {
+        let data_bits = match self.data_bits {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        };
+        let parity_bits = match self.parity {
+            Parity::None => 0,
+            Parity::Even | Parity::Odd => 1,
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        1 + data_bits + parity_bits + stop_bits
+    }
+
+    /// Time it takes to transmit one character.
+    ///
+    /// Panics if the baud rate is 0, which [`SerialPort::open`] rejects.
+    pub fn char_time(&self) -> Duration {
+        Duration::from_nanos(self.char_bits() as u64 * 1_000_000_000 / self.baud_rate as u64)
+    }
+
+    /// Silence ending an RTU frame: 3.5 characters, at least 1.75 ms like the spec says
+    /// above 19200 baud.
+    ///
+    /// Panics if the baud rate is 0, like [`SerialConfig::char_time`].
+    pub fn frame_gap(&self) -> Duration {
+        (self.char_time() * 7 / 2).max(Duration::from_micros(1750))
+    }
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct SerialRs485 {
+    flags: u32,
+    delay_rts_before_send: u32,
+    delay_rts_after_send: u32,
+    padding: [u32; 5],
+}
+
+const SER_RS485_ENABLED: u32 = 1 << 0;
+const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+const SER_RS485_RTS_AFTER_SEND: u32 = 1 << 2;
+const SER_RS485_RX_DURING_TX: u32 = 1 << 4;
+
+/// Blocking serial port on a Linux tty.
+///
+/// Reads wait at most `read_timeout` for the first byte and fail with
+/// [`io::ErrorKind::TimedOut`] after that. `flush` waits until every byte has been
+/// transmitted, which is what RS-485 turnaround timing needs.
+#[derive(Debug)]
+pub struct SerialPort {
+    fd: OwnedFd,
+    config: SerialConfig,
+    read_timeout: Option<Duration>,
+}
+
+impl SerialPort {
+    pub fn open(path: impl AsRef<Path>, config: SerialConfig) -> io::Result<Self> {
+        check_baud_rate(&config)?;
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        // O_NONBLOCK so open doesn't hang waiting for carrier detect
+        let fd = cvt(unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDWR | libc::O_NOCTTY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+            )
+        })?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let flags = cvt(unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFL) })?;
+        cvt(unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, flags & !libc::O_NONBLOCK) })?;
+
+        let port = Self {
+            fd,
+            config,
+            read_timeout: None,
+        };
+        port.apply_config()?;
+
+        Ok(port)
+    }
+
+    /// Opens the master side of a new pseudo terminal, returning it with the path of
+    /// the slave side for the peer to open.
+    ///
+    /// The line settings are those of the slave, applied when opening it with
+    /// [`SerialPort::open`], the master only keeps `config` for its timings. Reads fail
+    /// with EIO while the slave isn't open, keep it open to serve peers that come and go.
+    pub fn open_pty(config: SerialConfig) -> io::Result<(Self, PathBuf)> {
+        check_baud_rate(&config)?;
+        let fd =
+            cvt(unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC) })?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        cvt(unsafe { libc::grantpt(fd.as_raw_fd()) })?;
+        cvt(unsafe { libc::unlockpt(fd.as_raw_fd()) })?;
+
+        let mut name = [0 as libc::c_char; 64];
+        let ret = unsafe { libc::ptsname_r(fd.as_raw_fd(), name.as_mut_ptr(), name.len()) };
+        if ret != 0 {
+            return Err(io::Error::from_raw_os_error(ret));
+        }
+        let name = unsafe { CStr::from_ptr(name.as_ptr()) };
+        let path = PathBuf::from(OsStr::from_bytes(name.to_bytes()));
+
+        let port = Self {
+            fd,
+            config,
+            read_timeout: None,
+        };
+        Ok((port, path))
+    }
+
+    /// Opens a new pseudo terminal like [`SerialPort::open_pty`], along with its slave
+    /// side so that the master keeps working while peers come and go. Returns the
+    /// master, the slave to keep open and the path of the slave.
+    pub fn open_pty_pair(config: SerialConfig) -> io::Result<(Self, Self, PathBuf)> {
+        let (master, path) = Self::open_pty(config)?;
+        let slave = Self::open(&path, config)?;
+        Ok((master, slave, path))
+    }
+
+    pub fn config(&self) -> &SerialConfig {
+        &self.config
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+    pub fn set_read_timeout(&mut self, read_timeout: Option<Duration>) {
+        self.read_timeout = read_timeout;
+    }
+
+    /// Discards all data received but not read and written but not transmitted
+    pub fn clear(&self) -> io::Result<()> {
+        cvt(unsafe { libc::tcflush(self.fd.as_raw_fd(), libc::TCIOFLUSH) })?;
+        Ok(())
+    }
+
+    fn apply_config(&self) -> io::Result<()> {
+        let fd = self.fd.as_raw_fd();
+        let config = &self.config;
+
+        let Some(speed) = baud_rate_to_speed(config.baud_rate) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unsupported baud rate",
+            ));
+        };
+
+        let mut termios: libc::termios = unsafe { mem::zeroed() };
+        cvt(unsafe { libc::tcgetattr(fd, &mut termios) })?;
+        unsafe { libc::cfmakeraw(&mut termios) };
+
+        termios.c_cflag |= libc::CREAD | libc::CLOCAL;
+        termios.c_cflag &=
+            !(libc::CSIZE | libc::PARENB | libc::PARODD | libc::CSTOPB | libc::CRTSCTS);
+        termios.c_cflag |= match config.data_bits {
+            DataBits::Five => libc::CS5,
+            DataBits::Six => libc::CS6,
+            DataBits::Seven => libc::CS7,
+            DataBits::Eight => libc::CS8,
+        };
+        termios.c_iflag &= !(libc::INPCK | libc::IXOFF | libc::IXANY);
+        match config.parity {
+            Parity::None => {}
+            Parity::Even => {
+                termios.c_cflag |= libc::PARENB;
+                termios.c_iflag |= libc::INPCK;
+            }
+            Parity::Odd => {
+                termios.c_cflag |= libc::PARENB | libc::PARODD;
+                termios.c_iflag |= libc::INPCK;
+            }
+        }
+        if config.stop_bits == StopBits::Two {
+            termios.c_cflag |= libc::CSTOPB;
+        }
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+
+        cvt(unsafe { libc::cfsetispeed(&mut termios, speed) })?;
+        cvt(unsafe { libc::cfsetospeed(&mut termios, speed) })?;
+        if let Err(err) = cvt(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) }) {
+            // glibc reads the settings back and fails with EINVAL when the parity didn't
+            // stick, which pseudo terminals never keep
+            let mut applied: libc::termios = unsafe { mem::zeroed() };
+            cvt(unsafe { libc::tcgetattr(fd, &mut applied) })?;
+            let parity = libc::PARENB | libc::PARODD;
+            if err.raw_os_error() != Some(libc::EINVAL)
+                || applied.c_cflag & !parity != termios.c_cflag & !parity
+            {
+                return Err(err);
+            }
+        }
+
+        if let Some(rs485) = &config.rs485 {
+            let mut flags = SER_RS485_ENABLED;
+            if rs485.rts_on_send {
+                flags |= SER_RS485_RTS_ON_SEND;
+            }
+            if rs485.rts_after_send {
+                flags |= SER_RS485_RTS_AFTER_SEND;
+            }
+            if rs485.rx_during_tx {
+                flags |= SER_RS485_RX_DURING_TX;
+            }
+            let serial_rs485 = SerialRs485 {
+                flags,
+                delay_rts_before_send: duration_to_millis(rs485.delay_rts_before_send),
+                delay_rts_after_send: duration_to_millis(rs485.delay_rts_after_send),
+                ..Default::default()
+            };
+            cvt(unsafe { libc::ioctl(fd, libc::TIOCSRS485, &serial_rs485) })?;
+        }
+
+        self.clear()
+    }
+
+    fn wait_readable(&self) -> io::Result<()> {
+        let timeout = match self.read_timeout {
+            // Rounded up, so short inter-frame gaps don't turn into a non-blocking poll
+            Some(timeout) => i32::try_from(timeout.as_micros().div_ceil(1000)).unwrap_or(i32::MAX),
+            None => -1,
+        };
+        let mut pollfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        loop {
+            match cvt(unsafe { libc::poll(&mut pollfd, 1, timeout) }) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "serial port read timed out",
+                    ));
+                }
+                Ok(_) => return Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Read for SerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.wait_readable()?;
+
+        let len = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(len as usize)
+    }
+}
+
+impl Write for SerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = unsafe { libc::write(self.fd.as_raw_fd(), buf.as_ptr().cast(), buf.len()) };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(len as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        cvt(unsafe { libc::tcdrain(self.fd.as_raw_fd()) })?;
+        Ok(())
+    }
+}
+
+impl AsFd for SerialPort {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for SerialPort {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+fn cvt(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+fn duration_to_millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
+}
+
+fn check_baud_rate(config: &SerialConfig) -> io::Result<()> {
+    if config.baud_rate == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "baud rate must be positive",
+        ));
+    }
+    Ok(())
+}
+
+fn baud_rate_to_speed(baud_rate: u32) -> Option<libc::speed_t> {
+    let speed = match baud_rate {
+        110 => libc::B110,
+        300 => libc::B300,
+        600 => libc::B600,
+        1200 => libc::B1200,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        500000 => libc::B500000,
+        576000 => libc::B576000,
+        921600 => libc::B921600,
+        1000000 => libc::B1000000,
+        _ => return None,
+    };
+    Some(speed)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        ffi::CStr,
+        fs::File,
+        io::{ErrorKind, Read, Write},
+        mem,
+        os::fd::{AsRawFd, FromRawFd, OwnedFd},
+        ptr,
+        string::String,
+        time::Duration,
+    };
+
+    use super::{Parity, Rs485Config, SerialConfig, SerialPort, StopBits};
+
+    /// Returns the master side of a pseudo terminal and the path of its slave side.
+    /// The slave fd is returned as well, so the pty isn't hung up before the port is opened.
+    pub(crate) fn open_pty() -> (File, OwnedFd, String) {
+        let mut master = 0;
+        let mut slave = 0;
+        let mut name = [0 as libc::c_char; 128];
+        let ret = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                name.as_mut_ptr(),
+                ptr::null(),
+                ptr::null(),
+            )
+        };
+        assert_eq!(ret, 0, "openpty failed");
+
+        let name = unsafe { CStr::from_ptr(name.as_ptr()) }
+            .to_str()
+            .unwrap()
+            .into();
+        unsafe { (File::from_raw_fd(master), OwnedFd::from_raw_fd(slave), name) }
+    }
+
+    #[test]
+    fn exchange_bytes_over_pty() {
+        let (mut master, _slave, path) = open_pty();
+        let mut port = SerialPort::open(&path, SerialConfig::new(19200)).unwrap();
+        port.set_read_timeout(Some(Duration::from_secs(1)));
+
+        port.write_all(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x01])
+            .unwrap();
+        port.flush().unwrap();
+        let mut buf = [0_u8; 6];
+        master.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0x01, 0x03, 0x00, 0x00, 0x00, 0x01]);
+
+        master.write_all(&[0x01, 0x03, 0x02, 0x12, 0x34]).unwrap();
+        let mut buf = [0_u8; 5];
+        port.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0x01, 0x03, 0x02, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn read_times_out() {
+        let (_master, _slave, path) = open_pty();
+        let mut port = SerialPort::open(&path, SerialConfig::new(9600)).unwrap();
+        port.set_read_timeout(Some(Duration::from_millis(20)));
+
+        let mut buf = [0_u8; 1];
+        let err = port.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn applies_line_settings() {
+        let (_master, _slave, path) = open_pty();
+        let config = SerialConfig {
+            parity: Parity::Odd,
+            stop_bits: StopBits::Two,
+            ..SerialConfig::new(38400)
+        };
+        let port = SerialPort::open(&path, config).unwrap();
+        assert_eq!(port.config().char_bits(), 12);
+
+        let mut termios: libc::termios = unsafe { mem::zeroed() };
+        assert_eq!(
+            unsafe { libc::tcgetattr(port.as_raw_fd(), &mut termios) },
+            0
+        );
+        assert_eq!(unsafe { libc::cfgetospeed(&termios) }, libc::B38400);
+        assert_eq!(termios.c_lflag & (libc::ICANON | libc::ECHO), 0);
+        assert_eq!(termios.c_cc[libc::VMIN], 1);
+        // ptys force 8N1, so the character framing itself can't be checked here
+    }
+
+    #[test]
+    fn reopens_configured_pty() {
+        let (_master, _slave, path) = open_pty();
+        let _port = SerialPort::open(&path, SerialConfig::new(19200)).unwrap();
+        // Only the parity differs from the settings in place, which the pty drops
+        SerialPort::open(&path, SerialConfig::new(19200)).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsupported_baud_rate() {
+        let (_master, _slave, path) = open_pty();
+        let err = SerialPort::open(&path, SerialConfig::new(12345)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rejects_zero_baud_rate() {
+        let (_master, _slave, path) = open_pty();
+        let err = SerialPort::open(&path, SerialConfig::new(0)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = SerialPort::open_pty(SerialConfig::new(0)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rs485_is_not_supported_by_pty() {
+        let (_master, _slave, path) = open_pty();
+        let config = SerialConfig {
+            rs485: Some(Rs485Config::default()),
+            ..SerialConfig::new(9600)
+        };
+        assert!(SerialPort::open(&path, config).is_err());
+    }
+
+    #[test]
+    fn char_time() {
+        // 11 bits per character at 9600 baud
+        assert_eq!(
+            SerialConfig::new(9600).char_time(),
+            Duration::from_nanos(1_145_833)
+        );
+        let config = SerialConfig {
+            parity: Parity::None,
+            ..SerialConfig::new(9600)
+        };
+        assert_eq!(config.char_bits(), 10);
+    }
+
+    #[test]
+    fn frame_gap() {
+        assert_eq!(
+            SerialConfig::new(9600).frame_gap(),
+            Duration::from_nanos(4_010_415)
+        );
+        assert_eq!(
+            SerialConfig::new(115200).frame_gap(),
+            Duration::from_micros(1750)
+        );
+    }
+}